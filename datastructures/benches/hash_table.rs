@@ -0,0 +1,90 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use datastructures::HashMap;
+
+use std::collections::HashMap as StdHashMap;
+use std::hint::black_box;
+
+// Both maps hash with `RandomState`, so the difference is in the tables.
+
+fn insert(c: &mut Criterion) {
+    let mut group = c.benchmark_group("insert");
+    for n in [100, 10_000] {
+        group.bench_with_input(BenchmarkId::new("HashMap", n), &n, |b, &n| {
+            b.iter(|| {
+                let mut map = HashMap::new();
+                for i in 0..n {
+                    map.insert(black_box(i), i);
+                }
+                map
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("std HashMap", n), &n, |b, &n| {
+            b.iter(|| {
+                let mut map = StdHashMap::new();
+                for i in 0..n {
+                    map.insert(black_box(i), i);
+                }
+                map
+            })
+        });
+    }
+    group.finish();
+}
+
+/// Looking up every key once, then as many keys that are not there.
+fn get(c: &mut Criterion) {
+    let mut group = c.benchmark_group("get");
+    for n in [100, 10_000] {
+        let map: HashMap<_, _> = (0..n).map(|i| (i, i)).collect();
+        group.bench_with_input(BenchmarkId::new("HashMap", n), &n, |b, &n| {
+            b.iter(|| {
+                for i in 0..n * 2 {
+                    black_box(map.get(&i));
+                }
+            })
+        });
+        let map: StdHashMap<_, _> = (0..n).map(|i| (i, i)).collect();
+        group.bench_with_input(BenchmarkId::new("std HashMap", n), &n, |b, &n| {
+            b.iter(|| {
+                for i in 0..n * 2 {
+                    black_box(map.get(&i));
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+fn remove(c: &mut Criterion) {
+    let mut group = c.benchmark_group("remove");
+    for n in [100, 10_000] {
+        let map: HashMap<_, _> = (0..n).map(|i| (i, i)).collect();
+        group.bench_with_input(BenchmarkId::new("HashMap", n), &n, |b, &n| {
+            b.iter_batched_ref(
+                || map.clone(),
+                |map| {
+                    for i in 0..n {
+                        black_box(map.remove(&i));
+                    }
+                },
+                criterion::BatchSize::SmallInput,
+            )
+        });
+        let map: StdHashMap<_, _> = (0..n).map(|i| (i, i)).collect();
+        group.bench_with_input(BenchmarkId::new("std HashMap", n), &n, |b, &n| {
+            b.iter_batched_ref(
+                || map.clone(),
+                |map| {
+                    for i in 0..n {
+                        black_box(map.remove(&i));
+                    }
+                },
+                criterion::BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, insert, get, remove);
+criterion_main!(benches);
@@ -0,0 +1,53 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use datastructures::binary_search_tree::{AvlTree, BinarySearchTree};
+
+use std::hint::black_box;
+
+/// Sorted keys are the worst case of the naive tree, every insert walks
+/// the whole right spine.
+fn sorted_insert(c: &mut Criterion) {
+    let mut group = c.benchmark_group("sorted insert");
+    for n in [100, 1_000, 4_000] {
+        group.bench_with_input(BenchmarkId::new("BinarySearchTree", n), &n, |b, &n| {
+            b.iter(|| {
+                let mut tree = BinarySearchTree::new();
+                for key in 0..n {
+                    tree.insert(key);
+                }
+                tree
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("AvlTree", n), &n, |b, &n| {
+            b.iter(|| {
+                let mut tree = AvlTree::new();
+                for key in 0..n {
+                    tree.insert(key);
+                }
+                tree
+            })
+        });
+    }
+    group.finish();
+}
+
+fn sorted_contains(c: &mut Criterion) {
+    let mut group = c.benchmark_group("contains after sorted insert");
+    for n in [100, 1_000, 4_000] {
+        let mut bst = BinarySearchTree::new();
+        let mut avl = AvlTree::new();
+        for key in 0..n {
+            bst.insert(key);
+            avl.insert(key);
+        }
+        group.bench_with_input(BenchmarkId::new("BinarySearchTree", n), &n, |b, &n| {
+            b.iter(|| bst.contains(black_box(n - 1)))
+        });
+        group.bench_with_input(BenchmarkId::new("AvlTree", n), &n, |b, &n| {
+            b.iter(|| avl.contains(black_box(n - 1)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, sorted_insert, sorted_contains);
+criterion_main!(benches);
@@ -0,0 +1,87 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use datastructures::Vector;
+
+use std::hint::black_box;
+
+fn push(c: &mut Criterion) {
+    let mut group = c.benchmark_group("push");
+    for n in [100, 10_000] {
+        group.bench_with_input(BenchmarkId::new("Vector", n), &n, |b, &n| {
+            b.iter(|| {
+                let mut vector = Vector::new();
+                for i in 0..n {
+                    vector.push(black_box(i));
+                }
+                vector
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("Vec", n), &n, |b, &n| {
+            b.iter(|| {
+                let mut vec = Vec::new();
+                for i in 0..n {
+                    vec.push(black_box(i));
+                }
+                vec
+            })
+        });
+    }
+    group.finish();
+}
+
+/// Pushing then popping everything, which also shrinks `Vector`.
+fn push_pop(c: &mut Criterion) {
+    let mut group = c.benchmark_group("push then pop");
+    for n in [100, 10_000] {
+        group.bench_with_input(BenchmarkId::new("Vector", n), &n, |b, &n| {
+            b.iter(|| {
+                let mut vector = Vector::new();
+                for i in 0..n {
+                    vector.push(i);
+                }
+                while let Some(value) = vector.pop() {
+                    black_box(value);
+                }
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("Vec", n), &n, |b, &n| {
+            b.iter(|| {
+                let mut vec = Vec::new();
+                for i in 0..n {
+                    vec.push(i);
+                }
+                while let Some(value) = vec.pop() {
+                    black_box(value);
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+fn insert_front(c: &mut Criterion) {
+    let mut group = c.benchmark_group("insert at front");
+    for n in [100, 1_000] {
+        group.bench_with_input(BenchmarkId::new("Vector", n), &n, |b, &n| {
+            b.iter(|| {
+                let mut vector = Vector::new();
+                for i in 0..n {
+                    vector.insert(0, i);
+                }
+                vector
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("Vec", n), &n, |b, &n| {
+            b.iter(|| {
+                let mut vec = Vec::new();
+                for i in 0..n {
+                    vec.insert(0, i);
+                }
+                vec
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, push, push_pop, insert_front);
+criterion_main!(benches);
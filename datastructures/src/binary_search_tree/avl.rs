@@ -0,0 +1,300 @@
+/// A binary search tree kept balanced with AVL rotations, so the heights of
+/// the two subtrees of any node differ by at most one. Inserting sorted data
+/// keeps it O(log n) deep, where `BinarySearchTree` degrades to a list.
+#[derive(Debug)]
+pub struct AvlTree<T> {
+    root: Option<Box<AvlNode<T>>>,
+}
+
+#[derive(Debug)]
+pub struct AvlNode<T> {
+    data: T,
+    /// Number of nodes on the longest path down to a leaf, 1 for a leaf.
+    height: usize,
+    left: Option<Box<AvlNode<T>>>,
+    right: Option<Box<AvlNode<T>>>,
+}
+
+impl<T> AvlTree<T>
+where
+    T: PartialOrd,
+{
+    pub fn new() -> Self {
+        Self { root: None }
+    }
+
+    /// Equal values go to the right, like in `BinarySearchTree`.
+    pub fn insert(&mut self, data: T) {
+        self.root = Some(AvlNode::insert(self.root.take(), data));
+    }
+
+    pub fn contains(&self, data: T) -> bool {
+        let mut node = &self.root;
+
+        while let Some(next) = node {
+            if data == next.data {
+                return true;
+            } else if data < next.data {
+                node = &next.left;
+            } else {
+                node = &next.right;
+            }
+        }
+
+        false
+    }
+
+    /// Number of nodes on the longest path from the root, 0 when empty.
+    pub fn height(&self) -> usize {
+        height(&self.root)
+    }
+
+    /// Checks the ordering, the stored heights and the balance of every
+    /// node, describing the first violation found.
+    pub fn check_invariants(&self) -> Result<(), String> {
+        AvlNode::check(&self.root, None, None).map(|_| ())
+    }
+
+    pub fn iter(&self) -> AvlIterator<'_, T> {
+        let mut iter = AvlIterator { stack: Vec::new() };
+        iter.push_left(&self.root);
+        iter
+    }
+}
+
+impl<T> Default for AvlTree<T>
+where
+    T: PartialOrd,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn height<T>(node: &Option<Box<AvlNode<T>>>) -> usize {
+    node.as_ref().map_or(0, |node| node.height)
+}
+
+impl<T> AvlNode<T>
+where
+    T: PartialOrd,
+{
+    fn insert(node: Option<Box<Self>>, data: T) -> Box<Self> {
+        let mut node = match node {
+            Some(node) => node,
+            None => {
+                return Box::new(AvlNode {
+                    data,
+                    height: 1,
+                    left: None,
+                    right: None,
+                })
+            }
+        };
+
+        if data < node.data {
+            node.left = Some(Self::insert(node.left.take(), data));
+        } else {
+            node.right = Some(Self::insert(node.right.take(), data));
+        }
+        Self::rebalance(node)
+    }
+
+    fn update_height(&mut self) {
+        self.height = 1 + height(&self.left).max(height(&self.right));
+    }
+
+    /// Positive when the left subtree is the taller one.
+    fn balance_factor(&self) -> isize {
+        height(&self.left) as isize - height(&self.right) as isize
+    }
+
+    /// Restores the balance of a node whose subtrees differ by at most two
+    /// in height, returning the root of the rebalanced subtree.
+    fn rebalance(mut node: Box<Self>) -> Box<Self> {
+        node.update_height();
+        let balance = node.balance_factor();
+
+        if balance > 1 {
+            // Left-right case, turned into the left-left case first
+            if node.left.as_ref().unwrap().balance_factor() < 0 {
+                node.left = Some(Self::rotate_left(node.left.take().unwrap()));
+            }
+            Self::rotate_right(node)
+        } else if balance < -1 {
+            // Right-left case, turned into the right-right case first
+            if node.right.as_ref().unwrap().balance_factor() > 0 {
+                node.right = Some(Self::rotate_right(node.right.take().unwrap()));
+            }
+            Self::rotate_left(node)
+        } else {
+            node
+        }
+    }
+
+    /// ```text
+    ///     node              left
+    ///    /    \            /    \
+    ///  left    c   ->     a     node
+    ///  /  \                     /  \
+    /// a    b                   b    c
+    /// ```
+    fn rotate_right(mut node: Box<Self>) -> Box<Self> {
+        let mut left = node.left.take().expect("rotate_right without a left child");
+        node.left = left.right.take();
+        node.update_height();
+        left.right = Some(node);
+        left.update_height();
+        left
+    }
+
+    /// The mirror image of `rotate_right`.
+    fn rotate_left(mut node: Box<Self>) -> Box<Self> {
+        let mut right = node
+            .right
+            .take()
+            .expect("rotate_left without a right child");
+        node.right = right.left.take();
+        node.update_height();
+        right.left = Some(node);
+        right.update_height();
+        right
+    }
+
+    /// Checks the subtree holds values in `[min, max]`, returns its height.
+    /// Rotations can move a value equal to a node into its left subtree.
+    fn check(node: &Option<Box<Self>>, min: Option<&T>, max: Option<&T>) -> Result<usize, String> {
+        let node = match node {
+            Some(node) => node,
+            None => return Ok(0),
+        };
+
+        let below_min = min.is_some_and(|min| node.data < *min);
+        let above_max = max.is_some_and(|max| node.data > *max);
+        if below_min || above_max {
+            return Err("a value is on the wrong side of one of its ancestors".to_string());
+        }
+
+        let left = Self::check(&node.left, min, Some(&node.data))?;
+        let right = Self::check(&node.right, Some(&node.data), max)?;
+        if node.height != 1 + left.max(right) {
+            return Err(format!(
+                "a node stores height {} but is {} high",
+                node.height,
+                1 + left.max(right)
+            ));
+        }
+        if left.abs_diff(right) > 1 {
+            return Err(format!(
+                "a node has subtrees of heights {} and {}",
+                left, right
+            ));
+        }
+        Ok(node.height)
+    }
+}
+
+/// In-order iterator, the stack holds the nodes whose left subtree was
+/// walked but not their value.
+pub struct AvlIterator<'a, T> {
+    stack: Vec<&'a AvlNode<T>>,
+}
+
+impl<'a, T> AvlIterator<'a, T> {
+    fn push_left(&mut self, mut current: &'a Option<Box<AvlNode<T>>>) {
+        while let Some(node) = current {
+            self.stack.push(node);
+            current = &node.left;
+        }
+    }
+}
+
+impl<'a, T> Iterator for AvlIterator<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.stack.pop()?;
+        self.push_left(&node.right);
+        Some(&node.data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::rngs::StdRng;
+    use rand::seq::SliceRandom;
+    use rand::SeedableRng;
+
+    #[test]
+    fn insert() {
+        let mut avl = AvlTree::new();
+
+        for data in [5, 3, 7, 2, 4, 6, 8] {
+            avl.insert(data);
+        }
+
+        for data in 2..=8 {
+            assert!(avl.contains(data));
+        }
+        assert!(!avl.contains(1));
+        assert!(!avl.contains(9));
+        assert_eq!(avl.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn sorted_insert_stays_balanced() {
+        let mut ascending = AvlTree::new();
+        let mut descending = AvlTree::new();
+
+        for data in 0..1000 {
+            ascending.insert(data);
+            descending.insert(1000 - data);
+        }
+
+        // An AVL tree of n nodes is less than 1.45 * log2(n + 2) high
+        assert!(ascending.height() <= 14, "{}", ascending.height());
+        assert!(descending.height() <= 14, "{}", descending.height());
+        assert_eq!(ascending.check_invariants(), Ok(()));
+        assert_eq!(descending.check_invariants(), Ok(()));
+        assert!(ascending.iter().copied().eq(0..1000));
+    }
+
+    #[test]
+    fn random_insert() {
+        for seed in 0..20 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut data = (0..500).map(|i| i / 2).collect::<Vec<_>>();
+            data.shuffle(&mut rng);
+
+            let mut avl = AvlTree::new();
+            for &value in &data {
+                avl.insert(value);
+                assert_eq!(avl.check_invariants(), Ok(()));
+            }
+
+            // Duplicates are kept
+            data.sort();
+            assert!(avl.iter().copied().eq(data));
+        }
+    }
+
+    #[test]
+    fn iter() {
+        let mut avl = AvlTree::new();
+        assert_eq!(avl.iter().next(), None);
+        assert_eq!(avl.height(), 0);
+
+        for data in [3, 1, 2] {
+            avl.insert(data);
+        }
+
+        let mut iter = avl.iter();
+        assert_eq!(iter.next(), Some(&1));
+        assert_eq!(iter.next(), Some(&2));
+        assert_eq!(iter.next(), Some(&3));
+        assert_eq!(iter.next(), None);
+        assert_eq!(avl.height(), 2);
+    }
+}
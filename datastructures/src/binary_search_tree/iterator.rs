@@ -1,42 +1,308 @@
 use std::collections::VecDeque;
+use std::ops::{Bound, RangeBounds};
 
-use super::{BinarySearchTree, Node};
+use super::{size, BinarySearchTree, Node};
 
+type Link<T> = Option<Box<Node<T>>>;
+
+/// In-order iterator. `front` holds the nodes whose left subtree was walked
+/// but not their value, `back` the mirror image for iterating from the end,
+/// and `remaining` tells when the two meet.
 pub struct BSTIterator<'a, T> {
-    stack: VecDeque<&'a Box<Node<T>>>,
+    front: Vec<&'a Node<T>>,
+    back: Vec<&'a Node<T>>,
+    remaining: usize,
+}
+
+/// Yields each value before the values of its subtrees.
+pub struct PreOrder<'a, T> {
+    stack: Vec<&'a Node<T>>,
+}
+
+/// Yields each value after the values of its subtrees.
+pub struct PostOrder<'a, T> {
+    /// Nodes with whether their subtrees were already stacked.
+    stack: Vec<(&'a Node<T>, bool)>,
+}
+
+/// Yields the values one depth at a time, from left to right.
+pub struct LevelOrder<'a, T> {
+    queue: VecDeque<&'a Node<T>>,
+}
+
+/// Consuming in-order iterator.
+pub struct IntoIter<T> {
+    stack: Vec<Box<Node<T>>>,
+}
+
+/// Mutable in-order iterator, holding the value and the right subtree of
+/// each node whose left subtree was walked.
+pub struct IterMut<'a, T> {
+    stack: Vec<(&'a mut T, &'a mut Link<T>)>,
 }
 
 impl<T> BinarySearchTree<T>
 where
     T: PartialOrd,
 {
-    pub fn iter(&self) -> BSTIterator<T> {
-        let mut stack = VecDeque::new();
+    pub fn iter(&self) -> BSTIterator<'_, T> {
+        let mut iter = BSTIterator {
+            front: Vec::new(),
+            back: Vec::new(),
+            remaining: size(&self.root),
+        };
+        iter.push_left(&self.root);
+        iter.push_right(&self.root);
+        iter
+    }
+
+    /// Changing a value so it compares differently to the others breaks the
+    /// ordering the tree relies on.
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        let mut iter = IterMut { stack: Vec::new() };
+        iter.push_left(&mut self.root);
+        iter
+    }
+
+    pub fn pre_order(&self) -> PreOrder<'_, T> {
+        PreOrder {
+            stack: self.root.as_deref().into_iter().collect(),
+        }
+    }
+
+    pub fn post_order(&self) -> PostOrder<'_, T> {
+        PostOrder {
+            stack: self
+                .root
+                .as_deref()
+                .map(|root| (root, false))
+                .into_iter()
+                .collect(),
+        }
+    }
+
+    pub fn level_order(&self) -> LevelOrder<'_, T> {
+        LevelOrder {
+            queue: self.root.as_deref().into_iter().collect(),
+        }
+    }
+
+    /// In-order iterator over the values within `range`.
+    pub fn range<R>(&self, range: R) -> BSTRange<'_, T, R>
+    where
+        R: RangeBounds<T>,
+    {
+        // Only the nodes above the start bound are stacked, like `iter` would
+        // have them once it walked past everything below the bound
+        let mut stack: VecDeque<&Node<T>> = VecDeque::new();
         let mut current = &self.root;
         while let Some(node) = current {
-            stack.push_front(node);
+            let above_start = match range.start_bound() {
+                Bound::Included(start) => node.data >= *start,
+                Bound::Excluded(start) => node.data > *start,
+                Bound::Unbounded => true,
+            };
+            if above_start {
+                stack.push_front(node);
+                current = &node.left;
+            } else {
+                current = &node.right;
+            }
+        }
+        BSTRange { stack, range }
+    }
+}
+
+pub struct BSTRange<'a, T, R> {
+    stack: VecDeque<&'a Node<T>>,
+    range: R,
+}
+
+impl<'a, T> BSTIterator<'a, T> {
+    fn push_left(&mut self, mut current: &'a Link<T>) {
+        while let Some(node) = current {
+            self.front.push(node);
             current = &node.left;
         }
-        BSTIterator { stack }
+    }
+
+    fn push_right(&mut self, mut current: &'a Link<T>) {
+        while let Some(node) = current {
+            self.back.push(node);
+            current = &node.right;
+        }
+    }
+}
+
+impl<'a, T> Iterator for BSTIterator<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let node = self.front.pop().unwrap();
+        self.push_left(&node.right);
+        Some(&node.data)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<T> DoubleEndedIterator for BSTIterator<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let node = self.back.pop().unwrap();
+        self.push_right(&node.left);
+        Some(&node.data)
+    }
+}
+
+impl<T> ExactSizeIterator for BSTIterator<'_, T> {}
+
+impl<'a, T> Iterator for PreOrder<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.stack.pop()?;
+        // The left subtree is popped first
+        self.stack.extend(node.right.as_deref());
+        self.stack.extend(node.left.as_deref());
+        Some(&node.data)
     }
 }
 
-impl<'a, T> Iterator for BSTIterator<'a, T>
+impl<'a, T> Iterator for PostOrder<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (node, expanded) = self.stack.pop()?;
+            if expanded {
+                return Some(&node.data);
+            }
+            self.stack.push((node, true));
+            self.stack
+                .extend(node.right.as_deref().map(|right| (right, false)));
+            self.stack
+                .extend(node.left.as_deref().map(|left| (left, false)));
+        }
+    }
+}
+
+impl<'a, T> Iterator for LevelOrder<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.queue.pop_front()?;
+        self.queue.extend(node.left.as_deref());
+        self.queue.extend(node.right.as_deref());
+        Some(&node.data)
+    }
+}
+
+impl<T> IntoIter<T> {
+    fn push_left(&mut self, mut current: Link<T>) {
+        while let Some(mut node) = current {
+            current = node.left.take();
+            self.stack.push(node);
+        }
+    }
+}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut node = self.stack.pop()?;
+        self.push_left(node.right.take());
+        Some(node.data)
+    }
+}
+
+impl<'a, T> IterMut<'a, T> {
+    fn push_left(&mut self, mut current: &'a mut Link<T>) {
+        while let Some(node) = current {
+            let Node {
+                data, left, right, ..
+            } = &mut **node;
+            self.stack.push((data, right));
+            current = left;
+        }
+    }
+}
+
+impl<'a, T> Iterator for IterMut<'a, T> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (data, right) = self.stack.pop()?;
+        self.push_left(right);
+        Some(data)
+    }
+}
+
+impl<T> IntoIterator for BinarySearchTree<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(mut self) -> Self::IntoIter {
+        let mut iter = IntoIter { stack: Vec::new() };
+        iter.push_left(self.root.take());
+        iter
+    }
+}
+
+impl<'a, T> IntoIterator for &'a BinarySearchTree<T>
 where
     T: PartialOrd,
 {
     type Item = &'a T;
+    type IntoIter = BSTIterator<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut BinarySearchTree<T>
+where
+    T: PartialOrd,
+{
+    type Item = &'a mut T;
+    type IntoIter = IterMut<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<'a, T, R> Iterator for BSTRange<'a, T, R>
+where
+    T: PartialOrd,
+    R: RangeBounds<T>,
+{
+    type Item = &'a T;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if let Some(node) = self.stack.pop_front() {
-            let mut current = &node.right;
-            while let Some(next) = current {
-                self.stack.push_front(next);
-                current = &next.left;
-            }
-            Some(&node.data)
-        } else {
-            None
+        let node = self.stack.pop_front()?;
+        // Everything left is past the end bound too
+        if !self.range.contains(&node.data) {
+            self.stack.clear();
+            return None;
+        }
+
+        let mut current = &node.right;
+        while let Some(next) = current {
+            self.stack.push_front(next);
+            current = &next.left;
         }
+        Some(&node.data)
     }
 }
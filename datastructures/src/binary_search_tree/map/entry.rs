@@ -0,0 +1,126 @@
+use super::{unlink, Link, MapNode};
+
+use std::mem;
+
+/// A key of a `BstMap` that may or may not be in it, from `BstMap::entry`.
+pub enum Entry<'a, K, V> {
+    Vacant(VacantEntry<'a, K, V>),
+    Occupied(OccupiedEntry<'a, K, V>),
+}
+
+pub struct VacantEntry<'a, K, V> {
+    pub(super) key: K,
+    /// The empty link the key belongs at.
+    pub(super) link: &'a mut Link<K, V>,
+    pub(super) length: &'a mut usize,
+}
+
+pub struct OccupiedEntry<'a, K, V> {
+    /// The link holding the key, never empty.
+    pub(super) link: &'a mut Link<K, V>,
+    pub(super) length: &'a mut usize,
+}
+
+impl<'a, K, V> Entry<'a, K, V> {
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        self.or_insert_with(|| default)
+    }
+
+    pub fn or_insert_with<F>(self, default: F) -> &'a mut V
+    where
+        F: FnOnce() -> V,
+    {
+        self.or_insert_with_key(|_| default())
+    }
+
+    pub fn or_insert_with_key<F>(self, default: F) -> &'a mut V
+    where
+        F: FnOnce(&K) -> V,
+    {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let value = default(entry.key());
+                entry.insert(value)
+            }
+        }
+    }
+
+    pub fn or_default(self) -> &'a mut V
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+
+    /// Updates the value in place when the key is already in the map.
+    pub fn and_modify<F>(mut self, f: F) -> Self
+    where
+        F: FnOnce(&mut V),
+    {
+        if let Entry::Occupied(entry) = &mut self {
+            f(entry.get_mut());
+        }
+        self
+    }
+
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+}
+
+impl<'a, K, V> VacantEntry<'a, K, V> {
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    pub fn into_key(self) -> K {
+        self.key
+    }
+
+    pub fn insert(self, value: V) -> &'a mut V {
+        *self.length += 1;
+        let node = self.link.insert(MapNode::new(self.key, value));
+        &mut node.value
+    }
+}
+
+impl<'a, K, V> OccupiedEntry<'a, K, V> {
+    fn node(&self) -> &MapNode<K, V> {
+        self.link.as_ref().unwrap()
+    }
+
+    pub fn key(&self) -> &K {
+        &self.node().key
+    }
+
+    pub fn get(&self) -> &V {
+        &self.node().value
+    }
+
+    pub fn get_mut(&mut self) -> &mut V {
+        &mut self.link.as_mut().unwrap().value
+    }
+
+    pub fn into_mut(self) -> &'a mut V {
+        &mut self.link.as_mut().unwrap().value
+    }
+
+    /// Replaces the value, returning the old one.
+    pub fn insert(&mut self, value: V) -> V {
+        mem::replace(self.get_mut(), value)
+    }
+
+    pub fn remove(self) -> V {
+        self.remove_entry().1
+    }
+
+    pub fn remove_entry(self) -> (K, V) {
+        *self.length -= 1;
+        let node = unlink(self.link);
+        (node.key, node.value)
+    }
+}
@@ -0,0 +1,216 @@
+use super::{BstMap, Link, MapNode};
+
+/// In-order iterator over the entries of a `BstMap`, the stack holds the
+/// nodes whose left subtree was walked but not their entry.
+pub struct Iter<'a, K, V> {
+    stack: Vec<&'a MapNode<K, V>>,
+    length: usize,
+}
+
+/// Like `Iter`, with the fields of each stacked node borrowed apart so the
+/// value can be handed out mutably while its right subtree is still to come.
+pub struct IterMut<'a, K, V> {
+    stack: Vec<(&'a K, &'a mut V, &'a mut Link<K, V>)>,
+    length: usize,
+}
+
+pub struct IntoIter<K, V> {
+    stack: Vec<Box<MapNode<K, V>>>,
+    length: usize,
+}
+
+pub struct Keys<'a, K, V>(Iter<'a, K, V>);
+
+pub struct Values<'a, K, V>(Iter<'a, K, V>);
+
+pub struct ValuesMut<'a, K, V>(IterMut<'a, K, V>);
+
+impl<K, V> BstMap<K, V> {
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        let mut iter = Iter {
+            stack: Vec::new(),
+            length: self.length,
+        };
+        iter.push_left(&self.root);
+        iter
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        let mut iter = IterMut {
+            stack: Vec::new(),
+            length: self.length,
+        };
+        iter.push_left(&mut self.root);
+        iter
+    }
+
+    pub fn keys(&self) -> Keys<'_, K, V> {
+        Keys(self.iter())
+    }
+
+    pub fn values(&self) -> Values<'_, K, V> {
+        Values(self.iter())
+    }
+
+    pub fn values_mut(&mut self) -> ValuesMut<'_, K, V> {
+        ValuesMut(self.iter_mut())
+    }
+}
+
+impl<'a, K, V> Iter<'a, K, V> {
+    fn push_left(&mut self, mut link: &'a Link<K, V>) {
+        while let Some(node) = link {
+            self.stack.push(node);
+            link = &node.left;
+        }
+    }
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.stack.pop()?;
+        self.push_left(&node.right);
+        self.length -= 1;
+        Some((&node.key, &node.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.length, Some(self.length))
+    }
+}
+
+impl<K, V> ExactSizeIterator for Iter<'_, K, V> {}
+
+impl<'a, K, V> IterMut<'a, K, V> {
+    fn push_left(&mut self, mut link: &'a mut Link<K, V>) {
+        while let Some(node) = link {
+            let MapNode {
+                key,
+                value,
+                left,
+                right,
+            } = &mut **node;
+            self.stack.push((key, value, right));
+            link = left;
+        }
+    }
+}
+
+impl<'a, K, V> Iterator for IterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (key, value, right) = self.stack.pop()?;
+        self.push_left(right);
+        self.length -= 1;
+        Some((key, value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.length, Some(self.length))
+    }
+}
+
+impl<K, V> ExactSizeIterator for IterMut<'_, K, V> {}
+
+impl<K, V> IntoIter<K, V> {
+    fn push_left(&mut self, mut link: Link<K, V>) {
+        while let Some(mut node) = link {
+            link = node.left.take();
+            self.stack.push(node);
+        }
+    }
+}
+
+impl<K, V> Iterator for IntoIter<K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut node = self.stack.pop()?;
+        self.push_left(node.right.take());
+        self.length -= 1;
+        Some((node.key, node.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.length, Some(self.length))
+    }
+}
+
+impl<K, V> ExactSizeIterator for IntoIter<K, V> {}
+
+impl<'a, K, V> Iterator for Keys<'a, K, V> {
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(key, _)| key)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<K, V> ExactSizeIterator for Keys<'_, K, V> {}
+
+impl<'a, K, V> Iterator for Values<'a, K, V> {
+    type Item = &'a V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(_, value)| value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<K, V> ExactSizeIterator for Values<'_, K, V> {}
+
+impl<'a, K, V> Iterator for ValuesMut<'a, K, V> {
+    type Item = &'a mut V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(_, value)| value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<K, V> ExactSizeIterator for ValuesMut<'_, K, V> {}
+
+impl<K, V> IntoIterator for BstMap<K, V> {
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V>;
+
+    fn into_iter(mut self) -> Self::IntoIter {
+        let mut iter = IntoIter {
+            stack: Vec::new(),
+            length: self.length,
+        };
+        iter.push_left(self.root.take());
+        iter
+    }
+}
+
+impl<'a, K, V> IntoIterator for &'a BstMap<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, K, V> IntoIterator for &'a mut BstMap<K, V> {
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = IterMut<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
@@ -0,0 +1,434 @@
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::fmt::{self, Debug};
+use std::mem;
+use std::ops::Index;
+
+use super::{unlink, TreeNode};
+
+mod entry;
+mod iterator;
+
+pub use entry::{Entry, OccupiedEntry, VacantEntry};
+pub use iterator::{IntoIter, Iter, IterMut, Keys, Values, ValuesMut};
+
+/// An ordered map on an unbalanced binary search tree, mirroring the surface
+/// of `BTreeMap`. Keys are unique, inserting one again replaces its value.
+#[derive(Clone)]
+pub struct BstMap<K, V> {
+    root: Link<K, V>,
+    length: usize,
+}
+
+type Link<K, V> = Option<Box<MapNode<K, V>>>;
+
+#[derive(Clone)]
+struct MapNode<K, V> {
+    key: K,
+    value: V,
+    left: Link<K, V>,
+    right: Link<K, V>,
+}
+
+impl<K, V> BstMap<K, V> {
+    pub fn new() -> Self {
+        Self {
+            root: None,
+            length: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.length
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+
+    pub fn first_key_value(&self) -> Option<(&K, &V)> {
+        let mut node = self.root.as_ref()?;
+        while let Some(left) = &node.left {
+            node = left;
+        }
+        Some((&node.key, &node.value))
+    }
+
+    pub fn last_key_value(&self) -> Option<(&K, &V)> {
+        let mut node = self.root.as_ref()?;
+        while let Some(right) = &node.right {
+            node = right;
+        }
+        Some((&node.key, &node.value))
+    }
+}
+
+impl<K, V> BstMap<K, V>
+where
+    K: Ord,
+{
+    /// Returns the value the key had before, the key itself is not updated.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let link = search(&mut self.root, &key);
+        match link {
+            Some(node) => Some(mem::replace(&mut node.value, value)),
+            None => {
+                *link = Some(MapNode::new(key, value));
+                self.length += 1;
+                None
+            }
+        }
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.get_key_value(key).map(|(_, value)| value)
+    }
+
+    pub fn get_key_value<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut link = &self.root;
+
+        while let Some(node) = link {
+            match key.cmp(node.key.borrow()) {
+                Ordering::Less => link = &node.left,
+                Ordering::Greater => link = &node.right,
+                Ordering::Equal => return Some((&node.key, &node.value)),
+            }
+        }
+
+        None
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        search(&mut self.root, key)
+            .as_mut()
+            .map(|node| &mut node.value)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.get_key_value(key).is_some()
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.remove_entry(key).map(|(_, value)| value)
+    }
+
+    pub fn remove_entry<Q>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let link = search(&mut self.root, key);
+        link.as_ref()?;
+        self.length -= 1;
+        let node = unlink(link);
+        Some((node.key, node.value))
+    }
+
+    /// The entry for `key`, to look it up and insert or update it in one walk.
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V> {
+        let link = search(&mut self.root, &key);
+        if link.is_some() {
+            Entry::Occupied(OccupiedEntry {
+                link,
+                length: &mut self.length,
+            })
+        } else {
+            Entry::Vacant(VacantEntry {
+                key,
+                link,
+                length: &mut self.length,
+            })
+        }
+    }
+}
+
+impl<K, V> MapNode<K, V> {
+    fn new(key: K, value: V) -> Box<Self> {
+        Box::new(MapNode {
+            key,
+            value,
+            left: None,
+            right: None,
+        })
+    }
+}
+
+/// Nodes keep no subtree sizes, `BstMap` counts its entries itself.
+impl<K, V> TreeNode for MapNode<K, V> {
+    fn left(&mut self) -> &mut Link<K, V> {
+        &mut self.left
+    }
+
+    fn right(&mut self) -> &mut Link<K, V> {
+        &mut self.right
+    }
+}
+
+/// The link holding `key`, or the empty link it would be inserted at.
+fn search<'a, K, V, Q>(mut link: &'a mut Link<K, V>, key: &Q) -> &'a mut Link<K, V>
+where
+    K: Borrow<Q>,
+    Q: Ord + ?Sized,
+{
+    loop {
+        let ordering = match link.as_deref() {
+            Some(node) => key.cmp(node.key.borrow()),
+            None => return link,
+        };
+        link = match ordering {
+            Ordering::Less => &mut link.as_mut().unwrap().left,
+            Ordering::Greater => &mut link.as_mut().unwrap().right,
+            Ordering::Equal => return link,
+        };
+    }
+}
+
+impl<K, V> Default for BstMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V> Debug for BstMap<K, V>
+where
+    K: Debug,
+    V: Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K, V> PartialEq for BstMap<K, V>
+where
+    K: PartialEq,
+    V: PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.length == other.length && self.iter().eq(other.iter())
+    }
+}
+
+impl<K, V> Eq for BstMap<K, V>
+where
+    K: Eq,
+    V: Eq,
+{
+}
+
+impl<K, Q, V> Index<&Q> for BstMap<K, V>
+where
+    K: Borrow<Q> + Ord,
+    Q: Ord + ?Sized,
+{
+    type Output = V;
+
+    /// Panics when the key is not in the map.
+    fn index(&self, key: &Q) -> &V {
+        self.get(key).expect("no entry found for key")
+    }
+}
+
+impl<K, V> FromIterator<(K, V)> for BstMap<K, V>
+where
+    K: Ord,
+{
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut map = Self::new();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<K, V> Extend<(K, V)> for BstMap<K, V>
+where
+    K: Ord,
+{
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn insert_and_get() {
+        let mut map = BstMap::new();
+
+        assert_eq!(map.insert("b", 2), None);
+        assert_eq!(map.insert("a", 1), None);
+        assert_eq!(map.insert("c", 3), None);
+        assert_eq!(map.insert("b", 20), Some(2));
+
+        assert_eq!(map.len(), 3);
+        assert_eq!(map.get("b"), Some(&20));
+        assert_eq!(map.get("d"), None);
+        assert_eq!(map["a"], 1);
+        assert!(map.contains_key("c"));
+
+        *map.get_mut("c").unwrap() += 1;
+        assert_eq!(map.get_key_value("c"), Some((&"c", &4)));
+        assert_eq!(map.first_key_value(), Some((&"a", &1)));
+        assert_eq!(map.last_key_value(), Some((&"c", &4)));
+    }
+
+    #[test]
+    fn owned_keys_are_looked_up_by_borrow() {
+        let map = [("one".to_string(), 1), ("two".to_string(), 2)]
+            .into_iter()
+            .collect::<BstMap<_, _>>();
+
+        assert_eq!(map.get("two"), Some(&2));
+        assert_eq!(map["one"], 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "no entry found for key")]
+    fn index_missing_key() {
+        let map = BstMap::<i32, i32>::new();
+        let _ = map[&1];
+    }
+
+    #[test]
+    fn remove() {
+        let mut map = (0..10).map(|key| (key, key * 10)).collect::<BstMap<_, _>>();
+
+        assert_eq!(map.remove(&5), Some(50));
+        assert_eq!(map.remove(&5), None);
+        assert_eq!(map.remove_entry(&0), Some((0, 0)));
+        assert_eq!(map.len(), 8);
+        assert!(map.keys().copied().eq([1, 2, 3, 4, 6, 7, 8, 9]));
+
+        map.clear();
+        assert!(map.is_empty());
+        assert_eq!(map.first_key_value(), None);
+    }
+
+    #[test]
+    fn entry() {
+        let mut counts = BstMap::new();
+
+        for word in "the cat and the hat and the bat".split(' ') {
+            *counts.entry(word).or_insert(0) += 1;
+        }
+        assert_eq!(counts["the"], 3);
+        assert_eq!(counts["and"], 2);
+        assert_eq!(counts.len(), 5);
+
+        counts
+            .entry("cat")
+            .and_modify(|count| *count += 10)
+            .or_default();
+        counts
+            .entry("dog")
+            .and_modify(|count| *count += 10)
+            .or_default();
+        assert_eq!(counts["cat"], 11);
+        assert_eq!(counts["dog"], 0);
+
+        match counts.entry("hat") {
+            Entry::Occupied(mut entry) => {
+                assert_eq!(entry.key(), &"hat");
+                assert_eq!(entry.insert(7), 1);
+                assert_eq!(entry.remove(), 7);
+            }
+            Entry::Vacant(_) => panic!("hat is in the map"),
+        }
+        match counts.entry("hat") {
+            Entry::Vacant(entry) => assert_eq!(*entry.insert(8), 8),
+            Entry::Occupied(_) => panic!("hat was removed"),
+        }
+        assert_eq!(counts.len(), 6);
+    }
+
+    #[test]
+    fn iterators() {
+        let mut map = [(3, 'c'), (1, 'a'), (2, 'b')]
+            .into_iter()
+            .collect::<BstMap<_, _>>();
+
+        assert!(map.iter().eq([(&1, &'a'), (&2, &'b'), (&3, &'c')]));
+        assert!(map.keys().copied().eq([1, 2, 3]));
+        assert!(map.values().copied().eq(['a', 'b', 'c']));
+        assert_eq!(map.iter().len(), 3);
+
+        for value in map.values_mut() {
+            *value = value.to_ascii_uppercase();
+        }
+        for (key, value) in &mut map {
+            if *key == 2 {
+                *value = 'x';
+            }
+        }
+        let mut seen = Vec::new();
+        for (key, value) in &map {
+            seen.push((*key, *value));
+        }
+        assert_eq!(seen, [(1, 'A'), (2, 'x'), (3, 'C')]);
+
+        map.extend([(0, 'z'), (3, 'y')]);
+        assert!(map.into_iter().eq([(0, 'z'), (1, 'A'), (2, 'x'), (3, 'y')]));
+    }
+
+    /// Random inserts, updates and removals checked against `BTreeMap`.
+    #[test]
+    fn random_against_btree_map() {
+        for seed in 0..20 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut map = BstMap::new();
+            let mut expected = BTreeMap::new();
+
+            for step in 0..300 {
+                let key = rng.gen_range(0..60);
+                match rng.gen_range(0..4) {
+                    0 | 1 => assert_eq!(map.insert(key, step), expected.insert(key, step)),
+                    2 => assert_eq!(map.remove(&key), expected.remove(&key)),
+                    _ => {
+                        *map.entry(key).or_insert(0) += step;
+                        *expected.entry(key).or_insert(0) += step;
+                    }
+                }
+
+                assert_eq!(map.len(), expected.len());
+                assert_eq!(map.get(&key), expected.get(&key));
+            }
+
+            assert!(map.iter().eq(expected.iter()));
+            assert_eq!(map.first_key_value(), expected.first_key_value());
+            assert_eq!(map.last_key_value(), expected.last_key_value());
+            assert!(map.into_iter().eq(expected));
+        }
+    }
+}
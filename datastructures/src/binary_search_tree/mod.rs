@@ -1,4 +1,10 @@
+mod avl;
 mod iterator;
+pub mod map;
+
+pub use avl::AvlTree;
+pub use iterator::{BSTIterator, BSTRange, IntoIter, IterMut, LevelOrder, PostOrder, PreOrder};
+pub use map::BstMap;
 
 #[derive(Debug)]
 pub struct BinarySearchTree<T> {
@@ -8,10 +14,16 @@ pub struct BinarySearchTree<T> {
 #[derive(Debug)]
 pub struct Node<T> {
     data: T,
+    /// Number of values in the subtree rooted here, itself included.
+    size: usize,
     left: Option<Box<Node<T>>>,
     right: Option<Box<Node<T>>>,
 }
 
+fn size<T>(node: &Option<Box<Node<T>>>) -> usize {
+    node.as_ref().map_or(0, |node| node.size)
+}
+
 impl<T> BinarySearchTree<T>
 where
     T: PartialOrd,
@@ -23,6 +35,7 @@ where
     pub fn insert(&mut self, data: T) {
         let new_node = Box::new(Node {
             data,
+            size: 1,
             left: None,
             right: None,
         });
@@ -30,6 +43,7 @@ where
         let mut node = &mut self.root;
 
         while let Some(next) = node {
+            next.size += 1;
             if new_node.data < next.data {
                 node = &mut next.left;
             } else {
@@ -41,26 +55,214 @@ where
     }
 
     pub fn contains(&self, data: T) -> bool {
+        self.find(&data).is_some()
+    }
+
+    fn find(&self, data: &T) -> Option<&Node<T>> {
+        let mut node = &self.root;
+
+        while let Some(next) = node {
+            if *data == next.data {
+                return Some(next);
+            } else if *data < next.data {
+                node = &next.left;
+            } else {
+                node = &next.right;
+            }
+        }
+
+        None
+    }
+
+    /// Removes one occurrence of `data`, returns whether there was one.
+    pub fn remove(&mut self, data: T) -> bool {
+        if self.find(&data).is_none() {
+            return false;
+        }
+
+        // The value is there, every node on the way loses one descendant
+        let mut node = &mut self.root;
+        while data != node.as_ref().unwrap().data {
+            let next = node.as_mut().unwrap();
+            next.size -= 1;
+            if data < next.data {
+                node = &mut next.left;
+            } else {
+                node = &mut next.right;
+            }
+        }
+
+        unlink(node);
+        true
+    }
+
+    pub fn len(&self) -> usize {
+        size(&self.root)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    pub fn min(&self) -> Option<&T> {
+        let mut node = self.root.as_ref()?;
+        while let Some(left) = &node.left {
+            node = left;
+        }
+        Some(&node.data)
+    }
+
+    pub fn max(&self) -> Option<&T> {
+        let mut node = self.root.as_ref()?;
+        while let Some(right) = &node.right {
+            node = right;
+        }
+        Some(&node.data)
+    }
+
+    /// The largest value less than or equal to `data`.
+    pub fn floor(&self, data: T) -> Option<&T> {
+        let mut node = &self.root;
+        let mut floor = None;
+
+        while let Some(next) = node {
+            if next.data <= data {
+                floor = Some(&next.data);
+                node = &next.right;
+            } else {
+                node = &next.left;
+            }
+        }
+
+        floor
+    }
+
+    /// The smallest value greater than or equal to `data`.
+    pub fn ceiling(&self, data: T) -> Option<&T> {
+        let mut node = &self.root;
+        let mut ceiling = None;
+
+        while let Some(next) = node {
+            if next.data >= data {
+                ceiling = Some(&next.data);
+                node = &next.left;
+            } else {
+                node = &next.right;
+            }
+        }
+
+        ceiling
+    }
+
+    /// Number of values strictly less than `data`.
+    pub fn rank(&self, data: T) -> usize {
+        let mut node = &self.root;
+        let mut rank = 0;
+
+        while let Some(next) = node {
+            if next.data < data {
+                rank += size(&next.left) + 1;
+                node = &next.right;
+            } else {
+                node = &next.left;
+            }
+        }
+
+        rank
+    }
+
+    /// The value with `index` smaller values before it, the inverse of `rank`.
+    pub fn select(&self, mut index: usize) -> Option<&T> {
         let mut node = &self.root;
 
         while let Some(next) = node {
-            if data == next.data {
-                return true;
-            } else if data < next.data {
+            let left = size(&next.left);
+            if index < left {
                 node = &next.left;
+            } else if index == left {
+                return Some(&next.data);
             } else {
+                index -= left + 1;
                 node = &next.right;
             }
         }
 
-        false
+        None
+    }
+}
+
+/// The nodes of `BinarySearchTree` and `BstMap`, for the removal both share.
+trait TreeNode: Sized {
+    fn left(&mut self) -> &mut Option<Box<Self>>;
+
+    fn right(&mut self) -> &mut Option<Box<Self>>;
+
+    /// Called on each node a descendant is detached from.
+    fn shrink(&mut self) {}
+
+    /// Called on a node given new children.
+    fn adopt(&mut self) {}
+}
+
+impl<T> TreeNode for Node<T> {
+    fn left(&mut self) -> &mut Option<Box<Self>> {
+        &mut self.left
+    }
+
+    fn right(&mut self) -> &mut Option<Box<Self>> {
+        &mut self.right
+    }
+
+    fn shrink(&mut self) {
+        self.size -= 1;
+    }
+
+    fn adopt(&mut self) {
+        self.size = 1 + size(&self.left) + size(&self.right);
+    }
+}
+
+/// Detaches the node of a non-empty link, its successor takes its place.
+fn unlink<N: TreeNode>(link: &mut Option<Box<N>>) -> Box<N> {
+    let mut node = link.take().unwrap();
+    *link = match (node.left().take(), node.right().take()) {
+        (None, None) => None,
+        (Some(child), None) | (None, Some(child)) => Some(child),
+        (Some(left), Some(right)) => {
+            // The successor, the smallest value on the right, takes its place
+            let mut right = Some(right);
+            let mut successor = take_min(&mut right);
+            *successor.left() = Some(left);
+            *successor.right() = right;
+            successor.adopt();
+            Some(successor)
+        }
+    };
+    node
+}
+
+/// Detaches the leftmost node of a non-empty subtree.
+fn take_min<N: TreeNode>(mut link: &mut Option<Box<N>>) -> Box<N> {
+    while link.as_mut().unwrap().left().is_some() {
+        let next = link.as_mut().unwrap();
+        next.shrink();
+        link = next.left();
     }
+
+    let mut min = link.take().unwrap();
+    *link = min.right().take();
+    min
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use std::collections::BTreeSet;
+    use std::ops::Bound;
+
     #[test]
     fn insert() {
         let mut bst = BinarySearchTree::new();
@@ -106,4 +308,176 @@ mod tests {
         assert_eq!(iter.next(), Some(&8));
         assert_eq!(iter.next(), None);
     }
+
+    /// ```text
+    ///         5
+    ///       /   \
+    ///      3     8
+    ///     /     / \
+    ///    1     7   9
+    ///     \   /
+    ///      2 6
+    /// ```
+    fn asymmetric() -> BinarySearchTree<i32> {
+        let mut bst = BinarySearchTree::new();
+        for data in [5, 3, 8, 1, 7, 9, 2, 6] {
+            bst.insert(data);
+        }
+        bst
+    }
+
+    #[test]
+    fn traversals() {
+        let bst = asymmetric();
+
+        assert!(bst.pre_order().copied().eq([5, 3, 1, 2, 8, 7, 6, 9]));
+        assert!(bst.post_order().copied().eq([2, 1, 3, 6, 7, 9, 8, 5]));
+        assert!(bst.level_order().copied().eq([5, 3, 8, 1, 7, 9, 2, 6]));
+
+        let empty = BinarySearchTree::<i32>::new();
+        assert_eq!(empty.pre_order().next(), None);
+        assert_eq!(empty.post_order().next(), None);
+        assert_eq!(empty.level_order().next(), None);
+    }
+
+    #[test]
+    fn double_ended_iter() {
+        let bst = asymmetric();
+
+        assert!(bst.iter().rev().copied().eq([9, 8, 7, 6, 5, 3, 2, 1]));
+        assert_eq!(bst.iter().len(), 8);
+
+        // Both ends meet in the middle without yielding a value twice
+        let mut iter = bst.iter();
+        assert_eq!(iter.next(), Some(&1));
+        assert_eq!(iter.next_back(), Some(&9));
+        assert_eq!(iter.next_back(), Some(&8));
+        assert_eq!(iter.next(), Some(&2));
+        assert_eq!(iter.len(), 4);
+        assert!(iter.copied().eq([3, 5, 6, 7]));
+
+        // A chain with no left children
+        let mut chain = BinarySearchTree::new();
+        for data in 0..5 {
+            chain.insert(data);
+        }
+        let mut iter = chain.iter();
+        assert_eq!(iter.next_back(), Some(&4));
+        assert_eq!(iter.next(), Some(&0));
+        assert!(iter.rev().copied().eq([3, 2, 1]));
+    }
+
+    #[test]
+    fn into_iter_and_iter_mut() {
+        let mut bst = asymmetric();
+
+        for data in bst.iter_mut() {
+            *data *= 10;
+        }
+        for data in &mut bst {
+            *data += 1;
+        }
+
+        let mut seen = Vec::new();
+        for data in &bst {
+            seen.push(*data);
+        }
+        assert_eq!(seen, [11, 21, 31, 51, 61, 71, 81, 91]);
+        assert!(bst.into_iter().eq([11, 21, 31, 51, 61, 71, 81, 91]));
+    }
+
+    #[test]
+    fn remove() {
+        let mut bst = BinarySearchTree::new();
+
+        for data in [5, 3, 7, 2, 4, 6, 8, 7] {
+            bst.insert(data);
+        }
+
+        // Leaf, one child, two children and the root
+        assert!(bst.remove(2));
+        assert!(bst.remove(3));
+        assert!(bst.remove(7));
+        assert!(bst.remove(5));
+        assert!(!bst.remove(5));
+        assert!(!bst.remove(9));
+
+        assert!(bst.iter().copied().eq([4, 6, 7, 8]));
+        assert_eq!(bst.len(), 4);
+
+        for data in [4, 6, 7, 8] {
+            assert!(bst.remove(data));
+        }
+        assert!(bst.is_empty());
+        assert_eq!(bst.min(), None);
+    }
+
+    #[test]
+    fn queries() {
+        let mut bst = BinarySearchTree::new();
+
+        for data in [50, 30, 70, 20, 40, 60, 80] {
+            bst.insert(data);
+        }
+
+        assert_eq!(bst.min(), Some(&20));
+        assert_eq!(bst.max(), Some(&80));
+        assert_eq!(bst.floor(45), Some(&40));
+        assert_eq!(bst.floor(40), Some(&40));
+        assert_eq!(bst.floor(10), None);
+        assert_eq!(bst.ceiling(45), Some(&50));
+        assert_eq!(bst.ceiling(90), None);
+        assert_eq!(bst.rank(20), 0);
+        assert_eq!(bst.rank(55), 4);
+        assert_eq!(bst.select(4), Some(&60));
+        assert_eq!(bst.select(7), None);
+        assert!(bst.range(30..60).copied().eq([30, 40, 50]));
+        assert!(bst.range(..=30).copied().eq([20, 30]));
+        assert!(bst.range(75..).copied().eq([80]));
+    }
+
+    /// Random inserts and removals checked against `BTreeSet`.
+    #[test]
+    fn random_against_btree_set() {
+        for seed in 0..20 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut bst = BinarySearchTree::new();
+            let mut set = BTreeSet::new();
+
+            for _ in 0..300 {
+                let data = rng.gen_range(0..100);
+                if rng.gen_bool(0.6) {
+                    // The tree keeps duplicates, the set does not
+                    if set.insert(data) {
+                        bst.insert(data);
+                    }
+                } else {
+                    assert_eq!(bst.remove(data), set.remove(&data));
+                }
+
+                let probe = rng.gen_range(-10..110);
+                assert_eq!(bst.len(), set.len());
+                assert_eq!(bst.contains(probe), set.contains(&probe));
+                assert_eq!(bst.min(), set.first());
+                assert_eq!(bst.max(), set.last());
+                assert_eq!(bst.floor(probe), set.range(..=probe).next_back());
+                assert_eq!(bst.ceiling(probe), set.range(probe..).next());
+                assert_eq!(bst.rank(probe), set.range(..probe).count());
+                let index = rng.gen_range(0..=set.len());
+                assert_eq!(bst.select(index), set.iter().nth(index));
+            }
+
+            assert!(bst.iter().eq(set.iter()));
+            for _ in 0..50 {
+                let a = rng.gen_range(-10..110);
+                let b = rng.gen_range(a..110);
+                assert!(bst.range(a..b).eq(set.range(a..b)));
+                assert!(bst.range(a..=b).eq(set.range(a..=b)));
+                assert!(bst.range(..b).eq(set.range(..b)));
+                assert!(bst.range(a..).eq(set.range(a..)));
+                let bounds = (Bound::Excluded(a), Bound::Included(b));
+                assert!(bst.range(bounds).eq(set.range(bounds)));
+            }
+        }
+    }
 }
@@ -0,0 +1,125 @@
+use std::hash::BuildHasher;
+use std::mem;
+
+use super::HashMap;
+
+/// A key of a `HashMap` that may or may not be in it, from `HashMap::entry`.
+pub enum Entry<'a, K, V, S> {
+    Vacant(VacantEntry<'a, K, V, S>),
+    Occupied(OccupiedEntry<'a, K, V, S>),
+}
+
+pub struct VacantEntry<'a, K, V, S> {
+    pub(super) map: &'a mut HashMap<K, V, S>,
+    pub(super) hash: u64,
+    pub(super) key: K,
+}
+
+pub struct OccupiedEntry<'a, K, V, S> {
+    pub(super) map: &'a mut HashMap<K, V, S>,
+    /// The slot holding the key.
+    pub(super) index: usize,
+}
+
+impl<'a, K, V, S> Entry<'a, K, V, S>
+where
+    S: BuildHasher,
+{
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        self.or_insert_with(|| default)
+    }
+
+    pub fn or_insert_with<F>(self, default: F) -> &'a mut V
+    where
+        F: FnOnce() -> V,
+    {
+        self.or_insert_with_key(|_| default())
+    }
+
+    pub fn or_insert_with_key<F>(self, default: F) -> &'a mut V
+    where
+        F: FnOnce(&K) -> V,
+    {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let value = default(entry.key());
+                entry.insert(value)
+            }
+        }
+    }
+
+    pub fn or_default(self) -> &'a mut V
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+
+    /// Updates the value in place when the key is already in the map.
+    pub fn and_modify<F>(mut self, f: F) -> Self
+    where
+        F: FnOnce(&mut V),
+    {
+        if let Entry::Occupied(entry) = &mut self {
+            f(entry.get_mut());
+        }
+        self
+    }
+
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+}
+
+impl<'a, K, V, S> VacantEntry<'a, K, V, S> {
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    pub fn into_key(self) -> K {
+        self.key
+    }
+
+    /// Inserts the value, growing the map first when it is at capacity.
+    pub fn insert(self, value: V) -> &'a mut V {
+        self.map.grow();
+        let index = self.map.insert_new(self.hash, self.key, value);
+        &mut self.map.slots[index].as_mut().unwrap().value
+    }
+}
+
+impl<'a, K, V, S> OccupiedEntry<'a, K, V, S> {
+    pub fn key(&self) -> &K {
+        &self.map.slots[self.index].as_ref().unwrap().key
+    }
+
+    pub fn get(&self) -> &V {
+        &self.map.slots[self.index].as_ref().unwrap().value
+    }
+
+    pub fn get_mut(&mut self) -> &mut V {
+        &mut self.map.slots[self.index].as_mut().unwrap().value
+    }
+
+    pub fn into_mut(self) -> &'a mut V {
+        &mut self.map.slots[self.index].as_mut().unwrap().value
+    }
+
+    /// Replaces the value, returning the old one.
+    pub fn insert(&mut self, value: V) -> V {
+        mem::replace(self.get_mut(), value)
+    }
+
+    pub fn remove(self) -> V {
+        self.remove_entry().1
+    }
+
+    pub fn remove_entry(self) -> (K, V) {
+        let slot = self.map.remove_at(self.index);
+        (slot.key, slot.value)
+    }
+}
@@ -0,0 +1,608 @@
+use std::borrow::Borrow;
+use std::collections::hash_map::RandomState;
+use std::fmt::{self, Debug};
+use std::hash::{BuildHasher, Hash};
+use std::mem;
+use std::ops::Index;
+
+mod entry;
+mod iterator;
+pub mod set;
+
+pub use entry::{Entry, OccupiedEntry, VacantEntry};
+pub use iterator::{IntoIter, Iter, IterMut, Keys, Values, ValuesMut};
+pub use set::HashSet;
+
+/// A hash map with open addressing and Robin Hood probing: a key being
+/// inserted takes the slot of any key closer to its own ideal slot, which
+/// keeps probe sequences short. Removal shifts the following keys back
+/// instead of leaving tombstones. Keys are hashed by `S`.
+#[derive(Clone)]
+pub struct HashMap<K, V, S = RandomState> {
+    /// A power of two number of slots, never full.
+    slots: Vec<Option<Slot<K, V>>>,
+    length: usize,
+    hasher: S,
+}
+
+#[derive(Clone)]
+struct Slot<K, V> {
+    hash: u64,
+    key: K,
+    value: V,
+}
+
+const MIN_SLOTS: usize = 8;
+
+/// The table grows once more than 7/8 of its slots would be taken, so there
+/// is always a free slot for probing to stop at.
+const MAX_LOAD: (usize, usize) = (7, 8);
+
+impl<K, V> HashMap<K, V, RandomState> {
+    pub fn new() -> Self {
+        Self::with_hasher(RandomState::new())
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_and_hasher(capacity, RandomState::new())
+    }
+}
+
+impl<K, V, S> HashMap<K, V, S> {
+    pub fn with_hasher(hasher: S) -> Self {
+        Self::with_capacity_and_hasher(0, hasher)
+    }
+
+    /// A map that holds `capacity` entries before it grows.
+    pub fn with_capacity_and_hasher(capacity: usize, hasher: S) -> Self {
+        Self {
+            slots: Self::allocate_slots(Self::slots_for(capacity)),
+            length: 0,
+            hasher,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.length
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    /// Number of entries the map holds before it grows.
+    pub fn capacity(&self) -> usize {
+        self.slots.len() * MAX_LOAD.0 / MAX_LOAD.1
+    }
+
+    pub fn hasher(&self) -> &S {
+        &self.hasher
+    }
+
+    /// Removes every entry, keeping the allocated slots.
+    pub fn clear(&mut self) {
+        self.slots.iter_mut().for_each(|slot| *slot = None);
+        self.length = 0;
+    }
+
+    /// Makes room for `additional` more entries without growing in between.
+    pub fn reserve(&mut self, additional: usize) {
+        let needed = self
+            .length
+            .checked_add(additional)
+            .expect("capacity overflow");
+        if needed > self.capacity() {
+            self.resize(Self::slots_for(needed).max(self.slots.len() * 2));
+        }
+    }
+
+    fn mask(&self) -> usize {
+        self.slots.len() - 1
+    }
+
+    /// How far the slot at `index` is from the ideal slot for `hash`.
+    fn distance(mask: usize, hash: u64, index: usize) -> usize {
+        index.wrapping_sub(hash as usize) & mask
+    }
+
+    fn slots_for(capacity: usize) -> usize {
+        let slots = capacity
+            .checked_mul(MAX_LOAD.1)
+            .expect("capacity overflow")
+            .div_ceil(MAX_LOAD.0);
+        slots.next_power_of_two().max(MIN_SLOTS)
+    }
+
+    fn allocate_slots(count: usize) -> Vec<Option<Slot<K, V>>> {
+        std::iter::repeat_with(|| None).take(count).collect()
+    }
+
+    /// Places an entry whose key is not in the map yet, returning its slot.
+    /// There must be a free slot.
+    fn insert_new(&mut self, hash: u64, key: K, value: V) -> usize {
+        let mask = self.mask();
+        let mut carried = Slot { hash, key, value };
+        let mut index = hash as usize & mask;
+        let mut distance = 0;
+        let mut placed = None;
+
+        loop {
+            match &mut self.slots[index] {
+                None => {
+                    self.slots[index] = Some(carried);
+                    self.length += 1;
+                    return placed.unwrap_or(index);
+                }
+                Some(resident) => {
+                    // The entry further from home keeps the slot, the other
+                    // one carries on probing
+                    let resident_distance = Self::distance(mask, resident.hash, index);
+                    if resident_distance < distance {
+                        mem::swap(resident, &mut carried);
+                        placed.get_or_insert(index);
+                        distance = resident_distance;
+                    }
+                }
+            }
+            index = (index + 1) & mask;
+            distance += 1;
+        }
+    }
+
+    /// Takes the entry out of slot `index`, shifting the entries after it
+    /// back by one until one is in its ideal slot or a slot is free.
+    fn remove_at(&mut self, index: usize) -> Slot<K, V> {
+        let mask = self.mask();
+        let removed = self.slots[index].take().unwrap();
+        let mut hole = index;
+
+        loop {
+            let next = (hole + 1) & mask;
+            match &self.slots[next] {
+                Some(slot) if Self::distance(mask, slot.hash, next) > 0 => {
+                    self.slots[hole] = self.slots[next].take();
+                    hole = next;
+                }
+                _ => break,
+            }
+        }
+
+        self.length -= 1;
+        removed
+    }
+
+    /// Makes room for one more entry.
+    fn grow(&mut self) {
+        if self.length == self.capacity() {
+            self.resize(self.slots.len() * 2);
+        }
+    }
+
+    fn resize(&mut self, count: usize) {
+        let slots = mem::replace(&mut self.slots, Self::allocate_slots(count));
+        self.length = 0;
+        for slot in slots.into_iter().flatten() {
+            // The stored hashes spare hashing the keys again
+            self.insert_new(slot.hash, slot.key, slot.value);
+        }
+    }
+}
+
+impl<K, V, S> HashMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    /// Returns the value the key had before, the key itself is not updated.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        match self.entry(key) {
+            Entry::Occupied(mut entry) => Some(entry.insert(value)),
+            Entry::Vacant(entry) => {
+                entry.insert(value);
+                None
+            }
+        }
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get_key_value(key).map(|(_, value)| value)
+    }
+
+    pub fn get_key_value<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let slot = self.slots[self.find(key)?].as_ref().unwrap();
+        Some((&slot.key, &slot.value))
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let index = self.find(key)?;
+        Some(&mut self.slots[index].as_mut().unwrap().value)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.find(key).is_some()
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.remove_entry(key).map(|(_, value)| value)
+    }
+
+    pub fn remove_entry<Q>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let slot = self.remove_at(self.find(key)?);
+        Some((slot.key, slot.value))
+    }
+
+    /// The entry for `key`, to look it up and insert or update it with one
+    /// hash.
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V, S> {
+        let hash = self.hasher.hash_one(&key);
+        match self.probe(hash, &key) {
+            Some(index) => Entry::Occupied(OccupiedEntry { map: self, index }),
+            None => Entry::Vacant(VacantEntry {
+                map: self,
+                hash,
+                key,
+            }),
+        }
+    }
+
+    fn find<Q>(&self, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.probe(self.hasher.hash_one(key), key)
+    }
+
+    /// Slot holding `key`. The probe stops at a free slot, or at an entry
+    /// closer to home than `key` would be, as Robin Hood insertion would
+    /// have put `key` there.
+    fn probe<Q>(&self, hash: u64, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        let mask = self.mask();
+        let mut index = hash as usize & mask;
+        let mut distance = 0;
+
+        loop {
+            let slot = self.slots[index].as_ref()?;
+            if Self::distance(mask, slot.hash, index) < distance {
+                return None;
+            }
+            if slot.hash == hash && slot.key.borrow() == key {
+                return Some(index);
+            }
+            index = (index + 1) & mask;
+            distance += 1;
+        }
+    }
+}
+
+impl<K, V, S> Default for HashMap<K, V, S>
+where
+    S: Default,
+{
+    fn default() -> Self {
+        Self::with_hasher(S::default())
+    }
+}
+
+impl<K, V, S> Debug for HashMap<K, V, S>
+where
+    K: Debug,
+    V: Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K, V, S> PartialEq for HashMap<K, V, S>
+where
+    K: Hash + Eq,
+    V: PartialEq,
+    S: BuildHasher,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.length == other.length
+            && self
+                .iter()
+                .all(|(key, value)| other.get(key) == Some(value))
+    }
+}
+
+impl<K, V, S> Eq for HashMap<K, V, S>
+where
+    K: Hash + Eq,
+    V: Eq,
+    S: BuildHasher,
+{
+}
+
+impl<K, Q, V, S> Index<&Q> for HashMap<K, V, S>
+where
+    K: Borrow<Q> + Hash + Eq,
+    Q: Hash + Eq + ?Sized,
+    S: BuildHasher,
+{
+    type Output = V;
+
+    /// Panics when the key is not in the map.
+    fn index(&self, key: &Q) -> &V {
+        self.get(key).expect("no entry found for key")
+    }
+}
+
+impl<K, V, S> FromIterator<(K, V)> for HashMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher + Default,
+{
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut map = Self::default();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<K, V, S> Extend<(K, V)> for HashMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use std::hash::{BuildHasherDefault, Hasher};
+
+    /// Hashes every key to the same few values, so keys collide and probe.
+    #[derive(Default)]
+    struct Colliding(u64);
+
+    impl Hasher for Colliding {
+        fn finish(&self) -> u64 {
+            self.0 % 3
+        }
+
+        fn write(&mut self, bytes: &[u8]) {
+            for &byte in bytes {
+                self.0 = self.0.wrapping_add(u64::from(byte));
+            }
+        }
+    }
+
+    type CollidingMap<K, V> = HashMap<K, V, BuildHasherDefault<Colliding>>;
+
+    /// Hashes a `u64` key to itself, so tests pick the ideal slot of a key.
+    #[derive(Default)]
+    struct Identity(u64);
+
+    impl Hasher for Identity {
+        fn finish(&self) -> u64 {
+            self.0
+        }
+
+        fn write(&mut self, _: &[u8]) {
+            unreachable!("only u64 keys are hashed")
+        }
+
+        fn write_u64(&mut self, n: u64) {
+            self.0 = n;
+        }
+    }
+
+    type IdentityMap<V> = HashMap<u64, V, BuildHasherDefault<Identity>>;
+
+    fn keys_by_slot<V>(map: &IdentityMap<V>) -> Vec<Option<u64>> {
+        map.slots
+            .iter()
+            .map(|slot| slot.as_ref().map(|slot| slot.key))
+            .collect()
+    }
+
+    #[test]
+    fn probes_wrap_past_the_last_slot() {
+        let mut map = IdentityMap::default();
+        // 7, 15 and 23 all belong in the last of the 8 slots, 0 in the first
+        for key in [7, 15, 23, 0] {
+            map.insert(key, ());
+        }
+        assert_eq!(
+            keys_by_slot(&map),
+            [Some(15), Some(23), Some(0), None, None, None, None, Some(7)]
+        );
+
+        // Removing shifts the following keys back across the end
+        map.remove(&7);
+        assert_eq!(
+            keys_by_slot(&map),
+            [Some(23), Some(0), None, None, None, None, None, Some(15)]
+        );
+        map.remove(&15);
+        assert_eq!(
+            keys_by_slot(&map),
+            [Some(0), None, None, None, None, None, None, Some(23)]
+        );
+        assert!(map.contains_key(&0) && map.contains_key(&23));
+    }
+
+    #[test]
+    fn resizes_with_colliding_keys() {
+        let mut map = CollidingMap::default();
+        for i in 0..100 {
+            map.insert(i, i);
+            assert!((0..=i).all(|key| map.get(&key) == Some(&key)), "{}", i);
+        }
+        assert_eq!(map.slots.len(), 128);
+
+        for i in (0..100).rev() {
+            assert_eq!(map.remove(&i), Some(i));
+        }
+        assert!(map.slots.iter().all(Option::is_none));
+    }
+
+    #[test]
+    fn reserved_capacity_is_used_before_growing() {
+        let mut map = HashMap::with_capacity(100);
+        let slots = map.slots.len();
+        map.extend((0..100).map(|i| (i, i)));
+        assert_eq!(map.slots.len(), slots);
+
+        let mut map = HashMap::new();
+        map.extend((0..7).map(|i| (i, i)));
+        map.reserve(0);
+        assert_eq!(map.slots.len(), 8);
+
+        map.reserve(50);
+        let slots = map.slots.len();
+        assert!(map.capacity() >= 57);
+        map.extend((7..57).map(|i| (i, i)));
+        assert_eq!(map.slots.len(), slots);
+    }
+
+    #[test]
+    fn grows_past_the_load_factor() {
+        let mut map = HashMap::new();
+        assert_eq!(map.capacity(), 7);
+
+        for i in 0..1000 {
+            map.insert(i, i * 2);
+            assert!(map.len() <= map.capacity());
+        }
+        assert_eq!(map.len(), 1000);
+        assert!((0..1000).all(|i| map.get(&i) == Some(&(i * 2))));
+
+        let map = HashMap::<i32, i32>::with_capacity(100);
+        assert!(map.capacity() >= 100);
+    }
+
+    #[test]
+    fn colliding_keys() {
+        let mut map = CollidingMap::default();
+        for i in 0..50 {
+            map.insert(i, i);
+        }
+        for i in (0..50).step_by(2) {
+            assert_eq!(map.remove(&i), Some(i));
+        }
+        assert_eq!(map.remove(&0), None);
+
+        // Backward shifting kept every remaining key reachable
+        for i in 0..50 {
+            assert_eq!(map.get(&i), (i % 2 == 1).then_some(&i));
+        }
+        assert_eq!(map.len(), 25);
+    }
+
+    #[test]
+    fn iterators() {
+        let mut map = (0..10).map(|i| (i, i * 10)).collect::<HashMap<_, _>>();
+
+        let mut keys = map.keys().copied().collect::<Vec<_>>();
+        keys.sort();
+        assert_eq!(keys, (0..10).collect::<Vec<_>>());
+        assert_eq!(map.values().sum::<i32>(), 450);
+        assert_eq!(map.iter().len(), 10);
+
+        for value in map.values_mut() {
+            *value += 1;
+        }
+        for (key, value) in &mut map {
+            *value -= key * 10;
+        }
+        assert!((&map).into_iter().all(|(_, value)| *value == 1));
+
+        let mut entries = map.into_iter().collect::<Vec<_>>();
+        entries.sort();
+        assert_eq!(entries, (0..10).map(|i| (i, 1)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn clear_and_eq() {
+        let mut map = (0..20).map(|i| (i, i)).collect::<HashMap<_, _>>();
+        let other = (0..20).rev().map(|i| (i, i)).collect::<HashMap<_, _>>();
+        assert_eq!(map, other);
+
+        map.insert(0, 1);
+        assert_ne!(map, other);
+
+        let capacity = map.capacity();
+        map.clear();
+        assert!(map.is_empty());
+        assert_eq!(map.capacity(), capacity);
+        assert_eq!(map.get(&1), None);
+        assert_eq!(format!("{:?}", map), "{}");
+    }
+
+    /// Random inserts, updates and removals checked against std's map, with
+    /// both a real and a colliding hasher.
+    #[test]
+    fn random_against_std() {
+        fn run<S: BuildHasher + Default>(seed: u64) {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut map = HashMap::<u32, u32, S>::default();
+            let mut expected = std::collections::HashMap::new();
+
+            for step in 0..500 {
+                let key = rng.gen_range(0..80);
+                match rng.gen_range(0..4) {
+                    0 | 1 => assert_eq!(map.insert(key, step), expected.insert(key, step)),
+                    2 => assert_eq!(map.remove(&key), expected.remove(&key)),
+                    _ => {
+                        *map.entry(key).or_insert(0) += step;
+                        *expected.entry(key).or_insert(0) += step;
+                    }
+                }
+                assert_eq!(map.len(), expected.len());
+                assert_eq!(map.get(&key), expected.get(&key));
+            }
+
+            assert!(expected
+                .iter()
+                .all(|(key, value)| map.get(key) == Some(value)));
+            assert_eq!(map.iter().count(), expected.len());
+        }
+
+        for seed in 0..10 {
+            run::<RandomState>(seed);
+            run::<BuildHasherDefault<Colliding>>(seed);
+        }
+    }
+}
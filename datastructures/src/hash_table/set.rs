@@ -0,0 +1,261 @@
+use std::borrow::Borrow;
+use std::collections::hash_map::RandomState;
+use std::fmt::{self, Debug};
+use std::hash::{BuildHasher, Hash};
+
+use super::{HashMap, IntoIter as MapIntoIter, Keys};
+
+/// A set of values, stored as the keys of a `HashMap` with no values.
+#[derive(Clone)]
+pub struct HashSet<T, S = RandomState> {
+    map: HashMap<T, (), S>,
+}
+
+impl<T> HashSet<T, RandomState> {
+    pub fn new() -> Self {
+        Self {
+            map: HashMap::new(),
+        }
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            map: HashMap::with_capacity(capacity),
+        }
+    }
+}
+
+impl<T, S> HashSet<T, S> {
+    pub fn with_hasher(hasher: S) -> Self {
+        Self {
+            map: HashMap::with_hasher(hasher),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.map.capacity()
+    }
+
+    pub fn clear(&mut self) {
+        self.map.clear();
+    }
+
+    pub fn reserve(&mut self, additional: usize) {
+        self.map.reserve(additional);
+    }
+
+    pub fn iter(&self) -> Keys<'_, T, ()> {
+        self.map.keys()
+    }
+}
+
+impl<T, S> HashSet<T, S>
+where
+    T: Hash + Eq,
+    S: BuildHasher,
+{
+    /// Returns whether the value was new, an equal value already in the set
+    /// is kept.
+    pub fn insert(&mut self, value: T) -> bool {
+        self.map.insert(value, ()).is_none()
+    }
+
+    pub fn contains<Q>(&self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.map.contains_key(value)
+    }
+
+    /// The value in the set equal to the given one.
+    pub fn get<Q>(&self, value: &Q) -> Option<&T>
+    where
+        T: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.map.get_key_value(value).map(|(key, _)| key)
+    }
+
+    pub fn remove<Q>(&mut self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.map.remove(value).is_some()
+    }
+
+    /// Removes and returns the value in the set equal to the given one.
+    pub fn take<Q>(&mut self, value: &Q) -> Option<T>
+    where
+        T: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.map.remove_entry(value).map(|(key, _)| key)
+    }
+
+    pub fn union<'a>(&'a self, other: &'a Self) -> impl Iterator<Item = &'a T> {
+        self.iter().chain(other.difference(self))
+    }
+
+    pub fn intersection<'a>(&'a self, other: &'a Self) -> impl Iterator<Item = &'a T> {
+        self.iter().filter(|value| other.contains(value))
+    }
+
+    /// The values in `self` that are not in `other`.
+    pub fn difference<'a>(&'a self, other: &'a Self) -> impl Iterator<Item = &'a T> {
+        self.iter().filter(|value| !other.contains(value))
+    }
+
+    pub fn is_subset(&self, other: &Self) -> bool {
+        self.len() <= other.len() && self.iter().all(|value| other.contains(value))
+    }
+}
+
+impl<T, S> Default for HashSet<T, S>
+where
+    S: Default,
+{
+    fn default() -> Self {
+        Self::with_hasher(S::default())
+    }
+}
+
+impl<T, S> Debug for HashSet<T, S>
+where
+    T: Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<T, S> PartialEq for HashSet<T, S>
+where
+    T: Hash + Eq,
+    S: BuildHasher,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.is_subset(other)
+    }
+}
+
+impl<T, S> Eq for HashSet<T, S>
+where
+    T: Hash + Eq,
+    S: BuildHasher,
+{
+}
+
+impl<T, S> FromIterator<T> for HashSet<T, S>
+where
+    T: Hash + Eq,
+    S: BuildHasher + Default,
+{
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut set = Self::default();
+        set.extend(iter);
+        set
+    }
+}
+
+impl<T, S> Extend<T> for HashSet<T, S>
+where
+    T: Hash + Eq,
+    S: BuildHasher,
+{
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        self.map.extend(iter.into_iter().map(|value| (value, ())));
+    }
+}
+
+/// Iterator over the values of a `HashSet`, taking them out of it.
+pub struct IntoIter<T>(MapIntoIter<T, ()>);
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(value, _)| value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<T> ExactSizeIterator for IntoIter<T> {}
+
+impl<T, S> IntoIterator for HashSet<T, S> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter(self.map.into_iter())
+    }
+}
+
+impl<'a, T, S> IntoIterator for &'a HashSet<T, S> {
+    type Item = &'a T;
+    type IntoIter = Keys<'a, T, ()>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_and_remove() {
+        let mut set = HashSet::new();
+        assert!(set.insert("a".to_string()));
+        assert!(set.insert("b".to_string()));
+        assert!(!set.insert("a".to_string()));
+        assert_eq!(set.len(), 2);
+
+        assert!(set.contains("a"));
+        assert_eq!(set.get("b").map(String::as_str), Some("b"));
+        assert!(set.remove("a"));
+        assert!(!set.remove("a"));
+        assert_eq!(set.take("b"), Some("b".to_string()));
+        assert!(set.is_empty());
+    }
+
+    #[test]
+    fn set_operations() {
+        let a: HashSet<i32> = (0..6).collect();
+        let b: HashSet<i32> = (3..9).collect();
+
+        let mut union: Vec<_> = a.union(&b).copied().collect();
+        union.sort();
+        assert_eq!(union, (0..9).collect::<Vec<_>>());
+
+        let mut intersection: Vec<_> = a.intersection(&b).copied().collect();
+        intersection.sort();
+        assert_eq!(intersection, [3, 4, 5]);
+
+        let mut difference: Vec<_> = a.difference(&b).copied().collect();
+        difference.sort();
+        assert_eq!(difference, [0, 1, 2]);
+
+        assert!(!a.is_subset(&b));
+        assert!((3..6).collect::<HashSet<_>>().is_subset(&a));
+        assert_eq!(a, (0..6).rev().collect());
+        assert_ne!(a, b);
+
+        let mut values: Vec<_> = b.into_iter().collect();
+        values.sort();
+        assert_eq!(values, (3..9).collect::<Vec<_>>());
+    }
+}
@@ -1,8 +1,14 @@
 #![allow(dead_code)]
 
 pub mod binary_search_tree;
+pub mod hash_table;
 pub mod linked_list;
+pub mod queue;
+pub mod stack;
 pub mod vector;
 
+pub use hash_table::{HashMap, HashSet};
 pub use linked_list::LinkedList;
+pub use queue::{BoundedQueue, Deque, Queue};
+pub use stack::Stack;
 pub use vector::Vector;
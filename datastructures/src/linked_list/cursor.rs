@@ -0,0 +1,135 @@
+use super::{Link, LinkedList};
+
+/// A position in a `LinkedList` that moves either way and inserts or removes
+/// values around itself in O(1). Besides the values there is a "ghost"
+/// position between the back and the front, where there is no current value.
+pub struct CursorMut<'a, T> {
+    /// Index of the current value, the length of the list at the ghost.
+    index: usize,
+    current: Link<T>,
+    list: &'a mut LinkedList<T>,
+}
+
+impl<T> LinkedList<T> {
+    /// A cursor at the front, or at the ghost position when the list is empty.
+    pub fn cursor_front_mut(&mut self) -> CursorMut<'_, T> {
+        CursorMut {
+            index: 0,
+            current: self.head,
+            list: self,
+        }
+    }
+
+    /// A cursor at the back, or at the ghost position when the list is empty.
+    pub fn cursor_back_mut(&mut self) -> CursorMut<'_, T> {
+        CursorMut {
+            index: self.length.saturating_sub(1),
+            current: self.tail,
+            list: self,
+        }
+    }
+}
+
+impl<T> CursorMut<'_, T> {
+    /// `None` at the ghost position.
+    pub fn index(&self) -> Option<usize> {
+        self.current.map(|_| self.index)
+    }
+
+    pub fn current(&mut self) -> Option<&mut T> {
+        // SAFETY: the node belongs to the list the cursor borrows mutably
+        self.current
+            .map(|node| unsafe { &mut (*node.as_ptr()).value })
+    }
+
+    /// Moves to the next value, from the back to the ghost position and from
+    /// the ghost position to the front.
+    pub fn move_next(&mut self) {
+        match self.current {
+            // SAFETY: as in `current`
+            Some(node) => unsafe {
+                self.current = (*node.as_ptr()).next;
+                self.index += 1;
+            },
+            None => {
+                self.current = self.list.head;
+                self.index = 0;
+            }
+        }
+    }
+
+    /// The mirror image of `move_next`.
+    pub fn move_prev(&mut self) {
+        match self.current {
+            // SAFETY: as in `current`
+            Some(node) => unsafe {
+                self.current = (*node.as_ptr()).prev;
+                self.index = match self.current {
+                    Some(_) => self.index - 1,
+                    None => self.list.length,
+                };
+            },
+            None => {
+                self.current = self.list.tail;
+                self.index = self.list.length.saturating_sub(1);
+            }
+        }
+    }
+
+    pub fn peek_next(&mut self) -> Option<&mut T> {
+        // SAFETY: as in `current`
+        unsafe {
+            let next = match self.current {
+                Some(node) => (*node.as_ptr()).next,
+                None => self.list.head,
+            };
+            next.map(|node| &mut (*node.as_ptr()).value)
+        }
+    }
+
+    pub fn peek_prev(&mut self) -> Option<&mut T> {
+        // SAFETY: as in `current`
+        unsafe {
+            let prev = match self.current {
+                Some(node) => (*node.as_ptr()).prev,
+                None => self.list.tail,
+            };
+            prev.map(|node| &mut (*node.as_ptr()).value)
+        }
+    }
+
+    /// Inserts before the current value, at the back at the ghost position.
+    pub fn insert_before(&mut self, value: T) {
+        // SAFETY: as in `current`
+        let prev = match self.current {
+            Some(node) => unsafe { (*node.as_ptr()).prev },
+            None => self.list.tail,
+        };
+        self.list.link(prev, self.current, value);
+        self.index += 1;
+    }
+
+    /// Inserts after the current value, at the front at the ghost position.
+    pub fn insert_after(&mut self, value: T) {
+        // SAFETY: as in `current`
+        let next = match self.current {
+            Some(node) => unsafe { (*node.as_ptr()).next },
+            None => self.list.head,
+        };
+        self.list.link(self.current, next, value);
+        if self.current.is_none() {
+            self.index += 1;
+        }
+    }
+
+    /// Removes the current value and moves to the next one.
+    pub fn remove_current(&mut self) -> Option<T> {
+        let node = self.current?;
+        // SAFETY: as in `current`, no reference to the node outlives the
+        // borrow of the cursor that handed it out
+        unsafe {
+            self.current = (*node.as_ptr()).next;
+            Some(self.list.unlink(node))
+        }
+    }
+}
@@ -1,16 +1,41 @@
-use super::{LinkedList, Node};
+use std::marker::PhantomData;
 
+use super::{Link, LinkedList, Node};
 
-// The iterator type that will hold a reference to the current node
+// The iterator type that will hold pointers to the next nodes from each end
 pub struct LinkedListIter<'a, T> {
-    current: Option<&'a Box<Node<T>>>,
+    head: Link<T>,
+    tail: Link<T>,
+    remaining: usize,
+    marker: PhantomData<&'a Node<T>>,
 }
 
+pub struct IterMut<'a, T> {
+    head: Link<T>,
+    tail: Link<T>,
+    remaining: usize,
+    marker: PhantomData<&'a mut Node<T>>,
+}
+
+pub struct IntoIter<T>(LinkedList<T>);
+
 impl<T> LinkedList<T> {
     // Method to create an iterator for the LinkedList
-    pub fn iter(&self) -> LinkedListIter<T> {
+    pub fn iter(&self) -> LinkedListIter<'_, T> {
         LinkedListIter {
-            current: self.head.as_ref(),
+            head: self.head,
+            tail: self.tail,
+            remaining: self.length,
+            marker: PhantomData,
+        }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        IterMut {
+            head: self.head,
+            tail: self.tail,
+            remaining: self.length,
+            marker: PhantomData,
         }
     }
 }
@@ -19,9 +44,122 @@ impl<'a, T> Iterator for LinkedListIter<'a, T> {
     type Item = &'a T;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.current.map(|node| {
-            self.current = node.next.as_ref();
+        if self.remaining == 0 {
+            return None;
+        }
+        self.head.map(|node| {
+            // SAFETY: the list is borrowed for 'a, so its nodes stay alive
+            let node = unsafe { &*node.as_ptr() };
+            self.head = node.next;
+            self.remaining -= 1;
+            &node.value
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<T> DoubleEndedIterator for LinkedListIter<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.tail.map(|node| {
+            // SAFETY: as in `next`
+            let node = unsafe { &*node.as_ptr() };
+            self.tail = node.prev;
+            self.remaining -= 1;
             &node.value
         })
     }
-}
\ No newline at end of file
+}
+
+impl<T> ExactSizeIterator for LinkedListIter<'_, T> {}
+
+impl<'a, T> Iterator for IterMut<'a, T> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.head.map(|node| {
+            // SAFETY: the list is borrowed mutably for 'a, and `remaining`
+            // keeps the two ends from handing out the same value twice
+            let node = unsafe { &mut *node.as_ptr() };
+            self.head = node.next;
+            self.remaining -= 1;
+            &mut node.value
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<T> DoubleEndedIterator for IterMut<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.tail.map(|node| {
+            // SAFETY: as in `next`
+            let node = unsafe { &mut *node.as_ptr() };
+            self.tail = node.prev;
+            self.remaining -= 1;
+            &mut node.value
+        })
+    }
+}
+
+impl<T> ExactSizeIterator for IterMut<'_, T> {}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.pop_front()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.0.length, Some(self.0.length))
+    }
+}
+
+impl<T> DoubleEndedIterator for IntoIter<T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.pop_back()
+    }
+}
+
+impl<T> ExactSizeIterator for IntoIter<T> {}
+
+impl<T> IntoIterator for LinkedList<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter(self)
+    }
+}
+
+impl<'a, T> IntoIterator for &'a LinkedList<T> {
+    type Item = &'a T;
+    type IntoIter = LinkedListIter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut LinkedList<T> {
+    type Item = &'a mut T;
+    type IntoIter = IterMut<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
@@ -1,26 +1,44 @@
 use std::fmt::Debug;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::ptr::NonNull;
 
+mod cursor;
 mod iterator;
 
+pub use cursor::CursorMut;
+pub use iterator::{IntoIter, IterMut, LinkedListIter};
 
+/// A doubly linked list. It owns its nodes through raw pointers, so both
+/// ends can be pushed to and popped from in O(1).
 pub struct LinkedList<T> {
-    head: Option<Box<Node<T>>>,
+    head: Link<T>,
+    tail: Link<T>,
     length: usize,
+    /// The nodes are owned, dropping the list drops their values.
+    marker: PhantomData<Box<Node<T>>>,
 }
 
+type Link<T> = Option<NonNull<Node<T>>>;
+
 struct Node<T> {
     value: T,
-    next: Option<Box<Node<T>>>,
+    prev: Link<T>,
+    next: Link<T>,
 }
 
-impl<T> LinkedList<T>
-where
-    T: Default,
-{
+// Nothing else points into the nodes, so the list can cross threads like a
+// `Vec` of its values would.
+unsafe impl<T: Send> Send for LinkedList<T> {}
+unsafe impl<T: Sync> Sync for LinkedList<T> {}
+
+impl<T> LinkedList<T> {
     pub fn new() -> Self {
         Self {
             head: None,
+            tail: None,
             length: 0,
+            marker: PhantomData,
         }
     }
 
@@ -28,80 +46,281 @@ where
         self.length
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    /// Same as `push_back`.
     pub fn push(&mut self, value: T) {
-        let new_node = Box::new(Node { value, next: None });
-        let mut node = &mut self.head;
+        self.push_back(value);
+    }
+
+    /// Same as `pop_back`.
+    pub fn pop(&mut self) -> Option<T> {
+        self.pop_back()
+    }
+
+    pub fn push_front(&mut self, value: T) {
+        self.link(None, self.head, value);
+    }
 
-        while let Some(next) = node {
-            node = &mut next.next;
+    pub fn push_back(&mut self, value: T) {
+        self.link(self.tail, None, value);
+    }
+
+    pub fn pop_front(&mut self) -> Option<T> {
+        // SAFETY: the head is a node of this list
+        self.head.map(|head| unsafe { self.unlink(head) })
+    }
+
+    pub fn pop_back(&mut self) -> Option<T> {
+        // SAFETY: the tail is a node of this list
+        self.tail.map(|tail| unsafe { self.unlink(tail) })
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        // SAFETY: the node is owned by the list, borrowed as long as `self`
+        self.head.map(|node| unsafe { &(*node.as_ptr()).value })
+    }
+
+    pub fn front_mut(&mut self) -> Option<&mut T> {
+        // SAFETY: as in `front`, with `self` borrowed mutably
+        self.head.map(|node| unsafe { &mut (*node.as_ptr()).value })
+    }
+
+    pub fn back(&self) -> Option<&T> {
+        // SAFETY: as in `front`
+        self.tail.map(|node| unsafe { &(*node.as_ptr()).value })
+    }
+
+    pub fn back_mut(&mut self) -> Option<&mut T> {
+        // SAFETY: as in `front_mut`
+        self.tail.map(|node| unsafe { &mut (*node.as_ptr()).value })
+    }
+
+    pub fn get(&self, index: usize) -> Option<&T> {
+        // SAFETY: as in `front`
+        self.node(index)
+            .map(|node| unsafe { &(*node.as_ptr()).value })
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        // SAFETY: as in `front_mut`
+        self.node(index)
+            .map(|node| unsafe { &mut (*node.as_ptr()).value })
+    }
+
+    pub fn set(&mut self, index: usize, value: T) -> Option<T> {
+        self.get_mut(index)
+            .map(|current| std::mem::replace(current, value))
+    }
+
+    /// Inserts `value` so it ends up at `index`, walking from the nearer end.
+    /// Panics when `index` is past the end.
+    pub fn insert(&mut self, index: usize, value: T) {
+        if index > self.length {
+            panic!("Index out of bounds");
         }
+        let (prev, next) = match self.node(index) {
+            // SAFETY: the node belongs to this list
+            Some(next) => (unsafe { (*next.as_ptr()).prev }, Some(next)),
+            None => (self.tail, None),
+        };
+        self.link(prev, next, value);
+    }
 
-        *node = Some(new_node);
-        self.length += 1;
+    pub fn remove(&mut self, index: usize) -> Option<T> {
+        // SAFETY: the node belongs to this list
+        self.node(index).map(|node| unsafe { self.unlink(node) })
     }
 
-    pub fn pop(&mut self) -> Option<T> {
-        // If the list is empty
-        if self.head.is_none() {
-            return None;
+    /// Reverses the order of the values without moving them.
+    pub fn reverse(&mut self) {
+        let mut current = self.head;
+        while let Some(node) = current {
+            // SAFETY: each node of the list is visited once
+            unsafe {
+                let node = &mut *node.as_ptr();
+                std::mem::swap(&mut node.prev, &mut node.next);
+                current = node.prev;
+            }
         }
+        std::mem::swap(&mut self.head, &mut self.tail);
+    }
+
+    /// Moves all the values of `other` to the back of this list in O(1).
+    pub fn append(&mut self, other: &mut Self) {
+        let Some(other_head) = other.head.take() else {
+            return;
+        };
 
-        // If there's only one node in the list
-        if self.head.as_ref().unwrap().next.is_none() {
-            let node = self.head.take();
-            self.length -= 1;
-            return node.map(|node| node.value);
+        match self.tail {
+            // SAFETY: both nodes belong to lists borrowed mutably here
+            Some(tail) => unsafe {
+                (*tail.as_ptr()).next = Some(other_head);
+                (*other_head.as_ptr()).prev = Some(tail);
+            },
+            None => self.head = Some(other_head),
         }
+        self.tail = other.tail.take();
+        self.length += std::mem::replace(&mut other.length, 0);
+    }
 
-        // If there are two or more nodes in the list
-        let mut node = &mut self.head;
-        while node.as_ref().unwrap().next.as_ref().unwrap().next.is_some() {
-            node = &mut node.as_mut().unwrap().next;
+    /// Splits the list in two at `at`, returning the values from `at` on.
+    /// Panics when `at` is past the end.
+    pub fn split_off(&mut self, at: usize) -> Self {
+        assert!(at <= self.length, "split index out of bounds");
+        if at == 0 {
+            return std::mem::take(self);
         }
 
-        let last_node = node.as_mut().unwrap().next.take();
-        self.length -= 1;
-        last_node.map(|node| node.value)
+        let last = self.node(at - 1).unwrap();
+        // SAFETY: `last` is a node of this list
+        let head = unsafe { (*last.as_ptr()).next.take() };
+        let mut tail = None;
+        if let Some(head) = head {
+            // SAFETY: `head` followed `last` in this list
+            unsafe { (*head.as_ptr()).prev = None };
+            tail = self.tail.replace(last);
+        }
+
+        let split = Self {
+            head,
+            tail,
+            length: self.length - at,
+            marker: PhantomData,
+        };
+        self.length = at;
+        split
     }
 
-    pub fn get(&self, index: usize) -> Option<&T> {
+    pub fn clear(&mut self) {
+        while self.pop_front().is_some() {}
+    }
+
+    /// The node at `index`, walked to from the nearer end.
+    fn node(&self, index: usize) -> Link<T> {
         if index >= self.length {
             return None;
         }
 
-        let mut node = &self.head;
-        for _ in 0..index {
-            node = &node.as_ref().unwrap().next;
+        // SAFETY: the walk stays within the `length` nodes of the list
+        unsafe {
+            if index < self.length / 2 {
+                let mut node = self.head?;
+                for _ in 0..index {
+                    node = (*node.as_ptr()).next?;
+                }
+                Some(node)
+            } else {
+                let mut node = self.tail?;
+                for _ in index + 1..self.length {
+                    node = (*node.as_ptr()).prev?;
+                }
+                Some(node)
+            }
         }
-
-        node.as_ref().map(|node| &node.value)
     }
 
-    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
-        if index >= self.length {
-            return None;
+    /// Inserts a new node between `prev` and `next`, which must be adjacent
+    /// nodes of this list, or `None` for the ends.
+    fn link(&mut self, prev: Link<T>, next: Link<T>, value: T) -> NonNull<Node<T>> {
+        let node = NonNull::from(Box::leak(Box::new(Node { value, prev, next })));
+
+        // SAFETY: `prev` and `next` are nodes of this list
+        unsafe {
+            match prev {
+                Some(prev) => (*prev.as_ptr()).next = Some(node),
+                None => self.head = Some(node),
+            }
+            match next {
+                Some(next) => (*next.as_ptr()).prev = Some(node),
+                None => self.tail = Some(node),
+            }
         }
+        self.length += 1;
+        node
+    }
 
-        let mut node = &mut self.head;
-        for _ in 0..index {
-            node = &mut node.as_mut().unwrap().next;
+    /// Removes `node` from the list and frees it.
+    ///
+    /// # Safety
+    ///
+    /// `node` must be a node of this list, no reference to it may be alive.
+    unsafe fn unlink(&mut self, node: NonNull<Node<T>>) -> T {
+        let node = Box::from_raw(node.as_ptr());
+        match node.prev {
+            Some(prev) => (*prev.as_ptr()).next = node.next,
+            None => self.head = node.next,
+        }
+        match node.next {
+            Some(next) => (*next.as_ptr()).prev = node.prev,
+            None => self.tail = node.prev,
         }
+        self.length -= 1;
+        node.value
+    }
+}
 
-        node.as_mut().map(|node| &mut node.value)
+impl<T> Default for LinkedList<T> {
+    fn default() -> Self {
+        Self::new()
     }
+}
 
-    pub fn set(&mut self, index: usize, value: T) -> Option<T> {
-        if index >= self.length {
-            return None;
+impl<T> Clone for LinkedList<T>
+where
+    T: Clone,
+{
+    fn clone(&self) -> Self {
+        self.iter().cloned().collect()
+    }
+}
+
+impl<T> FromIterator<T> for LinkedList<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut list = Self::new();
+        list.extend(iter);
+        list
+    }
+}
+
+impl<T> Extend<T> for LinkedList<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            self.push_back(value);
         }
+    }
+}
 
-        let mut node = &mut self.head;
-        for _ in 0..index {
-            node = &mut node.as_mut().unwrap().next;
+impl<T> PartialEq for LinkedList<T>
+where
+    T: PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.length == other.length && self.iter().eq(other.iter())
+    }
+}
+
+impl<T> Eq for LinkedList<T> where T: Eq {}
+
+impl<T> Hash for LinkedList<T>
+where
+    T: Hash,
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // The length keeps lists of lists from hashing like their concatenation
+        self.length.hash(state);
+        for value in self {
+            value.hash(state);
         }
+    }
+}
 
-        node.as_mut()
-            .map(|node| std::mem::replace(&mut node.value, value))
+impl<T> Drop for LinkedList<T> {
+    fn drop(&mut self) {
+        // One node at a time, dropping a long chain of boxes would recurse
+        self.clear();
     }
 }
 
@@ -110,15 +329,12 @@ where
     T: Debug,
 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let mut node = &self.head;
         write!(f, "[")?;
-        while let Some(current) = node {
-            if let Some(_) = &current.next {
-                write!(f, "{:?}, ", current.value)?;
-            } else {
-                write!(f, "{:?}", current.value)?;
+        for (i, value) in self.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
             }
-            node = &current.next;
+            write!(f, "{:?}", value)?;
         }
         write!(f, "]")
     }
@@ -194,4 +410,287 @@ mod tests {
         assert_eq!(list.get(1), Some(&5));
         assert_eq!(list.get(2), Some(&6));
     }
+
+    #[test]
+    fn test_push_pop_both_ends() {
+        let mut list = LinkedList::new();
+        list.push_back(2);
+        list.push_front(1);
+        list.push_back(3);
+        assert_eq!(list.front(), Some(&1));
+        assert_eq!(list.back(), Some(&3));
+        assert_eq!(format!("{:?}", list), "[1, 2, 3]");
+
+        *list.front_mut().unwrap() = 10;
+        assert_eq!(list.pop_front(), Some(10));
+        assert_eq!(list.pop_back(), Some(3));
+        assert_eq!(list.pop_front(), Some(2));
+        assert_eq!(list.pop_front(), None);
+        assert_eq!(list.pop_back(), None);
+        assert_eq!(list.back(), None);
+        assert!(list.is_empty());
+
+        // The list is usable again once emptied
+        list.push_front(4);
+        assert_eq!(list.back(), Some(&4));
+    }
+
+    #[test]
+    fn test_get_from_both_ends() {
+        let mut list = LinkedList::new();
+        for i in 0..7 {
+            list.push(i);
+        }
+        for i in 0..7 {
+            assert_eq!(list.get(i), Some(&i));
+        }
+        assert_eq!(list.get(7), None);
+    }
+
+    #[test]
+    fn test_append() {
+        let mut list = LinkedList::new();
+        let mut other = LinkedList::new();
+        other.push(1);
+        list.append(&mut other);
+        assert!(other.is_empty());
+
+        other.push(2);
+        other.push(3);
+        list.append(&mut other);
+        list.append(&mut LinkedList::new());
+        assert!(list.iter().copied().eq([1, 2, 3]));
+        assert_eq!(list.length(), 3);
+        assert_eq!(list.back(), Some(&3));
+
+        // The emptied list can take new values
+        other.push(4);
+        assert_eq!(other.front(), Some(&4));
+    }
+
+    #[test]
+    fn test_split_off() {
+        let mut list = LinkedList::new();
+        for i in 0..5 {
+            list.push(i);
+        }
+
+        let mut back = list.split_off(2);
+        assert!(list.iter().copied().eq([0, 1]));
+        assert!(back.iter().copied().eq([2, 3, 4]));
+        assert_eq!(list.back(), Some(&1));
+        assert_eq!(back.front(), Some(&2));
+        assert_eq!(back.pop_front(), Some(2));
+
+        let empty = list.split_off(2);
+        assert!(empty.is_empty());
+        let all = list.split_off(0);
+        assert!(list.is_empty());
+        assert!(all.iter().copied().eq([0, 1]));
+    }
+
+    #[test]
+    #[should_panic(expected = "split index out of bounds")]
+    fn test_split_off_out_of_bounds() {
+        let mut list = LinkedList::<i32>::new();
+        list.split_off(1);
+    }
+
+    #[test]
+    fn test_cursor_moves() {
+        let mut list = LinkedList::new();
+        list.push(1);
+        list.push(2);
+
+        let mut cursor = list.cursor_front_mut();
+        assert_eq!(cursor.index(), Some(0));
+        assert_eq!(cursor.peek_prev(), None);
+        cursor.move_next();
+        assert_eq!(cursor.current(), Some(&mut 2));
+        assert_eq!(cursor.peek_prev(), Some(&mut 1));
+
+        // Past the back is the ghost position, then the front again
+        cursor.move_next();
+        assert_eq!(cursor.index(), None);
+        assert_eq!(cursor.current(), None);
+        assert_eq!(cursor.peek_next(), Some(&mut 1));
+        assert_eq!(cursor.peek_prev(), Some(&mut 2));
+        cursor.move_next();
+        assert_eq!(cursor.index(), Some(0));
+        cursor.move_prev();
+        assert_eq!(cursor.index(), None);
+        cursor.move_prev();
+        assert_eq!(cursor.index(), Some(1));
+
+        let mut empty = LinkedList::<i32>::new();
+        let mut cursor = empty.cursor_back_mut();
+        assert_eq!(cursor.index(), None);
+        cursor.move_next();
+        assert_eq!(cursor.current(), None);
+    }
+
+    #[test]
+    fn test_cursor_edits() {
+        let mut list = LinkedList::new();
+        list.push(2);
+        list.push(4);
+
+        let mut cursor = list.cursor_front_mut();
+        cursor.insert_before(1);
+        assert_eq!(cursor.index(), Some(1));
+        cursor.insert_after(3);
+        assert_eq!(cursor.remove_current(), Some(2));
+        assert_eq!(cursor.current(), Some(&mut 3));
+        assert_eq!(cursor.index(), Some(1));
+
+        cursor.move_next();
+        cursor.move_next();
+        assert_eq!(cursor.remove_current(), None);
+        cursor.insert_before(5);
+        cursor.insert_after(0);
+        assert_eq!(cursor.index(), None);
+        assert!(list.iter().copied().eq([0, 1, 3, 4, 5]));
+
+        // Removing the back leaves the cursor at the ghost position
+        let mut cursor = list.cursor_back_mut();
+        assert_eq!(cursor.remove_current(), Some(5));
+        assert_eq!(cursor.index(), None);
+        cursor.move_prev();
+        assert_eq!(cursor.current(), Some(&mut 4));
+        assert!(list.iter().copied().eq([0, 1, 3, 4]));
+
+        let mut cursor = list.cursor_front_mut();
+        while cursor.remove_current().is_some() {}
+        assert!(list.is_empty());
+        assert_eq!(list.back(), None);
+    }
+
+    #[test]
+    fn test_drops_values() {
+        use std::rc::Rc;
+
+        let value = Rc::new(());
+        let mut list = LinkedList::new();
+        for _ in 0..10 {
+            list.push(Rc::clone(&value));
+        }
+        let back = list.split_off(4);
+        list.cursor_front_mut().remove_current();
+        assert_eq!(Rc::strong_count(&value), 10);
+
+        drop(list);
+        drop(back);
+        assert_eq!(Rc::strong_count(&value), 1);
+    }
+
+    #[test]
+    fn test_insert_remove() {
+        let mut list = LinkedList::new();
+        list.insert(0, 2);
+        list.insert(0, 0);
+        list.insert(1, 1);
+        list.insert(3, 4);
+        list.insert(3, 3);
+        assert!(list.iter().copied().eq(0..5));
+
+        assert_eq!(list.remove(4), Some(4));
+        assert_eq!(list.remove(0), Some(0));
+        assert_eq!(list.remove(1), Some(2));
+        assert_eq!(list.remove(2), None);
+        assert!(list.iter().copied().eq([1, 3]));
+        assert_eq!(list.back(), Some(&3));
+    }
+
+    #[test]
+    #[should_panic(expected = "Index out of bounds")]
+    fn test_insert_out_of_bounds() {
+        let mut list = LinkedList::new();
+        list.insert(1, 1);
+    }
+
+    #[test]
+    fn test_reverse() {
+        let mut list = (0..5).collect::<LinkedList<_>>();
+        list.reverse();
+        assert!(list.iter().copied().eq([4, 3, 2, 1, 0]));
+        assert!(list.iter().rev().copied().eq(0..5));
+        assert_eq!(list.front(), Some(&4));
+        assert_eq!(list.back(), Some(&0));
+
+        let mut empty = LinkedList::<i32>::new();
+        empty.reverse();
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn test_iterators() {
+        let mut list = (1..=4).collect::<LinkedList<_>>();
+        list.extend([5, 6]);
+
+        let mut iter = list.iter();
+        assert_eq!(iter.next(), Some(&1));
+        assert_eq!(iter.next_back(), Some(&6));
+        assert_eq!(iter.len(), 4);
+        assert!(iter.copied().eq(2..=5));
+
+        for value in list.iter_mut().rev().take(2) {
+            *value *= 10;
+        }
+        for value in &mut list {
+            *value += 1;
+        }
+        let mut seen = Vec::new();
+        for value in &list {
+            seen.push(*value);
+        }
+        assert_eq!(seen, [2, 3, 4, 5, 51, 61]);
+
+        let mut iter = list.into_iter();
+        assert_eq!(iter.next_back(), Some(61));
+        assert!(iter.eq([2, 3, 4, 5, 51]));
+    }
+
+    #[test]
+    fn test_clone_eq_hash() {
+        use std::collections::hash_map::DefaultHasher;
+
+        fn hash<T: Hash>(value: &T) -> u64 {
+            let mut hasher = DefaultHasher::new();
+            value.hash(&mut hasher);
+            hasher.finish()
+        }
+
+        let list = ["a", "b"].into_iter().collect::<LinkedList<_>>();
+        let mut copy = list.clone();
+        assert_eq!(list, copy);
+        assert_eq!(hash(&list), hash(&copy));
+
+        copy.push("c");
+        assert_ne!(list, copy);
+        copy.pop();
+        copy.reverse();
+        assert_ne!(list, copy);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_million_nodes() {
+        let mut list = (0..1_000_000).collect::<LinkedList<u32>>();
+        assert_eq!(list.length(), 1_000_000);
+
+        list.reverse();
+        assert_eq!(list.front(), Some(&999_999));
+        assert_eq!(list.get(500_000), Some(&499_999));
+
+        let back = list.split_off(500_000);
+        assert_eq!(back.iter().rev().step_by(100_000).count(), 5);
+        assert_eq!(
+            list.iter().map(|&value| u64::from(value)).sum::<u64>(),
+            374_999_750_000
+        );
+
+        // Dropping must not recurse once per node
+        drop(list);
+        drop(back);
+    }
 }
@@ -0,0 +1,126 @@
+use std::fmt::{self, Debug};
+
+use super::{Deque, Iter};
+
+/// A first in, first out queue that holds at most `capacity` values. When
+/// full, `push` rejects new values and `push_overwrite` evicts the oldest.
+pub struct BoundedQueue<T> {
+    deque: Deque<T>,
+    capacity: usize,
+}
+
+impl<T> BoundedQueue<T> {
+    /// Panics when `capacity` is 0.
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "capacity must be at least 1");
+        Self {
+            // Never grows past, nor shrinks below, the bound
+            deque: Deque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn length(&self) -> usize {
+        self.deque.length()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.deque.is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.deque.length() == self.capacity
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Adds `value` to the back, or hands it back when the queue is full.
+    pub fn push(&mut self, value: T) -> Result<(), T> {
+        if self.is_full() {
+            return Err(value);
+        }
+        self.deque.push_back(value);
+        Ok(())
+    }
+
+    /// Adds `value` to the back, dropping the oldest value to make room
+    /// when the queue is full and returning it.
+    pub fn push_overwrite(&mut self, value: T) -> Option<T> {
+        let evicted = if self.is_full() {
+            self.deque.pop_front()
+        } else {
+            None
+        };
+        self.deque.push_back(value);
+        evicted
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        self.deque.pop_front()
+    }
+
+    pub fn peek(&self) -> Option<&T> {
+        self.deque.front()
+    }
+
+    pub fn clear(&mut self) {
+        self.deque.clear();
+    }
+
+    /// Iterates from the oldest value to the newest.
+    pub fn iter(&self) -> Iter<'_, T> {
+        self.deque.iter()
+    }
+}
+
+impl<T> Debug for BoundedQueue<T>
+where
+    T: Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.deque.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_when_full() {
+        let mut queue = BoundedQueue::new(2);
+        assert_eq!(queue.push(1), Ok(()));
+        assert_eq!(queue.push(2), Ok(()));
+        assert!(queue.is_full());
+        assert_eq!(queue.push(3), Err(3));
+
+        assert_eq!(queue.pop(), Some(1));
+        assert_eq!(queue.push(3), Ok(()));
+        assert!(queue.iter().copied().eq([2, 3]));
+        assert_eq!(queue.deque.capacity(), 2);
+    }
+
+    #[test]
+    fn overwrites_the_oldest() {
+        let mut queue = BoundedQueue::new(3);
+        for i in 0..3 {
+            assert_eq!(queue.push_overwrite(i), None);
+        }
+        assert_eq!(queue.push_overwrite(3), Some(0));
+        assert_eq!(queue.push_overwrite(4), Some(1));
+        assert_eq!(queue.peek(), Some(&2));
+        assert_eq!(format!("{:?}", queue), "[2, 3, 4]");
+
+        queue.clear();
+        assert!(queue.is_empty());
+        assert_eq!(queue.deque.capacity(), 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "capacity must be at least 1")]
+    fn zero_capacity() {
+        BoundedQueue::<i32>::new(0);
+    }
+}
@@ -0,0 +1,455 @@
+use std::fmt::{self, Debug};
+use std::mem::MaybeUninit;
+
+mod bounded;
+mod iterator;
+
+pub use bounded::BoundedQueue;
+pub use iterator::{IntoIter, Iter, IterMut};
+
+/// A double-ended queue on a ring buffer. Values are pushed and popped at
+/// either end in amortised O(1), the buffer doubles when full and halves
+/// when a quarter full, like `Vector`'s.
+pub struct Deque<T> {
+    buffer: Box<[MaybeUninit<T>]>,
+    /// Slot of the front value.
+    head: usize,
+    length: usize,
+    /// The buffer never shrinks below this.
+    min_capacity: usize,
+}
+
+const DEFAULT_CAPACITY: usize = 4;
+
+impl<T> Deque<T> {
+    pub fn new() -> Self {
+        Self::with_capacity(DEFAULT_CAPACITY)
+    }
+
+    /// A deque with room for `capacity` values before it grows, which does
+    /// not shrink below that.
+    pub fn with_capacity(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            buffer: Self::allocate_buffer(capacity),
+            head: 0,
+            length: 0,
+            min_capacity: capacity,
+        }
+    }
+
+    pub fn length(&self) -> usize {
+        self.length
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.buffer.len()
+    }
+
+    pub fn push_back(&mut self, value: T) {
+        self.grow();
+        let slot = self.slot(self.length);
+        self.buffer[slot].write(value);
+        self.length += 1;
+    }
+
+    pub fn push_front(&mut self, value: T) {
+        self.grow();
+        self.head = self.slot(self.capacity() - 1);
+        self.buffer[self.head].write(value);
+        self.length += 1;
+    }
+
+    pub fn pop_front(&mut self) -> Option<T> {
+        if self.length == 0 {
+            return None;
+        }
+        // SAFETY: the front slot is initialised, and no longer counted after
+        let value = unsafe { self.buffer[self.head].assume_init_read() };
+        self.head = self.slot(1);
+        self.length -= 1;
+        self.shrink();
+        Some(value)
+    }
+
+    pub fn pop_back(&mut self) -> Option<T> {
+        if self.length == 0 {
+            return None;
+        }
+        self.length -= 1;
+        // SAFETY: the back slot is initialised, and no longer counted
+        let value = unsafe { self.buffer[self.slot(self.length)].assume_init_read() };
+        self.shrink();
+        Some(value)
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        self.get(0)
+    }
+
+    pub fn back(&self) -> Option<&T> {
+        self.get(self.length.checked_sub(1)?)
+    }
+
+    pub fn front_mut(&mut self) -> Option<&mut T> {
+        self.get_mut(0)
+    }
+
+    pub fn back_mut(&mut self) -> Option<&mut T> {
+        self.get_mut(self.length.checked_sub(1)?)
+    }
+
+    /// The value `index` places from the front.
+    pub fn get(&self, index: usize) -> Option<&T> {
+        if index >= self.length {
+            return None;
+        }
+        // SAFETY: the first `length` slots from the head are initialised
+        Some(unsafe { self.buffer[self.slot(index)].assume_init_ref() })
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        if index >= self.length {
+            return None;
+        }
+        let slot = self.slot(index);
+        // SAFETY: as in `get`
+        Some(unsafe { self.buffer[slot].assume_init_mut() })
+    }
+
+    pub fn clear(&mut self) {
+        self.drop_values();
+        self.head = 0;
+        self.length = 0;
+        self.shrink();
+    }
+
+    /// The values as two slices, from the head to the end of the buffer
+    /// and the ones that wrapped around to its start.
+    pub fn as_mut_slices(&mut self) -> (&mut [T], &mut [T]) {
+        let first_length = self.length.min(self.capacity() - self.head);
+        let wrapped_length = self.length - first_length;
+        let (wrapped, from_head) = self.buffer.split_at_mut(self.head);
+        // SAFETY: both ranges are initialised slots, and `MaybeUninit<T>`
+        // has the layout of `T`
+        unsafe {
+            (
+                &mut *(&mut from_head[..first_length] as *mut [MaybeUninit<T>] as *mut [T]),
+                &mut *(&mut wrapped[..wrapped_length] as *mut [MaybeUninit<T>] as *mut [T]),
+            )
+        }
+    }
+
+    /// Buffer slot of the value `index` places from the front.
+    fn slot(&self, index: usize) -> usize {
+        (self.head + index) % self.capacity()
+    }
+
+    fn grow(&mut self) {
+        if self.length == self.capacity() {
+            self.reallocate(self.capacity() * 2);
+        }
+    }
+
+    fn shrink(&mut self) {
+        let mut capacity = self.capacity();
+        while self.length <= capacity / 4 && capacity / 2 >= self.min_capacity {
+            capacity /= 2;
+        }
+        if capacity != self.capacity() {
+            self.reallocate(capacity);
+        }
+    }
+
+    /// Moves the values to a new buffer, the front one into its first slot.
+    fn reallocate(&mut self, capacity: usize) {
+        let mut buffer = Self::allocate_buffer(capacity);
+        for (index, slot) in buffer.iter_mut().enumerate().take(self.length) {
+            // SAFETY: each value is moved out once, the old buffer does not
+            // drop its slots
+            *slot = MaybeUninit::new(unsafe { self.buffer[self.slot(index)].assume_init_read() });
+        }
+        self.buffer = buffer;
+        self.head = 0;
+    }
+
+    fn drop_values(&mut self) {
+        for index in 0..self.length {
+            let slot = self.slot(index);
+            // SAFETY: the slot is initialised, the caller stops counting it
+            unsafe { self.buffer[slot].assume_init_drop() };
+        }
+    }
+
+    fn allocate_buffer(capacity: usize) -> Box<[MaybeUninit<T>]> {
+        std::iter::repeat_with(MaybeUninit::uninit)
+            .take(capacity)
+            .collect::<Vec<_>>()
+            .into_boxed_slice()
+    }
+}
+
+impl<T> Default for Deque<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for Deque<T> {
+    fn drop(&mut self) {
+        self.drop_values();
+    }
+}
+
+impl<T> Debug for Deque<T>
+where
+    T: Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T> FromIterator<T> for Deque<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut deque = Self::new();
+        deque.extend(iter);
+        deque
+    }
+}
+
+impl<T> Extend<T> for Deque<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            self.push_back(value);
+        }
+    }
+}
+
+/// A first in, first out queue on a `Deque`.
+pub struct Queue<T> {
+    deque: Deque<T>,
+}
+
+impl<T> Queue<T> {
+    pub fn new() -> Self {
+        Self {
+            deque: Deque::new(),
+        }
+    }
+
+    pub fn length(&self) -> usize {
+        self.deque.length()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.deque.is_empty()
+    }
+
+    /// Adds `value` to the back of the queue.
+    pub fn push(&mut self, value: T) {
+        self.deque.push_back(value);
+    }
+
+    /// Takes the value at the front, the oldest one.
+    pub fn pop(&mut self) -> Option<T> {
+        self.deque.pop_front()
+    }
+
+    /// The value `pop` would return.
+    pub fn peek(&self) -> Option<&T> {
+        self.deque.front()
+    }
+
+    pub fn peek_mut(&mut self) -> Option<&mut T> {
+        self.deque.front_mut()
+    }
+
+    pub fn clear(&mut self) {
+        self.deque.clear();
+    }
+
+    /// Iterates from the front of the queue to the back.
+    pub fn iter(&self) -> Iter<'_, T> {
+        self.deque.iter()
+    }
+}
+
+impl<T> Default for Queue<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Debug for Queue<T>
+where
+    T: Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.deque.fmt(f)
+    }
+}
+
+impl<T> FromIterator<T> for Queue<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self {
+            deque: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl<T> Extend<T> for Queue<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        self.deque.extend(iter);
+    }
+}
+
+impl<T> IntoIterator for Queue<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.deque.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn push_and_pop_both_ends() {
+        let mut deque = Deque::new();
+        deque.push_back(2);
+        deque.push_front(1);
+        deque.push_back(3);
+        deque.push_front(0);
+        assert_eq!(deque.front(), Some(&0));
+        assert_eq!(deque.back(), Some(&3));
+        assert_eq!(format!("{:?}", deque), "[0, 1, 2, 3]");
+
+        assert_eq!(deque.pop_back(), Some(3));
+        assert_eq!(deque.pop_front(), Some(0));
+        assert_eq!(deque.pop_front(), Some(1));
+        assert_eq!(deque.pop_back(), Some(2));
+        assert_eq!(deque.pop_back(), None);
+        assert_eq!(deque.pop_front(), None);
+        assert_eq!(deque.back(), None);
+    }
+
+    #[test]
+    fn wraps_around_the_buffer() {
+        let mut deque = Deque::new();
+        for i in 0..4 {
+            deque.push_back(i);
+        }
+        // Moving the head forward makes the values wrap past the end
+        for i in 4..20 {
+            assert_eq!(deque.pop_front(), Some(i - 4));
+            deque.push_back(i);
+            assert_eq!(deque.capacity(), DEFAULT_CAPACITY);
+        }
+        assert!(deque.iter().copied().eq(16..20));
+        assert_eq!(deque.get(3), Some(&19));
+        assert_eq!(deque.get(4), None);
+
+        let (first, wrapped) = deque.as_mut_slices();
+        assert_eq!(first.len() + wrapped.len(), 4);
+    }
+
+    #[test]
+    fn grows_and_shrinks() {
+        let mut deque = Deque::new();
+        for i in 0..33 {
+            deque.push_front(i);
+        }
+        assert_eq!(deque.capacity(), 64);
+        assert!(deque.iter().rev().copied().eq(0..33));
+
+        while deque.length() > 8 {
+            deque.pop_back();
+        }
+        assert_eq!(deque.capacity(), 16);
+        assert!(deque.iter().copied().eq((25..33).rev()));
+
+        deque.clear();
+        assert_eq!(deque.capacity(), DEFAULT_CAPACITY);
+
+        let mut deque = Deque::<i32>::with_capacity(10);
+        deque.extend(0..11);
+        assert_eq!(deque.capacity(), 20);
+        deque.clear();
+        assert_eq!(deque.capacity(), 10);
+    }
+
+    #[test]
+    fn iterators() {
+        let mut deque = (1..=3).collect::<Deque<_>>();
+        deque.push_front(0);
+
+        let mut iter = deque.iter();
+        assert_eq!(iter.len(), 4);
+        assert_eq!(iter.next_back(), Some(&3));
+        assert_eq!(iter.next(), Some(&0));
+        assert!(iter.copied().eq([1, 2]));
+
+        for value in deque.iter_mut().rev().take(2) {
+            *value *= 10;
+        }
+        for value in &mut deque {
+            *value += 1;
+        }
+        let mut seen = Vec::new();
+        for value in &deque {
+            seen.push(*value);
+        }
+        assert_eq!(seen, [1, 2, 21, 31]);
+
+        let mut iter = deque.into_iter();
+        assert_eq!(iter.next_back(), Some(31));
+        assert!(iter.eq([1, 2, 21]));
+    }
+
+    #[test]
+    fn drops_values() {
+        use std::rc::Rc;
+
+        let value = Rc::new(());
+        let mut deque = Deque::new();
+        for _ in 0..10 {
+            deque.push_front(Rc::clone(&value));
+            deque.push_back(Rc::clone(&value));
+        }
+        for _ in 0..15 {
+            deque.pop_front();
+        }
+        assert_eq!(Rc::strong_count(&value), 6);
+
+        let mut iter = deque.into_iter();
+        iter.next();
+        drop(iter);
+        assert_eq!(Rc::strong_count(&value), 1);
+    }
+
+    #[test]
+    fn queue_is_first_in_first_out() {
+        let mut queue = Queue::new();
+        queue.push(1);
+        queue.push(2);
+        queue.extend([3, 4]);
+        assert_eq!(queue.peek(), Some(&1));
+        assert_eq!(queue.pop(), Some(1));
+        assert_eq!(queue.pop(), Some(2));
+        assert_eq!(queue.length(), 2);
+        assert!(queue.iter().copied().eq([3, 4]));
+        assert!(queue.into_iter().eq([3, 4]));
+
+        let mut queue = Queue::<i32>::default();
+        assert_eq!(queue.pop(), None);
+        assert!(queue.is_empty());
+    }
+}
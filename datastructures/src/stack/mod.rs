@@ -0,0 +1,118 @@
+use std::fmt::{self, Debug};
+use std::iter::Rev;
+use std::slice;
+
+use crate::Vector;
+
+/// A last in, first out stack on top of a `Vector`.
+pub struct Stack<T> {
+    items: Vector<T>,
+}
+
+impl<T> Stack<T> {
+    pub fn new() -> Self {
+        Self {
+            items: Vector::new(),
+        }
+    }
+
+    pub fn length(&self) -> usize {
+        self.items.length()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.length() == 0
+    }
+
+    pub fn push(&mut self, value: T) {
+        self.items.push(value);
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        self.items.pop()
+    }
+
+    /// The value `pop` would return.
+    pub fn peek(&self) -> Option<&T> {
+        self.items.last()
+    }
+
+    pub fn peek_mut(&mut self) -> Option<&mut T> {
+        self.items.last_mut()
+    }
+
+    pub fn clear(&mut self) {
+        self.items.clear();
+    }
+
+    /// Iterates from the top of the stack to the bottom.
+    pub fn iter(&self) -> Rev<slice::Iter<'_, T>> {
+        self.items.as_slice().iter().rev()
+    }
+}
+
+impl<T> Default for Stack<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Debug for Stack<T>
+where
+    T: Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+/// Pushes the values in order, the last one ends up on top.
+impl<T> FromIterator<T> for Stack<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self {
+            items: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl<T> Extend<T> for Stack<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        self.items.extend(iter);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn push_and_pop() {
+        let mut stack = Stack::new();
+        assert!(stack.is_empty());
+        assert_eq!(stack.pop(), None);
+
+        stack.push(1);
+        stack.push(2);
+        stack.push(3);
+        assert_eq!(stack.length(), 3);
+        assert_eq!(stack.peek(), Some(&3));
+
+        *stack.peek_mut().unwrap() = 30;
+        assert_eq!(stack.pop(), Some(30));
+        assert_eq!(stack.pop(), Some(2));
+        assert_eq!(stack.pop(), Some(1));
+        assert_eq!(stack.pop(), None);
+        assert_eq!(stack.peek(), None);
+    }
+
+    #[test]
+    fn iter_from_the_top() {
+        let mut stack = (1..=3).collect::<Stack<_>>();
+        stack.extend([4]);
+        assert!(stack.iter().copied().eq([4, 3, 2, 1]));
+        assert_eq!(format!("{:?}", stack), "[4, 3, 2, 1]");
+
+        stack.clear();
+        assert!(stack.is_empty());
+    }
+}
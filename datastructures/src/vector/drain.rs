@@ -0,0 +1,86 @@
+use std::ptr;
+
+use super::Vector;
+
+/// Iterator over the values removed by `Vector::drain`. The values it did
+/// not yield are dropped with it, then the values after the range move down.
+pub struct Drain<'a, T> {
+    vector: &'a mut Vector<T>,
+    /// The next slots to read from each end.
+    front: usize,
+    back: usize,
+    /// Where the values after the range start, and how many there are.
+    tail: usize,
+    tail_length: usize,
+}
+
+impl<'a, T> Drain<'a, T> {
+    pub(super) fn new(vector: &'a mut Vector<T>, start: usize, end: usize) -> Self {
+        let tail_length = vector.length - end;
+        // Only the values before the range are counted until the drain is
+        // dropped, so forgetting it leaks the rest instead of exposing the
+        // values it already read out
+        vector.length = start;
+        Drain {
+            vector,
+            front: start,
+            back: end,
+            tail: end,
+            tail_length,
+        }
+    }
+}
+
+impl<T> Iterator for Drain<'_, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+        // SAFETY: slots between `front` and `back` are initialised and read
+        // once
+        let value = unsafe { self.vector.slot(self.front).read() };
+        self.front += 1;
+        Some(value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.back - self.front;
+        (remaining, Some(remaining))
+    }
+}
+
+impl<T> DoubleEndedIterator for Drain<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+        self.back -= 1;
+        // SAFETY: as in `next`
+        Some(unsafe { self.vector.slot(self.back).read() })
+    }
+}
+
+impl<T> ExactSizeIterator for Drain<'_, T> {}
+
+impl<T> Drop for Drain<'_, T> {
+    fn drop(&mut self) {
+        let unread =
+            ptr::slice_from_raw_parts_mut(self.vector.slot(self.front), self.back - self.front);
+        self.front = self.back;
+        // SAFETY: the unread values are initialised and dropped once, then
+        // the tail moves down right after the values before the range
+        unsafe {
+            ptr::drop_in_place(unread);
+            let start = self.vector.length;
+            ptr::copy(
+                self.vector.slot(self.tail),
+                self.vector.slot(start),
+                self.tail_length,
+            );
+            self.vector.length = start + self.tail_length;
+        }
+        self.vector.shrink();
+    }
+}
@@ -7,12 +7,10 @@ pub struct VectorIter<'a, T> {
 
 impl<T> Vector<T> {
     pub fn iter(&self) -> VectorIter<'_, T> {
-        println!("Hello, world!");
         VectorIter {
             vector: self,
             index: 0,
         }
-
     }
 }
 
@@ -27,4 +25,4 @@ impl<'a, T> Iterator for VectorIter<'a, T> {
         self.index += 1;
         value
     }
-}
\ No newline at end of file
+}
@@ -1,21 +1,68 @@
+use std::alloc::{self, Layout};
 use std::fmt::{Debug, Display, Formatter, Result};
+use std::marker::PhantomData;
+use std::mem::{self, MaybeUninit};
+use std::ops::{Bound, Deref, DerefMut, Index, IndexMut, RangeBounds};
+use std::ptr::{self, NonNull};
+use std::slice::{self, SliceIndex};
 
+mod drain;
 mod iterator;
 
+pub use drain::Drain;
+pub use iterator::VectorIter;
+
+/// Creates a `Vector` like `vec!` creates a `Vec`, from a list of values or
+/// from a value cloned `n` times.
+#[macro_export]
+macro_rules! vector {
+    () => {
+        $crate::Vector::new()
+    };
+    ($value:expr; $n:expr) => {{
+        let mut vector = $crate::Vector::new();
+        vector.extend(::std::iter::repeat($value).take($n));
+        vector
+    }};
+    ($($value:expr),+ $(,)?) => {{
+        let mut vector = $crate::Vector::new();
+        $(vector.push($value);)+
+        vector
+    }};
+}
+
+/// A growable array on a buffer from `std::alloc`, of which the first
+/// `length` slots are initialised.
 pub struct Vector<T> {
-    data: Box<[Option<T>]>,
+    data: NonNull<MaybeUninit<T>>,
     length: usize,
     capacity: usize,
+    /// The vector owns its values, dropping it drops them.
+    marker: PhantomData<T>,
 }
 
 const DEFAULT_CAPACITY: usize = 4;
 
+// The buffer is owned like a `Vec`'s is.
+unsafe impl<T: Send> Send for Vector<T> {}
+unsafe impl<T: Sync> Sync for Vector<T> {}
+
 impl<T> Vector<T> {
+    const IS_ZST: bool = mem::size_of::<T>() == 0;
+
     pub fn new() -> Self {
+        // Zero-sized values take no memory, there is room for as many as
+        // can be counted.
+        let capacity = if Self::IS_ZST {
+            usize::MAX
+        } else {
+            DEFAULT_CAPACITY
+        };
         Self {
-            data: Self::allocate_buffer(DEFAULT_CAPACITY),
+            data: Self::allocate_buffer(capacity),
             length: 0,
-            capacity: DEFAULT_CAPACITY,
+            capacity,
+            marker: PhantomData,
         }
     }
 
@@ -28,8 +75,9 @@ impl<T> Vector<T> {
     }
 
     pub fn push(&mut self, value: T) {
-        self.resize();
-        self.data[self.length] = Some(value);
+        self.reserve(1);
+        // SAFETY: the slot is within the buffer and not initialised
+        unsafe { self.slot(self.length).write(value) };
         self.length += 1;
     }
 
@@ -38,68 +86,344 @@ impl<T> Vector<T> {
             return None;
         }
         self.length -= 1;
-        let value = self.data[self.length].take();
-        self.resize();
-        value
+        // SAFETY: the slot was initialised and is no longer counted
+        let value = unsafe { self.slot(self.length).read() };
+        self.shrink();
+        Some(value)
     }
 
     pub fn get(&self, index: usize) -> Option<&T> {
         if index >= self.length {
             return None;
         }
-        self.data[index].as_ref()
+        // SAFETY: the slot is initialised
+        Some(unsafe { &*self.slot(index) })
     }
 
     pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
         if index >= self.length {
             return None;
         }
-        self.data[index].as_mut()
+        // SAFETY: the slot is initialised, and `self` is borrowed mutably
+        Some(unsafe { &mut *self.slot(index) })
     }
 
     pub fn set(&mut self, index: usize, value: T) -> Option<T> {
-        if index >= self.length {
-            return None;
-        }
-        self.data[index].replace(value)
+        self.get_mut(index)
+            .map(|current| mem::replace(current, value))
     }
 
     pub fn insert(&mut self, index: usize, value: T) {
         if index > self.length {
             panic!("Index out of bounds");
         }
-        self.resize();
-        for i in (index..self.length).rev() {
-            self.data[i + 1] = self.data[i].take();
+        self.reserve(1);
+        // SAFETY: the values from `index` on move one slot up, into the
+        // free slot at `length`, then the emptied slot takes the value
+        unsafe {
+            ptr::copy(self.slot(index), self.slot(index + 1), self.length - index);
+            self.slot(index).write(value);
         }
-        self.data[index] = Some(value);
         self.length += 1;
     }
 
-    fn resize(&mut self) {
-        if self.length == self.capacity {
-            // resize up
-            self.capacity *= 2;
-        } else if self.length == self.capacity / 4 && self.capacity > DEFAULT_CAPACITY {
-            // resize down
-            self.capacity /= 2;
+    /// Removes the value at `index`, shifting the ones after it down.
+    pub fn remove(&mut self, index: usize) -> Option<T> {
+        if index >= self.length {
+            return None;
         }
-        self.reallocate()
+        // SAFETY: the value is read out, then the values after it move down
+        // over its slot
+        let value = unsafe {
+            let value = self.slot(index).read();
+            ptr::copy(
+                self.slot(index + 1),
+                self.slot(index),
+                self.length - index - 1,
+            );
+            value
+        };
+        self.length -= 1;
+        self.shrink();
+        Some(value)
     }
 
-    fn reallocate(&mut self) {
-        let mut new_data = Self::allocate_buffer(self.capacity);
-        for i in 0..self.length {
-            new_data[i] = self.data[i].take();
+    /// Removes the value at `index` in O(1) by moving the last value into
+    /// its place.
+    pub fn swap_remove(&mut self, index: usize) -> Option<T> {
+        if index >= self.length {
+            return None;
+        }
+        let last = self.length - 1;
+        self.as_mut_slice().swap(index, last);
+        self.pop()
+    }
+
+    /// Drops the values from `length` on, if there are any.
+    pub fn truncate(&mut self, length: usize) {
+        if length >= self.length {
+            return;
+        }
+        let dropped = ptr::slice_from_raw_parts_mut(self.slot(length), self.length - length);
+        // Shortened first, a panicking drop leaks the rest instead of
+        // dropping them twice
+        self.length = length;
+        // SAFETY: the values were initialised and are no longer counted
+        unsafe { ptr::drop_in_place(dropped) };
+        self.shrink();
+    }
+
+    pub fn clear(&mut self) {
+        self.truncate(0);
+    }
+
+    pub fn extend_from_slice(&mut self, values: &[T])
+    where
+        T: Clone,
+    {
+        self.reserve(values.len());
+        for value in values {
+            self.push(value.clone());
+        }
+    }
+
+    /// Removes the values in `range`, yielding them. Panics when the range
+    /// is decreasing or goes past the end.
+    pub fn drain<R>(&mut self, range: R) -> Drain<'_, T>
+    where
+        R: RangeBounds<usize>,
+    {
+        let start = match range.start_bound() {
+            Bound::Included(&start) => start,
+            Bound::Excluded(&start) => start.checked_add(1).expect("drain range out of bounds"),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&end) => end.checked_add(1).expect("drain range out of bounds"),
+            Bound::Excluded(&end) => end,
+            Bound::Unbounded => self.length,
+        };
+        assert!(
+            start <= end && end <= self.length,
+            "drain range out of bounds"
+        );
+        Drain::new(self, start, end)
+    }
+
+    /// Keeps only the values `keep` returns true for, in order.
+    pub fn retain<F>(&mut self, mut keep: F)
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.filter(|value, _| keep(value));
+    }
+
+    /// Removes runs of equal values but the first of each.
+    pub fn dedup(&mut self)
+    where
+        T: PartialEq,
+    {
+        self.dedup_by(|value, previous| value == previous);
+    }
+
+    /// Removes the values `same` says are duplicates of the value kept
+    /// before them.
+    pub fn dedup_by<F>(&mut self, mut same: F)
+    where
+        F: FnMut(&mut T, &mut T) -> bool,
+    {
+        self.filter(|value, previous| match previous {
+            Some(previous) => !same(value, previous),
+            None => true,
+        });
+    }
+
+    /// Ensures there is room for `additional` more values, growing the
+    /// buffer to at least twice its size. Removing values may shrink it
+    /// back.
+    pub fn reserve(&mut self, additional: usize) {
+        let needed = self
+            .length
+            .checked_add(additional)
+            .expect("capacity overflow");
+        if needed > self.capacity {
+            self.reallocate(needed.max(self.capacity.saturating_mul(2)));
+        }
+    }
+
+    /// Shrinks the buffer to the length, but not below the capacity of a
+    /// new vector.
+    pub fn shrink_to_fit(&mut self) {
+        let capacity = self.length.max(DEFAULT_CAPACITY);
+        if !Self::IS_ZST && capacity < self.capacity {
+            self.reallocate(capacity);
+        }
+    }
+
+    pub fn as_slice(&self) -> &[T] {
+        // SAFETY: the first `length` slots are initialised
+        unsafe { slice::from_raw_parts(self.slot(0), self.length) }
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        // SAFETY: as in `as_slice`, with `self` borrowed mutably
+        unsafe { slice::from_raw_parts_mut(self.slot(0), self.length) }
+    }
+
+    /// Keeps the values `keep` returns true for, given the value and the
+    /// last value kept before it, and moves them down over the gaps.
+    fn filter<F>(&mut self, mut keep: F)
+    where
+        F: FnMut(&mut T, Option<&mut T>) -> bool,
+    {
+        let length = self.length;
+        // A panic in `keep` leaks the values instead of dropping them twice
+        self.length = 0;
+
+        let mut kept: usize = 0;
+        for i in 0..length {
+            // SAFETY: slots below `kept` hold the kept values, slot `i` is
+            // the next unvisited one, and `kept <= i`
+            unsafe {
+                let value = &mut *self.slot(i);
+                let previous = kept.checked_sub(1).map(|last| &mut *self.slot(last));
+                if keep(value, previous) {
+                    if kept != i {
+                        ptr::copy_nonoverlapping(self.slot(i), self.slot(kept), 1);
+                    }
+                    kept += 1;
+                } else {
+                    ptr::drop_in_place(self.slot(i));
+                }
+            }
+        }
+
+        self.length = kept;
+        self.shrink();
+    }
+
+    /// Halves the buffer while it is at most a quarter full, so popping
+    /// after a push that grew it does not shrink it straight back.
+    fn shrink(&mut self) {
+        if Self::IS_ZST {
+            return;
+        }
+        let mut capacity = self.capacity;
+        while self.length <= capacity / 4 && capacity / 2 >= DEFAULT_CAPACITY {
+            capacity /= 2;
+        }
+        if capacity != self.capacity {
+            self.reallocate(capacity);
+        }
+    }
+
+    fn reallocate(&mut self, capacity: usize) {
+        let old_layout = Self::layout(self.capacity);
+        let new_layout = Self::layout(capacity);
+        // SAFETY: the buffer was allocated with the old layout, and the
+        // `length` values it holds fit in the new one
+        let data =
+            unsafe { alloc::realloc(self.data.as_ptr().cast(), old_layout, new_layout.size()) };
+        self.data = match NonNull::new(data.cast()) {
+            Some(data) => data,
+            None => alloc::handle_alloc_error(new_layout),
+        };
+        self.capacity = capacity;
+    }
+
+    fn allocate_buffer(capacity: usize) -> NonNull<MaybeUninit<T>> {
+        if Self::IS_ZST {
+            return NonNull::dangling();
+        }
+        let layout = Self::layout(capacity);
+        // SAFETY: the layout is not zero-sized, `DEFAULT_CAPACITY` is not 0
+        let data = unsafe { alloc::alloc(layout) };
+        match NonNull::new(data.cast()) {
+            Some(data) => data,
+            None => alloc::handle_alloc_error(layout),
+        }
+    }
+
+    fn layout(capacity: usize) -> Layout {
+        Layout::array::<T>(capacity).expect("capacity overflow")
+    }
+
+    /// Pointer to the slot at `index`, which must be at most `capacity`.
+    fn slot(&self, index: usize) -> *mut T {
+        // SAFETY: the offset stays within the buffer, or is 0 bytes for
+        // zero-sized values
+        unsafe { self.data.as_ptr().add(index).cast() }
+    }
+}
+
+impl<T> Default for Vector<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Deref for Vector<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        self.as_slice()
+    }
+}
+
+impl<T> DerefMut for Vector<T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        self.as_mut_slice()
+    }
+}
+
+impl<T, I> Index<I> for Vector<T>
+where
+    I: SliceIndex<[T]>,
+{
+    type Output = I::Output;
+
+    fn index(&self, index: I) -> &I::Output {
+        &self.as_slice()[index]
+    }
+}
+
+impl<T, I> IndexMut<I> for Vector<T>
+where
+    I: SliceIndex<[T]>,
+{
+    fn index_mut(&mut self, index: I) -> &mut I::Output {
+        &mut self.as_mut_slice()[index]
+    }
+}
+
+impl<T> FromIterator<T> for Vector<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut vector = Self::new();
+        vector.extend(iter);
+        vector
+    }
+}
+
+impl<T> Extend<T> for Vector<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+        for value in iter {
+            self.push(value);
         }
-        self.data = new_data;
     }
+}
 
-    fn allocate_buffer(capacity: usize) -> Box<[Option<T>]> {
-        std::iter::repeat_with(|| None)
-            .take(capacity)
-            .collect::<Vec<_>>()
-            .into_boxed_slice()
+impl<T> Drop for Vector<T> {
+    fn drop(&mut self) {
+        // SAFETY: the first `length` slots are initialised and dropped once,
+        // the buffer was allocated with the layout for `capacity`
+        unsafe {
+            ptr::drop_in_place(ptr::slice_from_raw_parts_mut(self.slot(0), self.length));
+            if !Self::IS_ZST {
+                alloc::dealloc(self.data.as_ptr().cast(), Self::layout(self.capacity));
+            }
+        }
     }
 }
 
@@ -111,7 +435,7 @@ where
         write!(f, "[")?;
 
         for i in 0..self.length {
-            write!(f, "{}", self.data[i].as_ref().unwrap())?;
+            write!(f, "{}", self.get(i).unwrap())?;
 
             if i < self.length - 1 {
                 write!(f, ", ")?;
@@ -229,4 +553,248 @@ mod tests {
         vector.push(2);
         assert_eq!(vector.set(2, 3), None);
     }
+
+    #[test]
+    fn insert_shifts_values() {
+        let mut vector = Vector::new();
+        for i in [1, 3, 4, 5] {
+            vector.push(i);
+        }
+        vector.insert(1, 2);
+        vector.insert(0, 0);
+        vector.insert(6, 6);
+        assert!(vector.iter().copied().eq(0..7));
+        assert_eq!(vector.capacity(), DEFAULT_CAPACITY * 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "Index out of bounds")]
+    fn insert_panics_if_index_out_of_bounds() {
+        let mut vector = Vector::new();
+        vector.insert(1, 1);
+    }
+
+    #[test]
+    fn values_are_dropped_once() {
+        use std::rc::Rc;
+
+        let value = Rc::new(());
+        let mut vector = Vector::new();
+        for _ in 0..10 {
+            vector.push(Rc::clone(&value));
+        }
+        vector.insert(3, Rc::clone(&value));
+        vector.set(0, Rc::clone(&value));
+        drop(vector.pop());
+        assert_eq!(Rc::strong_count(&value), 11);
+
+        drop(vector);
+        assert_eq!(Rc::strong_count(&value), 1);
+    }
+
+    #[test]
+    fn zero_sized_values() {
+        let mut vector = Vector::new();
+        assert_eq!(vector.capacity(), usize::MAX);
+        for _ in 0..100 {
+            vector.push(());
+        }
+        vector.insert(50, ());
+        assert_eq!(vector.length(), 101);
+        assert_eq!(vector.get(100), Some(&()));
+        assert_eq!(vector.pop(), Some(()));
+        assert_eq!(vector.capacity(), usize::MAX);
+    }
+
+    #[test]
+    fn many_values_with_heap_data() {
+        let mut vector = Vector::new();
+        for i in 0..1000 {
+            vector.push(i.to_string());
+        }
+        assert_eq!(vector.get(999).map(String::as_str), Some("999"));
+        while vector.length() > 1 {
+            vector.pop();
+        }
+        assert_eq!(vector.capacity(), DEFAULT_CAPACITY);
+        assert_eq!(vector.get(0).map(String::as_str), Some("0"));
+    }
+
+    #[test]
+    fn vector_macro() {
+        let empty: Vector<i32> = vector![];
+        assert_eq!(empty.length(), 0);
+        assert_eq!(vector![1, 2, 3].as_slice(), [1, 2, 3]);
+        assert_eq!(vector!["a".to_string(); 3].as_slice(), ["a", "a", "a"]);
+    }
+
+    #[test]
+    fn slice_methods_through_deref() {
+        let mut vector = vector![5, 3, 8, 1];
+        vector.sort();
+        assert_eq!(*vector, [1, 3, 5, 8]);
+        assert_eq!(vector.binary_search(&5), Ok(2));
+        assert_eq!(vector.windows(2).count(), 3);
+        assert_eq!(vector.len(), 4);
+
+        vector[0] = 2;
+        vector[1..3].reverse();
+        assert_eq!(vector[..], [2, 5, 3, 8]);
+        assert_eq!(vector[3], 8);
+        for value in vector.iter_mut() {
+            *value *= 2;
+        }
+        assert_eq!(vector.as_mut_slice(), [4, 10, 6, 16]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn index_panics_if_out_of_bounds() {
+        let vector = vector![1];
+        let _ = vector[1];
+    }
+
+    #[test]
+    fn remove_and_swap_remove() {
+        let mut vector = (0..6).collect::<Vector<_>>();
+        assert_eq!(vector.remove(1), Some(1));
+        assert_eq!(vector.swap_remove(0), Some(0));
+        assert_eq!(vector.remove(4), None);
+        assert_eq!(vector.swap_remove(4), None);
+        assert_eq!(*vector, [5, 2, 3, 4]);
+        assert_eq!(vector.swap_remove(3), Some(4));
+        assert_eq!(*vector, [5, 2, 3]);
+    }
+
+    #[test]
+    fn truncate_and_clear_shrink() {
+        let mut vector = Vector::new();
+        for i in 0..100 {
+            vector.push(i);
+        }
+        assert_eq!(vector.capacity(), 128);
+        vector.truncate(200);
+        assert_eq!(vector.length(), 100);
+        vector.truncate(10);
+        assert_eq!(*vector, (0..10).collect::<Vec<_>>());
+        assert_eq!(vector.capacity(), 32);
+        vector.clear();
+        assert_eq!(vector.length(), 0);
+        assert_eq!(vector.capacity(), DEFAULT_CAPACITY);
+    }
+
+    #[test]
+    fn extend_from_slice_and_reserve() {
+        let mut vector = vector![1];
+        vector.reserve(100);
+        assert!(vector.capacity() >= 101);
+        vector.extend_from_slice(&[2, 3]);
+        vector.extend(4..6);
+        assert_eq!(*vector, [1, 2, 3, 4, 5]);
+
+        vector.shrink_to_fit();
+        assert_eq!(vector.capacity(), 5);
+        // Shrinking never goes below the capacity of a new vector
+        vector.truncate(1);
+        assert_eq!(vector.capacity(), 5);
+        vector.shrink_to_fit();
+        assert_eq!(vector.capacity(), DEFAULT_CAPACITY);
+    }
+
+    #[test]
+    fn drain() {
+        let mut vector = (0..10).collect::<Vector<_>>();
+        let drained = vector.drain(2..5).collect::<Vec<_>>();
+        assert_eq!(drained, [2, 3, 4]);
+        assert_eq!(*vector, [0, 1, 5, 6, 7, 8, 9]);
+
+        let mut drain = vector.drain(1..=4);
+        assert_eq!(drain.len(), 4);
+        assert_eq!(drain.next_back(), Some(7));
+        assert_eq!(drain.next(), Some(1));
+        drop(drain);
+        assert_eq!(*vector, [0, 8, 9]);
+
+        assert_eq!(vector.drain(..).count(), 3);
+        assert_eq!(vector.length(), 0);
+        assert_eq!(vector.drain(..).next(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "drain range out of bounds")]
+    fn drain_panics_if_out_of_bounds() {
+        let mut vector = vector![1, 2];
+        vector.drain(1..3);
+    }
+
+    #[test]
+    #[should_panic(expected = "drain range out of bounds")]
+    fn drain_panics_if_end_overflows() {
+        let mut vector = vector![1, 2];
+        vector.drain(..=usize::MAX);
+    }
+
+    #[test]
+    fn drain_drops_unread_values() {
+        use std::rc::Rc;
+
+        let value = Rc::new(());
+        let mut vector = vector![Rc::clone(&value); 6];
+        let mut drain = vector.drain(1..4);
+        drop(drain.next());
+        drop(drain);
+        assert_eq!(vector.length(), 3);
+        assert_eq!(Rc::strong_count(&value), 4);
+    }
+
+    /// Leaks on purpose, which Miri reports.
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn forgotten_drain_leaks_the_range_and_tail() {
+        use std::rc::Rc;
+
+        let value = Rc::new(());
+        let mut vector = vector![Rc::clone(&value); 3];
+
+        // Nothing is freed twice
+        std::mem::forget(vector.drain(1..2));
+        assert_eq!(vector.length(), 1);
+        drop(vector);
+        assert_eq!(Rc::strong_count(&value), 3);
+    }
+
+    #[test]
+    fn retain_and_dedup() {
+        let mut vector = (0..10).collect::<Vector<_>>();
+        vector.retain(|value| value % 3 != 0);
+        assert_eq!(*vector, [1, 2, 4, 5, 7, 8]);
+
+        let mut vector = vector![1, 1, 2, 3, 3, 3, 1, 4, 4];
+        vector.dedup();
+        assert_eq!(*vector, [1, 2, 3, 1, 4]);
+
+        let mut words = vector!["a", "A", "b", "B", "b", "c"];
+        words.dedup_by(|word, previous| word.eq_ignore_ascii_case(previous));
+        assert_eq!(*words, ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn retain_drops_removed_values() {
+        use std::rc::Rc;
+
+        let kept = Rc::new(());
+        let removed = Rc::new(());
+        let mut vector = Vector::new();
+        for i in 0..6 {
+            vector.push(if i % 2 == 0 {
+                Rc::clone(&kept)
+            } else {
+                Rc::clone(&removed)
+            });
+        }
+        vector.retain(|value| Rc::ptr_eq(value, &kept));
+        assert_eq!(vector.length(), 3);
+        assert_eq!(Rc::strong_count(&kept), 4);
+        assert_eq!(Rc::strong_count(&removed), 1);
+    }
 }
@@ -0,0 +1,16 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use unsophisticated_lang::interpreter::{AstInterpreter, Limits};
+
+fuzz_target!(|data: &[u8]| {
+    // Sandboxed, and bounded so `(while true)` is not reported as a hang
+    let mut interpreter = AstInterpreter::new();
+    interpreter.limits = Limits {
+        fuel: Some(100_000),
+        max_memory: Some(1 << 24),
+        max_depth: Some(256),
+        ..Limits::default()
+    };
+    let _ = interpreter.eval(&String::from_utf8_lossy(data));
+});
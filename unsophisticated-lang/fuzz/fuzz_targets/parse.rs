@@ -0,0 +1,10 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use unsophisticated_lang::parser::Parser;
+use unsophisticated_lang::tokenizer::Tokenizer;
+
+fuzz_target!(|data: &[u8]| {
+    let tokens = Tokenizer::new(String::from_utf8_lossy(data)).tokenize();
+    Parser::new(tokens).parse_expr();
+});
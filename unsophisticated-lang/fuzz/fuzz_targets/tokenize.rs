@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use unsophisticated_lang::tokenizer::Tokenizer;
+
+fuzz_target!(|data: &[u8]| {
+    Tokenizer::new(String::from_utf8_lossy(data)).tokenize();
+});
@@ -0,0 +1,228 @@
+use unsophisticated_lang::compiler;
+use unsophisticated_lang::repl::{strip_shebang, Repl, RunError, STACK_SIZE};
+
+use std::io::Read;
+
+const USAGE: &str = "\
+Usage:
+    unsoph [repl]                    start the interactive REPL
+    unsoph [options] run <file> ...  run a script, `-` reads it from stdin
+    unsoph [options] <file> ...      same as `run`, for `#!/usr/bin/env unsoph`
+    unsoph [options] -e <expr> ...   evaluate an expression
+    unsoph compile [--wat] <file> [-o <out>]
+                                     translate a script to a standalone Rust program,
+                                     or to a WebAssembly text module
+
+Arguments after the script are available to it through `(args)`.
+
+Options:
+    -p, --print-result  print the value of the last expression
+        --no-prelude    do not define the prelude helpers, only the builtins
+        --profile       print the calls and time spent per function to stderr
+        --profile-stacks <file>
+                        write the profiled call stacks to <file>, in the
+                        collapsed format flame graph tools read
+    -h, --help          print this help";
+
+// Exit codes, following sysexits.h
+const EXIT_USAGE: i32 = 64;
+const EXIT_PARSE: i32 = 65;
+const EXIT_NO_INPUT: i32 = 66;
+const EXIT_RUNTIME: i32 = 70;
+
+enum Command {
+    Help,
+    Interactive,
+    Run { path: String },
+    Eval { expr: String },
+    Compile {
+        path: String,
+        output: Option<String>,
+        wat: bool,
+    },
+}
+
+struct Cli {
+    command: Command,
+    print_result: bool,
+    prelude: bool,
+    profile: bool,
+    profile_stacks: Option<String>,
+    args: Vec<String>,
+}
+
+impl Cli {
+    fn parse(mut argv: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut print_result = false;
+        let mut prelude = true;
+        let mut profile = false;
+        let mut profile_stacks = None;
+
+        let command = loop {
+            let arg = match argv.next() {
+                Some(arg) => arg,
+                None => break Command::Interactive,
+            };
+            match arg.as_str() {
+                "-p" | "--print-result" => print_result = true,
+                "--no-prelude" => prelude = false,
+                "--profile" => profile = true,
+                "--profile-stacks" => {
+                    profile_stacks = Some(argv.next().ok_or("--profile-stacks expects a file")?)
+                }
+                "-h" | "--help" => break Command::Help,
+                "-i" | "repl" => break Command::Interactive,
+                "-e" => match argv.next() {
+                    Some(expr) => break Command::Eval { expr },
+                    None => return Err("-e expects an expression".to_string()),
+                },
+                "run" => match argv.next() {
+                    Some(path) => break Command::Run { path },
+                    None => return Err("run expects a file".to_string()),
+                },
+                "compile" => {
+                    let (mut path, mut output, mut wat) = (None, None, false);
+                    while let Some(arg) = argv.next() {
+                        match arg.as_str() {
+                            "-o" => output = Some(argv.next().ok_or("-o expects a file")?),
+                            "--wat" => wat = true,
+                            _ if path.is_none() => path = Some(arg),
+                            _ => return Err(format!("unexpected argument {}", arg)),
+                        }
+                    }
+                    let path = path.ok_or("compile expects a file")?;
+                    break Command::Compile { path, output, wat };
+                }
+                "-" => break Command::Run { path: arg },
+                flag if flag.starts_with('-') => return Err(format!("unknown option {}", flag)),
+                _ => break Command::Run { path: arg },
+            }
+        };
+
+        Ok(Self {
+            command,
+            print_result,
+            prelude,
+            profile,
+            profile_stacks,
+            args: argv.collect(),
+        })
+    }
+}
+
+fn main() {
+    let code = std::thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(unsoph)
+        .unwrap()
+        .join()
+        .unwrap();
+    std::process::exit(code);
+}
+
+fn unsoph() -> i32 {
+    let cli = match Cli::parse(std::env::args().skip(1)) {
+        Ok(cli) => cli,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            return EXIT_USAGE;
+        }
+    };
+
+    let result = match cli.command {
+        Command::Help => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        Command::Interactive => {
+            let mut repl = Repl::interactive(">> ");
+            repl.set_prelude(cli.prelude);
+            repl.mainloop()
+        }
+        Command::Run { path } => {
+            let mut repl = Repl::non_interactive(&path);
+            repl.set_prelude(cli.prelude);
+            repl.set_args(cli.args);
+            repl.set_print_result(cli.print_result);
+            repl.set_profile(cli.profile || cli.profile_stacks.is_some());
+            let result = repl.mainloop();
+            let reported = report_profile(&repl, cli.profile, cli.profile_stacks.as_deref());
+            result.and(reported)
+        }
+        Command::Eval { expr } => {
+            let mut repl = Repl::non_interactive("");
+            repl.set_prelude(cli.prelude);
+            repl.set_args(cli.args);
+            repl.set_profile(cli.profile || cli.profile_stacks.is_some());
+            let result = repl.run(&expr).map(|result| {
+                if cli.print_result {
+                    println!("{:?}", result);
+                }
+            });
+            let reported = report_profile(&repl, cli.profile, cli.profile_stacks.as_deref());
+            result.and(reported)
+        }
+        Command::Compile { path, output, wat } => compile(&path, output.as_deref(), wat),
+    };
+
+    match result {
+        Ok(()) => 0,
+        Err(RunError::Exit(code)) => code,
+        Err(e @ RunError::Input(_)) => {
+            eprintln!("{}", e);
+            EXIT_NO_INPUT
+        }
+        Err(e @ RunError::Parse(_)) => {
+            eprintln!("{}", e);
+            EXIT_PARSE
+        }
+        Err(e @ RunError::Runtime(_)) => {
+            eprintln!("{}", e);
+            EXIT_RUNTIME
+        }
+    }
+}
+
+/// Prints the profile report to stderr and writes the collapsed call stacks
+/// to `stacks`, as asked. Done even when the script failed.
+fn report_profile(repl: &Repl, report: bool, stacks: Option<&str>) -> Result<(), RunError> {
+    let Some(profile) = repl.profile() else {
+        return Ok(());
+    };
+    if report {
+        eprint!("{}", profile.report());
+    }
+    match stacks {
+        Some(stacks) => std::fs::write(stacks, profile.collapsed_stacks())
+            .map_err(|e| RunError::Input(format!("{}: {}", stacks, e))),
+        None => Ok(()),
+    }
+}
+
+/// Writes the Rust, or WebAssembly text, translation of the script at `path`
+/// to `output`, or stdout.
+fn compile(path: &str, output: Option<&str>, wat: bool) -> Result<(), RunError> {
+    let mut source = String::new();
+    let read = if path == "-" {
+        std::io::stdin().read_to_string(&mut source)
+    } else {
+        std::fs::File::open(path).and_then(|mut file| file.read_to_string(&mut source))
+    };
+    read.map_err(|e| RunError::Input(format!("{}: {}", path, e)))?;
+
+    let source = strip_shebang(&source);
+    let program = if wat {
+        compiler::compile_to_wat(source)
+    } else {
+        compiler::compile_to_rust(source)
+    };
+    let program = program.map_err(RunError::Parse)?;
+    match output {
+        Some(output) => std::fs::write(output, program)
+            .map_err(|e| RunError::Input(format!("{}: {}", output, e))),
+        None => {
+            print!("{}", program);
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,46 @@
+//! Compiler module.
+//!
+//! Ahead-of-time backends, turning a program into source code for another
+//! toolchain instead of interpreting it.
+//!
+//! The Rust backend emits a standalone file, with a copy of the `Value`
+//! runtime and the prelude, that builds with a plain `rustc program.rs`.
+//! The wasm backend only accepts a typed subset of the language and emits a
+//! WebAssembly text module.
+
+mod rust;
+mod wasm;
+
+#[cfg(test)]
+mod tests;
+
+pub use rust::RustBackend;
+pub use wasm::WasmBackend;
+
+use crate::interpreter::PRELUDE;
+use crate::parser::{AstNode, Parser};
+use crate::tokenizer::Tokenizer;
+
+/// Parses `source` and compiles it with the [`RustBackend`], after the
+/// prelude so compiled programs see the same definitions as the interpreter.
+pub fn compile_to_rust(source: &str) -> Result<String, String> {
+    let mut program = match parse(PRELUDE)? {
+        AstNode::Do(exprs) => exprs,
+        _ => unreachable!("programs parse to a Do node"),
+    };
+    match parse(source)? {
+        AstNode::Do(exprs) => program.extend(exprs),
+        _ => unreachable!("programs parse to a Do node"),
+    }
+    RustBackend::new().compile(&AstNode::Do(program))
+}
+
+/// Parses `source` and compiles it with the [`WasmBackend`].
+pub fn compile_to_wat(source: &str) -> Result<String, String> {
+    WasmBackend::new().compile(&parse(source)?)
+}
+
+fn parse(source: &str) -> Result<AstNode, String> {
+    let tokens = Tokenizer::new(source).tokenize();
+    Parser::new(tokens).parse_program()
+}
@@ -0,0 +1,863 @@
+// Runtime support for programs emitted by the Rust backend.
+//
+// This file is not part of the crate, it is pasted at the top of every
+// generated program. It mirrors `interpreter::Value` closely enough that
+// `print` output and error messages are the same as under the interpreter.
+
+#![allow(unused)]
+
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::fmt::{Debug, Display, Formatter};
+use std::io::{BufRead, Write};
+use std::rc::Rc;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Nil,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Char(char),
+    Symbol(String),
+    List(Vec<Value>),
+    Vector(Vec<Value>),
+    Range {
+        start: f64,
+        end: Option<f64>,
+        step: f64,
+    },
+    Function(Rc<Function>),
+    Record(Rc<Record>),
+    Seq(Rc<Seq>),
+}
+
+/// How `print` shows values: strings and chars as their text, records as
+/// `#point{x: 1 y: 2}`.
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::String(s) => write!(f, "{}", s),
+            Value::Char(c) => write!(f, "{}", c),
+            value => write!(f, "{}", Nested(value)),
+        }
+    }
+}
+
+/// A value inside a collection, where strings are quoted so `(list "a b")`
+/// does not print like two items.
+struct Nested<'a>(&'a Value);
+
+impl Display for Nested<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            Value::Nil => write!(f, "nil"),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Number(n) => write!(f, "{}", n),
+            Value::String(s) => write!(f, "{:?}", s),
+            Value::Char(c) => write!(f, "{:?}", c),
+            Value::Symbol(symbol) => write!(f, "{}", symbol),
+            Value::List(items) => write_items(f, "(", items, ")"),
+            Value::Vector(items) => write_items(f, "[", items, "]"),
+            Value::Range { start, end, step } => {
+                write!(f, "#<range {}..", start)?;
+                if let Some(end) = end {
+                    write!(f, "{}", end)?;
+                }
+                if *step != 1.0 {
+                    write!(f, " by {}", step)?;
+                }
+                write!(f, ">")
+            }
+            Value::Function(function) => write!(f, "{:?}", function),
+            Value::Record(record) => {
+                write!(f, "#{}{{", record.ty.name)?;
+                for (i, (field, value)) in record.ty.fields.iter().zip(&record.values).enumerate() {
+                    if i > 0 {
+                        write!(f, " ")?;
+                    }
+                    write!(f, "{}: {}", field, Nested(value))?;
+                }
+                write!(f, "}}")
+            }
+            Value::Seq(seq) => write!(f, "{:?}", seq),
+        }
+    }
+}
+
+fn write_items(f: &mut Formatter<'_>, open: &str, items: &[Value], close: &str) -> std::fmt::Result {
+    write!(f, "{}", open)?;
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            write!(f, " ")?;
+        }
+        write!(f, "{}", Nested(item))?;
+    }
+    write!(f, "{}", close)
+}
+
+pub struct Function {
+    pub name: Option<&'static str>,
+    pub arity: usize,
+    pub body: Box<dyn Fn(&mut Env, Vec<Value>) -> Result<Value, String>>,
+}
+
+impl Debug for Function {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.name {
+            Some(name) => write!(f, "#<fn {}>", name),
+            None => write!(f, "#<fn>"),
+        }
+    }
+}
+
+impl PartialEq for Function {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+pub struct RecordType {
+    pub name: &'static str,
+    pub fields: Vec<&'static str>,
+}
+
+pub struct Record {
+    pub ty: Rc<RecordType>,
+    pub values: Vec<Value>,
+}
+
+impl Record {
+    pub fn is_a(&self, ty: &Rc<RecordType>) -> bool {
+        Rc::ptr_eq(&self.ty, ty)
+    }
+}
+
+impl Debug for Record {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{}{{", self.ty.name)?;
+        for (i, (field, value)) in self.ty.fields.iter().zip(&self.values).enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{}: {:?}", field, value)?;
+        }
+        write!(f, "}}")
+    }
+}
+
+impl PartialEq for Record {
+    fn eq(&self, other: &Self) -> bool {
+        self.is_a(&other.ty) && self.values == other.values
+    }
+}
+
+/// Defines the constructor, predicate and accessors of a `defstruct`,
+/// returns the constructor.
+pub fn defstruct(
+    env: &mut Env,
+    name: &'static str,
+    predicate: &'static str,
+    fields: &[&'static str],
+    accessors: &[&'static str],
+) -> Value {
+    let ty = Rc::new(RecordType {
+        name,
+        fields: fields.to_vec(),
+    });
+    env.types.insert(name, ty.clone());
+
+    let of = ty.clone();
+    let body = move |_: &mut Env, args: Vec<Value>| {
+        Ok(Value::Bool(matches!(&args[0], Value::Record(record) if record.is_a(&of))))
+    };
+    env.def(predicate, generated(predicate, 1, Box::new(body)));
+
+    for (index, &accessor) in accessors.iter().enumerate() {
+        let of = ty.clone();
+        let body = move |_: &mut Env, args: Vec<Value>| match &args[0] {
+            Value::Record(record) if record.is_a(&of) => Ok(record.values[index].clone()),
+            arg => Err(format!("{} expects a {}, found {:?}", accessor, of.name, arg)),
+        };
+        env.def(accessor, generated(accessor, 1, Box::new(body)));
+    }
+
+    let arity = fields.len();
+    let body = move |_: &mut Env, values: Vec<Value>| {
+        Ok(Value::Record(Rc::new(Record {
+            ty: ty.clone(),
+            values,
+        })))
+    };
+    env.def(name, generated(name, arity, Box::new(body)))
+}
+
+fn generated(
+    name: &'static str,
+    arity: usize,
+    body: Box<dyn Fn(&mut Env, Vec<Value>) -> Result<Value, String>>,
+) -> Value {
+    Value::Function(Rc::new(Function {
+        name: Some(name),
+        arity,
+        body,
+    }))
+}
+
+type Thunk = Box<dyn FnOnce(&mut Env) -> Result<Value, String>>;
+
+/// A lazy sequence, each cell is realised at most once, when first needed.
+pub struct Seq(RefCell<SeqState>);
+
+enum SeqState {
+    Pending(Thunk),
+    Forcing,
+    Failed(String),
+    Empty,
+    Cons(Value, Rc<Seq>),
+}
+
+impl Seq {
+    pub fn lazy(thunk: Thunk) -> Rc<Seq> {
+        Rc::new(Seq(RefCell::new(SeqState::Pending(thunk))))
+    }
+
+    pub fn cons(first: Value, rest: Rc<Seq>) -> Rc<Seq> {
+        Rc::new(Seq(RefCell::new(SeqState::Cons(first, rest))))
+    }
+
+    pub fn empty() -> Rc<Seq> {
+        Rc::new(Seq(RefCell::new(SeqState::Empty)))
+    }
+
+    /// Realises the first cell, `None` when the sequence is empty.
+    pub fn force(&self, env: &mut Env) -> Result<Option<(Value, Rc<Seq>)>, String> {
+        // Not borrowed while the thunk runs, it may walk other sequences.
+        let state = std::mem::replace(&mut *self.0.borrow_mut(), SeqState::Forcing);
+        let state = match state {
+            SeqState::Pending(thunk) => match thunk(env).and_then(|value| realise(env, value)) {
+                Ok(state) => state,
+                Err(e) => SeqState::Failed(e),
+            },
+            SeqState::Forcing => return Err("Lazy sequence depends on itself".to_string()),
+            state => state,
+        };
+        let cell = match state {
+            SeqState::Cons(ref first, ref rest) => Ok(Some((first.clone(), rest.clone()))),
+            SeqState::Empty => Ok(None),
+            SeqState::Failed(ref e) => Err(e.clone()),
+            _ => unreachable!("realised twice"),
+        };
+        *self.0.borrow_mut() = state;
+        cell
+    }
+}
+
+fn realise(env: &mut Env, value: Value) -> Result<SeqState, String> {
+    let seq = match value {
+        Value::Nil => return Ok(SeqState::Empty),
+        Value::Seq(seq) => seq,
+        Value::List(_) | Value::Vector(_) | Value::String(_) | Value::Range { .. } => {
+            seq_of(value.into_items()?)
+        }
+        value => return Err(format!("lazy-seq expects a sequence, found {:?}", value)),
+    };
+    let cell = seq.force(env)?;
+    Ok(match cell {
+        Some((first, rest)) => SeqState::Cons(first, rest),
+        None => SeqState::Empty,
+    })
+}
+
+fn seq_of(mut items: Box<dyn Iterator<Item = Value>>) -> Rc<Seq> {
+    Seq::lazy(Box::new(move |_: &mut Env| {
+        Ok(match items.next() {
+            Some(item) => Value::Seq(Seq::cons(item, seq_of(items))),
+            None => Value::Nil,
+        })
+    }))
+}
+
+fn lines(path: String, mut lines: std::io::Lines<std::io::BufReader<std::fs::File>>) -> Rc<Seq> {
+    Seq::lazy(Box::new(move |_: &mut Env| match lines.next() {
+        Some(Ok(line)) => Ok(Value::Seq(Seq::cons(Value::String(line), self::lines(path, lines)))),
+        Some(Err(e)) => Err(format!("read-lines {}: {}", path, e)),
+        None => Ok(Value::Nil),
+    }))
+}
+
+/// The sequence `lazy-seq` builds, `thunk` is called the first time it is walked.
+pub fn lazy_seq(thunk: Value) -> Value {
+    Value::Seq(Seq::lazy(Box::new(move |env: &mut Env| {
+        call(env, "lazy-seq", Some(thunk), vec![])
+    })))
+}
+
+impl Drop for Seq {
+    fn drop(&mut self) {
+        let mut next = match std::mem::replace(self.0.get_mut(), SeqState::Empty) {
+            SeqState::Cons(_, rest) => rest,
+            _ => return,
+        };
+        while let Ok(mut seq) = Rc::try_unwrap(next) {
+            next = match std::mem::replace(seq.0.get_mut(), SeqState::Empty) {
+                SeqState::Cons(_, rest) => rest,
+                _ => return,
+            };
+        }
+    }
+}
+
+impl Debug for Seq {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#<seq>")
+    }
+}
+
+impl PartialEq for Seq {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+/// What `for` walks, letting go of the cells of a sequence as it goes.
+pub enum Cursor {
+    Items(Box<dyn Iterator<Item = Value>>),
+    Seq(Rc<Seq>),
+}
+
+impl Cursor {
+    pub fn new(coll: Value) -> Result<Cursor, String> {
+        match coll {
+            Value::Seq(seq) => Ok(Cursor::Seq(seq)),
+            coll => Ok(Cursor::Items(coll.into_items()?)),
+        }
+    }
+
+    pub fn next(&mut self, env: &mut Env) -> Result<Option<Value>, String> {
+        let cell = match self {
+            Cursor::Items(items) => return Ok(items.next()),
+            Cursor::Seq(seq) => seq.force(env)?,
+        };
+        match cell {
+            Some((first, rest)) => {
+                *self = Cursor::Seq(rest);
+                Ok(Some(first))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+fn collect(env: &mut Env, coll: Value, mut items: Vec<Value>) -> Result<Vec<Value>, String> {
+    let mut cursor = Cursor::new(coll)?;
+    while let Some(item) = cursor.next(env)? {
+        items.push(item);
+    }
+    Ok(items)
+}
+
+/// Same limit as `unsoph run`, `main` runs on a thread with `STACK_SIZE`.
+const MAX_CALL_DEPTH: usize = 10_000;
+const STACK_SIZE: usize = 256 << 20;
+
+impl Function {
+    pub fn call(&self, env: &mut Env, args: Vec<Value>) -> Result<Value, String> {
+        if args.len() != self.arity {
+            return Err(format!(
+                "{} expects {} arguments, found {}",
+                self.name.unwrap_or("fn"),
+                self.arity,
+                args.len()
+            ));
+        }
+        if env.depth >= MAX_CALL_DEPTH {
+            return Err("call depth limit exceeded".to_string());
+        }
+
+        env.depth += 1;
+        let result = (self.body)(env, args);
+        env.depth -= 1;
+        result
+    }
+}
+
+/// Calls `callee` if it is a function, the builtin named `ident` otherwise.
+pub fn call(env: &mut Env, ident: &str, callee: Option<Value>, args: Vec<Value>) -> Result<Value, String> {
+    match callee {
+        Some(Value::Function(function)) => function.call(env, args),
+        _ => builtin(env, ident, args),
+    }
+}
+
+/// Calls the value the head of a call expression evaluated to.
+pub fn call_value(env: &mut Env, callee: Value, args: Vec<Value>) -> Result<Value, String> {
+    match callee {
+        Value::Function(function) => function.call(env, args),
+        value => Err(format!("Cannot call {:?}", value)),
+    }
+}
+
+impl Value {
+    pub fn add(&self, other: &Value) -> Result<Value, String> {
+        match (self, other) {
+            (Value::Number(lhs), Value::Number(rhs)) => Ok(Value::Number(lhs + rhs)),
+            (Value::String(lhs), Value::String(rhs)) => Ok(Value::String(lhs.to_string() + rhs)),
+            _ => Err(format!("Cannot add {:?} and {:?}", self, other)),
+        }
+    }
+
+    pub fn sub(&self, other: &Value) -> Result<Value, String> {
+        match (self, other) {
+            (Value::Number(lhs), Value::Number(rhs)) => Ok(Value::Number(lhs - rhs)),
+            _ => Err(format!("Cannot sub {:?} and {:?}", self, other)),
+        }
+    }
+
+    pub fn mul(&self, other: &Value) -> Result<Value, String> {
+        match (self, other) {
+            (Value::Number(lhs), Value::Number(rhs)) => Ok(Value::Number(lhs * rhs)),
+            _ => Err(format!("Cannot mul {:?} and {:?}", self, other)),
+        }
+    }
+
+    pub fn div(&self, other: &Value) -> Result<Value, String> {
+        match (self, other) {
+            (Value::Number(lhs), Value::Number(rhs)) => Ok(Value::Number(lhs / rhs)),
+            _ => Err(format!("Cannot div {:?} and {:?}", self, other)),
+        }
+    }
+
+    pub fn neg(&self) -> Result<Value, String> {
+        match self {
+            Value::Number(n) => Ok(Value::Number(-n)),
+            _ => Err(format!("Cannot neg {:?}", self)),
+        }
+    }
+
+    pub fn not(&self) -> Result<Value, String> {
+        match self {
+            Value::Bool(b) => Ok(Value::Bool(!b)),
+            _ => Err(format!("Cannot not {:?}", self)),
+        }
+    }
+
+    pub fn lt(&self, other: &Value) -> Result<Value, String> {
+        self.compare(other, Ordering::is_lt)
+    }
+
+    pub fn gt(&self, other: &Value) -> Result<Value, String> {
+        self.compare(other, Ordering::is_gt)
+    }
+
+    pub fn le(&self, other: &Value) -> Result<Value, String> {
+        self.compare(other, Ordering::is_le)
+    }
+
+    pub fn ge(&self, other: &Value) -> Result<Value, String> {
+        self.compare(other, Ordering::is_ge)
+    }
+
+    /// Whether the ordering of the two values satisfies `holds`, never for NaN.
+    fn compare(&self, other: &Value, holds: fn(Ordering) -> bool) -> Result<Value, String> {
+        let ordering = match (self, other) {
+            (Value::Number(lhs), Value::Number(rhs)) => lhs.partial_cmp(rhs),
+            (Value::String(lhs), Value::String(rhs)) => Some(lhs.cmp(rhs)),
+            (Value::Char(lhs), Value::Char(rhs)) => Some(lhs.cmp(rhs)),
+            _ => return Err(format!("Cannot compare {:?} and {:?}", self, other)),
+        };
+        Ok(Value::Bool(ordering.is_some_and(holds)))
+    }
+
+    pub fn equals(&self, other: &Value) -> Result<Value, String> {
+        Ok(Value::Bool(self == other))
+    }
+
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Value::Nil => false,
+            Value::Bool(b) => *b,
+            Value::Number(n) => *n != 0.0,
+            Value::String(s) => !s.is_empty(),
+            Value::List(items) | Value::Vector(items) => !items.is_empty(),
+            Value::Range { start, end, step } => match end {
+                Some(end) if *step > 0.0 => start < end,
+                Some(end) => start > end,
+                None => true,
+            },
+            Value::Symbol(_) | Value::Function(_) | Value::Record(_) | Value::Seq(_) => true,
+            _ => false,
+        }
+    }
+
+    pub fn iter(&self) -> Result<Box<dyn Iterator<Item = Value> + '_>, String> {
+        match self {
+            Value::List(items) | Value::Vector(items) => Ok(Box::new(items.iter().cloned())),
+            Value::String(s) => Ok(Box::new(s.chars().map(Value::Char))),
+            Value::Range { start, end, step } => range_items(*start, *end, *step),
+            _ => Err(format!("Cannot iterate over {:?}", self)),
+        }
+    }
+
+    pub fn into_items(self) -> Result<Box<dyn Iterator<Item = Value>>, String> {
+        match self {
+            Value::List(items) | Value::Vector(items) => Ok(Box::new(items.into_iter())),
+            Value::String(s) => Ok(Box::new(
+                s.chars().collect::<Vec<_>>().into_iter().map(Value::Char),
+            )),
+            Value::Range { start, end, step } => range_items(start, end, step),
+            _ => Err(format!("Cannot iterate over {:?}", self)),
+        }
+    }
+}
+
+fn range_items(
+    start: f64,
+    end: Option<f64>,
+    step: f64,
+) -> Result<Box<dyn Iterator<Item = Value>>, String> {
+    if step == 0.0 {
+        return Err("Range step cannot be 0".to_string());
+    }
+    Ok(Box::new(
+        (0u64..)
+            .map(move |i| start + i as f64 * step)
+            .take_while(move |n| match end {
+                Some(end) if step > 0.0 => *n < end,
+                Some(end) => *n > end,
+                None => true,
+            })
+            .map(Value::Number),
+    ))
+}
+
+/// Global bindings introduced by `def` and `defn`.
+pub struct Env {
+    globals: HashMap<&'static str, Value>,
+    types: HashMap<&'static str, Rc<RecordType>>,
+    depth: usize,
+}
+
+impl Env {
+    pub fn new() -> Self {
+        Self {
+            globals: HashMap::new(),
+            types: HashMap::new(),
+            depth: 0,
+        }
+    }
+
+    pub fn record_type(&self, ident: &'static str) -> Result<Rc<RecordType>, String> {
+        self.types
+            .get(ident)
+            .cloned()
+            .ok_or_else(|| format!("{} is not a record type", ident))
+    }
+
+    pub fn lookup(&self, ident: &'static str) -> Option<Value> {
+        self.globals.get(ident).cloned()
+    }
+
+    pub fn get(&self, ident: &'static str) -> Result<Value, String> {
+        self.globals
+            .get(ident)
+            .cloned()
+            .ok_or_else(|| format!("Undefined identifier: {}", ident))
+    }
+
+    pub fn def(&mut self, ident: &'static str, value: Value) -> Value {
+        self.globals.insert(ident, value.clone());
+        value
+    }
+
+    pub fn set(&mut self, ident: &'static str, value: Value) -> Result<Value, String> {
+        match self.globals.get_mut(ident) {
+            Some(slot) => {
+                *slot = value.clone();
+                Ok(value)
+            }
+            None => Err(format!("Undefined identifier: {}", ident)),
+        }
+    }
+}
+
+fn arity<const N: usize>(ident: &str, args: Vec<Value>) -> Result<[Value; N], String> {
+    let found = args.len();
+    args.try_into()
+        .map_err(|_| format!("{} expects {} arguments, found {}", ident, N, found))
+}
+
+fn strings<const N: usize>(ident: &str, args: Vec<Value>) -> Result<[String; N], String> {
+    let args: [Value; N] = arity(ident, args)?;
+    // By value, `array.into_iter()` iterates references before edition 2021
+    let strings = IntoIterator::into_iter(args)
+        .map(|arg| match arg {
+            Value::String(s) => Ok(s),
+            _ => Err(format!("{} expects strings, found {:?}", ident, arg)),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(strings.try_into().unwrap())
+}
+
+/// Builtins, compiled programs run with every capability.
+pub fn builtin(env: &mut Env, ident: &str, args: Vec<Value>) -> Result<Value, String> {
+    match ident {
+        "print" | "println" => {
+            let line = args
+                .iter()
+                .map(|arg| arg.to_string())
+                .collect::<Vec<_>>()
+                .join(" ");
+            println!("{}", line);
+            Ok(Value::Nil)
+        }
+
+        "list" => Ok(Value::List(args)),
+        "vector" => Ok(Value::Vector(args)),
+
+        "range" => {
+            let nums = args
+                .into_iter()
+                .map(|arg| match arg {
+                    Value::Number(n) => Ok(n),
+                    _ => Err(format!("range expects numbers, found {:?}", arg)),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let (start, end, step) = match nums[..] {
+                [] => (0.0, None, 1.0),
+                [end] => (0.0, Some(end), 1.0),
+                [start, end] => (start, Some(end), 1.0),
+                [start, end, step] => (start, Some(end), step),
+                _ => {
+                    return Err(format!(
+                        "range expects at most 3 arguments, found {}",
+                        nums.len()
+                    ))
+                }
+            };
+            if step == 0.0 {
+                return Err("range step cannot be 0".to_string());
+            }
+            Ok(Value::Range { start, end, step })
+        }
+
+        // -- collections, the prelude builds its list helpers on these --
+        "count" => {
+            let [coll] = arity(ident, args)?;
+            match coll {
+                Value::List(items) | Value::Vector(items) => Ok(Value::Number(items.len() as f64)),
+                Value::String(s) => Ok(Value::Number(s.chars().count() as f64)),
+                Value::Range { end: None, .. } => Err("Cannot count an unbounded range".to_string()),
+                Value::Range { .. } => Ok(Value::Number(coll.iter()?.count() as f64)),
+                Value::Seq(seq) => {
+                    let mut items = Cursor::Seq(seq);
+                    let mut count = 0;
+                    while items.next(env)?.is_some() {
+                        count += 1;
+                    }
+                    Ok(Value::Number(count as f64))
+                }
+                _ => Err(format!("count expects a collection, found {:?}", coll)),
+            }
+        }
+
+        // The first element, nil when empty
+        "first" => {
+            let [coll] = arity(ident, args)?;
+            let first = match coll {
+                Value::Seq(seq) => seq.force(env)?.map(|(first, _)| first),
+                coll => coll.iter()?.next(),
+            };
+            Ok(first.unwrap_or(Value::Nil))
+        }
+
+        "empty?" => {
+            let [coll] = arity(ident, args)?;
+            let empty = match coll {
+                Value::Seq(seq) => seq.force(env)?.is_none(),
+                Value::List(_) | Value::Vector(_) | Value::String(_) | Value::Range { .. } => {
+                    coll.iter()?.next().is_none()
+                }
+                _ => return Err(format!("empty? expects a collection, found {:?}", coll)),
+            };
+            Ok(Value::Bool(empty))
+        }
+
+        // Everything but the first element, in the same kind of collection
+        "rest" => {
+            let [coll] = arity(ident, args)?;
+            match coll {
+                Value::List(items) => Ok(Value::List(items.into_iter().skip(1).collect())),
+                Value::Vector(items) => Ok(Value::Vector(items.into_iter().skip(1).collect())),
+                Value::String(s) => Ok(Value::String(s.chars().skip(1).collect())),
+                Value::Range { start, end, step } => Ok(Value::Range {
+                    start: start + step,
+                    end,
+                    step,
+                }),
+                Value::Seq(seq) => Ok(Value::Seq(match seq.force(env)? {
+                    Some((_, rest)) => rest,
+                    None => Seq::empty(),
+                })),
+                _ => Err(format!("rest expects a collection, found {:?}", coll)),
+            }
+        }
+
+        // (cons x coll) prepends, (push coll x) appends
+        "cons" => match arity(ident, args)? {
+            [value, Value::Seq(seq)] => Ok(Value::Seq(Seq::cons(value, seq))),
+            [value, Value::List(mut items)] => {
+                items.insert(0, value);
+                Ok(Value::List(items))
+            }
+            [value, Value::Vector(mut items)] => {
+                items.insert(0, value);
+                Ok(Value::Vector(items))
+            }
+            [_, coll] => Err(format!(
+                "cons expects a list, a vector or a sequence, found {:?}",
+                coll
+            )),
+        },
+        "push" => match arity(ident, args)? {
+            [Value::List(mut items), value] => {
+                items.push(value);
+                Ok(Value::List(items))
+            }
+            [Value::Vector(mut items), value] => {
+                items.push(value);
+                Ok(Value::Vector(items))
+            }
+            [coll, _] => Err(format!("push expects a list or a vector, found {:?}", coll)),
+        },
+
+        "seq->list" => {
+            let [coll] = arity(ident, args)?;
+            collect(env, coll, Vec::new()).map(Value::List)
+        }
+        "reverse" => {
+            let [coll] = arity(ident, args)?;
+            let mut items = collect(env, coll, Vec::new())?;
+            items.reverse();
+            Ok(Value::List(items))
+        }
+        "concat" => {
+            let [a, b] = arity(ident, args)?;
+            let items = collect(env, a, Vec::new())?;
+            collect(env, b, items).map(Value::List)
+        }
+
+        "seq?" => {
+            let [value] = arity(ident, args)?;
+            Ok(Value::Bool(matches!(value, Value::Seq(_))))
+        }
+
+        "integer?" => {
+            let [value] = arity(ident, args)?;
+            Ok(Value::Bool(matches!(value, Value::Number(n) if n.fract() == 0.0)))
+        }
+
+        "error" => match arity(ident, args)? {
+            [Value::String(msg)] => Err(msg),
+            [arg] => Err(format!("error expects a string, found {:?}", arg)),
+        },
+
+        "symbol->string" => match arity(ident, args)? {
+            [Value::Symbol(symbol)] => Ok(Value::String(symbol)),
+            [arg] => Err(format!("symbol->string expects a symbol, found {:?}", arg)),
+        },
+        "string->symbol" => {
+            let [s] = strings(ident, args)?;
+            Ok(Value::Symbol(s))
+        }
+        // Reading code back needs the interpreter, compiled programs have none.
+        "eval" => Err(String::from("eval is not supported by compiled programs")),
+        "spawn" | "await" | "chan" | "send" | "recv" | "close" | "hash-map" | "get" | "assoc"
+        | "keys" | "vals" | "map?" | "json-parse" | "json-stringify" | "read" | "write" => {
+            Err(format!("{} is not supported by compiled programs", ident))
+        }
+
+        "read-file" => {
+            let [path] = strings(ident, args)?;
+            std::fs::read_to_string(&path)
+                .map(Value::String)
+                .map_err(|e| format!("read-file {}: {}", path, e))
+        }
+
+        "read-lines" => {
+            let [path] = strings(ident, args)?;
+            let file = std::fs::File::open(&path).map_err(|e| format!("read-lines {}: {}", path, e))?;
+            Ok(Value::Seq(lines(path, std::io::BufReader::new(file).lines())))
+        }
+
+        "write-file" | "append-file" => {
+            let [path, content] = strings(ident, args)?;
+            std::fs::OpenOptions::new()
+                .create(true)
+                .write(true)
+                .append(ident == "append-file")
+                .truncate(ident == "write-file")
+                .open(&path)
+                .and_then(|mut file| file.write_all(content.as_bytes()))
+                .map(|_| Value::Nil)
+                .map_err(|e| format!("{} {}: {}", ident, path, e))
+        }
+
+        "list-dir" => {
+            let [path] = strings(ident, args)?;
+            let mut names = std::fs::read_dir(&path)
+                .and_then(|entries| {
+                    entries
+                        .map(|entry| Ok(entry?.file_name().to_string_lossy().into_owned()))
+                        .collect::<std::io::Result<Vec<_>>>()
+                })
+                .map_err(|e| format!("list-dir {}: {}", path, e))?;
+            names.sort();
+            Ok(Value::List(names.into_iter().map(Value::String).collect()))
+        }
+
+        "getenv" => {
+            let [name] = strings(ident, args)?;
+            Ok(std::env::var(name).map(Value::String).unwrap_or(Value::Nil))
+        }
+
+        "args" => {
+            strings::<0>(ident, args)?;
+            Ok(Value::List(std::env::args().skip(1).map(Value::String).collect()))
+        }
+
+        "exit" => {
+            let code = match args[..] {
+                [] => 0,
+                [Value::Number(n)] => n as i32,
+                _ => return Err(format!("exit expects an optional number, found {:?}", args)),
+            };
+            std::process::exit(code)
+        }
+
+        "shell" => {
+            let [cmd] = strings(ident, args)?;
+            let output = std::process::Command::new("sh")
+                .arg("-c")
+                .arg(&cmd)
+                .output()
+                .map_err(|e| format!("shell {}: {}", cmd, e))?;
+            if !output.status.success() {
+                return Err(format!(
+                    "shell {}: {}: {}",
+                    cmd,
+                    output.status,
+                    String::from_utf8_lossy(&output.stderr).trim_end()
+                ));
+            }
+            Ok(Value::String(String::from_utf8_lossy(&output.stdout).into_owned()))
+        }
+
+        _ => Err(format!("Unsupported function call: {}", ident)),
+    }
+}
+
+// -- generated program --
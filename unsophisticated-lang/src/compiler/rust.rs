@@ -0,0 +1,797 @@
+use crate::parser::{AstNode, AstVisitor, Datum, Pattern};
+use crate::tokenizer::{BinaryOp, Literal, UnaryOp};
+
+use std::collections::HashMap;
+
+/// Value type, `Env` and builtins every generated program starts with.
+const RUNTIME: &str = include_str!("runtime.rs");
+
+const RECUR_OUTSIDE_LOOP: &str = "recur outside of loop";
+
+/// Turns a program into a standalone Rust source file.
+///
+/// Every expression becomes a Rust expression of type `Value`, evaluated in
+/// the same order as `AstInterpreter` would. Locals live in Rust variables,
+/// `def` goes through a global `Env`, and loops become labeled `loop`s.
+#[derive(Default)]
+pub struct RustBackend {
+    out: String,
+    /// Rust names of the locals in scope, innermost last.
+    scopes: Vec<HashMap<String, String>>,
+    /// Enclosing loops, innermost last.
+    loops: Vec<Loop>,
+    /// Counter making variable names and labels unique.
+    fresh: usize,
+}
+
+struct Loop {
+    label: String,
+    kind: LoopKind,
+}
+
+enum LoopKind {
+    While,
+    For,
+    /// A `loop` form, with the Rust names of its bindings for `recur`.
+    Loop(Vec<String>),
+}
+
+impl RustBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Emits a whole program, `main` exits with 70 on runtime errors like `unsoph run`.
+    pub fn compile(mut self, program: &AstNode) -> Result<String, String> {
+        self.emit(RUNTIME);
+        self.emit("\nfn run(env: &mut Env) -> Result<Value, String> {\nOk(");
+        program.accept(&mut self)?;
+        self.emit(
+            ")\n}\n\
+            \n\
+            fn main() {\n    \
+                let result = std::thread::Builder::new()\n        \
+                    .stack_size(STACK_SIZE)\n        \
+                    .spawn(|| run(&mut Env::new()).map(|_| ()))\n        \
+                    .unwrap()\n        \
+                    .join()\n        \
+                    .unwrap();\n    \
+                if let Err(e) = result {\n        \
+                    eprintln!(\"Error: {}\", e);\n        \
+                    std::process::exit(70);\n    \
+                }\n\
+            }\n",
+        );
+        Ok(self.out)
+    }
+}
+
+impl AstVisitor for RustBackend {
+    fn visit_literal(&mut self, node: &AstNode) -> Result<(), String> {
+        match node {
+            AstNode::Literal(literal) => {
+                let code = match literal {
+                    Literal::NumberLit(n) => {
+                        let n = n
+                            .parse::<f64>()
+                            .map_err(|_| format!("Invalid number literal: {}", n))?;
+                        if n.is_finite() {
+                            format!("Value::Number({:?}f64)", n)
+                        } else {
+                            "Value::Number(f64::INFINITY)".to_string()
+                        }
+                    }
+                    Literal::StringLit(s) => format!("Value::String(String::from({:?}))", s),
+                    Literal::BoolLit(b) => {
+                        let b = b
+                            .parse::<bool>()
+                            .map_err(|_| format!("Invalid bool literal: {}", b))?;
+                        format!("Value::Bool({})", b)
+                    }
+                    Literal::CharLit(c) => {
+                        let c = c
+                            .parse::<char>()
+                            .map_err(|_| format!("Invalid char literal: {}", c))?;
+                        format!("Value::Char({:?})", c)
+                    }
+                };
+                self.emit(&code);
+                Ok(())
+            }
+            _ => Err(format!("Expected a Literal node, found {:?}", node)),
+        }
+    }
+
+    fn visit_ident(&mut self, node: &AstNode) -> Result<(), String> {
+        if let AstNode::Ident(ident) = node {
+            let code = match self.local(ident) {
+                Some(name) => format!("{}.clone()", name),
+                None => format!("env.get({:?})?", ident),
+            };
+            self.emit(&code);
+            Ok(())
+        } else {
+            Err(format!("Expected an Ident node, found {:?}", node))
+        }
+    }
+
+    fn visit_nil(&mut self, node: &AstNode) -> Result<(), String> {
+        if let AstNode::Nil = node {
+            self.emit("Value::Nil");
+            Ok(())
+        } else {
+            Err(format!("Expected a Nil node, found {:?}", node))
+        }
+    }
+
+    fn visit_binary_op(&mut self, node: &AstNode) -> Result<(), String> {
+        if let AstNode::BinaryOp { op, lhs, rhs } = node {
+            let method = match op {
+                BinaryOp::Add => "add",
+                BinaryOp::Sub => "sub",
+                BinaryOp::Mul => "mul",
+                BinaryOp::Div => "div",
+                BinaryOp::Lt => "lt",
+                BinaryOp::Gt => "gt",
+                BinaryOp::Le => "le",
+                BinaryOp::Ge => "ge",
+                BinaryOp::Eq => "equals",
+            };
+            self.emit("{ let __lhs: Value = ");
+            lhs.accept(self)?;
+            self.emit("; let __rhs: Value = ");
+            rhs.accept(self)?;
+            self.emit(&format!("; __lhs.{}(&__rhs)? }}", method));
+            Ok(())
+        } else {
+            Err(format!("Expected a BinaryOp node, found {:?}", node))
+        }
+    }
+
+    fn visit_unary_op(&mut self, node: &AstNode) -> Result<(), String> {
+        if let AstNode::UnaryOp { op, expr } = node {
+            let method = match op {
+                UnaryOp::Neg => "neg",
+                UnaryOp::Not => "not",
+            };
+            self.emit("{ let __expr: Value = ");
+            expr.accept(self)?;
+            self.emit(&format!("; __expr.{}()? }}", method));
+            Ok(())
+        } else {
+            Err(format!("Expected a UnaryOp node, found {:?}", node))
+        }
+    }
+
+    fn visit_fn_call(&mut self, node: &AstNode) -> Result<(), String> {
+        if let AstNode::FnCall { ident, args } = node {
+            self.emit("{ let __args: Vec<Value> = vec![");
+            for arg in args {
+                arg.accept(self)?;
+                self.emit(", ");
+            }
+            let callee = match self.local(ident) {
+                Some(name) => format!("Some({}.clone())", name),
+                None => format!("env.lookup({:?})", ident),
+            };
+            self.emit(&format!(
+                "];\nlet __callee: Option<Value> = {};\ncall(env, {:?}, __callee, __args)? }}",
+                callee, ident
+            ));
+            Ok(())
+        } else {
+            Err(format!("Expected a FnCall node, found {:?}", node))
+        }
+    }
+
+    fn visit_call(&mut self, node: &AstNode) -> Result<(), String> {
+        if let AstNode::Call { callee, args } = node {
+            self.emit("{ let __callee: Value = ");
+            callee.accept(self)?;
+            self.emit(";
+let __args: Vec<Value> = vec![");
+            for arg in args {
+                arg.accept(self)?;
+                self.emit(", ");
+            }
+            self.emit("];
+call_value(env, __callee, __args)? }");
+            Ok(())
+        } else {
+            Err(format!("Expected a Call node, found {:?}", node))
+        }
+    }
+
+    fn visit_if(&mut self, node: &AstNode) -> Result<(), String> {
+        if let AstNode::If {
+            cond,
+            then,
+            otherwise,
+        } = node
+        {
+            self.emit("if (");
+            cond.accept(self)?;
+            self.emit(").is_truthy() {\n");
+            then.accept(self)?;
+            self.emit("\n} else {\n");
+            otherwise.accept(self)?;
+            self.emit("\n}");
+            Ok(())
+        } else {
+            Err(format!("Expected an If node, found {:?}", node))
+        }
+    }
+
+    fn visit_def(&mut self, node: &AstNode) -> Result<(), String> {
+        if let AstNode::Def { ident, expr } = node {
+            self.emit("{ let __value: Value = ");
+            expr.accept(self)?;
+            self.emit(&format!("; env.def({:?}, __value) }}", ident));
+            Ok(())
+        } else {
+            Err(format!("Expected a Def node, found {:?}", node))
+        }
+    }
+
+    fn visit_set(&mut self, node: &AstNode) -> Result<(), String> {
+        if let AstNode::Set { ident, expr } = node {
+            self.emit("{ let __value: Value = ");
+            expr.accept(self)?;
+            let code = match self.local(ident) {
+                Some(name) => format!("; {} = __value.clone(); __value }}", name),
+                None => format!("; env.set({:?}, __value)? }}", ident),
+            };
+            self.emit(&code);
+            Ok(())
+        } else {
+            Err(format!("Expected a Set node, found {:?}", node))
+        }
+    }
+
+    fn visit_let(&mut self, node: &AstNode) -> Result<(), String> {
+        if let AstNode::Let { bindings, body } = node {
+            self.scopes.push(HashMap::new());
+            self.emit("{\n");
+            self.bind_all(bindings)?;
+            self.body(body)?;
+            self.emit("\n}");
+            self.scopes.pop();
+            Ok(())
+        } else {
+            Err(format!("Expected a Let node, found {:?}", node))
+        }
+    }
+
+    fn visit_do(&mut self, node: &AstNode) -> Result<(), String> {
+        if let AstNode::Do(body) = node {
+            self.body(body)
+        } else {
+            Err(format!("Expected a Do node, found {:?}", node))
+        }
+    }
+
+    fn visit_while(&mut self, node: &AstNode) -> Result<(), String> {
+        if let AstNode::While { cond, body } = node {
+            // The condition is not part of the loop, a `break` in there
+            // belongs to the enclosing one.
+            let label = self.fresh("'l");
+            self.emit(&format!("{}: loop {{\nif !(", label));
+            cond.accept(self)?;
+            self.emit(&format!(
+                ").is_truthy() {{ break {} Value::Nil; }}\nlet _ = ",
+                label
+            ));
+
+            self.loops.push(Loop {
+                label,
+                kind: LoopKind::While,
+            });
+            let result = self.body(body);
+            self.loops.pop();
+            result?;
+
+            self.emit(";\n}");
+            Ok(())
+        } else {
+            Err(format!("Expected a While node, found {:?}", node))
+        }
+    }
+
+    fn visit_for(&mut self, node: &AstNode) -> Result<(), String> {
+        if let AstNode::For { ident, iter, body } = node {
+            self.emit("{ let __coll: Value = ");
+            iter.accept(self)?;
+
+            let label = self.fresh("'l");
+            self.scopes.push(HashMap::new());
+            let name = self.bind(ident);
+            self.emit(&format!(
+                ";\nlet mut __items = Cursor::new(__coll)?;\n\
+                {label}: loop {{\n\
+                let mut {name}: Value = match __items.next(env)? {{ Some(__item) => __item, None => break {label} Value::Nil }};\n\
+                let _ = ",
+                label = label,
+                name = name,
+            ));
+
+            self.loops.push(Loop {
+                label,
+                kind: LoopKind::For,
+            });
+            let result = self.body(body);
+            self.loops.pop();
+            self.scopes.pop();
+            result?;
+
+            self.emit(";\n} }");
+            Ok(())
+        } else {
+            Err(format!("Expected a For node, found {:?}", node))
+        }
+    }
+
+    fn visit_loop(&mut self, node: &AstNode) -> Result<(), String> {
+        if let AstNode::Loop { bindings, body } = node {
+            self.scopes.push(HashMap::new());
+            self.emit("{\n");
+            let names = self.bind_all(bindings)?;
+
+            // `recur` assigns the bindings and jumps back to the top.
+            let label = self.fresh("'l");
+            self.emit(&format!("{}: loop {{\nlet __body: Value = ", label));
+            self.loops.push(Loop {
+                label: label.clone(),
+                kind: LoopKind::Loop(names),
+            });
+            let result = self.body(body);
+            self.loops.pop();
+            self.scopes.pop();
+            result?;
+
+            self.emit(&format!(";\nbreak {} __body;\n}} }}", label));
+            Ok(())
+        } else {
+            Err(format!("Expected a Loop node, found {:?}", node))
+        }
+    }
+
+    fn visit_recur(&mut self, node: &AstNode) -> Result<(), String> {
+        if let AstNode::Recur(args) = node {
+            self.emit("{\n");
+            for (i, arg) in args.iter().enumerate() {
+                self.emit(&format!("let __recur{}: Value = ", i));
+                arg.accept(self)?;
+                self.emit(";\n");
+            }
+
+            let code = match self.loops.last() {
+                Some(Loop {
+                    label,
+                    kind: LoopKind::Loop(names),
+                }) => {
+                    if names.len() != args.len() {
+                        error(&format!(
+                            "recur expects {} arguments, found {}",
+                            names.len(),
+                            args.len()
+                        ))
+                    } else {
+                        let assign = names
+                            .iter()
+                            .enumerate()
+                            .map(|(i, name)| format!("{} = __recur{};\n", name, i))
+                            .collect::<String>();
+                        format!("{}continue {}", assign, label)
+                    }
+                }
+                _ => error(RECUR_OUTSIDE_LOOP),
+            };
+            self.emit(&code);
+            self.emit("\n}");
+            Ok(())
+        } else {
+            Err(format!("Expected a Recur node, found {:?}", node))
+        }
+    }
+
+    fn visit_break(&mut self, node: &AstNode) -> Result<(), String> {
+        if let AstNode::Break(expr) = node {
+            self.emit("{ let __value: Value = ");
+            expr.accept(self)?;
+            let code = match self.loops.last() {
+                Some(Loop { label, .. }) => format!("; break {} __value }}", label),
+                None => format!("; {} }}", error("break outside of loop")),
+            };
+            self.emit(&code);
+            Ok(())
+        } else {
+            Err(format!("Expected a Break node, found {:?}", node))
+        }
+    }
+
+    fn visit_continue(&mut self, node: &AstNode) -> Result<(), String> {
+        if let AstNode::Continue = node {
+            let code = match self.loops.last() {
+                Some(Loop {
+                    label,
+                    kind: LoopKind::While | LoopKind::For,
+                }) => {
+                    format!("continue {}", label)
+                }
+                Some(Loop {
+                    kind: LoopKind::Loop(_),
+                    ..
+                }) => error("continue is not allowed inside loop, use recur"),
+                None => error("continue outside of loop"),
+            };
+            self.emit(&code);
+            Ok(())
+        } else {
+            Err(format!("Expected a Continue node, found {:?}", node))
+        }
+    }
+
+    fn visit_fn(&mut self, node: &AstNode) -> Result<(), String> {
+        if let AstNode::Fn { params, body } = node {
+            self.function(None, params, body)
+        } else {
+            Err(format!("Expected a Fn node, found {:?}", node))
+        }
+    }
+
+    fn visit_defn(&mut self, node: &AstNode) -> Result<(), String> {
+        if let AstNode::Defn {
+            ident,
+            params,
+            body,
+        } = node
+        {
+            self.emit("{ let __value: Value = ");
+            self.function(Some(ident), params, body)?;
+            self.emit(&format!("; env.def({:?}, __value) }}", ident));
+            Ok(())
+        } else {
+            Err(format!("Expected a Defn node, found {:?}", node))
+        }
+    }
+
+    fn visit_defstruct(&mut self, node: &AstNode) -> Result<(), String> {
+        if let AstNode::Defstruct { ident, fields } = node {
+            let accessors = fields
+                .iter()
+                .map(|field| format!("{}-{}", ident, field))
+                .collect::<Vec<_>>();
+            self.emit(&format!(
+                "defstruct(env, {:?}, {:?}, &{:?}, &{:?})",
+                ident,
+                format!("{}?", ident),
+                fields,
+                accessors
+            ));
+            Ok(())
+        } else {
+            Err(format!("Expected a Defstruct node, found {:?}", node))
+        }
+    }
+
+    /// Each arm is a labeled block of checks on the subject, a failed check
+    /// breaks out of it to try the next arm.
+    fn visit_match(&mut self, node: &AstNode) -> Result<(), String> {
+        if let AstNode::Match { expr, arms } = node {
+            let subject = self.fresh("__subject");
+            let label = self.fresh("'m");
+            self.emit(&format!("{{
+let {}: Value = ", subject));
+            expr.accept(self)?;
+            self.emit(&format!(";
+{}: {{
+", label));
+
+            for arm in arms {
+                let next = self.fresh("'a");
+                self.scopes.push(HashMap::new());
+                self.emit(&format!("{}: {{
+", next));
+                let result = self.pattern(&arm.pattern, &format!("&{}", subject), &next);
+                let result = result.and_then(|_| match &arm.guard {
+                    Some(guard) => {
+                        self.emit("if !");
+                        guard.accept(self)?;
+                        self.emit(&format!(".is_truthy() {{ break {}; }}\n", next));
+                        Ok(())
+                    }
+                    None => Ok(()),
+                });
+                let result = result.and_then(|_| {
+                    self.emit(&format!("break {} ", label));
+                    self.body(&arm.body)
+                });
+                self.scopes.pop();
+                result?;
+                self.emit(";\n}\n");
+            }
+
+            self.emit(&format!(
+                "return Err(format!(\"No match arm for {{:?}}\", {}));\n}}\n}}",
+                subject
+            ));
+            Ok(())
+        } else {
+            Err(format!("Expected a Match node, found {:?}", node))
+        }
+    }
+
+    fn visit_quote(&mut self, node: &AstNode) -> Result<(), String> {
+        if let AstNode::Quote(datum) = node {
+            self.datum(datum)
+        } else {
+            Err(format!("Expected a Quote node, found {:?}", node))
+        }
+    }
+
+    fn visit_lazy_seq(&mut self, node: &AstNode) -> Result<(), String> {
+        if let AstNode::LazySeq(body) = node {
+            self.emit("{ let __thunk: Value = ");
+            self.function(None, &[], body)?;
+            self.emit("; lazy_seq(__thunk) }");
+            Ok(())
+        } else {
+            Err(format!("Expected a LazySeq node, found {:?}", node))
+        }
+    }
+
+    /// A generator needs its body suspended between statements, compiled
+    /// functions run to completion.
+    fn visit_yield(&mut self, node: &AstNode) -> Result<(), String> {
+        if let AstNode::Yield(_) = node {
+            Err("yield is not supported by compiled programs".to_string())
+        } else {
+            Err(format!("Expected a Yield node, found {:?}", node))
+        }
+    }
+
+    fn visit_token_error(&mut self, node: &AstNode) -> Result<(), String> {
+        if let AstNode::TokenError(token) = node {
+            Err(format!("TokenError: {:?}", token))
+        } else {
+            Err(format!("Expected a TokenError node, found {:?}", node))
+        }
+    }
+
+    fn visit_parser_error(&mut self, node: &AstNode) -> Result<(), String> {
+        if let AstNode::ParserError(msg, node) = node {
+            Err(format!("ParserError: {} {:?}", msg, node))
+        } else {
+            Err(format!("Expected a ParserError node, found {:?}", node))
+        }
+    }
+}
+
+// -- region : helpers --
+
+impl RustBackend {
+    fn emit(&mut self, code: &str) {
+        self.out.push_str(code);
+    }
+
+    fn fresh(&mut self, prefix: &str) -> String {
+        self.fresh += 1;
+        format!("{}{}", prefix, self.fresh)
+    }
+
+    fn local(&self, ident: &str) -> Option<&String> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(ident))
+    }
+
+    /// Declares a local in the innermost scope and returns its Rust name.
+    ///
+    /// Names are unique, so `recur` still reaches the loop bindings when an
+    /// inner `let` shadows them.
+    fn bind(&mut self, ident: &str) -> String {
+        let sanitized = ident
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect::<String>();
+        let name = self.fresh("v") + "_" + &sanitized;
+        self.scopes
+            .last_mut()
+            .expect("bind outside of a scope")
+            .insert(ident.to_string(), name.clone());
+        name
+    }
+
+    /// Emits one `let` per binding, each value sees the bindings before it.
+    fn bind_all(&mut self, bindings: &[(String, AstNode)]) -> Result<Vec<String>, String> {
+        let mut names = Vec::new();
+        for (ident, expr) in bindings {
+            self.emit("let __value: Value = ");
+            expr.accept(self)?;
+            let name = self.bind(ident);
+            self.emit(&format!(";\nlet mut {}: Value = __value;\n", name));
+            names.push(name);
+        }
+        Ok(names)
+    }
+
+    /// Emits a closure over copies of every local in scope, each call starts
+    /// from fresh copies so `set` does not leak from one call to the next.
+    fn function(
+        &mut self,
+        name: Option<&String>,
+        params: &[String],
+        body: &[AstNode],
+    ) -> Result<(), String> {
+        let mut captured = HashMap::new();
+        for scope in &self.scopes {
+            captured.extend(scope.clone());
+        }
+        let mut names = captured.values().cloned().collect::<Vec<_>>();
+        names.sort();
+
+        self.emit("{\n");
+        for name in &names {
+            self.emit(&format!("let {name} = {name}.clone();\n", name = name));
+        }
+        self.emit(&format!(
+            "Value::Function(Rc::new(Function {{ name: {:?}, arity: {}, body: Box::new(move |env: &mut Env, __args: Vec<Value>| -> Result<Value, String> {{\n",
+            name.map(String::as_str),
+            params.len()
+        ));
+        for name in &names {
+            self.emit(&format!("let mut {name} = {name}.clone();\n", name = name));
+        }
+        self.emit("let mut __args = __args.into_iter();\n");
+
+        // The body only sees the captured locals and its parameters, and
+        // is not inside any loop.
+        let scopes = std::mem::replace(&mut self.scopes, vec![captured, HashMap::new()]);
+        let loops = std::mem::take(&mut self.loops);
+        for param in params {
+            let name = self.bind(param);
+            self.emit(&format!(
+                "let mut {}: Value = __args.next().unwrap();\n",
+                name
+            ));
+        }
+        self.emit("Ok(");
+        let result = self.body(body);
+        self.scopes = scopes;
+        self.loops = loops;
+        result?;
+
+        self.emit(")\n}) }))\n}");
+        Ok(())
+    }
+
+    /// Emits a block evaluating to its last expression, `nil` when empty.
+    fn body(&mut self, body: &[AstNode]) -> Result<(), String> {
+        if body.is_empty() {
+            self.emit("Value::Nil");
+            return Ok(());
+        }
+
+        self.emit("{\n");
+        for (i, expr) in body.iter().enumerate() {
+            if i < body.len() - 1 {
+                self.emit("let _ = ");
+                expr.accept(self)?;
+                self.emit(";\n");
+            } else {
+                expr.accept(self)?;
+            }
+        }
+        self.emit("\n}");
+        Ok(())
+    }
+}
+
+impl RustBackend {
+    /// Emits the value of quoted data, built anew each time it is evaluated.
+    fn datum(&mut self, datum: &Datum) -> Result<(), String> {
+        match datum {
+            Datum::Nil => self.emit("Value::Nil"),
+            Datum::Literal(literal) => self.visit_literal(&AstNode::Literal(literal.clone()))?,
+            Datum::Symbol(symbol) => {
+                self.emit(&format!("Value::Symbol(String::from({:?}))", symbol))
+            }
+            Datum::List(items) => {
+                self.emit("Value::List(vec![");
+                for item in items {
+                    self.datum(item)?;
+                    self.emit(", ");
+                }
+                self.emit("])");
+            }
+        }
+        Ok(())
+    }
+
+    /// Emits the checks of `pattern` against `subject`, a `&Value`
+    /// expression, breaking out of `fail` when it does not match. Bindings
+    /// are declared in the innermost scope.
+    fn pattern(&mut self, pattern: &Pattern, subject: &str, fail: &str) -> Result<(), String> {
+        match pattern {
+            Pattern::Wildcard => Ok(()),
+            Pattern::Nil => {
+                self.emit(&format!("if *{} != Value::Nil {{ break {}; }}\n", subject, fail));
+                Ok(())
+            }
+            Pattern::Literal(literal) => {
+                self.emit(&format!("if *{} != ", subject));
+                self.visit_literal(&AstNode::Literal(literal.clone()))?;
+                self.emit(&format!(" {{ break {}; }}\n", fail));
+                Ok(())
+            }
+            Pattern::Bind(ident) => {
+                let name = self.bind(ident);
+                self.emit(&format!("let mut {}: Value = ({}).clone();\n", name, subject));
+                Ok(())
+            }
+            Pattern::List { items, rest } => {
+                let values = self.fresh("__items");
+                let arity = if rest.is_some() { "<" } else { "!=" };
+                self.emit(&format!(
+                    "let {values}: &[Value] = match {subject} {{\n\
+                        Value::List(items) | Value::Vector(items) => items,\n\
+                        _ => break {fail},\n\
+                    }};\n\
+                    if {values}.len() {arity} {len} {{ break {fail}; }}\n",
+                    values = values,
+                    subject = subject,
+                    fail = fail,
+                    arity = arity,
+                    len = items.len(),
+                ));
+                for (i, item) in items.iter().enumerate() {
+                    self.pattern(item, &format!("&{}[{}]", values, i), fail)?;
+                }
+                if let Some(rest) = rest {
+                    let remaining = self.fresh("__rest");
+                    self.emit(&format!(
+                        "let {remaining}: Value = match {subject} {{\n\
+                            Value::Vector(_) => Value::Vector({values}[{len}..].to_vec()),\n\
+                            _ => Value::List({values}[{len}..].to_vec()),\n\
+                        }};\n",
+                        remaining = remaining,
+                        subject = subject,
+                        values = values,
+                        len = items.len(),
+                    ));
+                    self.pattern(rest, &format!("&{}", remaining), fail)?;
+                }
+                Ok(())
+            }
+            Pattern::Record { ident, fields } => {
+                let ty = self.fresh("__type");
+                let values = self.fresh("__fields");
+                self.emit(&format!(
+                    "let {ty} = env.record_type({ident:?})?;\n\
+                    if {ty}.fields.len() != {len} {{\n\
+                        return Err(format!(\"{ident} has {{}} fields, found {len} in a pattern\", {ty}.fields.len()));\n\
+                    }}\n\
+                    let {values}: &[Value] = match {subject} {{\n\
+                        Value::Record(record) if record.is_a(&{ty}) => &record.values,\n\
+                        _ => break {fail},\n\
+                    }};\n",
+                    ty = ty,
+                    ident = ident,
+                    len = fields.len(),
+                    values = values,
+                    subject = subject,
+                    fail = fail,
+                ));
+                for (i, field) in fields.iter().enumerate() {
+                    self.pattern(field, &format!("&{}[{}]", values, i), fail)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Code failing at runtime with `msg`, as the interpreter would.
+fn error(msg: &str) -> String {
+    format!("return Err(String::from({:?}))", msg)
+}
+
+// -- end region : helpers --
@@ -0,0 +1,107 @@
+use super::*;
+
+fn program(source: &str) -> String {
+    // Without the prelude, so local names are numbered from the program
+    let code = RustBackend::new().compile(&parse(source).unwrap()).unwrap();
+    let start = code.find("fn run(").unwrap();
+    code[start..].to_string()
+}
+
+#[test]
+fn test_parse_errors_are_reported() {
+    assert!(compile_to_rust("(+ 1").is_err());
+    assert!(compile_to_rust("(let (1 2) x)").is_err());
+}
+
+#[test]
+fn test_program_is_standalone() {
+    let code = compile_to_rust("(print 1)").unwrap();
+    assert!(code.contains("pub enum Value"));
+    assert!(code.contains("fn main()"));
+    assert!(!code.contains("unsophisticated_lang"));
+}
+
+#[test]
+fn test_globals_and_locals() {
+    let code = program("(def x 1) (let (y x) (set y 2) (set x y))");
+    assert!(code.contains("env.def(\"x\", __value)"));
+    assert!(code.contains("env.get(\"x\")?"));
+    assert!(code.contains("env.set(\"x\", __value)?"));
+    assert!(!code.contains("env.get(\"y\")"));
+}
+
+#[test]
+fn test_shadowed_locals_get_distinct_names() {
+    let code = program("(let (x 1) (let (x 2) x) x)");
+    assert!(code.contains("let mut v1_x: Value"));
+    assert!(code.contains("let mut v2_x: Value"));
+}
+
+#[test]
+fn test_misplaced_signals_fail_at_runtime() {
+    let code = program("(print 1) (break 2)");
+    assert!(code.contains("return Err(String::from(\"break outside of loop\"))"));
+
+    let code = program("(loop (i 0) (continue))");
+    assert!(code.contains("continue is not allowed inside loop, use recur"));
+
+    let code = program("(loop (i 0) (recur 1 2))");
+    assert!(code.contains("recur expects 1 arguments, found 2"));
+}
+
+#[test]
+fn test_wat_infers_result_types() {
+    let wat = compile_to_wat(
+        "(defn even? (n) (if (= n 0) true (odd? (- n 1))))
+         (defn odd? (n) (if (= n 0) false (even? (- n 1))))
+         (even? 4)",
+    )
+    .unwrap();
+    assert!(wat.contains("(func $even? (export \"even?\")"));
+    assert!(wat.contains("(func $odd? (export \"odd?\")"));
+    assert!(wat.contains("(func $main (export \"main\") (result i32)"));
+    assert!(wat.contains("if (result i32)"));
+}
+
+#[test]
+fn test_wat_rejects_the_dynamic_parts() {
+    assert_eq!(
+        compile_to_wat("(print \"hi\")"),
+        Err("Unsupported function call: print".to_string())
+    );
+    assert_eq!(
+        compile_to_wat("(+ 1 true)"),
+        Err("expected a Number, found a Bool".to_string())
+    );
+    assert!(compile_to_wat("(if true 1)").is_err());
+}
+
+#[test]
+fn test_match_compiles_to_checks() {
+    let code = program("(match (list 1) ((x . _) x))");
+    assert!(code.contains("let __subject1: Value ="));
+    assert!(code.contains("if __items4.len() < 1 { break 'a3; }"));
+    assert!(code.contains("let mut v5_x: Value = (&__items4[0]).clone();"));
+    assert!(code.contains("return Err(format!(\"No match arm for {:?}\", __subject1));"));
+}
+
+#[test]
+fn test_quote_builds_values() {
+    let code = program("'(f 1 \"s\" nil)");
+    assert!(code.contains(
+        "Value::List(vec![Value::Symbol(String::from(\"f\")), Value::Number(1.0f64), \
+         Value::String(String::from(\"s\")), Value::Nil, ])"
+    ));
+}
+
+#[test]
+fn test_lazy_seq_wraps_a_thunk() {
+    let code = program("(lazy-seq (list 1))");
+    assert!(code.contains("{ let __thunk: Value = {"));
+    assert!(code.contains("lazy_seq(__thunk) }"));
+
+    assert_eq!(
+        compile_to_rust("(defn g () (yield 1))"),
+        Err("yield is not supported by compiled programs".to_string())
+    );
+}
@@ -0,0 +1,531 @@
+use crate::parser::{AstNode, AstVisitor};
+use crate::tokenizer::{BinaryOp, Literal, UnaryOp};
+
+use std::collections::HashMap;
+
+/// Turns a program written in the typed subset of the language into a
+/// WebAssembly text module.
+///
+/// The subset is numbers (`f64`), booleans (`i32`), arithmetic and
+/// comparisons, `if` with an else branch, `let`, `set`, `do`, and top level
+/// `defn`s calling each other. Parameters are numbers, results are inferred
+/// from the function bodies. Every `defn` is exported under its own name,
+/// the other top level expressions make up an exported `main`.
+#[derive(Default)]
+pub struct WasmBackend {
+    out: String,
+    /// Types of the expressions emitted so far, innermost last.
+    types: Vec<Type>,
+    /// Signatures of the top level functions.
+    functions: HashMap<String, Signature>,
+    /// Wasm names and types of the locals in scope, innermost last.
+    scopes: Vec<HashMap<String, (String, Type)>>,
+    /// Locals of the function being emitted, parameters excluded.
+    locals: Vec<(String, Type)>,
+    /// Counter making local names unique.
+    fresh: usize,
+    indent: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Type {
+    Number,
+    Bool,
+    /// Result of a call to a function whose result is still being
+    /// inferred, never left in emitted code.
+    Unknown,
+}
+
+#[derive(Debug, Clone)]
+struct Signature {
+    params: usize,
+    result: Type,
+}
+
+impl Type {
+    fn wasm(self) -> &'static str {
+        match self {
+            Type::Number => "f64",
+            Type::Bool | Type::Unknown => "i32",
+        }
+    }
+
+    /// Type of an expression that may evaluate to either `self` or `other`.
+    fn unify(self, other: Type) -> Result<Type, String> {
+        match (self, other) {
+            (Type::Unknown, t) | (t, Type::Unknown) => Ok(t),
+            (a, b) if a == b => Ok(a),
+            (a, b) => Err(format!(
+                "if branches have different types, {:?} and {:?}",
+                a, b
+            )),
+        }
+    }
+}
+
+impl WasmBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn compile(mut self, program: &AstNode) -> Result<String, String> {
+        let exprs = match program {
+            AstNode::Do(exprs) => exprs.as_slice(),
+            expr => std::slice::from_ref(expr),
+        };
+        let (defns, main): (Vec<_>, Vec<_>) = exprs
+            .iter()
+            .partition(|expr| matches!(expr, AstNode::Defn { .. }));
+
+        for defn in &defns {
+            if let AstNode::Defn { ident, params, .. } = defn {
+                let signature = Signature {
+                    params: params.len(),
+                    result: Type::Unknown,
+                };
+                if self.functions.insert(ident.clone(), signature).is_some() {
+                    return Err(format!("{} is defined twice", ident));
+                }
+            }
+        }
+        self.infer_results(&defns)?;
+
+        let mut module = String::from("(module\n");
+        for defn in &defns {
+            if let AstNode::Defn {
+                ident,
+                params,
+                body,
+            } = defn
+            {
+                module += &self.function(ident, params, body)?.0;
+            }
+        }
+        if !main.is_empty() {
+            if self.functions.contains_key("main") {
+                return Err("main is defined by the top level expressions".to_string());
+            }
+            let main = main.into_iter().cloned().collect::<Vec<_>>();
+            module += &self.function("main", &[], &main)?.0;
+        }
+        module += ")\n";
+        Ok(module)
+    }
+
+    /// Results start out unknown and are filled in from the bodies until
+    /// nothing changes, a function that never returns defaults to a number.
+    fn infer_results(&mut self, defns: &[&AstNode]) -> Result<(), String> {
+        for _ in 0..=defns.len() {
+            let mut changed = false;
+            for defn in defns {
+                if let AstNode::Defn {
+                    ident,
+                    params,
+                    body,
+                } = defn
+                {
+                    let (_, result) = self.function(ident, params, body)?;
+                    let signature = self.functions.get_mut(ident).unwrap();
+                    if signature.result != result {
+                        signature.result = result;
+                        changed = true;
+                    }
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+
+        for signature in self.functions.values_mut() {
+            if signature.result == Type::Unknown {
+                signature.result = Type::Number;
+            }
+        }
+        Ok(())
+    }
+
+    /// Emits one exported function, returns its code and result type.
+    fn function(
+        &mut self,
+        ident: &str,
+        params: &[String],
+        body: &[AstNode],
+    ) -> Result<(String, Type), String> {
+        self.out.clear();
+        self.locals.clear();
+        self.indent = 2;
+
+        let mut scope = HashMap::new();
+        let mut header = format!("  (func ${} (export {:?})", ident, ident);
+        for param in params {
+            let name = self.fresh(param);
+            header += &format!(" (param {} f64)", name);
+            scope.insert(param.clone(), (name, Type::Number));
+        }
+
+        self.scopes = vec![scope];
+        let result = self.body(body);
+        self.scopes.clear();
+        result?;
+
+        let result = self.types.pop().unwrap();
+        header += &format!(" (result {})\n", result.wasm());
+        for (name, ty) in &self.locals {
+            header += &format!("    (local {} {})\n", name, ty.wasm());
+        }
+        Ok((header + &self.out + "  )\n", result))
+    }
+}
+
+impl AstVisitor for WasmBackend {
+    fn visit_literal(&mut self, node: &AstNode) -> Result<(), String> {
+        match node {
+            AstNode::Literal(Literal::NumberLit(n)) => {
+                let n = n
+                    .parse::<f64>()
+                    .map_err(|_| format!("Invalid number literal: {}", n))?;
+                self.instr(&format!("f64.const {:?}", n));
+                self.types.push(Type::Number);
+                Ok(())
+            }
+            AstNode::Literal(Literal::BoolLit(b)) => {
+                let b = b
+                    .parse::<bool>()
+                    .map_err(|_| format!("Invalid bool literal: {}", b))?;
+                self.instr(&format!("i32.const {}", b as i32));
+                self.types.push(Type::Bool);
+                Ok(())
+            }
+            AstNode::Literal(_) => Err(unsupported("strings and chars")),
+            _ => Err(format!("Expected a Literal node, found {:?}", node)),
+        }
+    }
+
+    fn visit_ident(&mut self, node: &AstNode) -> Result<(), String> {
+        if let AstNode::Ident(ident) = node {
+            let (name, ty) = self.local(ident)?;
+            self.instr(&format!("local.get {}", name));
+            self.types.push(ty);
+            Ok(())
+        } else {
+            Err(format!("Expected an Ident node, found {:?}", node))
+        }
+    }
+
+    fn visit_nil(&mut self, node: &AstNode) -> Result<(), String> {
+        if let AstNode::Nil = node {
+            Err(unsupported("nil"))
+        } else {
+            Err(format!("Expected a Nil node, found {:?}", node))
+        }
+    }
+
+    fn visit_binary_op(&mut self, node: &AstNode) -> Result<(), String> {
+        if let AstNode::BinaryOp { op, lhs, rhs } = node {
+            lhs.accept(self)?;
+            rhs.accept(self)?;
+            let rhs = self.types.pop().unwrap();
+            let lhs = self.types.pop().unwrap();
+
+            let (instr, ty) = match op {
+                BinaryOp::Eq => match lhs.unify(rhs) {
+                    Ok(Type::Bool) => ("i32.eq", Type::Bool),
+                    Ok(_) => ("f64.eq", Type::Bool),
+                    Err(_) => return Err(format!("Cannot compare {:?} and {:?}", lhs, rhs)),
+                },
+                op => {
+                    expect(Type::Number, lhs)?;
+                    expect(Type::Number, rhs)?;
+                    match op {
+                        BinaryOp::Add => ("f64.add", Type::Number),
+                        BinaryOp::Sub => ("f64.sub", Type::Number),
+                        BinaryOp::Mul => ("f64.mul", Type::Number),
+                        BinaryOp::Div => ("f64.div", Type::Number),
+                        BinaryOp::Lt => ("f64.lt", Type::Bool),
+                        BinaryOp::Gt => ("f64.gt", Type::Bool),
+                        BinaryOp::Le => ("f64.le", Type::Bool),
+                        BinaryOp::Ge => ("f64.ge", Type::Bool),
+                        BinaryOp::Eq => unreachable!(),
+                    }
+                }
+            };
+            self.instr(instr);
+            self.types.push(ty);
+            Ok(())
+        } else {
+            Err(format!("Expected a BinaryOp node, found {:?}", node))
+        }
+    }
+
+    fn visit_unary_op(&mut self, node: &AstNode) -> Result<(), String> {
+        if let AstNode::UnaryOp { op, expr } = node {
+            expr.accept(self)?;
+            let ty = self.types.pop().unwrap();
+            match op {
+                UnaryOp::Neg => {
+                    expect(Type::Number, ty)?;
+                    self.instr("f64.neg");
+                    self.types.push(Type::Number);
+                }
+                UnaryOp::Not => {
+                    expect(Type::Bool, ty)?;
+                    self.instr("i32.eqz");
+                    self.types.push(Type::Bool);
+                }
+            }
+            Ok(())
+        } else {
+            Err(format!("Expected a UnaryOp node, found {:?}", node))
+        }
+    }
+
+    fn visit_fn_call(&mut self, node: &AstNode) -> Result<(), String> {
+        if let AstNode::FnCall { ident, args } = node {
+            let signature = self
+                .functions
+                .get(ident)
+                .cloned()
+                .ok_or_else(|| format!("Unsupported function call: {}", ident))?;
+            if args.len() != signature.params {
+                return Err(format!(
+                    "{} expects {} arguments, found {}",
+                    ident,
+                    signature.params,
+                    args.len()
+                ));
+            }
+
+            for arg in args {
+                arg.accept(self)?;
+                expect(Type::Number, self.types.pop().unwrap())?;
+            }
+            self.instr(&format!("call ${}", ident));
+            self.types.push(signature.result);
+            Ok(())
+        } else {
+            Err(format!("Expected a FnCall node, found {:?}", node))
+        }
+    }
+
+    fn visit_call(&mut self, _node: &AstNode) -> Result<(), String> {
+        Err(unsupported("calling an expression"))
+    }
+
+    fn visit_if(&mut self, node: &AstNode) -> Result<(), String> {
+        if let AstNode::If {
+            cond,
+            then,
+            otherwise,
+        } = node
+        {
+            if let AstNode::Nil = **otherwise {
+                return Err(unsupported("if without an else branch"));
+            }
+
+            cond.accept(self)?;
+            // Numbers are truthy when they are not zero, like in the interpreter.
+            if self.types.pop().unwrap() == Type::Number {
+                self.instr("f64.const 0");
+                self.instr("f64.ne");
+            }
+
+            // The result type is only known once both branches are emitted.
+            let start = self.out.len();
+            self.indent += 1;
+            then.accept(self)?;
+            self.indent -= 1;
+            self.instr("else");
+            self.indent += 1;
+            otherwise.accept(self)?;
+            self.indent -= 1;
+            self.instr("end");
+
+            let otherwise = self.types.pop().unwrap();
+            let then = self.types.pop().unwrap();
+            let ty = then.unify(otherwise)?;
+            let header = format!("{}if (result {})\n", "  ".repeat(self.indent), ty.wasm());
+            self.out.insert_str(start, &header);
+            self.types.push(ty);
+            Ok(())
+        } else {
+            Err(format!("Expected an If node, found {:?}", node))
+        }
+    }
+
+    fn visit_def(&mut self, node: &AstNode) -> Result<(), String> {
+        if let AstNode::Def { .. } = node {
+            Err(unsupported("def"))
+        } else {
+            Err(format!("Expected a Def node, found {:?}", node))
+        }
+    }
+
+    fn visit_set(&mut self, node: &AstNode) -> Result<(), String> {
+        if let AstNode::Set { ident, expr } = node {
+            let (name, ty) = self.local(ident)?;
+            expr.accept(self)?;
+            expect(ty, *self.types.last().unwrap())?;
+            self.instr(&format!("local.tee {}", name));
+            Ok(())
+        } else {
+            Err(format!("Expected a Set node, found {:?}", node))
+        }
+    }
+
+    fn visit_let(&mut self, node: &AstNode) -> Result<(), String> {
+        if let AstNode::Let { bindings, body } = node {
+            self.scopes.push(HashMap::new());
+            let result = self.let_bindings(bindings).and_then(|_| self.body(body));
+            self.scopes.pop();
+            result
+        } else {
+            Err(format!("Expected a Let node, found {:?}", node))
+        }
+    }
+
+    fn visit_do(&mut self, node: &AstNode) -> Result<(), String> {
+        if let AstNode::Do(body) = node {
+            self.body(body)
+        } else {
+            Err(format!("Expected a Do node, found {:?}", node))
+        }
+    }
+
+    fn visit_while(&mut self, _node: &AstNode) -> Result<(), String> {
+        Err(unsupported("while"))
+    }
+
+    fn visit_for(&mut self, _node: &AstNode) -> Result<(), String> {
+        Err(unsupported("for"))
+    }
+
+    fn visit_loop(&mut self, _node: &AstNode) -> Result<(), String> {
+        Err(unsupported("loop"))
+    }
+
+    fn visit_recur(&mut self, _node: &AstNode) -> Result<(), String> {
+        Err(unsupported("recur"))
+    }
+
+    fn visit_break(&mut self, _node: &AstNode) -> Result<(), String> {
+        Err(unsupported("break"))
+    }
+
+    fn visit_continue(&mut self, _node: &AstNode) -> Result<(), String> {
+        Err(unsupported("continue"))
+    }
+
+    fn visit_fn(&mut self, _node: &AstNode) -> Result<(), String> {
+        Err(unsupported("fn"))
+    }
+
+    fn visit_defn(&mut self, _node: &AstNode) -> Result<(), String> {
+        Err(unsupported("defn outside of the top level"))
+    }
+
+    fn visit_defstruct(&mut self, _node: &AstNode) -> Result<(), String> {
+        Err(unsupported("defstruct"))
+    }
+
+    fn visit_match(&mut self, _node: &AstNode) -> Result<(), String> {
+        Err(unsupported("match"))
+    }
+
+    fn visit_quote(&mut self, _node: &AstNode) -> Result<(), String> {
+        Err(unsupported("quote"))
+    }
+
+    fn visit_lazy_seq(&mut self, _node: &AstNode) -> Result<(), String> {
+        Err(unsupported("lazy-seq"))
+    }
+
+    fn visit_yield(&mut self, _node: &AstNode) -> Result<(), String> {
+        Err(unsupported("yield"))
+    }
+
+    fn visit_token_error(&mut self, node: &AstNode) -> Result<(), String> {
+        if let AstNode::TokenError(token) = node {
+            Err(format!("TokenError: {:?}", token))
+        } else {
+            Err(format!("Expected a TokenError node, found {:?}", node))
+        }
+    }
+
+    fn visit_parser_error(&mut self, node: &AstNode) -> Result<(), String> {
+        if let AstNode::ParserError(msg, node) = node {
+            Err(format!("ParserError: {} {:?}", msg, node))
+        } else {
+            Err(format!("Expected a ParserError node, found {:?}", node))
+        }
+    }
+}
+
+// -- region : helpers --
+
+impl WasmBackend {
+    fn instr(&mut self, instr: &str) {
+        self.out += &"  ".repeat(self.indent);
+        self.out += instr;
+        self.out += "\n";
+    }
+
+    fn fresh(&mut self, ident: &str) -> String {
+        self.fresh += 1;
+        format!("${}.{}", ident, self.fresh)
+    }
+
+    fn local(&self, ident: &str) -> Result<(String, Type), String> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(ident))
+            .cloned()
+            .ok_or_else(|| format!("Undefined identifier: {}", ident))
+    }
+
+    fn let_bindings(&mut self, bindings: &[(String, AstNode)]) -> Result<(), String> {
+        for (ident, expr) in bindings {
+            expr.accept(self)?;
+            let ty = self.types.pop().unwrap();
+            let name = self.fresh(ident);
+            self.instr(&format!("local.set {}", name));
+            self.locals.push((name.clone(), ty));
+            self.scopes
+                .last_mut()
+                .unwrap()
+                .insert(ident.clone(), (name, ty));
+        }
+        Ok(())
+    }
+
+    /// Emits every expression, dropping all values but the last one.
+    fn body(&mut self, body: &[AstNode]) -> Result<(), String> {
+        if body.is_empty() {
+            return Err(unsupported("nil"));
+        }
+
+        for (i, expr) in body.iter().enumerate() {
+            expr.accept(self)?;
+            if i < body.len() - 1 {
+                self.types.pop();
+                self.instr("drop");
+            }
+        }
+        Ok(())
+    }
+}
+
+fn expect(expected: Type, found: Type) -> Result<(), String> {
+    if expected.unify(found).is_ok() {
+        Ok(())
+    } else {
+        Err(format!("expected a {:?}, found a {:?}", expected, found))
+    }
+}
+
+fn unsupported(what: &str) -> String {
+    format!("{} is not supported by the wasm backend", what)
+}
+
+// -- end region : helpers --
@@ -0,0 +1,505 @@
+use super::data;
+use super::lazy::{self, Cursor};
+use super::quote;
+use super::values::{Body, Channel, Function, Seq, Value};
+use super::{AstInterpreter, Capability, LimitExceeded};
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::process::{Command, Output, Stdio};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+impl AstInterpreter {
+    pub(super) fn call_builtin(&mut self, ident: &str, args: Vec<Value>) -> Result<Value, String> {
+        match ident {
+            "print" | "println" => {
+                let line = args
+                    .iter()
+                    .map(|arg| arg.to_string())
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                println!("{}", line);
+                Ok(Value::Nil)
+            }
+
+            "list" => Ok(Value::List(args)),
+            "vector" => Ok(Value::Vector(args)),
+
+            // (range), (range end), (range start end), (range start end step)
+            "range" => {
+                let nums = args
+                    .into_iter()
+                    .map(|arg| match arg {
+                        Value::Number(n) => Ok(n),
+                        _ => Err(format!("range expects numbers, found {:?}", arg)),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                let (start, end, step) = match nums[..] {
+                    [] => (0.0, None, 1.0),
+                    [end] => (0.0, Some(end), 1.0),
+                    [start, end] => (start, Some(end), 1.0),
+                    [start, end, step] => (start, Some(end), step),
+                    _ => {
+                        return Err(format!(
+                            "range expects at most 3 arguments, found {}",
+                            nums.len()
+                        ))
+                    }
+                };
+                if step == 0.0 {
+                    return Err("range step cannot be 0".to_string());
+                }
+                Ok(Value::Range { start, end, step })
+            }
+
+            // -- collections, the prelude builds its list helpers on these --
+            "count" => {
+                let [coll] = arity(ident, args)?;
+                match coll {
+                    Value::List(items) | Value::Vector(items) => Ok(Value::Number(items.len() as f64)),
+                    Value::Map(entries) => Ok(Value::Number(entries.len() as f64)),
+                    Value::String(s) => Ok(Value::Number(s.chars().count() as f64)),
+                    Value::Range { end: None, .. } => Err("Cannot count an unbounded range".to_string()),
+                    Value::Range { .. } => Ok(Value::Number(coll.iter()?.count() as f64)),
+                    Value::Seq(seq) => {
+                        let mut items = Cursor::Seq(seq);
+                        let mut count = 0;
+                        while self.advance(&mut items)?.is_some() {
+                            count += 1;
+                        }
+                        Ok(Value::Number(count as f64))
+                    }
+                    _ => Err(format!("count expects a collection, found {:?}", coll)),
+                }
+            }
+
+            // The first element, nil when empty
+            "first" => {
+                let [coll] = arity(ident, args)?;
+                let first = match coll {
+                    Value::Seq(seq) => self.force(&seq)?.map(|(first, _)| first),
+                    coll => coll.iter()?.next(),
+                };
+                Ok(first.unwrap_or(Value::Nil))
+            }
+
+            "empty?" => {
+                let [coll] = arity(ident, args)?;
+                let empty = match coll {
+                    Value::Seq(seq) => self.force(&seq)?.is_none(),
+                    Value::List(_)
+                    | Value::Vector(_)
+                    | Value::Map(_)
+                    | Value::String(_)
+                    | Value::Range { .. } => coll.iter()?.next().is_none(),
+                    _ => return Err(format!("empty? expects a collection, found {:?}", coll)),
+                };
+                Ok(Value::Bool(empty))
+            }
+
+            // Everything but the first element, in the same kind of collection
+            "rest" => {
+                let [coll] = arity(ident, args)?;
+                match coll {
+                    Value::List(items) => Ok(Value::List(items.into_iter().skip(1).collect())),
+                    Value::Vector(items) => Ok(Value::Vector(items.into_iter().skip(1).collect())),
+                    Value::String(s) => Ok(Value::String(s.chars().skip(1).collect())),
+                    Value::Range { start, end, step } => Ok(Value::Range {
+                        start: start + step,
+                        end,
+                        step,
+                    }),
+                    Value::Seq(seq) => Ok(Value::Seq(
+                        self.force(&seq)?.map_or_else(Seq::empty, |(_, rest)| rest),
+                    )),
+                    _ => Err(format!("rest expects a collection, found {:?}", coll)),
+                }
+            }
+
+            // (cons x coll) prepends, (push coll x) appends
+            "cons" => match arity(ident, args)? {
+                [value, Value::Seq(seq)] => Ok(Value::Seq(Seq::cons(value, seq))),
+                [value, Value::List(mut items)] => {
+                    items.insert(0, value);
+                    Ok(Value::List(items))
+                }
+                [value, Value::Vector(mut items)] => {
+                    items.insert(0, value);
+                    Ok(Value::Vector(items))
+                }
+                [_, coll] => Err(format!(
+                    "cons expects a list, a vector or a sequence, found {:?}",
+                    coll
+                )),
+            },
+            "push" => match arity(ident, args)? {
+                [Value::List(mut items), value] => {
+                    items.push(value);
+                    Ok(Value::List(items))
+                }
+                [Value::Vector(mut items), value] => {
+                    items.push(value);
+                    Ok(Value::Vector(items))
+                }
+                [coll, _] => Err(format!("push expects a list or a vector, found {:?}", coll)),
+            },
+
+            // Built in one pass, growing a list in the prelude copies it every item
+            "seq->list" => {
+                let [coll] = arity(ident, args)?;
+                self.collect(coll, Vec::new()).map(Value::List)
+            }
+            "reverse" => {
+                let [coll] = arity(ident, args)?;
+                let mut items = self.collect(coll, Vec::new())?;
+                items.reverse();
+                Ok(Value::List(items))
+            }
+            "concat" => {
+                let [a, b] = arity(ident, args)?;
+                let items = self.collect(a, Vec::new())?;
+                self.collect(b, items).map(Value::List)
+            }
+
+            // -- maps, keyed by strings --
+
+            // (hash-map "a" 1 "b" 2)
+            "hash-map" => {
+                if !args.len().is_multiple_of(2) {
+                    return Err(format!(
+                        "hash-map expects keys and values, found {} arguments",
+                        args.len()
+                    ));
+                }
+                let mut entries = BTreeMap::new();
+                let mut args = args.into_iter();
+                while let (Some(key), Some(value)) = (args.next(), args.next()) {
+                    entries.insert(map_key(ident, key)?, value);
+                }
+                Ok(Value::Map(entries))
+            }
+            // The value of a key, nil when missing
+            "get" => match arity(ident, args)? {
+                [Value::Map(entries), key] => {
+                    Ok(entries.get(&map_key(ident, key)?).cloned().unwrap_or(Value::Nil))
+                }
+                [coll, _] => Err(format!("get expects a map, found {:?}", coll)),
+            },
+            "assoc" => match arity(ident, args)? {
+                [Value::Map(mut entries), key, value] => {
+                    entries.insert(map_key(ident, key)?, value);
+                    Ok(Value::Map(entries))
+                }
+                [coll, _, _] => Err(format!("assoc expects a map, found {:?}", coll)),
+            },
+            "keys" | "vals" => match arity(ident, args)? {
+                [Value::Map(entries)] if ident == "keys" => {
+                    Ok(Value::List(entries.into_keys().map(Value::String).collect()))
+                }
+                [Value::Map(entries)] => Ok(Value::List(entries.into_values().collect())),
+                [coll] => Err(format!("{} expects a map, found {:?}", ident, coll)),
+            },
+            "map?" => {
+                let [value] = arity(ident, args)?;
+                Ok(Value::Bool(matches!(value, Value::Map(_))))
+            }
+
+            "seq?" => {
+                let [value] = arity(ident, args)?;
+                Ok(Value::Bool(matches!(value, Value::Seq(_))))
+            }
+
+            "integer?" => {
+                let [value] = arity(ident, args)?;
+                Ok(Value::Bool(matches!(value, Value::Number(n) if n.fract() == 0.0)))
+            }
+
+            // (error msg) fails with msg, for the prelude to reject bad arguments
+            "error" => match arity(ident, args)? {
+                [Value::String(msg)] => Err(msg),
+                [arg] => Err(format!("error expects a string, found {:?}", arg)),
+            },
+
+            // -- code as data --
+            "symbol->string" => match arity(ident, args)? {
+                [Value::Symbol(symbol)] => Ok(Value::String(symbol)),
+                [arg] => Err(format!("symbol->string expects a symbol, found {:?}", arg)),
+            },
+            "string->symbol" => {
+                let [s] = strings(ident, args)?;
+                Ok(Value::Symbol(s))
+            }
+
+            // (eval form) or (eval form (list 'x 1 'y 2)), runs with only the
+            // globals and those bindings in scope, like a function body.
+            "eval" => {
+                let found = args.len();
+                let mut args = args.into_iter();
+                let (form, env) = match (args.next(), args.next(), args.next()) {
+                    (Some(form), env, None) => (form, env),
+                    _ => return Err(format!("eval expects 1 or 2 arguments, found {}", found)),
+                };
+                let (params, values) = match &env {
+                    Some(env) => quote::bindings(env)?,
+                    None => (vec![], vec![]),
+                };
+                let function = Function {
+                    name: Some(ident.to_string()),
+                    params,
+                    body: Body::Ast(vec![quote::to_ast(&form)?]),
+                    env: HashMap::new(),
+                };
+                self.call_function(&function, values)
+            }
+
+            // -- concurrency --
+
+            // (spawn f arg...) calls f on a new thread, (await future) waits
+            // for what it returns.
+            "spawn" => {
+                self.require(ident, Capability::Threads)?;
+                let mut args = args.into_iter();
+                match args.next() {
+                    Some(Value::Function(function)) => {
+                        Ok(Value::Future(self.spawn(function, args.collect())?))
+                    }
+                    arg => Err(format!(
+                        "spawn expects a function, found {:?}",
+                        arg.unwrap_or(Value::Nil)
+                    )),
+                }
+            }
+            "await" => match arity(ident, args)? {
+                [Value::Future(future)] => self.await_future(&future),
+                [arg] => Err(format!("await expects a future, found {:?}", arg)),
+            },
+
+            // (recv ch) blocks until a value is sent, nil once ch is closed and drained
+            "chan" => {
+                arity::<0>(ident, args)?;
+                Ok(Value::Chan(Arc::new(Channel::new())))
+            }
+            "send" => match arity(ident, args)? {
+                [Value::Chan(chan), value] => chan.send(value).map(|_| Value::Nil),
+                [arg, _] => Err(format!("send expects a channel, found {:?}", arg)),
+            },
+            "recv" => match arity(ident, args)? {
+                [Value::Chan(chan)] => Ok(self.recv(&chan)?.unwrap_or(Value::Nil)),
+                [arg] => Err(format!("recv expects a channel, found {:?}", arg)),
+            },
+            "close" => match arity(ident, args)? {
+                [Value::Chan(chan)] => {
+                    chan.close();
+                    Ok(Value::Nil)
+                }
+                [arg] => Err(format!("close expects a channel, found {:?}", arg)),
+            },
+
+            // -- data files --
+
+            // JSON arrays read as vectors and objects as maps
+            "json-parse" => {
+                let [s] = strings(ident, args)?;
+                data::json_parse(&s)
+            }
+            "json-stringify" => {
+                let [value] = arity(ident, args)?;
+                data::json_stringify(&value).map(Value::String)
+            }
+            // (write value) is the s-expression (read s) turns back into value
+            "read" => {
+                let [s] = strings(ident, args)?;
+                data::read(&s, &self.types)
+            }
+            "write" => {
+                let [value] = arity(ident, args)?;
+                data::write(&value).map(Value::String)
+            }
+
+            // -- I/O, gated by the interpreter capabilities --
+            "read-file" => {
+                self.require(ident, Capability::FsRead)?;
+                let [path] = strings(ident, args)?;
+                std::fs::read_to_string(&path)
+                    .map(Value::String)
+                    .map_err(|e| format!("read-file {}: {}", path, e))
+            }
+
+            // A lazy sequence of the lines, read as it is walked.
+            "read-lines" => {
+                self.require(ident, Capability::FsRead)?;
+                let [path] = strings(ident, args)?;
+                let file = File::open(&path).map_err(|e| format!("read-lines {}: {}", path, e))?;
+                Ok(Value::Seq(lazy::lines(path, BufReader::new(file).lines())))
+            }
+
+            "write-file" | "append-file" => {
+                self.require(ident, Capability::FsWrite)?;
+                let [path, content] = strings(ident, args)?;
+                std::fs::OpenOptions::new()
+                    .create(true)
+                    .write(true)
+                    .append(ident == "append-file")
+                    .truncate(ident == "write-file")
+                    .open(&path)
+                    .and_then(|mut file| file.write_all(content.as_bytes()))
+                    .map(|_| Value::Nil)
+                    .map_err(|e| format!("{} {}: {}", ident, path, e))
+            }
+
+            "list-dir" => {
+                self.require(ident, Capability::FsRead)?;
+                let [path] = strings(ident, args)?;
+                let mut names = std::fs::read_dir(&path)
+                    .and_then(|entries| {
+                        entries
+                            .map(|entry| Ok(entry?.file_name().to_string_lossy().into_owned()))
+                            .collect::<std::io::Result<Vec<_>>>()
+                    })
+                    .map_err(|e| format!("list-dir {}: {}", path, e))?;
+                names.sort();
+                Ok(Value::List(names.into_iter().map(Value::String).collect()))
+            }
+
+            "getenv" => {
+                self.require(ident, Capability::Env)?;
+                let [name] = strings(ident, args)?;
+                Ok(std::env::var(name).map(Value::String).unwrap_or(Value::Nil))
+            }
+
+            "args" => {
+                self.require(ident, Capability::Env)?;
+                strings::<0>(ident, args)?;
+                Ok(Value::List(
+                    self.args.iter().cloned().map(Value::String).collect(),
+                ))
+            }
+
+            "exit" => {
+                self.require(ident, Capability::Process)?;
+                let code = match args[..] {
+                    [] => 0,
+                    [Value::Number(n)] => n as i32,
+                    _ => return Err(format!("exit expects an optional number, found {:?}", args)),
+                };
+                // Unwinds like an error, the host reads `exit_code` to tell them apart.
+                self.exit_code = Some(code);
+                Err(format!("exit {}", code))
+            }
+
+            "shell" => {
+                self.require(ident, Capability::Process)?;
+                let [cmd] = strings(ident, args)?;
+                let output = match shell(&cmd, self.deadline()) {
+                    Ok(Some(output)) => output,
+                    Ok(None) => return self.exceeded(LimitExceeded::Time).map(|()| Value::Nil),
+                    Err(e) => return Err(format!("shell {}: {}", cmd, e)),
+                };
+                if !output.status.success() {
+                    return Err(format!(
+                        "shell {}: {}: {}",
+                        cmd,
+                        output.status,
+                        String::from_utf8_lossy(&output.stderr).trim_end()
+                    ));
+                }
+                Ok(Value::String(
+                    String::from_utf8_lossy(&output.stdout).into_owned(),
+                ))
+            }
+
+            _ => Err(format!("Unsupported function call: {}", ident)),
+        }
+    }
+}
+
+impl AstInterpreter {
+    fn require(&self, ident: &str, capability: Capability) -> Result<(), String> {
+        if self.capabilities.contains(&capability) {
+            Ok(())
+        } else {
+            Err(format!(
+                "{} is not permitted without the {:?} capability",
+                ident, capability
+            ))
+        }
+    }
+}
+
+/// How often `shell` checks whether its command exited before the deadline.
+const SHELL_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Runs `cmd` with `sh`, `None` when it is killed for running past
+/// `deadline`. The output is read on threads of its own, so that the command
+/// cannot block on a full pipe while it is being waited for.
+fn shell(cmd: &str, deadline: Option<Instant>) -> io::Result<Option<Output>> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(cmd)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let stdout = read_all(child.stdout.take().unwrap());
+    let stderr = read_all(child.stderr.take().unwrap());
+
+    let status = match deadline {
+        None => child.wait()?,
+        Some(deadline) => loop {
+            if let Some(status) = child.try_wait()? {
+                break status;
+            }
+            if Instant::now() >= deadline {
+                child.kill()?;
+                child.wait()?;
+                return Ok(None);
+            }
+            thread::sleep(SHELL_POLL_INTERVAL);
+        },
+    };
+
+    Ok(Some(Output {
+        status,
+        stdout: stdout.join().unwrap()?,
+        stderr: stderr.join().unwrap()?,
+    }))
+}
+
+fn read_all(mut pipe: impl Read + Send + 'static) -> JoinHandle<io::Result<Vec<u8>>> {
+    thread::spawn(move || {
+        let mut bytes = vec![];
+        pipe.read_to_end(&mut bytes)?;
+        Ok(bytes)
+    })
+}
+
+/// Checks that a builtin got exactly `N` arguments.
+fn arity<const N: usize>(ident: &str, args: Vec<Value>) -> Result<[Value; N], String> {
+    let found = args.len();
+    args.try_into()
+        .map_err(|_| format!("{} expects {} arguments, found {}", ident, N, found))
+}
+
+fn map_key(ident: &str, key: Value) -> Result<String, String> {
+    match key {
+        Value::String(key) => Ok(key),
+        _ => Err(format!("{} expects string keys, found {:?}", ident, key)),
+    }
+}
+
+/// Checks that a builtin got exactly `N` string arguments.
+fn strings<const N: usize>(ident: &str, args: Vec<Value>) -> Result<[String; N], String> {
+    let args: [Value; N] = arity(ident, args)?;
+    let strings = args
+        .into_iter()
+        .map(|arg| match arg {
+            Value::String(s) => Ok(s),
+            _ => Err(format!("{} expects strings, found {:?}", ident, arg)),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(strings.try_into().unwrap())
+}
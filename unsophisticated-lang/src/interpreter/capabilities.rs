@@ -0,0 +1,27 @@
+use std::collections::HashSet;
+
+/// Side effects a script is allowed to perform.
+///
+/// Builtins that touch the outside world check for the matching capability
+/// before doing anything, so an embedder can run untrusted scripts with an
+/// empty set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Capability {
+    FsRead,  // read-file, list-dir
+    FsWrite, // write-file, append-file
+    Env,     // getenv, args
+    Process, // shell, exit
+    Threads, // spawn
+}
+
+pub type Capabilities = HashSet<Capability>;
+
+impl Capability {
+    pub fn all() -> Capabilities {
+        use Capability::*;
+
+        [FsRead, FsWrite, Env, Process, Threads]
+            .into_iter()
+            .collect()
+    }
+}
@@ -0,0 +1,104 @@
+//! Threads started by `spawn`, the futures they return and channels.
+
+use super::limits::LimitExceeded;
+use super::values::{Channel, Function, Future, FutureState, Value};
+use super::AstInterpreter;
+
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+
+impl Channel {
+    pub(super) fn new() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            sender: Mutex::new(Some(sender)),
+            receiver: Mutex::new(receiver),
+        }
+    }
+
+    pub(super) fn send(&self, value: Value) -> Result<(), String> {
+        match &*self.sender.lock().unwrap() {
+            Some(sender) => {
+                // The channel holds its own receiver, sending cannot fail.
+                sender.send(value).unwrap();
+                Ok(())
+            }
+            None => Err("send on a closed channel".to_string()),
+        }
+    }
+
+    /// Values already sent can still be received, closing twice is a no-op.
+    pub(super) fn close(&self) {
+        self.sender.lock().unwrap().take();
+    }
+}
+
+impl AstInterpreter {
+    /// Calls `function` on a new thread, with an interpreter of its own
+    /// holding a copy of the globals, the capabilities, the limits and the
+    /// stack size. Globals defined or set by either thread afterwards are not
+    /// seen by the other. The fuel, memory and time of the `eval` call are
+    /// shared with it, a thread cannot outlast the limits by spawning.
+    pub(super) fn spawn(
+        &self,
+        function: Arc<Function>,
+        args: Vec<Value>,
+    ) -> Result<Arc<Future>, String> {
+        let mut worker = AstInterpreter::without_prelude(self.capabilities.clone());
+        worker.heap = self.heap.clone();
+        worker.types = self.types.clone();
+        worker.args = self.args.clone();
+        worker.limits = self.limits.clone();
+        worker.usage = self.usage.share();
+        worker.spawn_stack_size = self.spawn_stack_size;
+
+        let (sender, receiver) = mpsc::channel();
+        let mut builder = thread::Builder::new();
+        if let Some(size) = self.spawn_stack_size {
+            builder = builder.stack_size(size);
+        }
+        builder
+            .spawn(move || {
+                // Nobody is left to await the result once the future is dropped.
+                let _ = sender.send(worker.call_function(&function, args));
+            })
+            .map_err(|e| format!("spawn: {}", e))?;
+        Ok(Arc::new(Future(Mutex::new(FutureState::Running(receiver)))))
+    }
+
+    /// The value returned on the thread behind `future`, or its error.
+    pub(super) fn await_future(&mut self, future: &Future) -> Result<Value, String> {
+        let mut state = future.0.lock().unwrap();
+        if let FutureState::Running(receiver) = &*state {
+            let result = self
+                .wait(receiver)?
+                .unwrap_or_else(|| Err("spawned thread panicked".to_string()));
+            *state = FutureState::Done(result);
+        }
+        match &*state {
+            FutureState::Done(result) => result.clone(),
+            FutureState::Running(_) => unreachable!("awaited without a result"),
+        }
+    }
+
+    /// The next value sent on `chan`, `None` once it is closed and drained.
+    pub(super) fn recv(&mut self, chan: &Channel) -> Result<Option<Value>, String> {
+        let receiver = chan.receiver.lock().unwrap();
+        self.wait(&receiver)
+    }
+
+    /// Blocks until `receiver` gets something, `None` once its sender is
+    /// gone. Gives up when the time limit runs out.
+    fn wait<T>(&mut self, receiver: &Receiver<T>) -> Result<Option<T>, String> {
+        let Some(deadline) = self.deadline() else {
+            return Ok(receiver.recv().ok());
+        };
+        match receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+            Ok(value) => Ok(Some(value)),
+            Err(RecvTimeoutError::Disconnected) => Ok(None),
+            Err(RecvTimeoutError::Timeout) => self.exceeded(LimitExceeded::Time).map(|_| None),
+        }
+    }
+}
@@ -0,0 +1,578 @@
+//! Data files: values to and from JSON, and the s-expression data format
+//! read by `read` and written by `write`.
+//!
+//! ```text
+//! datum ::= 'nil' | 'true' | 'false' | NUMBER | STRING | SYMBOL
+//!     | '#\' CHAR | '#\space' | '#\newline' | '#\tab' | '#\u' HEX+
+//!     | '(' datum* ')'               list
+//!     | '[' datum* ']'               vector
+//!     | '{' (STRING datum)* '}'      map
+//!     | '#(' SYMBOL datum* ')'       record of a `defstruct` type
+//! ```
+//!
+//! Strings are written with the escapes of JSON strings in both formats.
+
+use super::values::{Record, RecordType, Value};
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::sync::Arc;
+
+/// Arrays read as vectors and objects as maps.
+pub(super) fn json_parse(input: &str) -> Result<Value, String> {
+    let mut reader = Reader::new("json-parse", input);
+    let value = reader.json()?;
+    reader.end()?;
+    Ok(value)
+}
+
+/// Compact JSON, lists and vectors are both written as arrays.
+pub(super) fn json_stringify(value: &Value) -> Result<String, String> {
+    let mut out = String::new();
+    write_json(value, &mut out)?;
+    Ok(out)
+}
+
+/// Reads a single datum, records are built with the types in `types`.
+pub(super) fn read(input: &str, types: &HashMap<String, Arc<RecordType>>) -> Result<Value, String> {
+    let mut reader = Reader::new("read", input);
+    let value = reader.datum(types)?;
+    reader.end()?;
+    Ok(value)
+}
+
+/// The datum `read` turns back into an equal value.
+pub(super) fn write(value: &Value) -> Result<String, String> {
+    let mut out = String::new();
+    write_datum(value, &mut out)?;
+    Ok(out)
+}
+
+fn write_json(value: &Value, out: &mut String) -> Result<(), String> {
+    match value {
+        Value::Nil => out.push_str("null"),
+        Value::Bool(b) => write!(out, "{}", b).unwrap(),
+        Value::Number(n) => write_number("json-stringify", *n, out)?,
+        Value::String(s) => write_string(s, out),
+        Value::List(items) | Value::Vector(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_json(item, out)?;
+            }
+            out.push(']');
+        }
+        Value::Map(entries) => {
+            out.push('{');
+            for (i, (key, value)) in entries.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_string(key, out);
+                out.push(':');
+                write_json(value, out)?;
+            }
+            out.push('}');
+        }
+        _ => return Err(format!("json-stringify cannot represent {:?}", value)),
+    }
+    Ok(())
+}
+
+fn write_datum(value: &Value, out: &mut String) -> Result<(), String> {
+    match value {
+        Value::Nil => out.push_str("nil"),
+        Value::Bool(b) => write!(out, "{}", b).unwrap(),
+        Value::Number(n) => write_number("write", *n, out)?,
+        Value::String(s) => write_string(s, out),
+        Value::Char(c) => match c {
+            ' ' => out.push_str("#\\space"),
+            '\n' => out.push_str("#\\newline"),
+            '\t' => out.push_str("#\\tab"),
+            c if c.is_whitespace() || c.is_control() => {
+                write!(out, "#\\u{:04x}", *c as u32).unwrap()
+            }
+            c => write!(out, "#\\{}", c).unwrap(),
+        },
+        Value::Symbol(symbol) => {
+            if !is_symbol(symbol) {
+                return Err(format!("write cannot represent the symbol {:?}", symbol));
+            }
+            out.push_str(symbol);
+        }
+        Value::List(items) => write_items("(", items, ")", out)?,
+        Value::Vector(items) => write_items("[", items, "]", out)?,
+        Value::Map(entries) => {
+            out.push('{');
+            for (i, (key, value)) in entries.iter().enumerate() {
+                if i > 0 {
+                    out.push(' ');
+                }
+                write_string(key, out);
+                out.push(' ');
+                write_datum(value, out)?;
+            }
+            out.push('}');
+        }
+        Value::Record(record) => {
+            write!(out, "#({}", record.ty.name).unwrap();
+            for value in &record.values {
+                out.push(' ');
+                write_datum(value, out)?;
+            }
+            out.push(')');
+        }
+        _ => return Err(format!("write cannot represent {:?}", value)),
+    }
+    Ok(())
+}
+
+fn write_items(open: &str, items: &[Value], close: &str, out: &mut String) -> Result<(), String> {
+    out.push_str(open);
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            out.push(' ');
+        }
+        write_datum(item, out)?;
+    }
+    out.push_str(close);
+    Ok(())
+}
+
+/// Written the shortest way that reads back as the same number.
+fn write_number(ident: &str, n: f64, out: &mut String) -> Result<(), String> {
+    if !n.is_finite() {
+        return Err(format!("{} cannot represent {}", ident, n));
+    }
+    write!(out, "{}", n).unwrap();
+    Ok(())
+}
+
+fn write_string(s: &str, out: &mut String) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// Whether `symbol` reads back as itself.
+fn is_symbol(symbol: &str) -> bool {
+    !symbol.is_empty()
+        && !symbol.starts_with('#')
+        && !symbol.chars().any(is_delimiter)
+        && !matches!(symbol, "nil" | "true" | "false")
+        && !looks_like_number(symbol)
+}
+
+fn is_delimiter(c: char) -> bool {
+    c.is_whitespace() || matches!(c, '(' | ')' | '[' | ']' | '{' | '}' | '"')
+}
+
+/// `1`, `-2.5` or `.5`, but not `-` or `inf`.
+fn looks_like_number(token: &str) -> bool {
+    let mut chars = token.chars();
+    let first = chars.next();
+    let first = match first {
+        Some('+' | '-') => chars.next(),
+        first => first,
+    };
+    match first {
+        Some('.') => chars.next().is_some_and(|c| c.is_ascii_digit()),
+        Some(c) => c.is_ascii_digit(),
+        None => false,
+    }
+}
+
+/// Deepest nesting of collections read, each level is a recursive call.
+const MAX_NESTING: usize = 512;
+
+struct Reader<'a> {
+    /// The builtin reading, errors start with it.
+    ident: &'static str,
+    input: &'a str,
+    /// Byte offset of the next char.
+    pos: usize,
+    /// Number of collections being read.
+    depth: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(ident: &'static str, input: &'a str) -> Self {
+        Self {
+            ident,
+            input,
+            pos: 0,
+            depth: 0,
+        }
+    }
+
+    fn error(&self, message: &str) -> String {
+        format!("{}: {} at byte {}", self.ident, message, self.pos)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        let eaten = self.peek() == Some(expected);
+        if eaten {
+            self.pos += expected.len_utf8();
+        }
+        eaten
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        if self.eat(expected) {
+            Ok(())
+        } else {
+            Err(self.unexpected(&format!("'{}'", expected)))
+        }
+    }
+
+    fn unexpected(&self, expected: &str) -> String {
+        match self.peek() {
+            Some(c) => self.error(&format!("expected {}, found '{}'", expected, c)),
+            None => self.error(&format!("expected {}, found the end of input", expected)),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.bump();
+        }
+    }
+
+    /// Reads a collection with `read`, failing before it opens one more
+    /// than `MAX_NESTING` deep.
+    fn nested<T>(&mut self, read: impl FnOnce(&mut Self) -> Result<T, String>) -> Result<T, String> {
+        if self.depth == MAX_NESTING {
+            return Err(self.error("nesting too deep"));
+        }
+        self.depth += 1;
+        let result = read(self);
+        self.depth -= 1;
+        result
+    }
+
+    /// Only whitespace may follow what was read.
+    fn end(&mut self) -> Result<(), String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(_) => Err(self.unexpected("the end of input")),
+            None => Ok(()),
+        }
+    }
+
+    // -- region : JSON --
+
+    fn json(&mut self) -> Result<Value, String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('n') => self.keyword("null", Value::Nil),
+            Some('t') => self.keyword("true", Value::Bool(true)),
+            Some('f') => self.keyword("false", Value::Bool(false)),
+            Some('"') => self.string().map(Value::String),
+            Some('-' | '0'..='9') => self.json_number(),
+            Some('[') => self.nested(|this| {
+                this.bump();
+                let mut items = vec![];
+                this.skip_whitespace();
+                if this.eat(']') {
+                    return Ok(Value::Vector(items));
+                }
+                loop {
+                    items.push(this.json()?);
+                    this.skip_whitespace();
+                    if this.eat(']') {
+                        return Ok(Value::Vector(items));
+                    }
+                    if !this.eat(',') {
+                        return Err(this.unexpected("',' or ']'"));
+                    }
+                }
+            }),
+            Some('{') => self.nested(|this| {
+                this.bump();
+                let mut entries = BTreeMap::new();
+                this.skip_whitespace();
+                if this.eat('}') {
+                    return Ok(Value::Map(entries));
+                }
+                loop {
+                    this.skip_whitespace();
+                    if this.peek() != Some('"') {
+                        return Err(this.unexpected("a string key"));
+                    }
+                    let key = this.string()?;
+                    this.skip_whitespace();
+                    this.expect(':')?;
+                    entries.insert(key, this.json()?);
+                    this.skip_whitespace();
+                    if this.eat('}') {
+                        return Ok(Value::Map(entries));
+                    }
+                    if !this.eat(',') {
+                        return Err(this.unexpected("',' or '}'"));
+                    }
+                }
+            }),
+            _ => Err(self.unexpected("a JSON value")),
+        }
+    }
+
+    fn keyword(&mut self, keyword: &str, value: Value) -> Result<Value, String> {
+        if self.input[self.pos..].starts_with(keyword) {
+            self.pos += keyword.len();
+            Ok(value)
+        } else {
+            Err(self.unexpected(keyword))
+        }
+    }
+
+    /// `-? (0 | [1-9][0-9]*) (. [0-9]+)? ([eE] [+-]? [0-9]+)?`
+    fn json_number(&mut self) -> Result<Value, String> {
+        let start = self.pos;
+        self.eat('-');
+        match self.bump() {
+            Some('0') => {}
+            Some('1'..='9') => self.digits(),
+            _ => return Err(self.error("expected a digit")),
+        }
+        if self.eat('.') {
+            self.digits1()?;
+        }
+        if self.eat('e') || self.eat('E') {
+            let _ = self.eat('+') || self.eat('-');
+            self.digits1()?;
+        }
+        let n: f64 = self.input[start..self.pos].parse().unwrap();
+        if n.is_infinite() {
+            return Err(self.error("number out of range"));
+        }
+        Ok(Value::Number(n))
+    }
+
+    fn digits(&mut self) {
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.bump();
+        }
+    }
+
+    fn digits1(&mut self) -> Result<(), String> {
+        if !self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            return Err(self.unexpected("a digit"));
+        }
+        self.digits();
+        Ok(())
+    }
+
+    /// A double-quoted string with JSON escapes.
+    fn string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut s = String::new();
+        loop {
+            match self.bump() {
+                Some('"') => return Ok(s),
+                Some('\\') => s.push(self.escape()?),
+                Some(c) if c < ' ' => {
+                    return Err(self.error("unescaped control character in a string"))
+                }
+                Some(c) => s.push(c),
+                None => return Err(self.error("unterminated string")),
+            }
+        }
+    }
+
+    fn escape(&mut self) -> Result<char, String> {
+        Ok(match self.bump() {
+            Some('"') => '"',
+            Some('\\') => '\\',
+            Some('/') => '/',
+            Some('b') => '\u{8}',
+            Some('f') => '\u{c}',
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('t') => '\t',
+            Some('u') => {
+                let high = self.hex4()?;
+                let code = if (0xd800..0xdc00).contains(&high) {
+                    // UTF-16 surrogate pair
+                    self.expect('\\')?;
+                    self.expect('u')?;
+                    let low = self.hex4()?;
+                    if !(0xdc00..0xe000).contains(&low) {
+                        return Err(self.error("invalid surrogate pair"));
+                    }
+                    0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00)
+                } else {
+                    high
+                };
+                char::from_u32(code).ok_or_else(|| self.error("invalid \\u escape"))?
+            }
+            _ => return Err(self.error("invalid escape")),
+        })
+    }
+
+    fn hex4(&mut self) -> Result<u32, String> {
+        let digits = self
+            .input
+            .get(self.pos..self.pos + 4)
+            .filter(|digits| digits.chars().all(|c| c.is_ascii_hexdigit()))
+            .ok_or_else(|| self.error("expected 4 hex digits"))?;
+        let code = u32::from_str_radix(digits, 16).unwrap();
+        self.pos += 4;
+        Ok(code)
+    }
+
+    // -- region : s-expressions --
+
+    fn datum(&mut self, types: &HashMap<String, Arc<RecordType>>) -> Result<Value, String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('"') => self.string().map(Value::String),
+            Some('(') => self.nested(|this| {
+                this.bump();
+                this.items(')', types).map(Value::List)
+            }),
+            Some('[') => self.nested(|this| {
+                this.bump();
+                this.items(']', types).map(Value::Vector)
+            }),
+            Some('{') => self.nested(|this| {
+                this.bump();
+                let mut entries = BTreeMap::new();
+                loop {
+                    this.skip_whitespace();
+                    if this.eat('}') {
+                        return Ok(Value::Map(entries));
+                    }
+                    let key = match this.datum(types)? {
+                        Value::String(key) => key,
+                        key => {
+                            let message = format!("map keys must be strings, found {:?}", key);
+                            return Err(this.error(&message));
+                        }
+                    };
+                    this.skip_whitespace();
+                    if this.peek() == Some('}') {
+                        return Err(this.error(&format!("missing value for the key {:?}", key)));
+                    }
+                    entries.insert(key, this.datum(types)?);
+                }
+            }),
+            Some('#') => {
+                self.bump();
+                match self.bump() {
+                    Some('\\') => self.char().map(Value::Char),
+                    Some('(') => self.nested(|this| this.record(types)),
+                    _ => Err(self.error("expected '\\' or '(' after '#'")),
+                }
+            }
+            Some(c) if !is_delimiter(c) => self.atom(),
+            _ => Err(self.unexpected("a datum")),
+        }
+    }
+
+    /// Data up to and including `close`.
+    fn items(
+        &mut self,
+        close: char,
+        types: &HashMap<String, Arc<RecordType>>,
+    ) -> Result<Vec<Value>, String> {
+        let mut items = vec![];
+        loop {
+            self.skip_whitespace();
+            if self.eat(close) {
+                return Ok(items);
+            }
+            if self.peek().is_none() {
+                return Err(self.unexpected(&format!("'{}'", close)));
+            }
+            items.push(self.datum(types)?);
+        }
+    }
+
+    /// After `#\`, a single char or the name of one.
+    fn char(&mut self) -> Result<char, String> {
+        let first = self.bump().ok_or_else(|| self.error("expected a character"))?;
+        if !first.is_alphanumeric() {
+            return Ok(first);
+        }
+        let start = self.pos - first.len_utf8();
+        while self.peek().is_some_and(char::is_alphanumeric) {
+            self.bump();
+        }
+        match &self.input[start..self.pos] {
+            name if name.chars().count() == 1 => Ok(first),
+            "space" => Ok(' '),
+            "newline" => Ok('\n'),
+            "tab" => Ok('\t'),
+            name => name
+                .strip_prefix('u')
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                .and_then(char::from_u32)
+                .ok_or_else(|| self.error(&format!("unknown character name {}", name))),
+        }
+    }
+
+    /// After `#(`, the type name and one datum per field.
+    fn record(&mut self, types: &HashMap<String, Arc<RecordType>>) -> Result<Value, String> {
+        let name = match self.datum(types)? {
+            Value::Symbol(name) => name,
+            name => return Err(self.error(&format!("expected a record type, found {:?}", name))),
+        };
+        let ty = types
+            .get(&name)
+            .ok_or_else(|| self.error(&format!("{} is not a record type", name)))?;
+        let values = self.items(')', types)?;
+        if values.len() != ty.fields.len() {
+            return Err(self.error(&format!(
+                "{} has {} fields, found {}",
+                name,
+                ty.fields.len(),
+                values.len()
+            )));
+        }
+        Ok(Value::Record(Arc::new(Record {
+            ty: ty.clone(),
+            values,
+        })))
+    }
+
+    /// A number, `nil`, `true`, `false` or a symbol.
+    fn atom(&mut self) -> Result<Value, String> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| !is_delimiter(c)) {
+            self.bump();
+        }
+        let token = &self.input[start..self.pos];
+        Ok(match token {
+            "nil" => Value::Nil,
+            "true" => Value::Bool(true),
+            "false" => Value::Bool(false),
+            token if looks_like_number(token) => match token.parse::<f64>() {
+                Ok(n) if n.is_finite() => Value::Number(n),
+                _ => return Err(self.error(&format!("invalid number {}", token))),
+            },
+            token => Value::Symbol(token.to_string()),
+        })
+    }
+}
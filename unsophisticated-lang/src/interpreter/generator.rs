@@ -0,0 +1,339 @@
+//! Generator functions. Calling a function whose body contains `yield`
+//! returns a lazy sequence, each cell runs the body up to its next `yield`.
+//!
+//! The parser only accepts `yield` between the statements of a body, so a
+//! suspended generator is its locals plus the bodies and loops it is in.
+//! Statements without a `yield` are evaluated as usual.
+
+use super::lazy::Cursor;
+use super::matcher::MatchError;
+use super::values::{Seq, Value};
+use super::visitor::RECUR_OUTSIDE_LOOP;
+use super::{AstInterpreter, Control};
+
+use crate::parser::AstNode;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+pub(super) struct Generator {
+    scopes: Vec<HashMap<String, Value>>,
+    /// Innermost last, the generator has returned once there are none left.
+    frames: Vec<Frame>,
+}
+
+enum Frame {
+    /// The statements of a body, `next` is the one to run next. A scoped
+    /// body owns the innermost scope, the one `let` or a `match` arm opened.
+    Body {
+        body: Arc<[AstNode]>,
+        next: usize,
+        scoped: bool,
+    },
+    /// On top of the frames when the condition is due.
+    While {
+        cond: Arc<AstNode>,
+        body: Arc<[AstNode]>,
+    },
+    /// Owns the scope holding the loop variable.
+    For {
+        ident: String,
+        items: Cursor,
+        body: Arc<[AstNode]>,
+    },
+    /// Owns the scope holding the loop bindings. On top of the frames once
+    /// the body returned without `recur`.
+    Loop {
+        idents: Vec<String>,
+        body: Arc<[AstNode]>,
+    },
+}
+
+impl Frame {
+    fn body(body: &[AstNode], scoped: bool) -> Self {
+        Frame::Body {
+            body: body.into(),
+            next: 0,
+            scoped,
+        }
+    }
+}
+
+impl Generator {
+    pub(super) fn new(scopes: Vec<HashMap<String, Value>>, body: Arc<[AstNode]>) -> Self {
+        Self {
+            scopes,
+            frames: vec![Frame::Body {
+                body,
+                next: 0,
+                scoped: false,
+            }],
+        }
+    }
+}
+
+/// The sequence of what `generator` yields, resumed as it is walked.
+pub(super) fn generate(mut generator: Generator) -> Arc<Seq> {
+    Seq::new(Box::new(move |this| {
+        Ok(match this.resume(&mut generator)? {
+            Some(value) => Value::Seq(Seq::cons(value, generate(generator))),
+            None => Value::Nil,
+        })
+    }))
+}
+
+impl AstInterpreter {
+    /// Runs the generator up to its next `yield`, `None` once it returned.
+    /// Like a function call, only its own locals are in scope.
+    fn resume(&mut self, generator: &mut Generator) -> Result<Option<Value>, String> {
+        self.check_depth()?;
+
+        let scopes = std::mem::replace(&mut self.scopes, std::mem::take(&mut generator.scopes));
+        let base = self.stack.len();
+
+        self.depth += 1;
+        let result = self.run(&mut generator.frames);
+        self.depth -= 1;
+        generator.scopes = std::mem::replace(&mut self.scopes, scopes);
+
+        if result.is_err() {
+            // `break`, `continue` and `recur` do not leave the generator.
+            self.control = None;
+            self.stack.truncate(base);
+        }
+        result
+    }
+
+    fn run(&mut self, frames: &mut Vec<Frame>) -> Result<Option<Value>, String> {
+        let base = self.stack.len();
+        loop {
+            let result = match frames.last_mut() {
+                None => return Ok(None),
+                Some(Frame::Body { body, next, scoped }) => {
+                    if *next == body.len() {
+                        if *scoped {
+                            self.scopes.pop();
+                        }
+                        frames.pop();
+                        continue;
+                    }
+                    let body = body.clone();
+                    let i = *next;
+                    *next += 1;
+                    self.statement(&body[i], frames)
+                }
+                Some(Frame::While { .. }) => {
+                    // A signal from the condition is for an enclosing loop.
+                    let Some(Frame::While { cond, body }) = frames.pop() else {
+                        unreachable!()
+                    };
+                    self.eval_node(&cond).map(|()| {
+                        if self.stack.pop().unwrap().is_truthy() {
+                            frames.push(Frame::While {
+                                cond,
+                                body: body.clone(),
+                            });
+                            frames.push(Frame::Body {
+                                body,
+                                next: 0,
+                                scoped: false,
+                            });
+                        }
+                        None
+                    })
+                }
+                Some(Frame::For { ident, items, body }) => match self.advance(items) {
+                    Ok(Some(item)) => {
+                        self.bind(ident, item);
+                        let body = body.clone();
+                        frames.push(Frame::Body {
+                            body,
+                            next: 0,
+                            scoped: false,
+                        });
+                        Ok(None)
+                    }
+                    Ok(None) => {
+                        frames.pop();
+                        self.scopes.pop();
+                        Ok(None)
+                    }
+                    Err(e) => Err(e),
+                },
+                Some(Frame::Loop { .. }) => {
+                    frames.pop();
+                    self.scopes.pop();
+                    continue;
+                }
+            };
+
+            match result {
+                Ok(Some(value)) => return Ok(Some(value)),
+                Ok(None) => {}
+                Err(e) => match self.control.take() {
+                    Some(control) => {
+                        self.stack.truncate(base);
+                        self.unwind(frames, control, e)?;
+                    }
+                    None => return Err(e),
+                },
+            }
+        }
+    }
+
+    /// Runs one statement, stepping into it when it may yield. Returns the
+    /// yielded value if it was a `yield`.
+    fn statement(
+        &mut self,
+        node: &AstNode,
+        frames: &mut Vec<Frame>,
+    ) -> Result<Option<Value>, String> {
+        if !node.yields() {
+            self.eval_node(node)?;
+            self.stack.pop();
+            return Ok(None);
+        }
+
+        self.step()?;
+        match node {
+            AstNode::Yield(expr) => {
+                self.eval_node(expr)?;
+                return Ok(self.stack.pop());
+            }
+            AstNode::Do(body) => frames.push(Frame::body(body, false)),
+            AstNode::If { cond, then, otherwise } => {
+                self.eval_node(cond)?;
+                let branch = if self.stack.pop().unwrap().is_truthy() {
+                    then
+                } else {
+                    otherwise
+                };
+                return self.statement(branch, frames);
+            }
+            AstNode::Let { bindings, body } => {
+                self.enter(bindings)?;
+                frames.push(Frame::body(body, true));
+            }
+            AstNode::While { cond, body } => frames.push(Frame::While {
+                cond: Arc::new((**cond).clone()),
+                body: body.as_slice().into(),
+            }),
+            AstNode::For { ident, iter, body } => {
+                self.eval_node(iter)?;
+                let items = Cursor::new(self.stack.pop().unwrap())?;
+                self.scopes.push(HashMap::new());
+                frames.push(Frame::For {
+                    ident: ident.clone(),
+                    items,
+                    body: body.as_slice().into(),
+                });
+            }
+            AstNode::Loop { bindings, body } => {
+                self.enter(bindings)?;
+                let idents = bindings.iter().map(|(ident, _)| ident.clone()).collect();
+                let body: Arc<[AstNode]> = body.as_slice().into();
+                frames.push(Frame::Loop {
+                    idents,
+                    body: body.clone(),
+                });
+                frames.push(Frame::Body {
+                    body,
+                    next: 0,
+                    scoped: false,
+                });
+            }
+            AstNode::Match { expr, arms } => {
+                self.eval_node(expr)?;
+                let value = self.stack.pop().unwrap();
+
+                for arm in arms {
+                    let bindings = match self.match_decision(&arm.decision, &value)? {
+                        Some(bindings) => bindings,
+                        None => continue,
+                    };
+                    self.scopes.push(bindings);
+                    if let Some(guard) = &arm.guard {
+                        let holds = self
+                            .eval_node(guard)
+                            .map(|()| self.stack.pop().unwrap().is_truthy());
+                        if holds != Ok(true) {
+                            self.scopes.pop();
+                            holds?;
+                            continue;
+                        }
+                    }
+                    frames.push(Frame::body(&arm.body, true));
+                    return Ok(None);
+                }
+
+                return self.match_failed(MatchError::NoMatch(value));
+            }
+            _ => return Err("yield must be a statement of a function body".to_string()),
+        }
+        Ok(None)
+    }
+
+    /// Opens a scope with `bindings`, closed again if one of them fails.
+    fn enter(&mut self, bindings: &[(String, AstNode)]) -> Result<(), String> {
+        self.scopes.push(HashMap::new());
+        let result = self.bind_all(bindings);
+        if result.is_err() {
+            self.scopes.pop();
+        }
+        result
+    }
+
+    /// Leaves frames up to the loop `control` is for, and lets it go on the
+    /// way `visit_while`, `visit_for` and `visit_loop` would.
+    fn unwind(&mut self, frames: &mut Vec<Frame>, control: Control, err: String) -> Result<(), String> {
+        let frame = match frames.pop() {
+            Some(frame) => frame,
+            None => return Err(err),
+        };
+        match (frame, control) {
+            (Frame::Body { scoped, .. }, signal) => {
+                if scoped {
+                    self.scopes.pop();
+                }
+                self.unwind(frames, signal, err)
+            }
+            (Frame::While { .. }, Control::Break(_)) => Ok(()),
+            (Frame::For { .. } | Frame::Loop { .. }, Control::Break(_)) => {
+                self.scopes.pop();
+                Ok(())
+            }
+            (frame @ (Frame::While { .. } | Frame::For { .. }), Control::Continue) => {
+                frames.push(frame);
+                Ok(())
+            }
+            (Frame::While { .. } | Frame::For { .. }, Control::Recur(_)) => {
+                Err(RECUR_OUTSIDE_LOOP.to_string())
+            }
+            (Frame::Loop { idents, body }, Control::Recur(values)) => {
+                if values.len() != idents.len() {
+                    return Err(format!(
+                        "recur expects {} arguments, found {}",
+                        idents.len(),
+                        values.len()
+                    ));
+                }
+                for (ident, value) in idents.iter().zip(values) {
+                    self.bind(ident, value);
+                }
+                frames.push(Frame::Loop {
+                    idents,
+                    body: body.clone(),
+                });
+                frames.push(Frame::Body {
+                    body,
+                    next: 0,
+                    scoped: false,
+                });
+                Ok(())
+            }
+            (Frame::Loop { .. }, Control::Continue) => {
+                Err("continue is not allowed inside loop, use recur".to_string())
+            }
+        }
+    }
+}
@@ -0,0 +1,144 @@
+//! Realising and walking lazy sequences.
+
+use super::values::{Channel, Seq, SeqState, Value};
+use super::AstInterpreter;
+
+use std::fs::File;
+use std::io::{BufReader, Lines};
+use std::sync::Arc;
+use std::thread;
+
+/// Walks a collection by value. Lazy sequences are realised one cell at a
+/// time and the cells already visited are let go of, so walking a sequence
+/// nothing else refers to runs in constant memory. Channels are received
+/// from until they are closed and drained.
+pub(super) enum Cursor {
+    Items(Box<dyn Iterator<Item = Value> + Send>),
+    Seq(Arc<Seq>),
+    Chan(Arc<Channel>),
+}
+
+impl Cursor {
+    pub(super) fn new(coll: Value) -> Result<Self, String> {
+        match coll {
+            Value::Seq(seq) => Ok(Cursor::Seq(seq)),
+            Value::Chan(chan) => Ok(Cursor::Chan(chan)),
+            coll => Ok(Cursor::Items(coll.into_items()?)),
+        }
+    }
+}
+
+/// A sequence of the items of a collection, taken as it is walked.
+pub(super) fn seq_of(mut items: Box<dyn Iterator<Item = Value> + Send>) -> Arc<Seq> {
+    Seq::new(Box::new(move |_| {
+        Ok(match items.next() {
+            Some(item) => Value::Seq(Seq::cons(item, seq_of(items))),
+            None => Value::Nil,
+        })
+    }))
+}
+
+/// The values received from a channel, taken as the sequence is walked.
+fn received(chan: Arc<Channel>) -> Arc<Seq> {
+    Seq::new(Box::new(move |this| {
+        Ok(match this.recv(&chan)? {
+            Some(value) => Value::Seq(Seq::cons(value, received(chan))),
+            None => Value::Nil,
+        })
+    }))
+}
+
+/// The lines of a file without their line endings, read as the sequence
+/// is walked.
+pub(super) fn lines(path: String, mut lines: Lines<BufReader<File>>) -> Arc<Seq> {
+    Seq::new(Box::new(move |_| match lines.next() {
+        Some(Ok(line)) => Ok(Value::Seq(Seq::cons(Value::String(line), self::lines(path, lines)))),
+        Some(Err(e)) => Err(format!("read-lines {}: {}", path, e)),
+        None => Ok(Value::Nil),
+    }))
+}
+
+impl AstInterpreter {
+    /// Realises the first cell of `seq`, `None` when it is empty. Waits
+    /// when another thread is already realising it.
+    pub(super) fn force(&mut self, seq: &Arc<Seq>) -> Result<Option<(Value, Arc<Seq>)>, String> {
+        let current = thread::current().id();
+        let mut guard = seq.state.lock().unwrap();
+        while let SeqState::Forcing(thread) = *guard {
+            if thread == current {
+                // Still being realised further up, which puts the state back.
+                return Err("Lazy sequence depends on itself".to_string());
+            }
+            guard = seq.realised.wait(guard).unwrap();
+        }
+        // Not locked while the thunk runs, it may walk other sequences.
+        let state = std::mem::replace(&mut *guard, SeqState::Forcing(current));
+        drop(guard);
+
+        let state = match state {
+            SeqState::Pending(thunk) => thunk(self)
+                .and_then(|value| self.realise(value))
+                .unwrap_or_else(SeqState::Failed),
+            state => state,
+        };
+        let cell = match &state {
+            SeqState::Cons(first, rest) => Ok(Some((first.clone(), rest.clone()))),
+            SeqState::Empty => Ok(None),
+            SeqState::Failed(e) => Err(e.clone()),
+            SeqState::Pending(_) | SeqState::Forcing(_) => unreachable!("realised twice"),
+        };
+        *seq.state.lock().unwrap() = state;
+        seq.realised.notify_all();
+        cell
+    }
+
+    /// What a thunk returned, as the state of the sequence it realises.
+    fn realise(&mut self, value: Value) -> Result<SeqState, String> {
+        let seq = match value {
+            Value::Nil => return Ok(SeqState::Empty),
+            Value::Seq(seq) => seq,
+            coll @ (Value::List(_)
+            | Value::Vector(_)
+            | Value::Map(_)
+            | Value::String(_)
+            | Value::Range { .. }) => {
+                seq_of(coll.into_items()?)
+            }
+            Value::Chan(chan) => received(chan),
+            value => return Err(format!("lazy-seq expects a sequence, found {:?}", value)),
+        };
+        Ok(match self.force(&seq)? {
+            Some((first, rest)) => SeqState::Cons(first, rest),
+            None => SeqState::Empty,
+        })
+    }
+
+    /// The next item of a walk, `None` at the end.
+    pub(super) fn advance(&mut self, cursor: &mut Cursor) -> Result<Option<Value>, String> {
+        match cursor {
+            Cursor::Items(items) => Ok(items.next()),
+            Cursor::Seq(seq) => match self.force(seq)? {
+                Some((first, rest)) => {
+                    *seq = rest;
+                    Ok(Some(first))
+                }
+                None => Ok(None),
+            },
+            Cursor::Chan(chan) => self.recv(chan),
+        }
+    }
+
+    /// Appends the items of `coll` to `items` in one walk, a step each.
+    pub(super) fn collect(
+        &mut self,
+        coll: Value,
+        mut items: Vec<Value>,
+    ) -> Result<Vec<Value>, String> {
+        let mut cursor = Cursor::new(coll)?;
+        while let Some(item) = self.advance(&mut cursor)? {
+            self.step()?;
+            items.push(item);
+        }
+        Ok(items)
+    }
+}
@@ -0,0 +1,206 @@
+use super::values::Value;
+use super::AstInterpreter;
+
+use std::fmt::{Display, Formatter};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Resource limits applied to each `eval` call, `None` means unlimited.
+#[derive(Debug, Clone, Default)]
+pub struct Limits {
+    /// Maximum number of AST nodes evaluated.
+    pub fuel: Option<u64>,
+    /// Maximum wall-clock time.
+    pub timeout: Option<Duration>,
+    /// Maximum number of values on the value stack.
+    pub max_stack: Option<usize>,
+    /// Maximum number of bytes held by live values, approximated.
+    pub max_memory: Option<usize>,
+    /// Maximum number of nested function calls. Calls nest on the Rust
+    /// stack, without a limit runaway recursion overflows it.
+    pub max_depth: Option<usize>,
+    /// Maximum number of expressions evaluated inside one another, across
+    /// function calls. Each one nests on the Rust stack too, so this bounds
+    /// its use where `max_depth` alone only bounds the number of calls.
+    pub max_nesting: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitExceeded {
+    Fuel,
+    Time,
+    Stack,
+    Memory,
+    Depth,
+    Nesting,
+}
+
+impl Display for LimitExceeded {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LimitExceeded::Fuel => write!(f, "fuel exhausted"),
+            LimitExceeded::Time => write!(f, "time limit exceeded"),
+            LimitExceeded::Stack => write!(f, "stack limit exceeded"),
+            LimitExceeded::Memory => write!(f, "memory limit exceeded"),
+            LimitExceeded::Depth => write!(f, "call depth limit exceeded"),
+            LimitExceeded::Nesting => write!(f, "nesting limit exceeded"),
+        }
+    }
+}
+
+/// Walking every live value is linear in their size, so the full memory
+/// check only runs every so many steps. Values built in between are
+/// checked on their own as they are created.
+const MEMORY_CHECK_INTERVAL: u64 = 256;
+
+/// What an `eval` call has used so far, across the threads it spawned.
+#[derive(Debug, Default)]
+pub(super) struct Usage {
+    steps: Arc<AtomicU64>,
+    started: Option<Instant>,
+    /// Bytes in use by all the threads, as each of them last measured.
+    memory: Arc<AtomicUsize>,
+    /// This thread's part of `memory`.
+    measured: usize,
+}
+
+impl Usage {
+    /// Usage of an `eval` call.
+    pub(super) fn start() -> Self {
+        Self {
+            steps: Arc::default(),
+            started: Some(Instant::now()),
+            memory: Arc::default(),
+            measured: 0,
+        }
+    }
+
+    /// Usage of a thread started by `spawn`, drawing on the same budget and
+    /// running out of time with the thread that started it.
+    pub(super) fn share(&self) -> Self {
+        Self {
+            steps: self.steps.clone(),
+            started: self.started,
+            memory: self.memory.clone(),
+            measured: 0,
+        }
+    }
+}
+
+/// A finished thread's values are gone, the others no longer count them.
+impl Drop for Usage {
+    fn drop(&mut self) {
+        self.memory.fetch_sub(self.measured, Ordering::Relaxed);
+    }
+}
+
+impl AstInterpreter {
+    /// Accounts for one evaluation step, failing once a limit is exceeded.
+    pub(super) fn step(&mut self) -> Result<(), String> {
+        let steps = self.usage.steps.fetch_add(1, Ordering::Relaxed) + 1;
+
+        if matches!(self.limits.fuel, Some(fuel) if steps > fuel) {
+            return self.exceeded(LimitExceeded::Fuel);
+        }
+        if let (Some(timeout), Some(started)) = (self.limits.timeout, self.usage.started) {
+            if started.elapsed() > timeout {
+                return self.exceeded(LimitExceeded::Time);
+            }
+        }
+        if matches!(self.limits.max_stack, Some(max) if self.stack.len() > max) {
+            return self.exceeded(LimitExceeded::Stack);
+        }
+        if self.limits.max_memory.is_some() && steps.is_multiple_of(MEMORY_CHECK_INTERVAL) {
+            let measured = self.memory_in_use();
+            // Added first, the total never drops below what is in use.
+            self.usage.memory.fetch_add(measured, Ordering::Relaxed);
+            self.usage
+                .memory
+                .fetch_sub(self.usage.measured, Ordering::Relaxed);
+            self.usage.measured = measured;
+            self.check_memory(0)?;
+        }
+        Ok(())
+    }
+
+    /// Checks a freshly built value against what is left of the memory budget.
+    pub(super) fn check_alloc(&mut self, value: &Value) -> Result<(), String> {
+        if self.limits.max_memory.is_none() {
+            return Ok(());
+        }
+        self.check_memory(footprint(value))
+    }
+
+    fn check_memory(&mut self, extra: usize) -> Result<(), String> {
+        match self.limits.max_memory {
+            Some(max) if self.usage.memory.load(Ordering::Relaxed) + extra > max => {
+                self.exceeded(LimitExceeded::Memory)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn memory_in_use(&self) -> usize {
+        let bindings = self
+            .scopes
+            .iter()
+            .flat_map(|scope| scope.iter())
+            .chain(self.heap.iter())
+            .map(|(ident, value)| ident.len() + footprint(value))
+            .sum::<usize>();
+        bindings + self.stack.iter().map(footprint).sum::<usize>()
+    }
+
+    /// Checks there is room for one more nested function call.
+    pub(super) fn check_depth(&mut self) -> Result<(), String> {
+        match self.limits.max_depth {
+            Some(max) if self.depth >= max => self.exceeded(LimitExceeded::Depth),
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks there is room for one more expression inside the ones being
+    /// evaluated.
+    pub(super) fn check_nesting(&mut self) -> Result<(), String> {
+        match self.limits.max_nesting {
+            Some(max) if self.nesting >= max => self.exceeded(LimitExceeded::Nesting),
+            _ => Ok(()),
+        }
+    }
+
+    /// When the time limit of the current `eval` call runs out, if it has one.
+    pub(super) fn deadline(&self) -> Option<Instant> {
+        Some(self.usage.started? + self.limits.timeout?)
+    }
+
+    pub(super) fn exceeded(&mut self, limit: LimitExceeded) -> Result<(), String> {
+        self.limit_exceeded = Some(limit);
+        Err(limit.to_string())
+    }
+}
+
+/// Approximate number of bytes a value keeps alive.
+fn footprint(value: &Value) -> usize {
+    std::mem::size_of::<Value>()
+        + match value {
+            Value::String(s) | Value::Symbol(s) => s.len(),
+            Value::List(items) | Value::Vector(items) => items.iter().map(footprint).sum(),
+            Value::Map(entries) => entries
+                .iter()
+                .map(|(key, value)| key.len() + footprint(value))
+                .sum(),
+            Value::Record(record) => record.values.iter().map(footprint).sum(),
+            Value::Function(function) => function
+                .env
+                .iter()
+                .map(|(ident, value)| ident.len() + footprint(value))
+                .sum(),
+            Value::Seq(seq) => {
+                let mut bytes = 0;
+                seq.for_each_realised(|value| bytes += footprint(value));
+                bytes
+            }
+            _ => 0,
+        }
+}
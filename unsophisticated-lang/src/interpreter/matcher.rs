@@ -0,0 +1,127 @@
+//! Runs the decisions `match` arms are compiled to.
+
+use super::values::Value;
+use super::visitor::literal_value;
+use super::AstInterpreter;
+
+use crate::parser::{Check, Decision, Step};
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MatchError {
+    /// No arm accepted the value.
+    NoMatch(Value),
+    /// A record pattern names a type never declared.
+    UnknownType(String),
+    /// A record pattern has a different number of fields than its type.
+    FieldCount {
+        ident: String,
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl Display for MatchError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MatchError::NoMatch(value) => write!(f, "No match arm for {:?}", value),
+            MatchError::UnknownType(ident) => write!(f, "{} is not a record type", ident),
+            MatchError::FieldCount {
+                ident,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{} has {} fields, found {} in a pattern",
+                ident, expected, found
+            ),
+        }
+    }
+}
+
+impl AstInterpreter {
+    /// Runs the checks of `decision` on `value`, returning what it binds if
+    /// they all pass. Nothing is evaluated, record checks only look their
+    /// type up.
+    pub(super) fn match_decision(
+        &mut self,
+        decision: &Decision,
+        value: &Value,
+    ) -> Result<Option<HashMap<String, Value>>, String> {
+        for (path, check) in &decision.checks {
+            let part = match resolve(value, path) {
+                Some(part) => part,
+                None => return Ok(None),
+            };
+            let passed = match check {
+                Check::Nil => *part == Value::Nil,
+                Check::Literal(literal) => *part == literal_value(literal)?,
+                Check::List { len, rest } => match &*part {
+                    Value::List(items) | Value::Vector(items) if *rest => items.len() >= *len,
+                    Value::List(items) | Value::Vector(items) => items.len() == *len,
+                    _ => false,
+                },
+                Check::Record { ident, fields } => {
+                    let ty = match self.types.get(ident) {
+                        Some(ty) => ty.clone(),
+                        None => return self.match_failed(MatchError::UnknownType(ident.clone())),
+                    };
+                    if ty.fields.len() != *fields {
+                        return self.match_failed(MatchError::FieldCount {
+                            ident: ident.clone(),
+                            expected: ty.fields.len(),
+                            found: *fields,
+                        });
+                    }
+                    matches!(&*part, Value::Record(record) if record.is_a(&ty))
+                }
+            };
+            if !passed {
+                return Ok(None);
+            }
+        }
+
+        let mut bindings = HashMap::new();
+        for (ident, path) in &decision.bindings {
+            match resolve(value, path) {
+                Some(part) => bindings.insert(ident.clone(), part.into_owned()),
+                None => return Ok(None),
+            };
+        }
+        Ok(Some(bindings))
+    }
+
+    pub(super) fn match_failed<T>(&mut self, error: MatchError) -> Result<T, String> {
+        let err = error.to_string();
+        self.match_error = Some(error);
+        Err(err)
+    }
+}
+
+/// The part of `value` at `path`, `None` when there is none, which the
+/// checks of a decision rule out before its paths are followed.
+fn resolve<'a>(value: &'a Value, path: &[Step]) -> Option<Cow<'a, Value>> {
+    let (step, path) = match path.split_first() {
+        Some(split) => split,
+        None => return Some(Cow::Borrowed(value)),
+    };
+    let items = match value {
+        Value::List(items) | Value::Vector(items) => items,
+        Value::Record(record) => &record.values,
+        _ => return None,
+    };
+    match *step {
+        Step::Item(i) => resolve(items.get(i)?, path),
+        Step::Rest(from) => {
+            let rest = items.get(from..)?.to_vec();
+            let rest = match value {
+                Value::Vector(_) => Value::Vector(rest),
+                _ => Value::List(rest),
+            };
+            resolve(&rest, path).map(|part| Cow::Owned(part.into_owned()))
+        }
+    }
+}
@@ -2,27 +2,120 @@
 //!
 //! This module contains the interpreter for the s-expressions language.
 
+mod builtins;
+mod capabilities;
+mod concurrency;
+mod data;
+mod generator;
+mod lazy;
+mod limits;
+mod matcher;
+mod profiler;
+mod quote;
 mod values;
 mod visitor;
 
-pub use values::Value;
+#[cfg(test)]
+mod tests;
+
+pub use capabilities::{Capabilities, Capability};
+pub use limits::{LimitExceeded, Limits};
+pub use matcher::MatchError;
+pub use profiler::{Profile, Stats};
+pub use values::{Body, Channel, Function, Future, Record, RecordType, Value};
 
 use crate::parser;
 use crate::parser::Parser;
 use crate::tokenizer::Tokenizer;
 
+use limits::Usage;
+
 use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Helpers written in the language itself, `inc`, `map`, `filter` and so
+/// on, defined by every interpreter unless created `without_prelude`.
+pub const PRELUDE: &str = include_str!("prelude.unsoph");
 
 pub struct AstInterpreter {
     pub stack: Vec<Value>,
     pub heap: HashMap<String, Value>,
+    /// Side effects scripts may perform, empty unless granted.
+    pub capabilities: Capabilities,
+    /// Script arguments, returned by the `args` builtin.
+    pub args: Vec<String>,
+    /// Set once the script called `exit`, the host decides what to do with it.
+    pub exit_code: Option<i32>,
+    /// Resource limits for each `eval` call, unlimited by default.
+    pub limits: Limits,
+    /// Set when the last `eval` call was aborted by one of the `limits`.
+    pub limit_exceeded: Option<LimitExceeded>,
+    /// Set when the last `eval` call failed in a `match`, no arm accepting
+    /// the value or a record pattern not fitting its type.
+    pub match_error: Option<MatchError>,
+    /// Stack size of the threads `spawn` starts, the platform default when
+    /// `None`. Should be as large as the thread the interpreter runs on,
+    /// otherwise calls within `limits.max_depth` can overflow the workers.
+    pub spawn_stack_size: Option<usize>,
+    /// Set to profile the function calls of every later `eval` call.
+    pub profile: Option<Profile>,
+    /// Record types declared by `defstruct`, the latest declaration of a
+    /// name is the one record patterns refer to.
+    types: HashMap<String, Arc<RecordType>>,
+    usage: Usage,
+    /// Number of function calls in progress.
+    depth: usize,
+    /// Number of expressions being evaluated inside one another.
+    nesting: usize,
+    /// Local bindings introduced by `let`, `for` and `loop`, innermost last.
+    scopes: Vec<HashMap<String, Value>>,
+    /// Pending non-local exit, set while `break`, `continue` or `recur`
+    /// unwinds to the enclosing loop.
+    control: Option<Control>,
+}
+
+#[derive(Debug)]
+enum Control {
+    Break(Value),
+    Continue,
+    Recur(Vec<Value>),
 }
 
 impl AstInterpreter {
+    /// Creates a sandboxed interpreter, with no I/O capabilities.
     pub fn new() -> Self {
+        Self::with_capabilities(Capabilities::new())
+    }
+
+    /// Creates an interpreter granted `capabilities`, with the prelude loaded.
+    pub fn with_capabilities(capabilities: Capabilities) -> Self {
+        let mut interpreter = Self::without_prelude(capabilities);
+        interpreter
+            .eval(PRELUDE)
+            .unwrap_or_else(|e| panic!("prelude failed to load: {}", e));
+        interpreter.stack.clear();
+        interpreter
+    }
+
+    /// Creates an interpreter without the prelude, only the builtins are defined.
+    pub fn without_prelude(capabilities: Capabilities) -> Self {
         Self {
             stack: Vec::new(),
             heap: HashMap::new(),
+            capabilities,
+            args: Vec::new(),
+            exit_code: None,
+            limits: Limits::default(),
+            limit_exceeded: None,
+            match_error: None,
+            spawn_stack_size: None,
+            profile: None,
+            types: HashMap::new(),
+            usage: Usage::default(),
+            depth: 0,
+            nesting: 0,
+            scopes: Vec::new(),
+            control: None,
         }
     }
 
@@ -31,12 +124,32 @@ impl AstInterpreter {
         let tokens = tokenizer.tokenize();
 
         let mut parser = Parser::new(tokens);
-        let ast = parser.parse_expr();
+        let ast = parser.parse_program()?;
 
         self.eval_ast(ast)
     }
 
     pub fn eval_ast(&mut self, ast: parser::AstNode) -> Result<(), String> {
-        ast.accept(self)
+        self.usage = Usage::start();
+        self.limit_exceeded = None;
+        self.match_error = None;
+
+        let base = self.stack.len();
+        let result = self.eval_node(&ast);
+        if result.is_err() {
+            // A signal that escaped every loop is reported by its error message.
+            self.control = None;
+            self.scopes.clear();
+            self.stack.truncate(base);
+        }
+        result
+    }
+
+    fn lookup(&self, ident: &str) -> Option<&Value> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(ident))
+            .or_else(|| self.heap.get(ident))
     }
 }
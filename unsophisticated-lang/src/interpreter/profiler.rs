@@ -0,0 +1,163 @@
+//! Call counts and timings of the functions and builtins a script calls.
+
+use super::AstInterpreter;
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+/// What was called while profiling, collected by an interpreter whose
+/// `profile` is set. Functions are named by `defn`, anonymous ones after
+/// the identifier they were called through.
+#[derive(Debug)]
+pub struct Profile {
+    pub functions: HashMap<String, Stats>,
+    /// Call tree, the root is the top level of the script.
+    nodes: Vec<Node>,
+    /// Calls in progress, innermost last.
+    open: Vec<Open>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Stats {
+    pub calls: u64,
+    /// Time from call to return, counted once for recursive calls.
+    pub total: Duration,
+    /// Time not spent in nested calls.
+    pub self_time: Duration,
+    /// Calls of this function in progress.
+    active: usize,
+}
+
+#[derive(Debug)]
+struct Node {
+    name: String,
+    parent: usize,
+    children: HashMap<String, usize>,
+    self_time: Duration,
+}
+
+#[derive(Debug)]
+struct Open {
+    node: usize,
+    started: Instant,
+    /// Time spent in the calls it made so far.
+    nested: Duration,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Self {
+            functions: HashMap::new(),
+            nodes: vec![Node {
+                name: String::new(),
+                parent: 0,
+                children: HashMap::new(),
+                self_time: Duration::ZERO,
+            }],
+            open: Vec::new(),
+        }
+    }
+}
+
+impl Profile {
+    fn enter(&mut self, name: &str) {
+        let parent = self.open.last().map_or(0, |open| open.node);
+        let node = match self.nodes[parent].children.get(name) {
+            Some(&node) => node,
+            None => {
+                let node = self.nodes.len();
+                self.nodes.push(Node {
+                    name: name.to_string(),
+                    parent,
+                    children: HashMap::new(),
+                    self_time: Duration::ZERO,
+                });
+                self.nodes[parent].children.insert(name.to_string(), node);
+                node
+            }
+        };
+        let stats = self.functions.entry(name.to_string()).or_default();
+        stats.calls += 1;
+        stats.active += 1;
+        self.open.push(Open {
+            node,
+            started: Instant::now(),
+            nested: Duration::ZERO,
+        });
+    }
+
+    fn exit(&mut self) {
+        let open = self.open.pop().expect("profiled call exited twice");
+        let elapsed = open.started.elapsed();
+        let self_time = elapsed.saturating_sub(open.nested);
+        if let Some(caller) = self.open.last_mut() {
+            caller.nested += elapsed;
+        }
+
+        let node = &mut self.nodes[open.node];
+        node.self_time += self_time;
+        let stats = self.functions.get_mut(&node.name).unwrap();
+        stats.self_time += self_time;
+        stats.active -= 1;
+        if stats.active == 0 {
+            stats.total += elapsed;
+        }
+    }
+
+    /// One line per function, the slowest first.
+    pub fn report(&self) -> String {
+        let mut functions = self.functions.iter().collect::<Vec<_>>();
+        functions.sort_by(|(a, a_stats), (b, b_stats)| {
+            b_stats.total.cmp(&a_stats.total).then_with(|| a.cmp(b))
+        });
+
+        let mut report = format!("{:>10} {:>12} {:>12}  function\n", "calls", "total ms", "self ms");
+        for (name, stats) in functions {
+            writeln!(
+                report,
+                "{:>10} {:>12.3} {:>12.3}  {}",
+                stats.calls,
+                stats.total.as_secs_f64() * 1000.0,
+                stats.self_time.as_secs_f64() * 1000.0,
+                name
+            )
+            .unwrap();
+        }
+        report
+    }
+
+    /// The call stacks in the collapsed format flame graph tools read,
+    /// `f;g;h 42`, with the time spent in the innermost function in
+    /// microseconds. Sorted, one line per distinct stack.
+    pub fn collapsed_stacks(&self) -> String {
+        let mut lines = self.nodes[1..]
+            .iter()
+            .map(|node| {
+                let mut names = vec![node.name.as_str()];
+                let mut parent = node.parent;
+                while parent != 0 {
+                    names.push(&self.nodes[parent].name);
+                    parent = self.nodes[parent].parent;
+                }
+                names.reverse();
+                format!("{} {}", names.join(";"), node.self_time.as_micros())
+            })
+            .collect::<Vec<_>>();
+        lines.sort();
+        lines.iter().map(|line| format!("{}\n", line)).collect()
+    }
+}
+
+impl AstInterpreter {
+    /// Runs `call` as a call to `name`, timed when profiling.
+    pub(super) fn profiled<T>(&mut self, name: &str, call: impl FnOnce(&mut Self) -> T) -> T {
+        if self.profile.is_none() {
+            return call(self);
+        }
+        self.profile.as_mut().unwrap().enter(name);
+        let result = call(self);
+        self.profile.as_mut().unwrap().exit();
+        result
+    }
+}
@@ -0,0 +1,99 @@
+//! Code as data: `quote` turns source into values and `eval` turns values
+//! back into code.
+
+use super::values::Value;
+use super::visitor::literal_value;
+
+use crate::parser::{AstNode, Datum, Parser};
+use crate::tokenizer::{Delimiter, Literal, ReservedKw, Token, Tokenizer};
+
+/// The value of a quoted datum, symbols stand for identifiers, keywords
+/// and operators, lists for forms.
+pub(super) fn datum_value(datum: &Datum) -> Result<Value, String> {
+    Ok(match datum {
+        Datum::Nil => Value::Nil,
+        Datum::Literal(literal) => literal_value(literal)?,
+        Datum::Symbol(symbol) => Value::Symbol(symbol.clone()),
+        Datum::List(items) => Value::List(items.iter().map(datum_value).collect::<Result<_, _>>()?),
+    })
+}
+
+/// Reads a value as code, the way the parser would read its quoted source.
+/// Vectors read as calls to `vector`.
+pub(super) fn to_ast(value: &Value) -> Result<AstNode, String> {
+    let mut tokens = vec![];
+    write_tokens(value, &mut tokens)?;
+    tokens.push(Token::Delimiter(Delimiter::EOF));
+
+    match Parser::new(tokens).parse_program()? {
+        AstNode::Do(mut exprs) if exprs.len() == 1 => Ok(exprs.remove(0)),
+        _ => Err(format!("Cannot eval {:?}", value)),
+    }
+}
+
+/// Splits `(list 'x 1 'y 2)` into the names and values to bind.
+pub(super) fn bindings(value: &Value) -> Result<(Vec<String>, Vec<Value>), String> {
+    let items = match value {
+        Value::List(items) | Value::Vector(items) if items.len() % 2 == 0 => items,
+        _ => return Err(format!("eval expects a list of symbols and values, found {:?}", value)),
+    };
+    let mut params = vec![];
+    let mut values = vec![];
+    for pair in items.chunks(2) {
+        match &pair[0] {
+            Value::Symbol(symbol) => params.push(symbol.clone()),
+            key => return Err(format!("eval expects a symbol to bind, found {:?}", key)),
+        }
+        values.push(pair[1].clone());
+    }
+    Ok((params, values))
+}
+
+fn write_tokens(value: &Value, tokens: &mut Vec<Token>) -> Result<(), String> {
+    let token = match value {
+        Value::Nil => Token::ReservedKw(ReservedKw::Nil),
+        Value::Bool(true) => Token::ReservedKw(ReservedKw::True),
+        Value::Bool(false) => Token::ReservedKw(ReservedKw::False),
+        Value::Number(n) => Token::Literal(Literal::NumberLit(n.to_string())),
+        Value::String(s) => Token::Literal(Literal::StringLit(s.clone())),
+        Value::Char(c) => Token::Literal(Literal::CharLit(c.to_string())),
+        Value::Symbol(symbol) => symbol_token(symbol)?,
+        Value::List(items) => return write_list(None, items, tokens),
+        Value::Vector(items) => {
+            return write_list(Some(Token::Ident("vector".to_string())), items, tokens)
+        }
+        _ => return Err(format!("Cannot eval {:?}", value)),
+    };
+    tokens.push(token);
+    Ok(())
+}
+
+fn write_list(head: Option<Token>, items: &[Value], tokens: &mut Vec<Token>) -> Result<(), String> {
+    tokens.push(Token::Delimiter(Delimiter::LParen));
+    let has_head = head.is_some();
+    tokens.extend(head);
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 || has_head {
+            tokens.push(Token::Delimiter(Delimiter::Space));
+        }
+        write_tokens(item, tokens)?;
+    }
+    tokens.push(Token::Delimiter(Delimiter::RParen));
+    Ok(())
+}
+
+/// The single token a symbol names, `string->symbol` can build symbols
+/// that are no name at all.
+fn symbol_token(symbol: &str) -> Result<Token, String> {
+    let mut tokens = Tokenizer::new(symbol).tokenize();
+    match tokens[..] {
+        [Token::Ident(_)
+        | Token::ReservedKw(_)
+        | Token::BinaryOp(_)
+        | Token::UnaryOp(_)
+        | Token::Delimiter(Delimiter::Dot | Delimiter::Hash), Token::Delimiter(Delimiter::EOF)] => {
+            Ok(tokens.remove(0))
+        }
+        _ => Err(format!("Cannot eval symbol {:?}", symbol)),
+    }
+}
@@ -0,0 +1,1574 @@
+use super::*;
+
+use proptest::prelude::*;
+
+fn eval(input: &str) -> Result<Value, String> {
+    let mut interpreter = AstInterpreter::new();
+    interpreter.eval(input)?;
+    Ok(interpreter.stack.pop().unwrap())
+}
+
+fn list(items: &[f64]) -> Value {
+    Value::List(items.iter().map(|n| Value::Number(*n)).collect())
+}
+
+#[test]
+fn test_if() {
+    assert_eq!(eval("(if (< 1 2) 10 20)"), Ok(Value::Number(10.0)));
+    assert_eq!(eval("(if (> 1 2) 10 20)"), Ok(Value::Number(20.0)));
+    assert_eq!(eval("(if false 10)"), Ok(Value::Nil));
+    assert_eq!(eval("(if (range 0 3) 10 20)"), Ok(Value::Number(10.0)));
+    assert_eq!(eval("(if (range 3 0) 10 20)"), Ok(Value::Number(20.0)));
+    assert_eq!(eval("(if (range) 10 20)"), Ok(Value::Number(10.0)));
+}
+
+#[test]
+fn test_comparisons_with_nan() {
+    for op in ["<", ">", "<=", ">="] {
+        assert_eq!(eval(&format!("({} (/ 0 0) 1)", op)), Ok(Value::Bool(false)), "{}", op);
+    }
+    assert_eq!(eval("(<= 1 1)"), Ok(Value::Bool(true)));
+    assert_eq!(eval("(>= \"a\" \"b\")"), Ok(Value::Bool(false)));
+}
+
+#[test]
+fn test_let_and_set() {
+    assert_eq!(
+        eval("(let (x 1 y (+ x 1)) (set x 5) (+ x y))"),
+        Ok(Value::Number(7.0))
+    );
+    assert_eq!(eval("(let (x 1) (let (x 2) x))"), Ok(Value::Number(2.0)));
+    assert!(eval("(set x 1)").is_err());
+}
+
+#[test]
+fn test_while() {
+    let src = "(let (i 0 acc 0)
+        (while (< i 5)
+            (set acc (+ acc i))
+            (set i (+ i 1)))
+        acc)";
+    assert_eq!(eval(src), Ok(Value::Number(10.0)));
+    assert_eq!(eval("(while false 1)"), Ok(Value::Nil));
+}
+
+#[test]
+fn test_while_break_and_continue() {
+    let src = "(let (i 0 acc 0)
+        (while true
+            (set i (+ i 1))
+            (if (> i 10) (break acc))
+            (if (= i 3) (continue))
+            (set acc (+ acc i))))";
+    assert_eq!(eval(src), Ok(Value::Number(52.0)));
+}
+
+#[test]
+fn test_for_over_collections() {
+    let src = "(let (acc 0) (for (x (list 1 2 3)) (set acc (+ acc x))) acc)";
+    assert_eq!(eval(src), Ok(Value::Number(6.0)));
+
+    let src = "(let (acc 0) (for (x (vector 4 5)) (set acc (+ acc x))) acc)";
+    assert_eq!(eval(src), Ok(Value::Number(9.0)));
+
+    let src = "(let (acc \"\") (for (c \"abc\") (set acc (+ \"x\" acc))) acc)";
+    assert_eq!(eval(src), Ok(Value::String("xxx".to_string())));
+
+    assert!(eval("(for (x 1) x)").is_err());
+}
+
+#[test]
+fn test_for_break_and_continue() {
+    let src = "(let (acc 0)
+        (for (x (range 10))
+            (if (= x 2) (continue))
+            (if (= x 5) (break acc))
+            (set acc (+ acc x))))";
+    assert_eq!(eval(src), Ok(Value::Number(8.0)));
+}
+
+#[test]
+fn test_range() {
+    assert_eq!(
+        eval("(range 1 5)"),
+        Ok(Value::Range {
+            start: 1.0,
+            end: Some(5.0),
+            step: 1.0
+        })
+    );
+    assert!(eval("(range 1 5 0)").is_err());
+
+    let collect = |range: &str| -> Vec<Value> { eval(range).unwrap().iter().unwrap().collect() };
+    assert_eq!(Value::List(collect("(range 3)")), list(&[0.0, 1.0, 2.0]));
+    assert_eq!(
+        Value::List(collect("(range 0 10 4)")),
+        list(&[0.0, 4.0, 8.0])
+    );
+    assert_eq!(
+        Value::List(collect("(range 3 0 (~ 1))")),
+        list(&[3.0, 2.0, 1.0])
+    );
+}
+
+#[test]
+fn test_range_is_lazy() {
+    let src = "(let (n 0) (for (x (range)) (if (= x 1000) (break x))))";
+    assert_eq!(eval(src), Ok(Value::Number(1000.0)));
+}
+
+#[test]
+fn test_loop_recur() {
+    let src = "(loop (i 0 acc 1)
+        (if (< i 5)
+            (recur (+ i 1) (* acc 2))
+            acc))";
+    assert_eq!(eval(src), Ok(Value::Number(32.0)));
+}
+
+#[test]
+fn test_loop_recur_does_not_grow_the_stack() {
+    let src = "(loop (i 0) (if (< i 100000) (recur (+ i 1)) i))";
+    assert_eq!(eval(src), Ok(Value::Number(100000.0)));
+}
+
+#[test]
+fn test_loop_break() {
+    let src = "(loop (i 0) (if (= i 7) (break (* i 2))) (recur (+ i 1)))";
+    assert_eq!(eval(src), Ok(Value::Number(14.0)));
+}
+
+#[test]
+fn test_nested_loops_break_innermost() {
+    let src = "(let (acc 0)
+        (for (x (range 3))
+            (for (y (range 10))
+                (if (= y 2) (break))
+                (set acc (+ acc 1))))
+        acc)";
+    assert_eq!(eval(src), Ok(Value::Number(6.0)));
+}
+
+#[test]
+fn test_recur_must_be_in_tail_position() {
+    let err = eval("(loop (i 0) (+ 1 (recur i)))").unwrap_err();
+    assert!(err.contains("recur must be in tail position"), "{}", err);
+
+    let err = eval("(loop (i 0) (recur i) i)").unwrap_err();
+    assert!(err.contains("recur must be in tail position"), "{}", err);
+}
+
+#[test]
+fn test_recur_arity() {
+    assert!(eval("(loop (i 0) (recur 1 2))").is_err());
+}
+
+#[test]
+fn test_signals_outside_of_loop() {
+    assert_eq!(eval("(break 1)"), Err("break outside of loop".to_string()));
+    assert_eq!(
+        eval("(continue)"),
+        Err("continue outside of loop".to_string())
+    );
+    assert_eq!(eval("(recur 1)"), Err("recur outside of loop".to_string()));
+    assert!(eval("(loop (i 0) (continue))").is_err());
+}
+
+#[test]
+fn test_defn_and_recursion() {
+    let src = "(defn fact (n) (if (<= n 1) 1 (* n (fact (- n 1))))) (fact 5)";
+    assert_eq!(eval(src), Ok(Value::Number(120.0)));
+    assert_eq!(
+        eval("(defn f (x) x) (f 1 2)"),
+        Err("f expects 1 arguments, found 2".to_string())
+    );
+}
+
+#[test]
+fn test_closures_capture_locals() {
+    let src = "(defn adder (n) (fn (x) (+ x n))) (def add2 (adder 2)) (add2 40)";
+    assert_eq!(eval(src), Ok(Value::Number(42.0)));
+
+    // Captured by value, later `set`s are not seen
+    let src = "(let (n 1) (def f (fn () n)) (set n 2) (f))";
+    assert_eq!(eval(src), Ok(Value::Number(1.0)));
+}
+
+#[test]
+fn test_functions_do_not_see_caller_locals() {
+    let src = "(defn f () y) (let (y 1) (f))";
+    assert_eq!(eval(src), Err("Undefined identifier: y".to_string()));
+}
+
+#[test]
+fn test_functions_shadow_builtins() {
+    assert_eq!(eval("(let (list (fn (x) x)) (list 1))"), Ok(Value::Number(1.0)));
+    assert_eq!(eval("(let (list 1) (list 1))"), Ok(list(&[1.0])));
+}
+
+#[test]
+fn test_signals_do_not_cross_function_calls() {
+    let src = "(defn f () (break 1)) (for (x (list 1 2)) (f))";
+    assert_eq!(eval(src), Err("break outside of loop".to_string()));
+    let src = "(loop (i 0) (if (< i 1) ((fn () (recur 1))) i))";
+    assert_eq!(eval(src), Err("recur outside of loop".to_string()));
+}
+
+#[test]
+fn test_call_expression() {
+    assert_eq!(eval("((fn (x) (* x 2)) 21)"), Ok(Value::Number(42.0)));
+    assert_eq!(eval("(((fn (n) (fn (x) (+ x n))) 1) 2)"), Ok(Value::Number(3.0)));
+    assert_eq!(eval("((if false inc dec) 1)"), Ok(Value::Number(0.0)));
+    assert_eq!(eval("((+ 1 2) 3)"), Err("Cannot call Number(3.0)".to_string()));
+}
+
+#[test]
+fn test_defstruct() {
+    let src = "(defstruct point x y) (def p (point 1 2))";
+    assert_eq!(
+        eval(&format!("{} (list (point-x p) (point-y p))", src)),
+        Ok(list(&[1.0, 2.0]))
+    );
+    assert_eq!(
+        eval(&format!("{} (list (point? p) (point? 1))", src)),
+        Ok(Value::List(vec![Value::Bool(true), Value::Bool(false)]))
+    );
+    assert_eq!(
+        eval(&format!("{} (map point-x (list p (point 3 4)))", src)),
+        Ok(list(&[1.0, 3.0]))
+    );
+
+    let record = eval(&format!("{} p", src)).unwrap();
+    assert_eq!(record.to_string(), "#point{x: 1 y: 2}");
+}
+
+#[test]
+fn test_print_display() {
+    let show = |src: &str| eval(src).unwrap().to_string();
+    assert_eq!(show("\"a b\""), "a b");
+    assert_eq!(show("(list 1 (/ 5 2) \"a b\" nil true 'x)"), "(1 2.5 \"a b\" nil true x)");
+    assert_eq!(show("(vector (list) (/ 1 2))"), "[() 0.5]");
+    assert_eq!(show("(hash-map \"k\" (vector 1))"), "{\"k\" [1]}");
+    assert_eq!(
+        show("(defstruct point x y) (defstruct line a b) (line (point 0 1) (point \"a\" nil))"),
+        "#line{a: #point{x: 0 y: 1} b: #point{x: \"a\" y: nil}}"
+    );
+    assert_eq!(
+        show("(list (range 3) (range 1 9 2) (range))"),
+        "(#<range 0..3> #<range 1..9 by 2> #<range 0..>)"
+    );
+    assert_eq!(show("(defn f () 1) (list f (fn () 1))"), "(#<fn f> #<fn>)");
+}
+
+#[test]
+fn test_defstruct_errors() {
+    assert_eq!(
+        eval("(defstruct point x y) (point 1)"),
+        Err("point expects 2 arguments, found 1".to_string())
+    );
+    assert_eq!(
+        eval("(defstruct point x y) (point-x 1)"),
+        Err("point-x expects a point, found Number(1.0)".to_string())
+    );
+    assert_eq!(
+        eval("(defstruct a v) (defstruct b v) (a-v (b 1))"),
+        Err("a-v expects a a, found Record(#b{v: Number(1.0)})".to_string())
+    );
+}
+
+#[test]
+fn test_records_compare_by_type_and_fields() {
+    let src = "(defstruct point x y)";
+    assert_eq!(eval(&format!("{} (= (point 1 2) (point 1 2))", src)), Ok(Value::Bool(true)));
+    assert_eq!(eval(&format!("{} (= (point 1 2) (point 2 1))", src)), Ok(Value::Bool(false)));
+
+    // Declaring the type again makes a new one, old records are not of it
+    let src = "(defstruct point x y) (def p (point 1 2)) (defstruct point x y)";
+    assert_eq!(eval(&format!("{} (= p (point 1 2))", src)), Ok(Value::Bool(false)));
+    assert_eq!(eval(&format!("{} (point? p)", src)), Ok(Value::Bool(false)));
+}
+
+#[test]
+fn test_match_literals_and_bindings() {
+    let src = "(defn describe (x)
+        (match x
+            (nil \"nil\")
+            (0 \"zero\")
+            (true \"true\")
+            (\"hi\" \"greeting\")
+            (n if (< n 0) \"negative\")
+            (_ \"other\")))";
+    for (arg, expected) in [
+        ("nil", "nil"),
+        ("0", "zero"),
+        ("true", "true"),
+        ("\"hi\"", "greeting"),
+        ("(~ 5)", "negative"),
+        ("5", "other"),
+    ] {
+        assert_eq!(
+            eval(&format!("{} (describe {})", src, arg)),
+            Ok(Value::from(expected))
+        );
+    }
+    assert_eq!(eval("(match 41 (x (+ x 1)))"), Ok(Value::Number(42.0)));
+}
+
+#[test]
+fn test_match_lists() {
+    let src = "(defn shape (xs)
+        (match xs
+            (() 0)
+            ((a) a)
+            ((a b) (+ a b))
+            ((a b . rest) rest)))";
+    assert_eq!(eval(&format!("{} (shape (list))", src)), Ok(Value::Number(0.0)));
+    assert_eq!(eval(&format!("{} (shape (list 5))", src)), Ok(Value::Number(5.0)));
+    assert_eq!(eval(&format!("{} (shape (vector 1 2))", src)), Ok(Value::Number(3.0)));
+    assert_eq!(eval(&format!("{} (shape (list 1 2 3 4))", src)), Ok(list(&[3.0, 4.0])));
+    assert_eq!(
+        eval(&format!("{} (shape (vector 1 2 3))", src)),
+        Ok(Value::Vector(vec![Value::Number(3.0)]))
+    );
+
+    // Nested patterns, the first matching arm wins
+    let src = "(match (list (list 1 2) 3) (((a . _) b) (list a b)) (_ nil))";
+    assert_eq!(eval(src), Ok(list(&[1.0, 3.0])));
+}
+
+#[test]
+fn test_match_records() {
+    let src = "(defstruct point x y) (defstruct circle center radius)
+        (defn area (shape)
+            (match shape
+                (#(circle _ r) (* 3 (* r r)))
+                (#(point 0 0) \"origin\")
+                (#(point x y) (+ x y))))";
+    assert_eq!(eval(&format!("{} (area (circle (point 1 1) 2))", src)), Ok(Value::Number(12.0)));
+    assert_eq!(eval(&format!("{} (area (point 0 0))", src)), Ok(Value::from("origin")));
+    assert_eq!(eval(&format!("{} (area (point 1 2))", src)), Ok(Value::Number(3.0)));
+
+    assert_eq!(
+        eval("(match 1 (#(point x) x))"),
+        Err("point is not a record type".to_string())
+    );
+    assert_eq!(
+        eval("(defstruct point x y) (match 1 (#(point x) x))"),
+        Err("point has 2 fields, found 1 in a pattern".to_string())
+    );
+}
+
+#[test]
+fn test_match_scopes_and_loops() {
+    // Bindings are only visible in their arm, `set` updates them
+    assert_eq!(
+        eval("(let (x 1) (match 2 (x (set x 3) x)) x)"),
+        Ok(Value::Number(1.0))
+    );
+    // Arms are in tail position for `recur`
+    let src = "(loop (xs (list 1 2 3) acc 0) (match xs (() acc) ((x . rest) (recur rest (+ acc x)))))";
+    assert_eq!(eval(src), Ok(Value::Number(6.0)));
+}
+
+#[test]
+fn test_match_without_matching_arm() {
+    let mut interpreter = AstInterpreter::new();
+    assert_eq!(
+        eval_with(&mut interpreter, "(match (list 1 2) ((x) x) (() 0))"),
+        Err("No match arm for List([Number(1.0), Number(2.0)])".to_string())
+    );
+    assert_eq!(interpreter.match_error, Some(MatchError::NoMatch(list(&[1.0, 2.0]))));
+
+    assert_eq!(eval_with(&mut interpreter, "(match 1 (1 2))"), Ok(Value::Number(2.0)));
+    assert_eq!(interpreter.match_error, None);
+
+    assert!(eval_with(&mut interpreter, "(defstruct point x y) (match 1 (#(point x) x))").is_err());
+    assert_eq!(
+        interpreter.match_error,
+        Some(MatchError::FieldCount {
+            ident: "point".to_string(),
+            expected: 2,
+            found: 1
+        })
+    );
+}
+
+fn symbol(s: &str) -> Value {
+    Value::Symbol(s.to_string())
+}
+
+#[test]
+fn test_quote() {
+    assert_eq!(eval("'x"), Ok(symbol("x")));
+    assert_eq!(
+        eval("'(+ 1 (f \"a\" nil true))"),
+        Ok(Value::List(vec![
+            symbol("+"),
+            Value::Number(1.0),
+            Value::List(vec![symbol("f"), "a".into(), Value::Nil, Value::Bool(true)]),
+        ]))
+    );
+    assert_eq!(eval("(quote (if x))"), Ok(Value::List(vec![symbol("if"), symbol("x")])));
+    assert_eq!(eval("''x"), Ok(Value::List(vec![symbol("quote"), symbol("x")])));
+    assert_eq!(eval("(first '(a b))"), Ok(symbol("a")));
+    assert_eq!(eval("(if 'x 1 2)"), Ok(Value::Number(1.0)));
+}
+
+#[test]
+fn test_symbol_conversions() {
+    assert_eq!(eval("(= 'a (string->symbol \"a\"))"), Ok(Value::Bool(true)));
+    assert_eq!(eval("(= 'a \"a\")"), Ok(Value::Bool(false)));
+    assert_eq!(eval("(symbol->string 'symbol->string)"), Ok("symbol->string".into()));
+    assert!(eval("(symbol->string \"a\")").is_err());
+    assert!(eval("(string->symbol 'a)").is_err());
+}
+
+#[test]
+fn test_eval() {
+    assert_eq!(eval("(eval '(+ 1 2))"), Ok(Value::Number(3.0)));
+    assert_eq!(eval("(eval (list '* 6 7))"), Ok(Value::Number(42.0)));
+    assert_eq!(eval("(eval (list '- (~ 5) (/ 1 2)))"), Ok(Value::Number(-5.5)));
+    assert_eq!(eval("(eval \"s\")"), Ok("s".into()));
+    assert_eq!(
+        eval("(eval (vector 1 (+ 1 1)))"),
+        Ok(Value::Vector(vec![Value::Number(1.0), Value::Number(2.0)]))
+    );
+    assert_eq!(eval("(eval '(let (x 1) (match (list x 2) ((a . b) b))))"), Ok(list(&[2.0])));
+
+    // Globals are visible and can be defined, locals are not
+    assert_eq!(eval("(def x 10) (eval 'x)"), Ok(Value::Number(10.0)));
+    assert_eq!(eval("(eval '(defn sq (x) (* x x))) (sq 4)"), Ok(Value::Number(16.0)));
+    assert_eq!(
+        eval("(let (y 1) (eval 'y))"),
+        Err("Undefined identifier: y".to_string())
+    );
+    assert_eq!(eval("(eval '(+ x y) (list 'x 1 'y 2))"), Ok(Value::Number(3.0)));
+
+    // Code built at runtime
+    let src = "(defn twice (form) (list 'do form form))
+        (def n 0)
+        (eval (twice '(set n (+ n 1))))
+        n";
+    assert_eq!(eval(src), Ok(Value::Number(2.0)));
+}
+
+#[test]
+fn test_eval_errors() {
+    assert_eq!(eval("(eval (fn () 1))"), Err("Cannot eval Function(#<fn>)".to_string()));
+    assert_eq!(
+        eval("(eval (string->symbol \"a b\"))"),
+        Err("Cannot eval symbol \"a b\"".to_string())
+    );
+    assert!(eval("(eval '(if))").unwrap_err().starts_with("ParserError"));
+    assert!(eval("(eval 'x (list 'x))").is_err());
+    assert!(eval("(eval 'x (list 1 2))").is_err());
+    assert!(eval("(eval)").is_err());
+    assert!(eval("(eval 1 (list) 2)").is_err());
+
+    // Loop signals do not cross into the evaluated code
+    assert!(eval("(while true (eval '(break 1)))").is_err());
+}
+
+#[test]
+fn test_lazy_seq() {
+    assert_eq!(eval("(seq->list (lazy-seq (list 1 2)))"), Ok(list(&[1.0, 2.0])));
+    assert_eq!(eval("(seq->list (lazy-seq (range 3)))"), Ok(list(&[0.0, 1.0, 2.0])));
+    assert_eq!(eval("(first (lazy-seq nil))"), Ok(Value::Nil));
+    assert_eq!(eval("(empty? (rest (lazy-seq (list 1))))"), Ok(Value::Bool(true)));
+    assert_eq!(eval("(count (cons 0 (lazy-seq (range 3))))"), Ok(Value::Number(4.0)));
+    assert_eq!(eval("(seq? (lazy-seq nil))"), Ok(Value::Bool(true)));
+    assert_eq!(eval("(seq? (list))"), Ok(Value::Bool(false)));
+    assert_eq!(eval("(if (lazy-seq nil) 1 2)"), Ok(Value::Number(1.0)));
+
+    // The body runs when the sequence is first walked, and only once
+    let src = "(def n 0)
+        (def s (lazy-seq (set n (+ n 1)) (list 1 2)))
+        (def before n)
+        (first s)
+        (first (rest s))
+        (list before n)";
+    assert_eq!(eval(src), Ok(list(&[0.0, 1.0])));
+
+    // Locals are captured like in a closure
+    assert_eq!(
+        eval("(let (x 5) (def s (lazy-seq (list x)))) (first s)"),
+        Ok(Value::Number(5.0))
+    );
+}
+
+#[test]
+fn test_lazy_seq_errors() {
+    assert_eq!(
+        eval("(first (lazy-seq 1))"),
+        Err("lazy-seq expects a sequence, found Number(1.0)".to_string())
+    );
+    assert_eq!(
+        eval("(def s (lazy-seq (list (first s)))) (first s)"),
+        Err("Lazy sequence depends on itself".to_string())
+    );
+
+    // A failed sequence keeps failing, its body does not run again
+    let src = "(def n 0)
+        (def s (lazy-seq (set n (+ n 1)) (+ 1 \"a\")))";
+    let mut interpreter = AstInterpreter::new();
+    eval_with(&mut interpreter, src).unwrap();
+    for _ in 0..2 {
+        assert!(eval_with(&mut interpreter, "(first s)").is_err());
+    }
+    assert_eq!(eval_with(&mut interpreter, "n"), Ok(Value::Number(1.0)));
+}
+
+#[test]
+fn test_lazy_prelude() {
+    assert_eq!(eval("(seq->list (take 4 (iterate inc 0)))"), Ok(list(&[0.0, 1.0, 2.0, 3.0])));
+    assert_eq!(
+        eval("(seq->list (take 3 (drop 2 (iterate (fn (x) (* x 2)) 1))))"),
+        Ok(list(&[4.0, 8.0, 16.0]))
+    );
+    assert_eq!(
+        eval("(seq->list (take-while (fn (x) (< x 3)) (iterate inc 0)))"),
+        Ok(list(&[0.0, 1.0, 2.0]))
+    );
+    assert_eq!(
+        eval("(seq->list (take 3 (map (fn (x) (* x x)) (filter pos? (iterate inc (~ 5))))))"),
+        Ok(list(&[1.0, 4.0, 9.0]))
+    );
+    assert_eq!(eval("(nth (iterate inc 0) 1000)"), Ok(Value::Number(1000.0)));
+    assert_eq!(eval("(sum (take 4 (iterate inc 1)))"), Ok(Value::Number(10.0)));
+    assert_eq!(eval("(seq->list (take 0 (iterate inc 0)))"), Ok(list(&[])));
+
+    // Nothing is computed before it is needed
+    let src = "(def calls 0)
+        (def s (map (fn (x) (set calls (+ calls 1)) x) (iterate inc 0)))
+        (def before calls)
+        (nth s 2)
+        (nth s 1)
+        (list before calls)";
+    assert_eq!(eval(src), Ok(list(&[0.0, 3.0])));
+
+    // Other collections are still handled eagerly
+    assert_eq!(eval("(map inc (list 1 2))"), Ok(list(&[2.0, 3.0])));
+    assert_eq!(eval("(take-while neg? (list (~ 1) 2 (~ 3)))"), Ok(list(&[-1.0])));
+    assert_eq!(eval("(drop 1 (list 1 2))"), Ok(list(&[2.0])));
+}
+
+#[test]
+fn test_generators() {
+    let src = "(defn countdown (n) (while (pos? n) (yield n) (set n (dec n))))
+        (seq->list (countdown 3))";
+    assert_eq!(eval(src), Ok(list(&[3.0, 2.0, 1.0])));
+
+    let src = "(defn fib () (loop (a 0 b 1) (yield a) (recur b (+ a b))))
+        (seq->list (take 7 (fib)))";
+    assert_eq!(eval(src), Ok(list(&[0.0, 1.0, 1.0, 2.0, 3.0, 5.0, 8.0])));
+
+    let src = "(defn flatten (coll)
+            (for (x coll)
+                (match x
+                    ((a b) (yield a) (yield b))
+                    (_ (let (y x) (yield y))))))
+        (seq->list (flatten (list (list 1 2) 3 (list 4 5))))";
+    assert_eq!(eval(src), Ok(list(&[1.0, 2.0, 3.0, 4.0, 5.0])));
+
+    let src = "(defn upto (coll limit)
+            (for (x coll)
+                (if (> x limit) (break))
+                (if (= x 2) (continue))
+                (yield x))
+            (yield limit))
+        (seq->list (upto (range) 4))";
+    assert_eq!(eval(src), Ok(list(&[0.0, 1.0, 3.0, 4.0, 4.0])));
+
+    // No yield reached, nothing generated
+    assert_eq!(eval("(defn none () (if false (yield 1))) (empty? (none))"), Ok(Value::Bool(true)));
+
+    // Each call runs on its own, up to the yield asked for
+    let src = "(def steps 0)
+        (defn g (x) (set steps (+ steps 1)) (yield x) (set steps (+ steps 1)) (yield x))
+        (def a (g 1))
+        (def b (g 2))
+        (list (first a) (first (rest b)) steps)";
+    assert_eq!(eval(src), Ok(list(&[1.0, 2.0, 3.0])));
+}
+
+#[test]
+fn test_generator_errors() {
+    assert_eq!(
+        eval("(yield 1)"),
+        Err("yield outside of a generator function".to_string())
+    );
+    assert_eq!(
+        eval("(defn g () (yield 1) (+ 1 \"a\")) (count (g))"),
+        Err("Cannot add Number(1.0) and String(\"a\")".to_string())
+    );
+    assert_eq!(
+        eval("(defn g () (loop (i 0) (yield i) (recur))) (count (g))"),
+        Err("recur expects 1 arguments, found 0".to_string())
+    );
+    assert_eq!(
+        eval("(defn g () (yield 1) (break 2)) (count (g))"),
+        Err("break outside of loop".to_string())
+    );
+    assert_eq!(
+        eval("(defn g () (yield 1) (break 2)) (while true (count (g)))"),
+        Err("break outside of loop".to_string())
+    );
+}
+
+/// Like `eval`, with the capability to `spawn`.
+fn eval_threaded(input: &str) -> Result<Value, String> {
+    let mut interpreter = AstInterpreter::with_capabilities([Capability::Threads].into());
+    eval_with(&mut interpreter, input)
+}
+
+#[test]
+fn test_spawn_and_await() {
+    assert_eq!(
+        eval_threaded("(await (spawn (fn () (+ 1 2))))"),
+        Ok(Value::Number(3.0))
+    );
+    assert_eq!(
+        eval_threaded("(defn sq (x) (* x x)) (await (spawn sq 4))"),
+        Ok(Value::Number(16.0))
+    );
+
+    // Awaiting again returns the same value
+    let src = "(def f (spawn (fn () (list 1 2))))
+        (= (await f) (await f))";
+    assert_eq!(eval_threaded(src), Ok(Value::Bool(true)));
+
+    let src = "(defn sq (x) (* x x))
+        (def futures (map (fn (n) (spawn sq n)) (list 1 2 3)))
+        (map (fn (f) (await f)) futures)";
+    assert_eq!(eval_threaded(src), Ok(list(&[1.0, 4.0, 9.0])));
+
+    // The thread has its own copy of the globals
+    let src = "(def x 1)
+        (def f (spawn (fn () (set x 2) x)))
+        (list (await f) x)";
+    assert_eq!(eval_threaded(src), Ok(list(&[2.0, 1.0])));
+
+    // Lazy sequences can be shared, each cell is still realised once
+    let src = "(def s (map (fn (x) (* x 2)) (iterate inc 0)))
+        (def f (spawn (fn () (nth s 100))))
+        (list (nth s 100) (await f))";
+    assert_eq!(eval_threaded(src), Ok(list(&[200.0, 200.0])));
+}
+
+#[test]
+fn test_channels() {
+    let src = "(def c (chan))
+        (send c 1)
+        (send c 2)
+        (close c)
+        (list (recv c) (recv c) (recv c))";
+    assert_eq!(
+        eval_threaded(src),
+        Ok(Value::List(vec![
+            Value::Number(1.0),
+            Value::Number(2.0),
+            Value::Nil
+        ]))
+    );
+
+    // A producer/consumer pipeline, each stage on its own thread
+    let src = "(def nums (chan))
+        (def squares (chan))
+        (spawn (fn () (for (i (range 1 6)) (send nums i)) (close nums)))
+        (spawn (fn () (for (x nums) (send squares (* x x))) (close squares)))
+        (let (acc (list))
+            (for (x squares) (set acc (push acc x)))
+            acc)";
+    assert_eq!(eval_threaded(src), Ok(list(&[1.0, 4.0, 9.0, 16.0, 25.0])));
+
+    // Fan-in, the consumer awaits every producer before closing
+    let src = "(def c (chan))
+        (defn produce (from) (for (i (range from (+ from 3))) (send c i)))
+        (def producers (map (fn (from) (spawn produce from)) (list 0 10 20)))
+        (spawn (fn () (map (fn (f) (await f)) producers) (close c)))
+        (let (total 0)
+            (for (x c) (set total (+ total x)))
+            total)";
+    assert_eq!(eval_threaded(src), Ok(Value::Number(99.0)));
+}
+
+#[test]
+fn test_concurrency_errors() {
+    assert_eq!(
+        eval_threaded("(await (spawn (fn () (+ 1 \"a\"))))"),
+        Err("Cannot add Number(1.0) and String(\"a\")".to_string())
+    );
+    assert_eq!(
+        eval_threaded("(await (spawn (fn (x) x)))"),
+        Err("fn expects 1 arguments, found 0".to_string())
+    );
+    assert_eq!(
+        eval_threaded("(spawn 1)"),
+        Err("spawn expects a function, found Number(1.0)".to_string())
+    );
+    assert_eq!(
+        eval("(spawn (fn () 1))"),
+        Err("spawn is not permitted without the Threads capability".to_string())
+    );
+    assert_eq!(
+        eval_threaded("(await 1)"),
+        Err("await expects a future, found Number(1.0)".to_string())
+    );
+    assert_eq!(
+        eval_threaded("(def c (chan)) (close c) (send c 1)"),
+        Err("send on a closed channel".to_string())
+    );
+    assert_eq!(
+        eval_threaded("(recv (list))"),
+        Err("recv expects a channel, found List([])".to_string())
+    );
+
+    // Waiting on a channel nobody sends on runs into the time limit
+    let mut interpreter = limited(Limits {
+        timeout: Some(std::time::Duration::from_millis(50)),
+        ..Limits::default()
+    });
+    assert_eq!(
+        eval_with(&mut interpreter, "(recv (chan))"),
+        Err("time limit exceeded".to_string())
+    );
+    assert_eq!(interpreter.limit_exceeded, Some(LimitExceeded::Time));
+}
+
+#[test]
+fn test_interpreter_is_send() {
+    fn assert_send<T: Send>() {}
+    fn assert_sync<T: Sync>() {}
+
+    assert_send::<AstInterpreter>();
+    assert_send::<Value>();
+    assert_sync::<Value>();
+}
+
+#[test]
+fn test_collection_builtins() {
+    assert_eq!(eval("(count (list 1 2 3))"), Ok(Value::Number(3.0)));
+    assert_eq!(eval("(count \"héllo\")"), Ok(Value::Number(5.0)));
+    assert_eq!(eval("(count (range 0 10 3))"), Ok(Value::Number(4.0)));
+    assert!(eval("(count (range))").is_err());
+
+    assert_eq!(eval("(first (list 1 2))"), Ok(Value::Number(1.0)));
+    assert_eq!(eval("(first (list))"), Ok(Value::Nil));
+    assert_eq!(eval("(first \"ab\")"), Ok(Value::Char('a')));
+    assert_eq!(eval("(rest (list 1 2 3))"), Ok(list(&[2.0, 3.0])));
+    assert_eq!(eval("(rest (list))"), Ok(list(&[])));
+    assert_eq!(eval("(first (rest (range 5)))"), Ok(Value::Number(1.0)));
+
+    assert_eq!(eval("(cons 1 (list 2))"), Ok(list(&[1.0, 2.0])));
+    assert_eq!(
+        eval("(push (vector 1) 2)"),
+        Ok(Value::Vector(vec![Value::Number(1.0), Value::Number(2.0)]))
+    );
+    assert_eq!(
+        eval("(cons 1 2)"),
+        Err("cons expects a list, a vector or a sequence, found Number(2.0)".to_string())
+    );
+    assert_eq!(
+        eval("(first)"),
+        Err("first expects 1 arguments, found 0".to_string())
+    );
+}
+
+#[test]
+fn test_maps() {
+    let src = "(def m (hash-map \"b\" 2 \"a\" 1))
+        (list (get m \"a\") (get m \"c\") (count m) (keys m) (vals m))";
+    assert_eq!(
+        eval(src),
+        Ok(Value::List(vec![
+            Value::Number(1.0),
+            Value::Nil,
+            Value::Number(2.0),
+            Value::List(vec!["a".into(), "b".into()]),
+            list(&[1.0, 2.0]),
+        ]))
+    );
+    assert_eq!(
+        eval("(= (assoc (hash-map) \"a\" 1) (hash-map \"a\" 1))"),
+        Ok(Value::Bool(true))
+    );
+    assert_eq!(eval("(map? (hash-map))"), Ok(Value::Bool(true)));
+    assert_eq!(eval("(empty? (hash-map))"), Ok(Value::Bool(true)));
+
+    // Walked as (key value) lists, in key order
+    let src = "(let (acc (list))
+            (for (entry (hash-map \"y\" 2 \"x\" 1)) (set acc (push acc (first entry))))
+            acc)";
+    assert_eq!(eval(src), Ok(Value::List(vec!["x".into(), "y".into()])));
+
+    assert_eq!(
+        eval("(hash-map \"a\")"),
+        Err("hash-map expects keys and values, found 1 arguments".to_string())
+    );
+    assert_eq!(
+        eval("(get (hash-map) 1)"),
+        Err("get expects string keys, found Number(1.0)".to_string())
+    );
+    assert_eq!(
+        eval("(keys (list))"),
+        Err("keys expects a map, found List([])".to_string())
+    );
+}
+
+/// Evaluates `input` with `s` bound to `text`, string literals have no
+/// escapes to write most JSON with.
+fn eval_on(text: &str, input: &str) -> Result<Value, String> {
+    let mut interpreter = AstInterpreter::new();
+    interpreter.heap.insert("s".to_string(), text.into());
+    eval_with(&mut interpreter, input)
+}
+
+#[test]
+fn test_json() {
+    let text = r#" {"name": "caf\u00e9 \ud83d\ude00", "tags": ["a", "b\n"], "n": -1.5e2,
+        "ok": true, "none": null, "nested": {"empty": []}} "#;
+    let map = |entries: Vec<(&str, Value)>| {
+        Value::Map(entries.into_iter().map(|(k, v)| (k.to_string(), v)).collect())
+    };
+    assert_eq!(
+        eval_on(text, "(json-parse s)"),
+        Ok(map(vec![
+            ("name", "café 😀".into()),
+            ("tags", Value::Vector(vec!["a".into(), "b\n".into()])),
+            ("n", Value::Number(-150.0)),
+            ("ok", Value::Bool(true)),
+            ("none", Value::Nil),
+            ("nested", map(vec![("empty", Value::Vector(vec![]))])),
+        ]))
+    );
+
+    let json = r#"{"n":-150,"name":"café 😀","nested":{"empty":[]},"none":null,"ok":true,"tags":["a","b\n"]}"#;
+    assert_eq!(eval_on(text, "(json-stringify (json-parse s))"), Ok(json.into()));
+    assert_eq!(
+        eval("(json-stringify (list 1 (vector \"x\") nil))"),
+        Ok(r#"[1,["x"],null]"#.into())
+    );
+    assert_eq!(eval_on("\"\\u0001\"", "(json-stringify (json-parse s))"), Ok(r#""\u0001""#.into()));
+}
+
+#[test]
+fn test_json_errors() {
+    let errors = [
+        ("[1,]", "json-parse: expected a JSON value, found ']' at byte 3"),
+        ("01", "json-parse: expected the end of input, found '1' at byte 1"),
+        ("{\"a\" 1}", "json-parse: expected ':', found '1' at byte 5"),
+        ("{1: 2}", "json-parse: expected a string key, found '1' at byte 1"),
+        ("\"abc", "json-parse: unterminated string at byte 4"),
+        ("\"\\x\"", "json-parse: invalid escape at byte 3"),
+        ("\"\\ud800\"", "json-parse: expected '\\', found '\"' at byte 7"),
+        ("1e999", "json-parse: number out of range at byte 5"),
+        ("nul", "json-parse: expected null, found 'n' at byte 0"),
+        ("", "json-parse: expected a JSON value, found the end of input at byte 0"),
+    ];
+    for (text, error) in errors {
+        assert_eq!(eval_on(text, "(json-parse s)"), Err(error.to_string()), "{}", text);
+    }
+
+    // Nesting is limited before it overflows the stack
+    let deep = format!("{}{}", "[".repeat(512), "]".repeat(512));
+    assert!(eval_on(&deep, "(json-parse s)").is_ok());
+    assert_eq!(
+        eval_on(&"[".repeat(200_000), "(json-parse s)"),
+        Err("json-parse: nesting too deep at byte 512".to_string())
+    );
+
+    assert_eq!(
+        eval("(json-stringify (fn (x) x))"),
+        Err("json-stringify cannot represent Function(#<fn>)".to_string())
+    );
+    assert_eq!(
+        eval("(json-stringify (list (/ 1 0)))"),
+        Err("json-stringify cannot represent inf".to_string())
+    );
+    assert_eq!(
+        eval("(json-stringify 'x)"),
+        Err("json-stringify cannot represent Symbol(\"x\")".to_string())
+    );
+}
+
+#[test]
+fn test_read_write() {
+    let text = r#"(1 -2.5 "a \"b\"" #\a #\space #\( [x nil true] {"k" (1)} sym-bol? + .5)"#;
+    assert_eq!(
+        eval_on(text, "(read s)"),
+        Ok(Value::List(vec![
+            Value::Number(1.0),
+            Value::Number(-2.5),
+            "a \"b\"".into(),
+            Value::Char('a'),
+            Value::Char(' '),
+            Value::Char('('),
+            Value::Vector(vec![Value::Symbol("x".to_string()), Value::Nil, Value::Bool(true)]),
+            Value::Map([("k".to_string(), list(&[1.0]))].into_iter().collect()),
+            Value::Symbol("sym-bol?".to_string()),
+            Value::Symbol("+".to_string()),
+            Value::Number(0.5),
+        ]))
+    );
+    assert_eq!(
+        eval_on(text, "(write (read s))"),
+        Ok(r#"(1 -2.5 "a \"b\"" #\a #\space #\( [x nil true] {"k" (1)} sym-bol? + 0.5)"#.into())
+    );
+
+    // Records are read back as the type of that name
+    let src = "(defstruct point x y)
+        (def p (point 1 (list 2)))
+        (list (write p) (= (read (write p)) p))";
+    assert_eq!(
+        eval(src),
+        Ok(Value::List(vec!["#(point 1 (2))".into(), Value::Bool(true)]))
+    );
+    assert_eq!(eval("(write (first \"\n\"))"), Ok("#\\newline".into()));
+    assert_eq!(eval_on("#\\u0007", "(read s)"), Ok(Value::Char('\u{7}')));
+}
+
+#[test]
+fn test_read_write_errors() {
+    let errors = [
+        ("(1 2", "read: expected ')', found the end of input at byte 4"),
+        ("1 2", "read: expected the end of input, found '2' at byte 2"),
+        ("1x", "read: invalid number 1x at byte 2"),
+        ("{1 2}", "read: map keys must be strings, found Number(1.0) at byte 2"),
+        ("{\"a\"}", "read: missing value for the key \"a\" at byte 4"),
+        ("#\\bell", "read: unknown character name bell at byte 6"),
+        ("#(point 1)", "read: point is not a record type at byte 7"),
+        (")", "read: expected a datum, found ')' at byte 0"),
+    ];
+    for (text, error) in errors {
+        assert_eq!(eval_on(text, "(read s)"), Err(error.to_string()), "{}", text);
+    }
+    assert_eq!(
+        eval_on(&"(".repeat(200_000), "(read s)"),
+        Err("read: nesting too deep at byte 512".to_string())
+    );
+
+    assert_eq!(
+        eval("(defstruct point x y) (read \"#(point 1)\")"),
+        Err("read: point has 2 fields, found 1 at byte 10".to_string())
+    );
+    assert_eq!(
+        eval("(defn f (x) x) (write (list f))"),
+        Err("write cannot represent Function(#<fn f>)".to_string())
+    );
+    assert_eq!(
+        eval("(write (lazy-seq nil))"),
+        Err("write cannot represent Seq(#<seq>)".to_string())
+    );
+    assert_eq!(
+        eval("(write (string->symbol \"a b\"))"),
+        Err("write cannot represent the symbol \"a b\"".to_string())
+    );
+}
+
+#[test]
+fn test_prelude() {
+    assert_eq!(eval("(not= 1 2)"), Ok(Value::Bool(true)));
+    assert_eq!(eval("(list (inc 1) (dec 1) (abs (~ 3)))"), Ok(list(&[2.0, 0.0, 3.0])));
+    assert_eq!(eval("(list (min 1 2) (max 1 2))"), Ok(list(&[1.0, 2.0])));
+    assert_eq!(
+        eval("(let (f (compose inc abs)) (f (~ 4)))"),
+        Ok(Value::Number(5.0))
+    );
+    assert_eq!(eval("(identity nil)"), Ok(Value::Nil));
+
+    assert_eq!(eval("(map inc (range 3))"), Ok(list(&[1.0, 2.0, 3.0])));
+    assert_eq!(eval("(filter pos? (list (~ 1) 0 1))"), Ok(list(&[1.0])));
+    assert_eq!(eval("(remove zero? (list 0 1 0 2))"), Ok(list(&[1.0, 2.0])));
+    assert_eq!(eval("(reduce max 0 (list 3 9 2))"), Ok(Value::Number(9.0)));
+    assert_eq!(eval("(sum (range 101))"), Ok(Value::Number(5050.0)));
+    assert_eq!(eval("(reverse (list 1 2 3))"), Ok(list(&[3.0, 2.0, 1.0])));
+    assert_eq!(eval("(concat (list 1) (range 2 4))"), Ok(list(&[1.0, 2.0, 3.0])));
+    assert_eq!(eval("(take 3 (range))"), Ok(list(&[0.0, 1.0, 2.0])));
+    assert_eq!(eval("(first (drop 5 (range)))"), Ok(Value::Number(5.0)));
+    assert_eq!(eval("(list (nth (list 1 2) 1) (last (list 1 2)))"), Ok(list(&[2.0, 2.0])));
+    assert_eq!(eval("(nth (list 1 2) (~ 1))"), Ok(Value::Nil));
+    assert_eq!(eval("(nth (list 1 2) 5)"), Ok(Value::Nil));
+    assert_eq!(eval("(nth (range 3) 2)"), Ok(Value::Number(2.0)));
+    assert_eq!(
+        eval("(nth (list 1 2) (/ 1 2))"),
+        Err("nth expects an integer index".to_string())
+    );
+    assert_eq!(
+        eval("(nth (list 1 2) \"1\")"),
+        Err("nth expects an integer index".to_string())
+    );
+    assert_eq!(eval("(any? pos? (range))"), Ok(Value::Bool(true)));
+    assert_eq!(eval("(any? neg? (list 0 1))"), Ok(Value::Bool(false)));
+    assert_eq!(eval("(every? pos? (list 1 2))"), Ok(Value::Bool(true)));
+    assert_eq!(eval("(contains? \"abc\" (first \"c\"))"), Ok(Value::Bool(true)));
+    assert_eq!(eval("(empty? (list))"), Ok(Value::Bool(true)));
+}
+
+#[test]
+fn test_list_helpers_take_one_pass() {
+    // Growing the list item by item copied it each time, this took half a minute
+    let mut interpreter = limited(Limits {
+        timeout: Some(std::time::Duration::from_secs(10)),
+        ..Limits::default()
+    });
+    let src = "(def xs (seq->list (range 20000)))
+        (count (concat (reverse (filter pos? xs)) (take 10000 (map inc xs))))";
+    assert_eq!(eval_with(&mut interpreter, src), Ok(Value::Number(29999.0)));
+
+    // The walk counts against the limits like the loop it replaced
+    let mut interpreter = limited(Limits {
+        fuel: Some(1000),
+        ..Limits::default()
+    });
+    assert_eq!(
+        eval_with(&mut interpreter, "(seq->list (range))"),
+        Err("fuel exhausted".to_string())
+    );
+
+    let src = "(def c (chan))
+        (spawn (fn () (send c 1) (send c 2) (close c)))
+        (map inc c)";
+    assert_eq!(eval_threaded(src), Ok(list(&[2.0, 3.0])));
+}
+
+#[test]
+fn test_prelude_can_be_redefined_or_left_out() {
+    assert_eq!(eval("(defn inc (n) (+ n 2)) (inc 1)"), Ok(Value::Number(3.0)));
+
+    let mut interpreter = AstInterpreter::without_prelude(Capabilities::new());
+    assert_eq!(
+        eval_with(&mut interpreter, "(inc 1)"),
+        Err("Unsupported function call: inc".to_string())
+    );
+    assert_eq!(eval_with(&mut interpreter, "(count (list 1))"), Ok(Value::Number(1.0)));
+}
+
+fn eval_with(interpreter: &mut AstInterpreter, input: &str) -> Result<Value, String> {
+    interpreter.eval(input)?;
+    Ok(interpreter.stack.pop().unwrap())
+}
+
+fn temp_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("unsoph-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn test_io_is_disabled_by_default() {
+    for src in [
+        "(read-file \"foo\")",
+        "(read-lines \"foo\")",
+        "(write-file \"foo\" \"bar\")",
+        "(append-file \"foo\" \"bar\")",
+        "(list-dir \".\")",
+        "(getenv \"PATH\")",
+        "(args)",
+        "(exit 1)",
+        "(shell \"echo hi\")",
+    ] {
+        let err = eval(src).unwrap_err();
+        assert!(err.contains("is not permitted"), "{}: {}", src, err);
+    }
+}
+
+#[test]
+fn test_capabilities_are_checked_individually() {
+    let mut interpreter = AstInterpreter::with_capabilities([Capability::Env].into());
+    assert!(eval_with(&mut interpreter, "(getenv \"PATH\")").is_ok());
+    assert!(eval_with(&mut interpreter, "(read-file \"foo\")").is_err());
+}
+
+#[test]
+fn test_file_builtins() {
+    let dir = temp_dir("files");
+    let file = dir.join("out.txt");
+    let file = file.to_str().unwrap();
+
+    let mut interpreter = AstInterpreter::with_capabilities(Capability::all());
+    let src = format!(
+        "(do (write-file \"{0}\" \"hello\") (append-file \"{0}\" \" world\") (read-file \"{0}\"))",
+        file
+    );
+    assert_eq!(
+        eval_with(&mut interpreter, &src),
+        Ok(Value::from("hello world"))
+    );
+
+    let src = format!("(write-file \"{0}\" \"truncated\")", file);
+    eval_with(&mut interpreter, &src).unwrap();
+    assert_eq!(std::fs::read_to_string(file).unwrap(), "truncated");
+
+    std::fs::write(dir.join("a.txt"), "").unwrap();
+    let src = format!("(list-dir \"{}\")", dir.to_str().unwrap());
+    assert_eq!(
+        eval_with(&mut interpreter, &src),
+        Ok(Value::List(vec![
+            Value::from("a.txt"),
+            Value::from("out.txt")
+        ]))
+    );
+
+    assert!(eval_with(&mut interpreter, "(read-file \"/does/not/exist\")").is_err());
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_read_lines() {
+    let dir = temp_dir("lines");
+    let file = dir.join("lines.txt");
+    std::fs::write(&file, "one\ntwo\r\nthree\n").unwrap();
+    let file = file.to_str().unwrap();
+
+    let mut interpreter = AstInterpreter::with_capabilities(Capability::all());
+    assert_eq!(
+        eval_with(&mut interpreter, &format!("(seq->list (read-lines \"{}\"))", file)),
+        Ok(Value::List(vec!["one".into(), "two".into(), "three".into()]))
+    );
+
+    let err = eval_with(&mut interpreter, "(read-lines \"/does/not/exist\")").unwrap_err();
+    assert!(err.starts_with("read-lines /does/not/exist: "), "{}", err);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_env_builtins() {
+    let mut interpreter = AstInterpreter::with_capabilities(Capability::all());
+    interpreter.args = vec!["one".to_string(), "two".to_string()];
+
+    assert_eq!(
+        eval_with(&mut interpreter, "(args)"),
+        Ok(Value::List(vec![Value::from("one"), Value::from("two")]))
+    );
+    assert_eq!(
+        eval_with(&mut interpreter, "(getenv \"UNSOPH_SURELY_UNSET\")"),
+        Ok(Value::Nil)
+    );
+}
+
+#[test]
+fn test_exit_unwinds_loops() {
+    let mut interpreter = AstInterpreter::with_capabilities(Capability::all());
+    let result = eval_with(
+        &mut interpreter,
+        "(loop (i 0) (if (= i 3) (exit 3)) (recur (+ i 1)))",
+    );
+    assert!(result.is_err());
+    assert_eq!(interpreter.exit_code, Some(3));
+}
+
+#[test]
+fn test_shell() {
+    let mut interpreter = AstInterpreter::with_capabilities(Capability::all());
+    assert_eq!(
+        eval_with(&mut interpreter, "(shell \"echo hi\")"),
+        Ok(Value::from("hi\n"))
+    );
+    assert!(eval_with(&mut interpreter, "(shell \"exit 2\")").is_err());
+}
+
+#[test]
+fn test_shell_is_killed_at_the_time_limit() {
+    let mut interpreter = AstInterpreter::with_capabilities(Capability::all());
+    interpreter.limits.timeout = Some(std::time::Duration::from_millis(200));
+    let started = std::time::Instant::now();
+    assert_eq!(
+        eval_with(&mut interpreter, "(shell \"sleep 10\")"),
+        Err("time limit exceeded".to_string())
+    );
+    assert!(started.elapsed().as_secs() < 5);
+    assert_eq!(interpreter.limit_exceeded, Some(LimitExceeded::Time));
+
+    // Output larger than a pipe holds is read while waiting
+    assert_eq!(
+        eval_with(&mut interpreter, "(count (shell \"yes | head -c 100000\"))"),
+        Ok(Value::Number(100000.0))
+    );
+}
+
+fn limited(limits: Limits) -> AstInterpreter {
+    let mut interpreter = AstInterpreter::new();
+    interpreter.limits = limits;
+    interpreter
+}
+
+#[test]
+fn test_fuel_limit() {
+    let mut interpreter = limited(Limits {
+        fuel: Some(1000),
+        ..Limits::default()
+    });
+    let err = eval_with(&mut interpreter, "(loop (i 0) (recur (+ i 1)))").unwrap_err();
+    assert_eq!(err, "fuel exhausted");
+    assert_eq!(interpreter.limit_exceeded, Some(LimitExceeded::Fuel));
+
+    // The budget is per eval call
+    assert_eq!(
+        eval_with(&mut interpreter, "(+ 1 2)"),
+        Ok(Value::Number(3.0))
+    );
+    assert_eq!(interpreter.limit_exceeded, None);
+}
+
+#[test]
+fn test_time_limit() {
+    let mut interpreter = limited(Limits {
+        timeout: Some(std::time::Duration::from_millis(50)),
+        ..Limits::default()
+    });
+    let err = eval_with(&mut interpreter, "(while true (continue))").unwrap_err();
+    assert_eq!(err, "time limit exceeded");
+    assert_eq!(interpreter.limit_exceeded, Some(LimitExceeded::Time));
+}
+
+#[test]
+fn test_stack_limit() {
+    let mut interpreter = limited(Limits {
+        max_stack: Some(3),
+        ..Limits::default()
+    });
+    assert!(eval_with(&mut interpreter, "(+ 1 (+ 2 3))").is_ok());
+
+    let err = eval_with(&mut interpreter, "(+ 1 (+ 2 (+ 3 (+ 4 5))))").unwrap_err();
+    assert_eq!(err, "stack limit exceeded");
+    assert_eq!(interpreter.limit_exceeded, Some(LimitExceeded::Stack));
+}
+
+#[test]
+fn test_memory_limit() {
+    let mut interpreter = limited(Limits {
+        max_memory: Some(1 << 20),
+        ..Limits::default()
+    });
+    let err = eval_with(
+        &mut interpreter,
+        "(let (s \"x\") (while true (set s (+ s s))))",
+    )
+    .unwrap_err();
+    assert_eq!(err, "memory limit exceeded");
+    assert_eq!(interpreter.limit_exceeded, Some(LimitExceeded::Memory));
+}
+
+#[test]
+fn test_memory_limit_counts_live_values() {
+    let mut interpreter = limited(Limits {
+        max_memory: Some(1 << 16),
+        ..Limits::default()
+    });
+    let src = "(let (acc (list)) (for (i (range 100000)) (set acc (list acc acc))))";
+    assert_eq!(
+        eval_with(&mut interpreter, src),
+        Err("memory limit exceeded".to_string())
+    );
+
+    // Garbage does not count against the budget
+    let src = "(for (i (range 100000)) (+ \"some\" \"string\"))";
+    assert_eq!(eval_with(&mut interpreter, src), Ok(Value::Nil));
+}
+
+#[test]
+fn test_sequences_stream_in_constant_memory() {
+    let dir = temp_dir("stream");
+    let file = dir.join("big.txt");
+    std::fs::write(&file, "line\n".repeat(20_000)).unwrap();
+    let file = file.to_str().unwrap();
+
+    let mut interpreter = AstInterpreter::with_capabilities(Capability::all());
+    interpreter.limits.max_memory = Some(1 << 16);
+
+    let src = format!("(count (filter (fn (l) (= l \"line\")) (read-lines \"{}\")))", file);
+    assert_eq!(eval_with(&mut interpreter, &src), Ok(Value::Number(20000.0)));
+
+    let src = format!(
+        "(defn numbered (path)
+            (let (i 0) (for (l (read-lines path)) (set i (inc i)) (yield (list i l)))))
+        (let (last nil) (for (x (numbered \"{}\")) (set last x)) (first last))",
+        file
+    );
+    assert_eq!(eval_with(&mut interpreter, &src), Ok(Value::Number(20000.0)));
+
+    // Holding on to the first cell keeps every cell realised after it
+    let src = format!(
+        "(let (lines (read-lines \"{}\") n 0) (for (l lines) (set n (inc n))) n)",
+        file
+    );
+    assert_eq!(
+        eval_with(&mut interpreter, &src),
+        Err("memory limit exceeded".to_string())
+    );
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_long_sequences_drop_without_recursion() {
+    assert_eq!(
+        eval("(let (s (iterate inc 0)) (nth s 100000))"),
+        Ok(Value::Number(100000.0))
+    );
+}
+
+#[test]
+fn test_limits_are_not_caught_by_loops() {
+    let mut interpreter = limited(Limits {
+        fuel: Some(100),
+        ..Limits::default()
+    });
+    let src = "(for (x (range)) (for (y (range)) (continue)))";
+    assert_eq!(
+        eval_with(&mut interpreter, src),
+        Err("fuel exhausted".to_string())
+    );
+}
+
+#[test]
+fn test_depth_limit() {
+    let mut interpreter = limited(Limits {
+        max_depth: Some(50),
+        ..Limits::default()
+    });
+    let src = "(defn f (n) (if (= n 0) 0 (+ 1 (f (- n 1)))))";
+    assert_eq!(
+        eval_with(&mut interpreter, &format!("{} (f 49)", src)),
+        Ok(Value::Number(49.0))
+    );
+    assert_eq!(
+        eval_with(&mut interpreter, "(f 50)"),
+        Err("call depth limit exceeded".to_string())
+    );
+    assert_eq!(interpreter.limit_exceeded, Some(LimitExceeded::Depth));
+
+    // Unwinding gives the depth back
+    assert_eq!(eval_with(&mut interpreter, "(f 49)"), Ok(Value::Number(49.0)));
+}
+
+#[test]
+fn test_nesting_limit() {
+    let mut interpreter = limited(Limits {
+        max_nesting: Some(100),
+        ..Limits::default()
+    });
+    let nested = |depth: usize| format!("{}1{}", "(+ 1 ".repeat(depth), ")".repeat(depth));
+    assert_eq!(eval_with(&mut interpreter, &nested(90)), Ok(Value::Number(91.0)));
+    assert_eq!(
+        eval_with(&mut interpreter, &nested(100)),
+        Err("nesting limit exceeded".to_string())
+    );
+    assert_eq!(interpreter.limit_exceeded, Some(LimitExceeded::Nesting));
+
+    // The expressions in the bodies of nested calls count too
+    let src = "(defn f (n) (if (= n 0) 0 (+ 1 (f (- n 1)))))";
+    assert_eq!(
+        eval_with(&mut interpreter, &format!("{} (f 20)", src)),
+        Ok(Value::Number(20.0))
+    );
+    assert_eq!(
+        eval_with(&mut interpreter, "(f 50)"),
+        Err("nesting limit exceeded".to_string())
+    );
+    assert_eq!(eval_with(&mut interpreter, "(f 20)"), Ok(Value::Number(20.0)));
+
+    // Source nested deeper than the parser allows fails before running
+    assert!(eval_with(&mut interpreter, &nested(20_000))
+        .unwrap_err()
+        .contains("nesting too deep"));
+}
+
+#[test]
+fn test_depth_limit_in_spawned_thread() {
+    use crate::repl::{MAX_CALL_DEPTH, STACK_SIZE};
+
+    let mut interpreter = limited(Limits {
+        max_depth: Some(MAX_CALL_DEPTH),
+        ..Limits::default()
+    });
+    interpreter.capabilities.insert(Capability::Threads);
+    interpreter.spawn_stack_size = Some(STACK_SIZE);
+    let src = "(defn f (n) (if (= n 0) 0 (+ 1 (f (- n 1)))))";
+    assert_eq!(
+        eval_with(&mut interpreter, &format!("{} (await (spawn f {}))", src, MAX_CALL_DEPTH * 2)),
+        Err("call depth limit exceeded".to_string())
+    );
+    // Workers pass the stack size on to their own workers
+    assert_eq!(
+        eval_with(&mut interpreter, "(await (spawn (fn () (await (spawn f 9000)))))"),
+        Ok(Value::Number(9000.0))
+    );
+}
+
+#[test]
+fn test_spawned_threads_share_the_limits() {
+    // Each thread spawning the next one used to start on a fresh budget
+    let mut interpreter = limited(Limits {
+        fuel: Some(10_000),
+        ..Limits::default()
+    });
+    interpreter.capabilities.insert(Capability::Threads);
+    let src = "(defn burn (n) (if (> n 0) (burn (- n 1))))
+        (defn chain () (burn 100) (await (spawn chain)))";
+    eval_with(&mut interpreter, src).unwrap();
+    assert_eq!(
+        eval_with(&mut interpreter, "(chain)"),
+        Err("fuel exhausted".to_string())
+    );
+
+    // and kept running after the eval call that started them returned
+    interpreter.limits = Limits {
+        timeout: Some(std::time::Duration::from_millis(100)),
+        ..Limits::default()
+    };
+    eval_with(&mut interpreter, "(def f (spawn chain))").unwrap();
+    interpreter.limits = Limits::default();
+    assert_eq!(
+        eval_with(&mut interpreter, "(await f)"),
+        Err("time limit exceeded".to_string())
+    );
+}
+
+#[test]
+fn test_profile() {
+    let mut interpreter = AstInterpreter::new();
+    interpreter.profile = Some(Profile::default());
+    let src = "(defn fib (n) (if (< n 2) n (+ (fib (- n 1)) (fib (- n 2)))))
+        (def twice (fn (x) (list (fib x) (fib x))))
+        (twice 5)
+        (map inc (list 1 2))";
+    eval_with(&mut interpreter, src).unwrap();
+
+    let profile = interpreter.profile.as_ref().unwrap();
+    let calls = |name: &str| profile.functions.get(name).map(|stats| stats.calls);
+    assert_eq!(calls("fib"), Some(30));
+    assert_eq!(calls("twice"), Some(1));
+    assert_eq!(calls("inc"), Some(2));
+    assert_eq!(calls("list"), Some(2));
+    assert_eq!(calls("x"), None);
+
+    // Recursive calls are counted once in the total
+    let twice = &profile.functions["twice"];
+    assert!(profile.functions["fib"].total <= twice.total);
+    assert!(twice.self_time <= twice.total);
+
+    let stacks = profile
+        .collapsed_stacks()
+        .lines()
+        .map(|line| line.rsplit_once(' ').unwrap().0.to_string())
+        .collect::<Vec<_>>();
+    assert!(stacks.contains(&"twice;fib;fib;fib;fib".to_string()), "{:?}", stacks);
+    assert!(stacks.contains(&"map;seq->list;inc".to_string()), "{:?}", stacks);
+    assert!(!stacks.contains(&"fib".to_string()), "{:?}", stacks);
+
+    let report = profile.report();
+    assert!(report.lines().next().unwrap().ends_with("function"));
+    assert_eq!(report.lines().count(), 1 + profile.functions.len());
+}
+
+#[test]
+fn test_profile_survives_errors() {
+    let mut interpreter = AstInterpreter::new();
+    interpreter.profile = Some(Profile::default());
+    let src = "(defn f (n) (if (= n 0) (+ 1 \"a\") (f (- n 1))))";
+    eval_with(&mut interpreter, src).unwrap();
+    assert!(eval_with(&mut interpreter, "(f 3)").is_err());
+    eval_with(&mut interpreter, "(f 1)").unwrap_err();
+
+    // Both runs unwound back to the top level
+    let profile = interpreter.profile.as_ref().unwrap();
+    assert_eq!(profile.functions["f"].calls, 6);
+    let stacks = profile.collapsed_stacks();
+    assert!(stacks.lines().any(|line| line.starts_with("f;f;f;f ")), "{}", stacks);
+    assert!(stacks.lines().all(|line| line.starts_with('f')), "{}", stacks);
+}
+
+proptest! {
+    #[test]
+    fn prop_eval_never_panics(input in "[()a-z0-9 +*/<>=~!'\"\n-]{0,64}") {
+        let mut interpreter = limited(Limits {
+            fuel: Some(10_000),
+            max_depth: Some(100),
+            ..Limits::default()
+        });
+        let _ = interpreter.eval(&input);
+    }
+
+    /// Evaluating quoted code gives what running the code gives.
+    #[test]
+    fn prop_eval_quoted_code(expr in expr()) {
+        let expected = eval(&expr);
+        prop_assert_eq!(eval(&format!("(eval '{})", expr)), expected.clone());
+        prop_assert_eq!(eval(&format!("(eval (eval ''{}))", expr)), expected);
+    }
+
+    #[test]
+    fn prop_write_read_round_trip(value in data_value()) {
+        let text = data::write(&value).unwrap();
+        prop_assert_eq!(data::read(&text, &HashMap::new()), Ok(value));
+    }
+
+    #[test]
+    fn prop_json_round_trip(value in json_value()) {
+        let text = data::json_stringify(&value).unwrap();
+        prop_assert_eq!(data::json_parse(&text), Ok(value));
+    }
+}
+
+/// Values `write` can represent, records aside.
+fn data_value() -> impl Strategy<Value = Value> {
+    let leaf = prop_oneof![
+        Just(Value::Nil),
+        any::<bool>().prop_map(Value::Bool),
+        any::<f64>().prop_filter("finite", |n| n.is_finite()).prop_map(Value::Number),
+        any::<String>().prop_map(Value::String),
+        any::<char>().prop_map(Value::Char),
+        "[a-z*<=>?!][a-z0-9*<=>?!-]{0,8}"
+            .prop_filter("not a keyword", |s| !matches!(s.as_str(), "nil" | "true" | "false"))
+            .prop_map(Value::Symbol),
+    ];
+    leaf.prop_recursive(3, 32, 4, |inner| {
+        prop_oneof![
+            prop::collection::vec(inner.clone(), 0..4).prop_map(Value::List),
+            prop::collection::vec(inner.clone(), 0..4).prop_map(Value::Vector),
+            prop::collection::btree_map(any::<String>(), inner, 0..4).prop_map(Value::Map),
+        ]
+    })
+}
+
+/// Values JSON can represent the way `json-parse` reads them back.
+fn json_value() -> impl Strategy<Value = Value> {
+    let leaf = prop_oneof![
+        Just(Value::Nil),
+        any::<bool>().prop_map(Value::Bool),
+        any::<f64>().prop_filter("finite", |n| n.is_finite()).prop_map(Value::Number),
+        any::<String>().prop_map(Value::String),
+    ];
+    leaf.prop_recursive(3, 32, 4, |inner| {
+        prop_oneof![
+            prop::collection::vec(inner.clone(), 0..4).prop_map(Value::Vector),
+            prop::collection::btree_map(any::<String>(), inner, 0..4).prop_map(Value::Map),
+        ]
+    })
+}
+
+fn expr() -> impl Strategy<Value = String> {
+    let leaf = prop_oneof![
+        (0u32..100).prop_map(|n| n.to_string()),
+        Just("x".to_string()),
+        Just("nil".to_string()),
+        Just("\"s\"".to_string()),
+    ];
+    leaf.prop_recursive(4, 32, 3, |inner| {
+        prop_oneof![
+            (
+                prop_oneof![Just("+"), Just("-"), Just("*"), Just("<"), Just("=")],
+                inner.clone(),
+                inner.clone()
+            )
+                .prop_map(|(op, lhs, rhs)| format!("({} {} {})", op, lhs, rhs)),
+            (inner.clone(), inner.clone(), inner.clone())
+                .prop_map(|(c, t, e)| format!("(if {} {} {})", c, t, e)),
+            (inner.clone(), inner.clone())
+                .prop_map(|(value, body)| format!("(let (x {}) {})", value, body)),
+            prop::collection::vec(inner, 0..3)
+                .prop_map(|items| format!("(list {})", items.join(" "))),
+        ]
+    })
+}
@@ -1,3 +1,13 @@
+use super::AstInterpreter;
+use crate::parser::AstNode;
+
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Debug, Display, Formatter};
+use std::sync::mpsc::{Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::ThreadId;
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Value {
     Nil,
@@ -6,6 +16,314 @@ pub enum Value {
     String(String),
     Char(char),
     Symbol(String),
+    List(Vec<Value>),
+    Vector(Vec<Value>),
+    /// Built by `hash-map` and `json-parse`, walked in key order.
+    Map(BTreeMap<String, Value>),
+    /// Lazy arithmetic progression, `end` is exclusive and `None` means unbounded.
+    Range {
+        start: f64,
+        end: Option<f64>,
+        step: f64,
+    },
+    Function(Arc<Function>),
+    Record(Arc<Record>),
+    Seq(Arc<Seq>),
+    Chan(Arc<Channel>),
+    Future(Arc<Future>),
+}
+
+/// How `print` shows values: strings and chars as their text, records as
+/// `#point{x: 1 y: 2}`.
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::String(s) => write!(f, "{}", s),
+            Value::Char(c) => write!(f, "{}", c),
+            value => write!(f, "{}", Nested(value)),
+        }
+    }
+}
+
+/// A value inside a collection, where strings are quoted so `(list "a b")`
+/// does not print like two items.
+struct Nested<'a>(&'a Value);
+
+impl Display for Nested<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            Value::Nil => write!(f, "nil"),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Number(n) => write!(f, "{}", n),
+            Value::String(s) => write!(f, "{:?}", s),
+            Value::Char(c) => write!(f, "{:?}", c),
+            Value::Symbol(symbol) => write!(f, "{}", symbol),
+            Value::List(items) => write_items(f, "(", items, ")"),
+            Value::Vector(items) => write_items(f, "[", items, "]"),
+            Value::Map(entries) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " ")?;
+                    }
+                    write!(f, "{:?} {}", key, Nested(value))?;
+                }
+                write!(f, "}}")
+            }
+            Value::Range { start, end, step } => {
+                write!(f, "#<range {}..", start)?;
+                if let Some(end) = end {
+                    write!(f, "{}", end)?;
+                }
+                if *step != 1.0 {
+                    write!(f, " by {}", step)?;
+                }
+                write!(f, ">")
+            }
+            Value::Function(function) => write!(f, "{:?}", function),
+            Value::Record(record) => {
+                write!(f, "#{}{{", record.ty.name)?;
+                for (i, (field, value)) in record.ty.fields.iter().zip(&record.values).enumerate() {
+                    if i > 0 {
+                        write!(f, " ")?;
+                    }
+                    write!(f, "{}: {}", field, Nested(value))?;
+                }
+                write!(f, "}}")
+            }
+            Value::Seq(seq) => write!(f, "{:?}", seq),
+            Value::Chan(chan) => write!(f, "{:?}", chan),
+            Value::Future(future) => write!(f, "{:?}", future),
+        }
+    }
+}
+
+fn write_items(f: &mut Formatter<'_>, open: &str, items: &[Value], close: &str) -> std::fmt::Result {
+    write!(f, "{}", open)?;
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            write!(f, " ")?;
+        }
+        write!(f, "{}", Nested(item))?;
+    }
+    write!(f, "{}", close)
+}
+
+/// A closure, created by `fn` and `defn`, or one of the functions
+/// generated by `defstruct`.
+pub struct Function {
+    /// Set by `defn` and `defstruct`, only used when printing.
+    pub name: Option<String>,
+    pub params: Vec<String>,
+    pub body: Body,
+    /// Locals in scope where the function was created, copied at that point.
+    pub env: HashMap<String, Value>,
+}
+
+/// What calling a function evaluates.
+pub enum Body {
+    /// Expressions, the value of the last one is returned.
+    Ast(Vec<AstNode>),
+    /// `(point x y)`, builds a record from the arguments.
+    Constructor(Arc<RecordType>),
+    /// `(point? value)`
+    Predicate(Arc<RecordType>),
+    /// `(point-x record)`, reads the field at that index.
+    Accessor(Arc<RecordType>, usize),
+    /// A body containing `yield`, calling the function returns a lazy
+    /// sequence of what it yields.
+    Generator(Arc<[AstNode]>),
+}
+
+/// A type declared by `defstruct`. Every declaration is a distinct type,
+/// even when it reuses the name of an earlier one.
+#[derive(Debug)]
+pub struct RecordType {
+    pub name: String,
+    pub fields: Vec<String>,
+}
+
+/// An instance of a `defstruct` type, with one value per field.
+pub struct Record {
+    pub ty: Arc<RecordType>,
+    pub values: Vec<Value>,
+}
+
+impl Record {
+    /// Whether the record was built by the constructor of `ty`.
+    pub fn is_a(&self, ty: &Arc<RecordType>) -> bool {
+        Arc::ptr_eq(&self.ty, ty)
+    }
+}
+
+/// Printed as `#point{x: Number(1.0) y: Number(2.0)}`.
+impl Debug for Record {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{}{{", self.ty.name)?;
+        for (i, (field, value)) in self.ty.fields.iter().zip(&self.values).enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{}: {:?}", field, value)?;
+        }
+        write!(f, "}}")
+    }
+}
+
+/// Records are equal when they have the same type and equal fields.
+impl PartialEq for Record {
+    fn eq(&self, other: &Self) -> bool {
+        self.is_a(&other.ty) && self.values == other.values
+    }
+}
+
+/// A lazy sequence, built by `lazy-seq`, generator functions and the
+/// functions taking them. Each cell is realised on demand, at most once, so
+/// a sequence walked twice is computed once. Cells stay alive as long as
+/// something refers to a cell before them.
+pub struct Seq {
+    pub(super) state: Mutex<SeqState>,
+    /// Wakes the threads waiting for another one to realise the cell.
+    pub(super) realised: Condvar,
+}
+
+pub(super) enum SeqState {
+    /// Computes what the sequence holds: `nil`, a collection or another
+    /// sequence.
+    Pending(Thunk),
+    /// Being realised by that thread. On the same thread, the sequence
+    /// needs its own value.
+    Forcing(ThreadId),
+    /// Realising failed, the error is reported each time.
+    Failed(String),
+    Empty,
+    Cons(Value, Arc<Seq>),
+}
+
+pub(super) type Thunk = Box<dyn FnOnce(&mut AstInterpreter) -> Result<Value, String> + Send>;
+
+impl Seq {
+    fn with_state(state: SeqState) -> Arc<Self> {
+        Arc::new(Self {
+            state: Mutex::new(state),
+            realised: Condvar::new(),
+        })
+    }
+
+    pub(super) fn new(thunk: Thunk) -> Arc<Self> {
+        Self::with_state(SeqState::Pending(thunk))
+    }
+
+    pub(super) fn cons(first: Value, rest: Arc<Seq>) -> Arc<Self> {
+        Self::with_state(SeqState::Cons(first, rest))
+    }
+
+    pub(super) fn empty() -> Arc<Self> {
+        Self::with_state(SeqState::Empty)
+    }
+
+    /// Calls `f` on the cells realised so far, starting at this one.
+    pub(super) fn for_each_realised(self: &Arc<Self>, mut f: impl FnMut(&Value)) {
+        let mut next = Some(self.clone());
+        while let Some(seq) = next.take() {
+            if let SeqState::Cons(first, rest) = &*seq.state.lock().unwrap() {
+                f(first);
+                next = Some(rest.clone());
+            }
+        }
+    }
+}
+
+/// Unlinks the cells one by one, dropping a long realised sequence
+/// recursively would overflow the stack.
+impl Drop for Seq {
+    fn drop(&mut self) {
+        let mut next = match std::mem::replace(self.state.get_mut().unwrap(), SeqState::Empty) {
+            SeqState::Cons(_, rest) => rest,
+            _ => return,
+        };
+        while let Ok(mut seq) = Arc::try_unwrap(next) {
+            next = match std::mem::replace(seq.state.get_mut().unwrap(), SeqState::Empty) {
+                SeqState::Cons(_, rest) => rest,
+                _ => return,
+            };
+        }
+    }
+}
+
+/// Printing never realises anything.
+impl Debug for Seq {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#<seq>")
+    }
+}
+
+/// Sequences are only equal to themselves, comparing them could realise
+/// infinitely many cells.
+impl PartialEq for Seq {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+/// A channel built by `chan`. Values are received in the order they were
+/// sent, each by one receiver.
+pub struct Channel {
+    /// Dropped by `close`, receivers then get `nil` once the channel is drained.
+    pub(super) sender: Mutex<Option<Sender<Value>>>,
+    pub(super) receiver: Mutex<Receiver<Value>>,
+}
+
+/// The result of a function run on another thread by `spawn`.
+pub struct Future(pub(super) Mutex<FutureState>);
+
+pub(super) enum FutureState {
+    /// Receives the result once the thread is done.
+    Running(Receiver<Result<Value, String>>),
+    /// Awaited, the result is returned to every later `await`.
+    Done(Result<Value, String>),
+}
+
+impl Debug for Channel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#<chan>")
+    }
+}
+
+/// Channels are only equal to themselves.
+impl PartialEq for Channel {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl Debug for Future {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#<future>")
+    }
+}
+
+/// Futures are only equal to themselves.
+impl PartialEq for Future {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl Debug for Function {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "#<fn {}>", name),
+            None => write!(f, "#<fn>"),
+        }
+    }
+}
+
+/// Functions are only equal to themselves.
+impl PartialEq for Function {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
 }
 
 impl Value {
@@ -52,6 +370,37 @@ impl Value {
         }
     }
 
+    pub fn lt(&self, other: &Value) -> Result<Value, String> {
+        self.compare(other, Ordering::is_lt)
+    }
+
+    pub fn gt(&self, other: &Value) -> Result<Value, String> {
+        self.compare(other, Ordering::is_gt)
+    }
+
+    pub fn le(&self, other: &Value) -> Result<Value, String> {
+        self.compare(other, Ordering::is_le)
+    }
+
+    pub fn ge(&self, other: &Value) -> Result<Value, String> {
+        self.compare(other, Ordering::is_ge)
+    }
+
+    /// Whether the ordering of the two values satisfies `holds`, never for NaN.
+    fn compare(&self, other: &Value, holds: fn(Ordering) -> bool) -> Result<Value, String> {
+        let ordering = match (self, other) {
+            (Value::Number(lhs), Value::Number(rhs)) => lhs.partial_cmp(rhs),
+            (Value::String(lhs), Value::String(rhs)) => Some(lhs.cmp(rhs)),
+            (Value::Char(lhs), Value::Char(rhs)) => Some(lhs.cmp(rhs)),
+            _ => return Err(format!("Cannot compare {:?} and {:?}", self, other)),
+        };
+        Ok(Value::Bool(ordering.is_some_and(holds)))
+    }
+
+    pub fn equals(&self, other: &Value) -> Result<Value, String> {
+        Ok(Value::Bool(self == other))
+    }
+
     // -- region : helper functions
 
     pub fn is_truthy(&self) -> bool {
@@ -60,9 +409,67 @@ impl Value {
             Value::Bool(b) => *b,
             Value::Number(n) => *n != 0.0,
             Value::String(s) => !s.is_empty(),
+            Value::List(items) | Value::Vector(items) => !items.is_empty(),
+            Value::Range { start, end, step } => match end {
+                Some(end) if *step > 0.0 => start < end,
+                Some(end) => start > end,
+                None => true,
+            },
+            Value::Map(entries) => !entries.is_empty(),
+            Value::Symbol(_)
+            | Value::Function(_)
+            | Value::Record(_)
+            | Value::Seq(_)
+            | Value::Chan(_)
+            | Value::Future(_) => true,
             _ => false,
         }
     }
+
+    /// Iterates over the elements of a collection, without materialising ranges.
+    pub fn iter(&self) -> Result<Box<dyn Iterator<Item = Value> + '_>, String> {
+        match self {
+            Value::List(items) | Value::Vector(items) => Ok(Box::new(items.iter().cloned())),
+            Value::Map(entries) => Ok(Box::new(
+                entries.iter().map(|(k, v)| entry(k.clone(), v.clone())),
+            )),
+            Value::String(s) => Ok(Box::new(s.chars().map(Value::Char))),
+            Value::Range { start, end, step } => Ok(Box::new(range_items(*start, *end, *step)?)),
+            _ => Err(format!("Cannot iterate over {:?}", self)),
+        }
+    }
+
+    /// Like `iter`, taking the collection by value.
+    pub fn into_items(self) -> Result<Box<dyn Iterator<Item = Value> + Send>, String> {
+        match self {
+            Value::List(items) | Value::Vector(items) => Ok(Box::new(items.into_iter())),
+            Value::Map(entries) => Ok(Box::new(entries.into_iter().map(|(k, v)| entry(k, v)))),
+            Value::String(s) => Ok(Box::new(
+                s.chars().collect::<Vec<_>>().into_iter().map(Value::Char),
+            )),
+            Value::Range { start, end, step } => Ok(Box::new(range_items(start, end, step)?)),
+            _ => Err(format!("Cannot iterate over {:?}", self)),
+        }
+    }
+}
+
+/// Maps are walked as `(key value)` lists.
+fn entry(key: String, value: Value) -> Value {
+    Value::List(vec![Value::String(key), value])
+}
+
+fn range_items(start: f64, end: Option<f64>, step: f64) -> Result<impl Iterator<Item = Value>, String> {
+    if step == 0.0 {
+        return Err("Range step cannot be 0".to_string());
+    }
+    Ok((0u64..)
+        .map(move |i| start + i as f64 * step)
+        .take_while(move |n| match end {
+            Some(end) if step > 0.0 => *n < end,
+            Some(end) => *n > end,
+            None => true,
+        })
+        .map(Value::Number))
 }
 
 impl From<char> for Value {
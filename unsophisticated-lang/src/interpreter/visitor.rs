@@ -2,8 +2,15 @@ use crate::parser::*;
 use crate::tokenizer::*;
 use crate::parser::AstVisitor;
 
-use super::values::Value;
-use super::AstInterpreter;
+use super::generator::{self, Generator};
+use super::lazy::Cursor;
+use super::matcher::MatchError;
+use super::quote::datum_value;
+use super::values::{Body, Function, Record, RecordType, Seq, Value};
+use super::{AstInterpreter, Control};
+
+use std::collections::HashMap;
+use std::sync::Arc;
 
 
 impl AstVisitor for AstInterpreter {
@@ -11,23 +18,7 @@ impl AstVisitor for AstInterpreter {
 
         match node {
             AstNode::Literal(literal) => {
-                match literal {
-                    Literal::NumberLit(n) => {
-                        let n = n.parse::<f64>().unwrap();
-                        self.stack.push(Value::Number(n));
-                    },
-                    Literal::StringLit(s) => {
-                        self.stack.push(Value::String(s.clone()));
-                    },
-                    Literal::BoolLit(b) => {
-                        let b = b.parse::<bool>().unwrap();
-                        self.stack.push(Value::Bool(b));
-                    },
-                    Literal::CharLit(c) => {
-                        let c = c.parse::<char>().unwrap();
-                        self.stack.push(Value::Char(c));
-                    },
-                }
+                self.stack.push(literal_value(literal)?);
                 Ok(())
             },
             _ => Err(format!("Expected a Literal node, found {:?}", node)),
@@ -37,7 +28,7 @@ impl AstVisitor for AstInterpreter {
     fn visit_ident(&mut self, node: &AstNode) -> Result<(), String> {
         match node {
             AstNode::Ident(ident) => {
-                if let Some(value) = self.heap.get(ident) {
+                if let Some(value) = self.lookup(ident) {
                     self.stack.push(value.clone());
                 } else {
                     return Err(format!("Undefined identifier: {}", ident));
@@ -50,8 +41,8 @@ impl AstVisitor for AstInterpreter {
 
     fn visit_binary_op(&mut self, node: &AstNode) -> Result<(), String> {
         if let AstNode::BinaryOp { op, lhs, rhs } = node {
-            lhs.accept(self)?;
-            rhs.accept(self)?;
+            self.eval_node(lhs)?;
+            self.eval_node(rhs)?;
 
             let rhs_val = self.stack.pop().unwrap();
             let lhs_val = self.stack.pop().unwrap();
@@ -59,6 +50,7 @@ impl AstVisitor for AstInterpreter {
             match op {
                 BinaryOp::Add => {
                     let result = lhs_val.add(&rhs_val)?;
+                    self.check_alloc(&result)?;
                     self.stack.push(result);
                 },
                 BinaryOp::Sub => {
@@ -73,6 +65,26 @@ impl AstVisitor for AstInterpreter {
                     let result = lhs_val.div(&rhs_val)?;
                     self.stack.push(result);
                 },
+                BinaryOp::Lt => {
+                    let result = lhs_val.lt(&rhs_val)?;
+                    self.stack.push(result);
+                },
+                BinaryOp::Gt => {
+                    let result = lhs_val.gt(&rhs_val)?;
+                    self.stack.push(result);
+                },
+                BinaryOp::Le => {
+                    let result = lhs_val.le(&rhs_val)?;
+                    self.stack.push(result);
+                },
+                BinaryOp::Ge => {
+                    let result = lhs_val.ge(&rhs_val)?;
+                    self.stack.push(result);
+                },
+                BinaryOp::Eq => {
+                    let result = lhs_val.equals(&rhs_val)?;
+                    self.stack.push(result);
+                },
             }
             Ok(())
         } else {
@@ -82,7 +94,7 @@ impl AstVisitor for AstInterpreter {
 
     fn visit_unary_op(&mut self, node: &AstNode) -> Result<(), String> {
         if let AstNode::UnaryOp { op, expr } = node {
-            expr.accept(self)?;
+            self.eval_node(expr)?;
 
             let expr_val = self.stack.pop().unwrap();
 
@@ -103,25 +115,362 @@ impl AstVisitor for AstInterpreter {
     }
 
     fn visit_fn_call(&mut self, node: &AstNode) -> Result<(), String> {
-        if let AstNode::FnCall { ident, expr } = node {
-            // TODO: user defined functions
-            expr.accept(self)?;
+        if let AstNode::FnCall { ident, args } = node {
+            for arg in args {
+                self.eval_node(arg)?;
+            }
+            let args = self.stack.split_off(self.stack.len() - args.len());
 
-            let expr_val = self.stack.pop().unwrap();
+            // Names bound to a function shadow the builtins.
+            let result = match self.lookup(ident) {
+                Some(Value::Function(function)) => {
+                    let function = function.clone();
+                    let name = function.name.as_deref().unwrap_or(ident);
+                    self.profiled(name, |this| this.call_function(&function, args))?
+                }
+                _ => self.profiled(ident, |this| this.call_builtin(ident, args))?,
+            };
+            self.check_alloc(&result)?;
+            self.stack.push(result);
+            Ok(())
+        } else {
+            Err(format!("Expected a FnCall node, found {:?}", node))
+        }
+    }
 
-            // TODO: better way to handle built-in functions
-            match ident.as_str() {
-                "print" => {
-                    println!("{:?}", expr_val);
-                },
-                "println" => {
-                    println!("{:?}", expr_val);
-                },
-                _ => return Err(format!("Unsupported function call: {}", ident)),
+    fn visit_call(&mut self, node: &AstNode) -> Result<(), String> {
+        if let AstNode::Call { callee, args } = node {
+            self.eval_node(callee)?;
+            let callee = self.stack.pop().unwrap();
+            for arg in args {
+                self.eval_node(arg)?;
             }
+            let args = self.stack.split_off(self.stack.len() - args.len());
+
+            let function = match callee {
+                Value::Function(function) => function,
+                value => return Err(format!("Cannot call {:?}", value)),
+            };
+            let name = function.name.as_deref().unwrap_or("fn");
+            let result = self.profiled(name, |this| this.call_function(&function, args))?;
+            self.check_alloc(&result)?;
+            self.stack.push(result);
             Ok(())
         } else {
-            Err(format!("Expected a FnCall node, found {:?}", node))
+            Err(format!("Expected a Call node, found {:?}", node))
+        }
+    }
+
+    fn visit_if(&mut self, node: &AstNode) -> Result<(), String> {
+        if let AstNode::If { cond, then, otherwise } = node {
+            self.eval_node(cond)?;
+            if self.stack.pop().unwrap().is_truthy() {
+                self.eval_node(then)
+            } else {
+                self.eval_node(otherwise)
+            }
+        } else {
+            Err(format!("Expected an If node, found {:?}", node))
+        }
+    }
+
+    fn visit_def(&mut self, node: &AstNode) -> Result<(), String> {
+        if let AstNode::Def { ident, expr } = node {
+            self.eval_node(expr)?;
+            let value = self.stack.last().unwrap().clone();
+            self.heap.insert(ident.clone(), value);
+            Ok(())
+        } else {
+            Err(format!("Expected a Def node, found {:?}", node))
+        }
+    }
+
+    fn visit_set(&mut self, node: &AstNode) -> Result<(), String> {
+        if let AstNode::Set { ident, expr } = node {
+            self.eval_node(expr)?;
+            let value = self.stack.last().unwrap().clone();
+
+            let slot = match self.scopes.iter_mut().rev().find_map(|scope| scope.get_mut(ident)) {
+                Some(slot) => slot,
+                None => self
+                    .heap
+                    .get_mut(ident)
+                    .ok_or_else(|| format!("Undefined identifier: {}", ident))?,
+            };
+            *slot = value;
+            Ok(())
+        } else {
+            Err(format!("Expected a Set node, found {:?}", node))
+        }
+    }
+
+    fn visit_let(&mut self, node: &AstNode) -> Result<(), String> {
+        if let AstNode::Let { bindings, body } = node {
+            self.with_scope(|this| {
+                this.bind_all(bindings)?;
+                this.eval_body(body)
+            })
+        } else {
+            Err(format!("Expected a Let node, found {:?}", node))
+        }
+    }
+
+    fn visit_do(&mut self, node: &AstNode) -> Result<(), String> {
+        if let AstNode::Do(body) = node {
+            self.eval_body(body)
+        } else {
+            Err(format!("Expected a Do node, found {:?}", node))
+        }
+    }
+
+    fn visit_while(&mut self, node: &AstNode) -> Result<(), String> {
+        if let AstNode::While { cond, body } = node {
+            let base = self.stack.len();
+            loop {
+                self.eval_node(cond)?;
+                if !self.stack.pop().unwrap().is_truthy() {
+                    break;
+                }
+
+                match self.eval_body(body) {
+                    Ok(()) => {
+                        self.stack.pop();
+                    }
+                    Err(e) => match self.catch(base, e)? {
+                        Control::Break(value) => {
+                            self.stack.push(value);
+                            return Ok(());
+                        }
+                        Control::Continue => continue,
+                        Control::Recur(_) => return Err(RECUR_OUTSIDE_LOOP.to_string()),
+                    },
+                }
+            }
+            self.stack.push(Value::Nil);
+            Ok(())
+        } else {
+            Err(format!("Expected a While node, found {:?}", node))
+        }
+    }
+
+    fn visit_for(&mut self, node: &AstNode) -> Result<(), String> {
+        if let AstNode::For { ident, iter, body } = node {
+            self.eval_node(iter)?;
+            // Taken by value, a sequence nothing else refers to is let go
+            // of as it is walked.
+            let mut items = Cursor::new(self.stack.pop().unwrap())?;
+
+            let base = self.stack.len();
+            self.with_scope(|this| {
+                while let Some(item) = this.advance(&mut items)? {
+                    this.bind(ident, item);
+
+                    match this.eval_body(body) {
+                        Ok(()) => {
+                            this.stack.pop();
+                        }
+                        Err(e) => match this.catch(base, e)? {
+                            Control::Break(value) => {
+                                this.stack.push(value);
+                                return Ok(());
+                            }
+                            Control::Continue => continue,
+                            Control::Recur(_) => return Err(RECUR_OUTSIDE_LOOP.to_string()),
+                        },
+                    }
+                }
+                this.stack.push(Value::Nil);
+                Ok(())
+            })
+        } else {
+            Err(format!("Expected a For node, found {:?}", node))
+        }
+    }
+
+    fn visit_loop(&mut self, node: &AstNode) -> Result<(), String> {
+        if let AstNode::Loop { bindings, body } = node {
+            let base = self.stack.len();
+            self.with_scope(|this| {
+                this.bind_all(bindings)?;
+
+                // `recur` unwinds back here and the body runs again with the
+                // new bindings, so iterating does not grow the Rust stack.
+                loop {
+                    let e = match this.eval_body(body) {
+                        Ok(()) => return Ok(()),
+                        Err(e) => e,
+                    };
+                    match this.catch(base, e)? {
+                        Control::Break(value) => {
+                            this.stack.push(value);
+                            return Ok(());
+                        }
+                        Control::Recur(values) => {
+                            if values.len() != bindings.len() {
+                                return Err(format!(
+                                    "recur expects {} arguments, found {}",
+                                    bindings.len(),
+                                    values.len()
+                                ));
+                            }
+                            for ((ident, _), value) in bindings.iter().zip(values) {
+                                this.bind(ident, value);
+                            }
+                        }
+                        Control::Continue => {
+                            return Err("continue is not allowed inside loop, use recur".to_string())
+                        }
+                    }
+                }
+            })
+        } else {
+            Err(format!("Expected a Loop node, found {:?}", node))
+        }
+    }
+
+    fn visit_recur(&mut self, node: &AstNode) -> Result<(), String> {
+        if let AstNode::Recur(args) = node {
+            for arg in args {
+                self.eval_node(arg)?;
+            }
+            let values = self.stack.split_off(self.stack.len() - args.len());
+
+            self.control = Some(Control::Recur(values));
+            Err(RECUR_OUTSIDE_LOOP.to_string())
+        } else {
+            Err(format!("Expected a Recur node, found {:?}", node))
+        }
+    }
+
+    fn visit_break(&mut self, node: &AstNode) -> Result<(), String> {
+        if let AstNode::Break(expr) = node {
+            self.eval_node(expr)?;
+            let value = self.stack.pop().unwrap();
+
+            self.control = Some(Control::Break(value));
+            Err("break outside of loop".to_string())
+        } else {
+            Err(format!("Expected a Break node, found {:?}", node))
+        }
+    }
+
+    fn visit_continue(&mut self, node: &AstNode) -> Result<(), String> {
+        if let AstNode::Continue = node {
+            self.control = Some(Control::Continue);
+            Err("continue outside of loop".to_string())
+        } else {
+            Err(format!("Expected a Continue node, found {:?}", node))
+        }
+    }
+
+    fn visit_fn(&mut self, node: &AstNode) -> Result<(), String> {
+        if let AstNode::Fn { params, body } = node {
+            let function = Value::Function(self.closure(None, params, body));
+            self.check_alloc(&function)?;
+            self.stack.push(function);
+            Ok(())
+        } else {
+            Err(format!("Expected a Fn node, found {:?}", node))
+        }
+    }
+
+    fn visit_defn(&mut self, node: &AstNode) -> Result<(), String> {
+        if let AstNode::Defn { ident, params, body } = node {
+            let function = Value::Function(self.closure(Some(ident), params, body));
+            self.check_alloc(&function)?;
+            self.heap.insert(ident.clone(), function.clone());
+            self.stack.push(function);
+            Ok(())
+        } else {
+            Err(format!("Expected a Defn node, found {:?}", node))
+        }
+    }
+
+    /// Defines the constructor `point`, the predicate `point?` and one
+    /// accessor per field, `point-x`, returns the constructor.
+    fn visit_defstruct(&mut self, node: &AstNode) -> Result<(), String> {
+        if let AstNode::Defstruct { ident, fields } = node {
+            let ty = Arc::new(RecordType {
+                name: ident.clone(),
+                fields: fields.clone(),
+            });
+
+            let predicate = format!("{}?", ident);
+            let body = Body::Predicate(ty.clone());
+            let value = Self::generated(predicate.clone(), vec!["value".to_string()], body);
+            self.heap.insert(predicate, value);
+            for (index, field) in fields.iter().enumerate() {
+                let accessor = format!("{}-{}", ident, field);
+                let body = Body::Accessor(ty.clone(), index);
+                let value = Self::generated(accessor.clone(), vec![ident.clone()], body);
+                self.heap.insert(accessor, value);
+            }
+
+            self.types.insert(ident.clone(), ty.clone());
+            let constructor = Self::generated(ident.clone(), fields.clone(), Body::Constructor(ty));
+            self.heap.insert(ident.clone(), constructor.clone());
+            self.stack.push(constructor);
+            Ok(())
+        } else {
+            Err(format!("Expected a Defstruct node, found {:?}", node))
+        }
+    }
+
+    /// Tries the arms in order, the first one whose pattern matches and
+    /// whose guard holds is evaluated with the pattern bindings in scope.
+    fn visit_match(&mut self, node: &AstNode) -> Result<(), String> {
+        if let AstNode::Match { expr, arms } = node {
+            self.eval_node(expr)?;
+            let value = self.stack.pop().unwrap();
+
+            for arm in arms {
+                let bindings = match self.match_decision(&arm.decision, &value)? {
+                    Some(bindings) => bindings,
+                    None => continue,
+                };
+                self.scopes.push(bindings);
+                let result = self.eval_arm(arm);
+                self.scopes.pop();
+                if result? {
+                    return Ok(());
+                }
+            }
+
+            self.match_failed(MatchError::NoMatch(value))
+        } else {
+            Err(format!("Expected a Match node, found {:?}", node))
+        }
+    }
+
+    fn visit_quote(&mut self, node: &AstNode) -> Result<(), String> {
+        if let AstNode::Quote(datum) = node {
+            self.stack.push(datum_value(datum)?);
+            Ok(())
+        } else {
+            Err(format!("Expected a Quote node, found {:?}", node))
+        }
+    }
+
+    /// The body runs the first time the sequence is walked, and its value,
+    /// `nil`, a collection or another sequence, is what the sequence holds.
+    fn visit_lazy_seq(&mut self, node: &AstNode) -> Result<(), String> {
+        if let AstNode::LazySeq(body) = node {
+            let function = self.closure(None, &[], body);
+            let seq = Seq::new(Box::new(move |this| this.call_function(&function, vec![])));
+            self.stack.push(Value::Seq(seq));
+            Ok(())
+        } else {
+            Err(format!("Expected a LazySeq node, found {:?}", node))
+        }
+    }
+
+    /// Generator bodies are run by `generator`, a `yield` evaluated here is
+    /// not in one.
+    fn visit_yield(&mut self, node: &AstNode) -> Result<(), String> {
+        if let AstNode::Yield(_) = node {
+            Err("yield outside of a generator function".to_string())
+        } else {
+            Err(format!("Expected a Yield node, found {:?}", node))
         }
     }
 
@@ -156,3 +505,196 @@ impl AstVisitor for AstInterpreter {
         }
     }
 }
+
+pub(super) const RECUR_OUTSIDE_LOOP: &str = "recur outside of loop";
+
+// -- region : helpers --
+
+impl AstInterpreter {
+    pub(super) fn eval_node(&mut self, node: &AstNode) -> Result<(), String> {
+        self.step()?;
+        self.check_nesting()?;
+        self.nesting += 1;
+        let result = node.accept(self);
+        self.nesting -= 1;
+        result
+    }
+
+    /// Evaluates a body of expressions, leaving only the last value on the
+    /// stack (`nil` for an empty body).
+    fn eval_body(&mut self, body: &[AstNode]) -> Result<(), String> {
+        if body.is_empty() {
+            self.stack.push(Value::Nil);
+            return Ok(());
+        }
+
+        for (i, expr) in body.iter().enumerate() {
+            self.eval_node(expr)?;
+            if i < body.len() - 1 {
+                self.stack.pop();
+            }
+        }
+        Ok(())
+    }
+
+    fn with_scope<F>(&mut self, f: F) -> Result<(), String>
+    where
+        F: FnOnce(&mut Self) -> Result<(), String>,
+    {
+        self.scopes.push(HashMap::new());
+        let result = f(self);
+        self.scopes.pop();
+        result
+    }
+
+    pub(super) fn bind(&mut self, ident: &str, value: Value) {
+        self.scopes
+            .last_mut()
+            .expect("bind outside of a scope")
+            .insert(ident.to_string(), value);
+    }
+
+    pub(super) fn bind_all(&mut self, bindings: &[(String, AstNode)]) -> Result<(), String> {
+        for (ident, expr) in bindings {
+            self.eval_node(expr)?;
+            let value = self.stack.pop().unwrap();
+            self.bind(ident, value);
+        }
+        Ok(())
+    }
+
+    /// Captures the locals currently in scope, globals are looked up when
+    /// the function runs so `defn` can recurse. A body that yields makes a
+    /// generator.
+    fn closure(&self, name: Option<&String>, params: &[String], body: &[AstNode]) -> Arc<Function> {
+        let env = self
+            .scopes
+            .iter()
+            .flat_map(|scope| scope.iter())
+            .map(|(ident, value)| (ident.clone(), value.clone()))
+            .collect();
+        let body = if body.iter().any(AstNode::yields) {
+            Body::Generator(body.into())
+        } else {
+            Body::Ast(body.to_vec())
+        };
+        Arc::new(Function {
+            name: name.cloned(),
+            params: params.to_vec(),
+            body,
+            env,
+        })
+    }
+
+    /// One of the functions generated by `defstruct`, they capture nothing.
+    fn generated(name: String, params: Vec<String>, body: Body) -> Value {
+        Value::Function(Arc::new(Function {
+            name: Some(name),
+            params,
+            body,
+            env: HashMap::new(),
+        }))
+    }
+
+    /// Runs a function body with only its captured locals and parameters
+    /// in scope, the caller's locals are not visible.
+    pub(super) fn call_function(
+        &mut self,
+        function: &Function,
+        args: Vec<Value>,
+    ) -> Result<Value, String> {
+        if args.len() != function.params.len() {
+            return Err(format!(
+                "{} expects {} arguments, found {}",
+                function.name.as_deref().unwrap_or("fn"),
+                function.params.len(),
+                args.len()
+            ));
+        }
+        let body = match &function.body {
+            Body::Ast(body) => body,
+            Body::Constructor(ty) => {
+                return Ok(Value::Record(Arc::new(Record {
+                    ty: ty.clone(),
+                    values: args,
+                })))
+            }
+            Body::Predicate(ty) => {
+                return Ok(Value::Bool(matches!(&args[0], Value::Record(record) if record.is_a(ty))))
+            }
+            Body::Generator(body) => {
+                let params = function.params.iter().cloned().zip(args).collect();
+                let generator = Generator::new(vec![function.env.clone(), params], body.clone());
+                return Ok(Value::Seq(generator::generate(generator)));
+            }
+            Body::Accessor(ty, index) => {
+                return match &args[0] {
+                    Value::Record(record) if record.is_a(ty) => Ok(record.values[*index].clone()),
+                    arg => Err(format!(
+                        "{} expects a {}, found {:?}",
+                        function.name.as_deref().unwrap_or("fn"),
+                        ty.name,
+                        arg
+                    )),
+                }
+            }
+        };
+        self.check_depth()?;
+
+        let params = function.params.iter().cloned().zip(args).collect();
+        let scopes = std::mem::replace(&mut self.scopes, vec![function.env.clone(), params]);
+        let base = self.stack.len();
+
+        self.depth += 1;
+        let result = self.eval_body(body);
+        self.depth -= 1;
+        self.scopes = scopes;
+
+        match result {
+            Ok(()) => Ok(self.stack.pop().unwrap()),
+            Err(e) => {
+                // `break`, `continue` and `recur` do not cross function calls.
+                self.control = None;
+                self.stack.truncate(base);
+                Err(e)
+            }
+        }
+    }
+
+    /// Evaluates the guard, then the body if it holds. Returns whether the
+    /// arm was taken.
+    fn eval_arm(&mut self, arm: &MatchArm) -> Result<bool, String> {
+        if let Some(guard) = &arm.guard {
+            self.eval_node(guard)?;
+            if !self.stack.pop().unwrap().is_truthy() {
+                return Ok(false);
+            }
+        }
+        self.eval_body(&arm.body)?;
+        Ok(true)
+    }
+
+    /// Turns an error back into the pending loop signal that caused it,
+    /// discarding whatever the interrupted iteration left on the stack.
+    fn catch(&mut self, base: usize, err: String) -> Result<Control, String> {
+        match self.control.take() {
+            Some(control) => {
+                self.stack.truncate(base);
+                Ok(control)
+            }
+            None => Err(err),
+        }
+    }
+}
+
+pub(super) fn literal_value(literal: &Literal) -> Result<Value, String> {
+    let invalid = || format!("Invalid literal {:?}", literal);
+    Ok(match literal {
+        Literal::NumberLit(n) => Value::Number(n.parse::<f64>().map_err(|_| invalid())?),
+        Literal::StringLit(s) => Value::String(s.clone()),
+        Literal::BoolLit(b) => Value::Bool(b.parse::<bool>().map_err(|_| invalid())?),
+        Literal::CharLit(c) => Value::Char(c.parse::<char>().map_err(|_| invalid())?),
+    })
+}
+
+// -- end region : helpers --
@@ -2,4 +2,5 @@ pub mod parser;
 pub mod tokenizer;
 
 pub mod repl;
-pub mod interpreter;
\ No newline at end of file
+pub mod interpreter;
+pub mod compiler;
@@ -1,5 +1,156 @@
 use crate::tokenizer::{BinaryOp, Literal, Token, UnaryOp};
 
+/// One clause of a `match`, `(pattern body*)` or `(pattern if guard body*)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    /// The pattern compiled once, what matching it at runtime goes by.
+    pub decision: Decision,
+    pub guard: Option<AstNode>,
+    pub body: Vec<AstNode>,
+}
+
+impl MatchArm {
+    pub fn new(pattern: Pattern, guard: Option<AstNode>, body: Vec<AstNode>) -> Self {
+        let decision = pattern.compile();
+        Self {
+            pattern,
+            decision,
+            guard,
+            body,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pattern {
+    /// `_`, matches anything.
+    Wildcard,
+    /// `nil`, matches `nil`.
+    Nil,
+    /// `1`, `"s"` or `true`, matches an equal value.
+    Literal(Literal),
+    /// `x`, matches anything and binds it to `x`.
+    Bind(String),
+    /// `(a b)` matches a list or vector of two items, `(a b . rest)` one of
+    /// at least two, `rest` matching the remaining items.
+    List {
+        items: Vec<Pattern>,
+        rest: Option<Box<Pattern>>,
+    },
+    /// `#(point x y)` matches a record of the latest type named `point`.
+    Record {
+        ident: String,
+        fields: Vec<Pattern>,
+    },
+}
+
+impl Pattern {
+    /// Identifiers bound by the pattern, left to right.
+    pub fn bindings(&self) -> Vec<&String> {
+        match self {
+            Pattern::Wildcard | Pattern::Nil | Pattern::Literal(_) => vec![],
+            Pattern::Bind(ident) => vec![ident],
+            Pattern::List { items, rest } => items
+                .iter()
+                .chain(rest.as_deref())
+                .flat_map(Pattern::bindings)
+                .collect(),
+            Pattern::Record { fields, .. } => fields.iter().flat_map(Pattern::bindings).collect(),
+        }
+    }
+
+    /// Flattens the pattern into the checks a value has to pass and the
+    /// parts of it bound once they all do.
+    pub fn compile(&self) -> Decision {
+        let mut decision = Decision {
+            checks: vec![],
+            bindings: vec![],
+        };
+        self.compile_at(&mut vec![], &mut decision);
+        decision
+    }
+
+    fn compile_at(&self, path: &mut Vec<Step>, decision: &mut Decision) {
+        match self {
+            Pattern::Wildcard => {}
+            Pattern::Nil => decision.checks.push((path.clone(), Check::Nil)),
+            Pattern::Literal(literal) => {
+                decision.checks.push((path.clone(), Check::Literal(literal.clone())))
+            }
+            Pattern::Bind(ident) => decision.bindings.push((ident.clone(), path.clone())),
+            Pattern::List { items, rest } => {
+                let check = Check::List {
+                    len: items.len(),
+                    rest: rest.is_some(),
+                };
+                decision.checks.push((path.clone(), check));
+                for (i, item) in items.iter().enumerate() {
+                    path.push(Step::Item(i));
+                    item.compile_at(path, decision);
+                    path.pop();
+                }
+                if let Some(rest) = rest {
+                    path.push(Step::Rest(items.len()));
+                    rest.compile_at(path, decision);
+                    path.pop();
+                }
+            }
+            Pattern::Record { ident, fields } => {
+                let check = Check::Record {
+                    ident: ident.clone(),
+                    fields: fields.len(),
+                };
+                decision.checks.push((path.clone(), check));
+                for (i, field) in fields.iter().enumerate() {
+                    path.push(Step::Item(i));
+                    field.compile_at(path, decision);
+                    path.pop();
+                }
+            }
+        }
+    }
+}
+
+/// One step from a matched value into one of its parts.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Step {
+    /// The item at that index of a list or vector, or the field of a record.
+    Item(usize),
+    /// The items from that index on, in the same kind of collection.
+    Rest(usize),
+}
+
+/// What a pattern requires of the part of the value a path leads to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Check {
+    Nil,
+    Literal(Literal),
+    /// A list or vector of exactly `len` items, or at least `len` with a rest.
+    List { len: usize, rest: bool },
+    /// A record of the latest type named `ident`, declared with `fields` fields.
+    Record { ident: String, fields: usize },
+}
+
+/// A compiled pattern. Checks are in pattern order, those on a collection
+/// before those on its items, so each path leads somewhere once the checks
+/// before it passed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Decision {
+    pub checks: Vec<(Vec<Step>, Check)>,
+    pub bindings: Vec<(String, Vec<Step>)>,
+}
+
+/// Data read by `quote`, any balanced s-expression whether or not it is
+/// valid code. Keywords and operators are read as symbols.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Datum {
+    Nil,
+    Literal(Literal),
+    Symbol(String),
+    List(Vec<Datum>),
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum AstNode {
     // Leaf
@@ -18,9 +169,82 @@ pub enum AstNode {
         expr: Box<AstNode>,
     },
     FnCall {
+        ident: String,
+        args: Vec<AstNode>,
+    },
+    /// `((fn (x) x) 1)`, a call whose head is an expression evaluating to a function.
+    Call {
+        callee: Box<AstNode>,
+        args: Vec<AstNode>,
+    },
+
+    // Special forms
+    If {
+        cond: Box<AstNode>,
+        then: Box<AstNode>,
+        otherwise: Box<AstNode>,
+    },
+    Def {
+        ident: String,
+        expr: Box<AstNode>,
+    },
+    Set {
         ident: String,
         expr: Box<AstNode>,
     },
+    Let {
+        bindings: Vec<(String, AstNode)>,
+        body: Vec<AstNode>,
+    },
+    Do(Vec<AstNode>),
+
+    // Iteration
+    While {
+        cond: Box<AstNode>,
+        body: Vec<AstNode>,
+    },
+    For {
+        ident: String,
+        iter: Box<AstNode>,
+        body: Vec<AstNode>,
+    },
+    Loop {
+        bindings: Vec<(String, AstNode)>,
+        body: Vec<AstNode>,
+    },
+    Recur(Vec<AstNode>),
+    Break(Box<AstNode>),
+    Continue,
+
+    // Functions
+    Fn {
+        params: Vec<String>,
+        body: Vec<AstNode>,
+    },
+    Defn {
+        ident: String,
+        params: Vec<String>,
+        body: Vec<AstNode>,
+    },
+
+    // Records
+    Defstruct {
+        ident: String,
+        fields: Vec<String>,
+    },
+
+    // Pattern matching
+    Match {
+        expr: Box<AstNode>,
+        arms: Vec<MatchArm>,
+    },
+
+    // Code as data
+    Quote(Datum),
+
+    // Lazy sequences
+    LazySeq(Vec<AstNode>),
+    Yield(Box<AstNode>),
 
     // Error
     TokenError(Token),
@@ -34,6 +258,29 @@ pub trait AstVisitor {
     fn visit_binary_op(&mut self, node: &AstNode) -> Result<(), String>;
     fn visit_unary_op(&mut self, node: &AstNode) -> Result<(), String>;
     fn visit_fn_call(&mut self, node: &AstNode) -> Result<(), String>;
+    fn visit_call(&mut self, node: &AstNode) -> Result<(), String>;
+
+    fn visit_if(&mut self, node: &AstNode) -> Result<(), String>;
+    fn visit_def(&mut self, node: &AstNode) -> Result<(), String>;
+    fn visit_set(&mut self, node: &AstNode) -> Result<(), String>;
+    fn visit_let(&mut self, node: &AstNode) -> Result<(), String>;
+    fn visit_do(&mut self, node: &AstNode) -> Result<(), String>;
+
+    fn visit_while(&mut self, node: &AstNode) -> Result<(), String>;
+    fn visit_for(&mut self, node: &AstNode) -> Result<(), String>;
+    fn visit_loop(&mut self, node: &AstNode) -> Result<(), String>;
+    fn visit_recur(&mut self, node: &AstNode) -> Result<(), String>;
+    fn visit_break(&mut self, node: &AstNode) -> Result<(), String>;
+    fn visit_continue(&mut self, node: &AstNode) -> Result<(), String>;
+
+    fn visit_fn(&mut self, node: &AstNode) -> Result<(), String>;
+    fn visit_defn(&mut self, node: &AstNode) -> Result<(), String>;
+
+    fn visit_defstruct(&mut self, node: &AstNode) -> Result<(), String>;
+    fn visit_match(&mut self, node: &AstNode) -> Result<(), String>;
+    fn visit_quote(&mut self, node: &AstNode) -> Result<(), String>;
+    fn visit_lazy_seq(&mut self, node: &AstNode) -> Result<(), String>;
+    fn visit_yield(&mut self, node: &AstNode) -> Result<(), String>;
 
     fn visit_token_error(&mut self, node: &AstNode) -> Result<(), String>;
     fn visit_parser_error(&mut self, node: &AstNode) -> Result<(), String>;
@@ -49,6 +296,29 @@ impl AstNode {
             AstNode::BinaryOp { .. } => visitor.visit_binary_op(self),
             AstNode::UnaryOp { .. } => visitor.visit_unary_op(self),
             AstNode::FnCall { .. } => visitor.visit_fn_call(self),
+            AstNode::Call { .. } => visitor.visit_call(self),
+
+            AstNode::If { .. } => visitor.visit_if(self),
+            AstNode::Def { .. } => visitor.visit_def(self),
+            AstNode::Set { .. } => visitor.visit_set(self),
+            AstNode::Let { .. } => visitor.visit_let(self),
+            AstNode::Do(_) => visitor.visit_do(self),
+
+            AstNode::While { .. } => visitor.visit_while(self),
+            AstNode::For { .. } => visitor.visit_for(self),
+            AstNode::Loop { .. } => visitor.visit_loop(self),
+            AstNode::Recur(_) => visitor.visit_recur(self),
+            AstNode::Break(_) => visitor.visit_break(self),
+            AstNode::Continue => visitor.visit_continue(self),
+
+            AstNode::Fn { .. } => visitor.visit_fn(self),
+            AstNode::Defn { .. } => visitor.visit_defn(self),
+
+            AstNode::Defstruct { .. } => visitor.visit_defstruct(self),
+            AstNode::Match { .. } => visitor.visit_match(self),
+            AstNode::Quote(_) => visitor.visit_quote(self),
+            AstNode::LazySeq(_) => visitor.visit_lazy_seq(self),
+            AstNode::Yield(_) => visitor.visit_yield(self),
 
             AstNode::TokenError(_) => visitor.visit_token_error(self),
             AstNode::ParserError(_, _) => visitor.visit_parser_error(self),
@@ -56,4 +326,64 @@ impl AstNode {
     }
 }
 
+impl AstNode {
+    /// Direct sub-expressions, in evaluation order.
+    pub fn children(&self) -> Vec<&AstNode> {
+        match self {
+            AstNode::BinaryOp { lhs, rhs, .. } => vec![lhs, rhs],
+            AstNode::UnaryOp { expr, .. }
+            | AstNode::Def { expr, .. }
+            | AstNode::Set { expr, .. }
+            | AstNode::Break(expr)
+            | AstNode::Yield(expr) => vec![expr],
+            AstNode::FnCall { args: body, .. }
+            | AstNode::Do(body)
+            | AstNode::LazySeq(body)
+            | AstNode::Recur(body)
+            | AstNode::Fn { body, .. }
+            | AstNode::Defn { body, .. } => body.iter().collect(),
+            AstNode::If { cond, then, otherwise } => vec![cond, then, otherwise],
+            AstNode::Let { bindings, body } | AstNode::Loop { bindings, body } => bindings
+                .iter()
+                .map(|(_, expr)| expr)
+                .chain(body.iter())
+                .collect(),
+            AstNode::While { cond, body } => std::iter::once(&**cond).chain(body.iter()).collect(),
+            AstNode::For { iter, body, .. } => std::iter::once(&**iter).chain(body.iter()).collect(),
+            AstNode::Call { callee, args } => std::iter::once(&**callee).chain(args.iter()).collect(),
+            AstNode::Match { expr, arms } => std::iter::once(&**expr)
+                .chain(arms.iter().flat_map(|arm| arm.guard.iter().chain(arm.body.iter())))
+                .collect(),
+            AstNode::Literal(_)
+            | AstNode::Ident(_)
+            | AstNode::Nil
+            | AstNode::Continue
+            | AstNode::Defstruct { .. }
+            | AstNode::Quote(_)
+            | AstNode::TokenError(_)
+            | AstNode::ParserError(..) => vec![],
+        }
+    }
+
+    /// Whether evaluating the node may `yield`, which makes the function
+    /// it is in a generator. Nested functions and `lazy-seq` bodies are
+    /// not evaluated with it, their yields are their own.
+    pub fn yields(&self) -> bool {
+        match self {
+            AstNode::Yield(_) => true,
+            AstNode::Fn { .. } | AstNode::Defn { .. } | AstNode::LazySeq(_) => false,
+            node => node.children().into_iter().any(AstNode::yields),
+        }
+    }
+
+    /// The first tokenizer or parser error left in the tree, if any.
+    pub fn find_error(&self) -> Option<String> {
+        match self {
+            AstNode::TokenError(token) => Some(format!("TokenError: {:?}", token)),
+            AstNode::ParserError(msg, node) => Some(format!("ParserError: {} {:?}", msg, node)),
+            node => node.children().into_iter().find_map(AstNode::find_error),
+        }
+    }
+}
+
 // TODO: orginize reserved keywords
@@ -4,7 +4,7 @@
 //!
 //! We follow the Lisplike grammar:
 //!
-//! ```
+//! ```text
 //! program ::=
 //!     *expr EOF
 //!
@@ -13,21 +13,61 @@
 //!     | NIL
 //!     | IDENT
 //!     | '(' paren_expr ')'
+//!     | QUOTE datum
 //!
 //! paren_expr ::=
 //!     expr*
 //!     | binary_op expr expr
 //!     | unary_op expr
 //!     | func_call
-//!     | RESERVED expr
+//!     | reserved
 //!
-//! binary_op ::= ADD | SUB | MUL | DIV
+//! binary_op ::= ADD | SUB | MUL | DIV | LT | GT | LE | GE | EQ
 //!
 //! unary_op ::=
 //!     NEG
 //!     | NOT
 //!
-//! func_call ::= IDENT expr
+//! func_call ::= IDENT expr*
+//!
+//! reserved ::=
+//!     IF expr expr expr?
+//!     | DEF IDENT expr
+//!     | SET IDENT expr
+//!     | LET bindings expr*
+//!     | DO expr*
+//!     | WHILE expr expr*
+//!     | FOR '(' IDENT expr ')' expr*
+//!     | LOOP bindings expr*
+//!     | RECUR expr*
+//!     | BREAK expr?
+//!     | CONTINUE
+//!     | FN params expr*
+//!     | DEFN IDENT params expr*
+//!     | DEFSTRUCT IDENT IDENT*
+//!     | MATCH expr arm*
+//!     | QUOTE datum
+//!     | LAZY_SEQ expr*
+//!     | YIELD expr?
+//!
+//! bindings ::= '(' (IDENT expr)* ')'
+//! params ::= '(' IDENT* ')'
+//! arm ::= '(' pattern (IF expr)? expr* ')'
+//!
+//! pattern ::=
+//!     '_'
+//!     | NIL
+//!     | LITERAL
+//!     | IDENT
+//!     | '(' pattern* ('.' pattern)? ')'
+//!     | '#' '(' IDENT pattern* ')'
+//!
+//! datum ::=
+//!     NIL
+//!     | LITERAL
+//!     | IDENT | RESERVED | binary_op | unary_op | '.' | '#'
+//!     | QUOTE datum
+//!     | '(' datum* ')'
 //!
 //! NUMBER ::= Token::Number
 //! STRING ::= Token::String
@@ -43,11 +83,12 @@
 
 mod parser;
 mod ast;
+mod printer;
 
-// #[cfg(test)]
-// mod tests;
+#[cfg(test)]
+mod tests;
 
-pub use ast::{AstNode, AstVisitor};
+pub use ast::{AstNode, AstVisitor, Check, Datum, Decision, MatchArm, Pattern, Step};
 
 use crate::tokenizer::Token;
 
@@ -56,5 +97,7 @@ pub struct Parser {
     tokens: Vec<Token>,
     pos: usize,
     errors_pos: Vec<usize>,
+    /// Number of forms being parsed inside one another.
+    depth: usize,
 }
 
@@ -1,16 +1,21 @@
 // use crate::tokenizer::{BinaryOp::*, Delimiter::*, UnaryOp::*};
 
-use super::{AstNode, Parser};
-use crate::tokenizer::{BinaryOp, Delimiter, Literal, Token, UnaryOp};
+use super::{AstNode, Datum, MatchArm, Parser, Pattern};
+use crate::tokenizer::{Delimiter, Literal, ReservedKw, Token, UnaryOp};
 
 use Delimiter::*;
 
+/// Deepest nesting of forms, patterns and quoted data parsed, each level is
+/// a recursive call.
+const MAX_NESTING: usize = 512;
+
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Self {
         Self {
             tokens,
             pos: 0,
             errors_pos: vec![],
+            depth: 0,
         }
     }
 
@@ -20,17 +25,41 @@ impl Parser {
 
     fn next_token(&mut self) -> Option<Token> {
         let token = self.peek_next_token()?;
-        self.pos += 1;
+        // EOF is sticky, so error recovery can never run past the input
+        if token != Token::Delimiter(EOF) {
+            self.pos += 1;
+        }
         Some(token)
     }
 
     // -- region : Grammar rules --
 
+    /// program ::= expr* EOF
+    ///
+    /// Parses every top level expression into a `Do` node, failing on the
+    /// first syntax error instead of leaving it in the tree.
+    pub fn parse_program(&mut self) -> Result<AstNode, String> {
+        let mut exprs = vec![];
+        loop {
+            self.skip_whitespace();
+            if let Some(Token::Delimiter(EOF)) | None = self.peek_next_token() {
+                return Ok(AstNode::Do(exprs));
+            }
+
+            let expr = self.parse_expr();
+            if let Some(err) = expr.find_error() {
+                return Err(err);
+            }
+            exprs.push(expr);
+        }
+    }
+
     // expr ::=
     //     LITERAL
     //     | NIL
     //     | IDENT
     //     | '(' paren_expr ')'
+    //     | QUOTE datum
     pub fn parse_expr(&mut self) -> AstNode {
         let node = match self
             .peek_next_token()
@@ -39,26 +68,47 @@ impl Parser {
             // LITERAL ::= NUMBER | STRING | BOOL | CHAR
             Token::Literal(_) => self.parse_literal(), // number, string, bool, char
 
-            // IDENT ::= [a-zA-Z_][a-zA-Z0-9_]*
+            // IDENT ::= [a-zA-Z][a-zA-Z0-9_?!-]*
             Token::Ident(ident) => {
                 self.next_token();
                 AstNode::Ident(ident)
             }
 
+            // BOOL ::= 'true' | 'false'
+            Token::ReservedKw(ReservedKw::True) => {
+                self.next_token();
+                AstNode::Literal(Literal::BoolLit("true".to_string()))
+            }
+            Token::ReservedKw(ReservedKw::False) => {
+                self.next_token();
+                AstNode::Literal(Literal::BoolLit("false".to_string()))
+            }
+
+            // NIL ::= 'nil'
+            Token::ReservedKw(ReservedKw::Nil) => {
+                self.next_token();
+                AstNode::Nil
+            }
+
             // paren_expr ::=
-            //     expr*
+            //     call
             //     | binary_op expr expr
             //     | unary_op expr
             //     | func_call
             //     | RESERVED expr
-            Token::Delimiter(LParen) => {
-                self.parse_paren_expr()
-                // check if
+            Token::Delimiter(LParen) => self
+                .nested(|this| Ok(this.parse_paren_expr()))
+                .unwrap_or_else(|err| err),
+
+            // QUOTE datum, short for (quote datum)
+            Token::Delimiter(Quote) => {
+                self.next_token();
+                self.parse_datum().map(AstNode::Quote).unwrap_or_else(|err| err)
             }
 
             // We skip space and newline (TODO: should we?)
             Token::Delimiter(Space | NewLine) => {
-                self.next_token();
+                self.skip_whitespace();
                 self.parse_expr()
             }
 
@@ -88,62 +138,233 @@ impl Parser {
     }
 
     /// paren_expr ::=
-    ///     paren_expr*
+    ///     call
     ///     | binary_op expr expr
     ///     | unary_op expr
     ///     | func_call
     ///     | RESERVED expr
     fn parse_paren_expr(&mut self) -> AstNode {
-        use BinaryOp::*;
         use UnaryOp::*;
 
         self.next_token(); // consume '(' token
+        self.skip_whitespace();
+
+        // call ::= '(' paren_expr ')' expr*
+        if let Some(Token::Delimiter(LParen)) = self.peek_next_token() {
+            let callee = match self
+                .nested(|this| Ok(this.parse_paren_expr()))
+                .unwrap_or_else(|err| err)
+            {
+                node @ (AstNode::TokenError(_) | AstNode::ParserError(..)) => return node,
+                node => Box::new(node),
+            };
+            return match self.parse_body() {
+                Ok(args) => AstNode::Call { callee, args },
+                Err(err) => err,
+            };
+        }
 
         match self.next_token().unwrap() {
-            Token::Delimiter(LParen) => self.parse_paren_expr(),
 
             // binary_op expr expr
-            Token::BinaryOp(op)
-                if op == Add || op == Sub || op == Mul || op == Div =>
-            {
-                let node = AstNode::BinaryOp {
-                    op,
-                    lhs: Box::new(self.parse_expr()),
-                    rhs: Box::new(self.parse_expr()),
+            Token::BinaryOp(op) => {
+                let lhs = match self.parse_expr() {
+                    node @ (AstNode::TokenError(_) | AstNode::ParserError(..)) => return node,
+                    node => Box::new(node),
                 };
+                let rhs = match self.parse_expr() {
+                    node @ (AstNode::TokenError(_) | AstNode::ParserError(..)) => return node,
+                    node => Box::new(node),
+                };
+                let node = AstNode::BinaryOp { op, lhs, rhs };
 
                 self.expect_token(node, Token::Delimiter(RParen))
             }
 
             // unary_op expr
             Token::UnaryOp(op) if op == Neg || op == Not => {
-                let node = AstNode::UnaryOp {
-                    op,
-                    expr: Box::new(self.parse_expr()),
+                let expr = match self.parse_expr() {
+                    node @ (AstNode::TokenError(_) | AstNode::ParserError(..)) => return node,
+                    node => Box::new(node),
                 };
+                let node = AstNode::UnaryOp { op, expr };
 
                 self.expect_token(node, Token::Delimiter(RParen))
             },
 
-            // func_call ::= IDENT expr
-            Token::Ident(ident) => {
-                let node = AstNode::FnCall {
-                    ident,
-                    expr: Box::new(self.parse_expr()),
-                };
-
-                self.expect_token(node, Token::Delimiter(RParen))
+            // func_call ::= IDENT expr*
+            Token::Ident(ident) => match self.parse_body() {
+                Ok(args) => AstNode::FnCall { ident, args },
+                Err(err) => err,
             },
-            
-            // TODO: RESERVED expr
-            Token::ReservedKw(_) => {
-                todo!()
+
+            // RESERVED expr*
+            Token::ReservedKw(kw) => {
+                self.parse_reserved(kw).unwrap_or_else(|err| err)
             },
 
             tok => AstNode::TokenError(tok),
         }
     }
 
+    /// reserved ::=
+    ///     IF expr expr expr?
+    ///     | DEF IDENT expr
+    ///     | SET IDENT expr
+    ///     | LET bindings expr*
+    ///     | DO expr*
+    ///     | WHILE expr expr*
+    ///     | FOR '(' IDENT expr ')' expr*
+    ///     | LOOP bindings expr*
+    ///     | RECUR expr*
+    ///     | BREAK expr?
+    ///     | CONTINUE
+    ///     | FN params expr*
+    ///     | DEFN IDENT params expr*
+    ///     | DEFSTRUCT IDENT IDENT*
+    ///     | MATCH expr arm*
+    ///     | QUOTE datum
+    ///     | LAZY_SEQ expr*
+    ///     | YIELD expr?
+    ///
+    /// bindings ::= '(' (IDENT expr)* ')'
+    /// params ::= '(' IDENT* ')'
+    fn parse_reserved(&mut self, kw: ReservedKw) -> Result<AstNode, AstNode> {
+        match kw {
+            ReservedKw::If => {
+                let mut exprs = self.parse_body()?.into_iter();
+                match (exprs.next(), exprs.next(), exprs.next(), exprs.next()) {
+                    (Some(cond), Some(then), otherwise, None) => Ok(AstNode::If {
+                        cond: Box::new(cond),
+                        then: Box::new(then),
+                        otherwise: Box::new(otherwise.unwrap_or(AstNode::Nil)),
+                    }),
+                    _ => Err(Self::error(
+                        "if expects a condition, a branch and an optional else branch",
+                    )),
+                }
+            }
+
+            ReservedKw::Def | ReservedKw::Set => {
+                let ident = self.parse_ident()?;
+                let expr = Box::new(self.parse_expr());
+                let node = if kw == ReservedKw::Def {
+                    AstNode::Def { ident, expr }
+                } else {
+                    AstNode::Set { ident, expr }
+                };
+                Ok(self.expect_token(node, Token::Delimiter(RParen)))
+            }
+
+            ReservedKw::Let => {
+                let bindings = self.parse_bindings()?;
+                let body = self.parse_body()?;
+                Ok(AstNode::Let { bindings, body })
+            }
+
+            ReservedKw::Do => Ok(AstNode::Do(self.parse_body()?)),
+
+            ReservedKw::While => {
+                let cond = Box::new(self.parse_expr());
+                let body = self.parse_body()?;
+                Ok(AstNode::While { cond, body })
+            }
+
+            ReservedKw::For => {
+                self.expect(Token::Delimiter(LParen))?;
+                let ident = self.parse_ident()?;
+                let iter = Box::new(self.parse_expr());
+                self.expect(Token::Delimiter(RParen))?;
+                let body = self.parse_body()?;
+                Ok(AstNode::For { ident, iter, body })
+            }
+
+            ReservedKw::Loop => {
+                let bindings = self.parse_bindings()?;
+                let body = self.parse_body()?;
+                Self::check_tail_body(&body, true)?;
+                Ok(AstNode::Loop { bindings, body })
+            }
+
+            ReservedKw::Recur => Ok(AstNode::Recur(self.parse_body()?)),
+
+            ReservedKw::Break | ReservedKw::Yield => {
+                let mut exprs = self.parse_body()?;
+                let expr = match exprs.len() {
+                    0 => Box::new(AstNode::Nil),
+                    1 => Box::new(exprs.remove(0)),
+                    _ => {
+                        return Err(Self::error(&format!(
+                            "{} expects at most one value",
+                            kw.as_str()
+                        )))
+                    }
+                };
+                if kw == ReservedKw::Break {
+                    Ok(AstNode::Break(expr))
+                } else {
+                    Ok(AstNode::Yield(expr))
+                }
+            }
+
+            ReservedKw::Continue => {
+                Ok(self.expect_token(AstNode::Continue, Token::Delimiter(RParen)))
+            }
+
+            ReservedKw::Fn => {
+                let params = self.parse_params()?;
+                let body = self.parse_body()?;
+                Self::check_yield_body(&body, true)?;
+                Ok(AstNode::Fn { params, body })
+            }
+
+            ReservedKw::Defn => {
+                let ident = self.parse_ident()?;
+                let params = self.parse_params()?;
+                let body = self.parse_body()?;
+                Self::check_yield_body(&body, true)?;
+                Ok(AstNode::Defn { ident, params, body })
+            }
+
+            ReservedKw::Defstruct => {
+                let ident = self.parse_ident()?;
+                let fields = self.parse_idents()?;
+                for (i, field) in fields.iter().enumerate() {
+                    if fields[..i].contains(field) {
+                        return Err(Self::error(&format!("duplicate field {}", field)));
+                    }
+                }
+                Ok(AstNode::Defstruct { ident, fields })
+            }
+
+            ReservedKw::Match => {
+                let expr = Box::new(self.parse_expr());
+                if let AstNode::TokenError(_) | AstNode::ParserError(..) = *expr {
+                    return Err(*expr);
+                }
+                let mut arms = vec![];
+                loop {
+                    self.skip_whitespace();
+                    if let Some(Token::Delimiter(RParen)) = self.peek_next_token() {
+                        self.next_token();
+                        return Ok(AstNode::Match { expr, arms });
+                    }
+                    arms.push(self.parse_arm()?);
+                }
+            }
+
+            ReservedKw::LazySeq => Ok(AstNode::LazySeq(self.parse_body()?)),
+
+            ReservedKw::Quote => {
+                let datum = self.parse_datum()?;
+                self.expect(Token::Delimiter(RParen))?;
+                Ok(AstNode::Quote(datum))
+            }
+
+            kw => Err(Self::error(&format!("unexpected reserved keyword {:?}", kw))),
+        }
+    }
+
     // -- end region : Grammar rules --
 
     // -- region : helpers --
@@ -167,5 +388,357 @@ impl Parser {
         }
     }
 
+    fn skip_whitespace(&mut self) {
+        while let Some(Token::Delimiter(Space | NewLine)) = self.peek_next_token() {
+            self.next_token();
+        }
+    }
+
+    /// Parses a form nested in the current one with `parse`, failing once
+    /// forms nest more than `MAX_NESTING` deep.
+    fn nested<T>(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<T, AstNode>,
+    ) -> Result<T, AstNode> {
+        if self.depth == MAX_NESTING {
+            return Err(Self::error("nesting too deep"));
+        }
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn error(msg: &str) -> AstNode {
+        AstNode::ParserError(msg.to_string(), Box::new(AstNode::Nil))
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), AstNode> {
+        match self.expect_token(AstNode::Nil, expected) {
+            AstNode::Nil => Ok(()),
+            err => Err(err),
+        }
+    }
+
+    fn parse_ident(&mut self) -> Result<String, AstNode> {
+        self.skip_whitespace();
+        match self.next_token() {
+            Some(Token::Ident(ident)) => Ok(ident),
+            Some(tok) => Err(Self::error(&format!(
+                "unexpected token {:?}, expected an identifier",
+                tok
+            ))),
+            None => Err(Self::error("unexpected EOF")),
+        }
+    }
+
+    /// Parses expressions up to and including the closing ')'.
+    fn parse_body(&mut self) -> Result<Vec<AstNode>, AstNode> {
+        let mut body = vec![];
+        loop {
+            self.skip_whitespace();
+            match self.peek_next_token() {
+                Some(Token::Delimiter(RParen)) => {
+                    self.next_token();
+                    return Ok(body);
+                }
+                Some(Token::Delimiter(EOF)) | None => {
+                    return Err(Self::error("unexpected EOF, expected ')'"));
+                }
+                _ => match self.parse_expr() {
+                    node @ (AstNode::TokenError(_) | AstNode::ParserError(..)) => {
+                        return Err(node)
+                    }
+                    node => body.push(node),
+                },
+            }
+        }
+    }
+
+    /// bindings ::= '(' (IDENT expr)* ')'
+    fn parse_bindings(&mut self) -> Result<Vec<(String, AstNode)>, AstNode> {
+        self.expect(Token::Delimiter(LParen))?;
+        let mut bindings = vec![];
+        loop {
+            self.skip_whitespace();
+            if let Some(Token::Delimiter(RParen)) = self.peek_next_token() {
+                self.next_token();
+                return Ok(bindings);
+            }
+            let ident = self.parse_ident()?;
+            match self.parse_expr() {
+                node @ (AstNode::TokenError(_) | AstNode::ParserError(..)) => return Err(node),
+                node => bindings.push((ident, node)),
+            }
+        }
+    }
+
+    /// arm ::= '(' pattern (IF expr)? expr* ')'
+    fn parse_arm(&mut self) -> Result<MatchArm, AstNode> {
+        self.expect(Token::Delimiter(LParen))?;
+        let pattern = self.parse_pattern()?;
+        let bindings = pattern.bindings();
+        for (i, ident) in bindings.iter().enumerate() {
+            if bindings[..i].contains(ident) {
+                return Err(Self::error(&format!("{} is bound twice in a pattern", ident)));
+            }
+        }
+
+        self.skip_whitespace();
+        let guard = match self.peek_next_token() {
+            Some(Token::ReservedKw(ReservedKw::If)) => {
+                self.next_token();
+                match self.parse_expr() {
+                    node @ (AstNode::TokenError(_) | AstNode::ParserError(..)) => return Err(node),
+                    node => Some(node),
+                }
+            }
+            _ => None,
+        };
+        let body = self.parse_body()?;
+        Ok(MatchArm::new(pattern, guard, body))
+    }
+
+    /// pattern ::=
+    ///     '_'
+    ///     | NIL
+    ///     | LITERAL
+    ///     | IDENT
+    ///     | '(' pattern* ('.' pattern)? ')'
+    ///     | '#' '(' IDENT pattern* ')'
+    fn parse_pattern(&mut self) -> Result<Pattern, AstNode> {
+        self.skip_whitespace();
+        match self.next_token() {
+            Some(Token::Ident(ident)) if ident == "_" => Ok(Pattern::Wildcard),
+            Some(Token::Ident(ident)) => Ok(Pattern::Bind(ident)),
+            Some(Token::ReservedKw(ReservedKw::Nil)) => Ok(Pattern::Nil),
+            Some(Token::ReservedKw(kw @ (ReservedKw::True | ReservedKw::False))) => {
+                let b = kw == ReservedKw::True;
+                Ok(Pattern::Literal(Literal::BoolLit(b.to_string())))
+            }
+            Some(Token::Literal(literal)) => Ok(Pattern::Literal(literal)),
+            Some(Token::Delimiter(LParen)) => self.nested(|this| {
+                let mut items = vec![];
+                loop {
+                    this.skip_whitespace();
+                    match this.peek_next_token() {
+                        Some(Token::Delimiter(RParen)) => {
+                            this.next_token();
+                            return Ok(Pattern::List { items, rest: None });
+                        }
+                        Some(Token::Delimiter(Dot)) => {
+                            this.next_token();
+                            let rest = Some(Box::new(this.parse_pattern()?));
+                            this.expect(Token::Delimiter(RParen))?;
+                            return Ok(Pattern::List { items, rest });
+                        }
+                        _ => items.push(this.parse_pattern()?),
+                    }
+                }
+            }),
+            Some(Token::Delimiter(Hash)) => self.nested(|this| {
+                this.expect(Token::Delimiter(LParen))?;
+                let ident = this.parse_ident()?;
+                let mut fields = vec![];
+                loop {
+                    this.skip_whitespace();
+                    if let Some(Token::Delimiter(RParen)) = this.peek_next_token() {
+                        this.next_token();
+                        return Ok(Pattern::Record { ident, fields });
+                    }
+                    fields.push(this.parse_pattern()?);
+                }
+            }),
+            Some(tok) => Err(Self::error(&format!("unexpected token {:?} in a pattern", tok))),
+            None => Err(Self::error("unexpected EOF")),
+        }
+    }
+
+    /// datum ::=
+    ///     NIL
+    ///     | LITERAL
+    ///     | IDENT | RESERVED | binary_op | unary_op | '.' | '#'
+    ///     | QUOTE datum
+    ///     | '(' datum* ')'
+    fn parse_datum(&mut self) -> Result<Datum, AstNode> {
+        self.skip_whitespace();
+        let symbol = |s: &str| Ok(Datum::Symbol(s.to_string()));
+        match self.next_token() {
+            Some(Token::ReservedKw(ReservedKw::Nil)) => Ok(Datum::Nil),
+            Some(Token::ReservedKw(kw @ (ReservedKw::True | ReservedKw::False))) => {
+                let b = kw == ReservedKw::True;
+                Ok(Datum::Literal(Literal::BoolLit(b.to_string())))
+            }
+            Some(Token::Literal(literal)) => Ok(Datum::Literal(literal)),
+            Some(Token::Ident(ident)) => Ok(Datum::Symbol(ident)),
+            Some(Token::ReservedKw(kw)) => symbol(kw.as_str()),
+            Some(Token::BinaryOp(op)) => symbol(&op.to_string()),
+            Some(Token::UnaryOp(op)) => symbol(&op.to_string()),
+            Some(Token::Delimiter(Dot)) => symbol("."),
+            Some(Token::Delimiter(Hash)) => symbol("#"),
+            Some(Token::Delimiter(Quote)) => self.nested(|this| {
+                Ok(Datum::List(vec![
+                    Datum::Symbol(ReservedKw::Quote.as_str().to_string()),
+                    this.parse_datum()?,
+                ]))
+            }),
+            Some(Token::Delimiter(LParen)) => self.nested(|this| {
+                let mut items = vec![];
+                loop {
+                    this.skip_whitespace();
+                    match this.peek_next_token() {
+                        Some(Token::Delimiter(RParen)) => {
+                            this.next_token();
+                            return Ok(Datum::List(items));
+                        }
+                        Some(Token::Delimiter(EOF)) | None => {
+                            return Err(Self::error("unexpected EOF, expected ')'"));
+                        }
+                        _ => items.push(this.parse_datum()?),
+                    }
+                }
+            }),
+            Some(tok) => Err(Self::error(&format!("unexpected token {:?} in quoted data", tok))),
+            None => Err(Self::error("unexpected EOF")),
+        }
+    }
+
+    /// params ::= '(' IDENT* ')'
+    fn parse_params(&mut self) -> Result<Vec<String>, AstNode> {
+        self.expect(Token::Delimiter(LParen))?;
+        self.parse_idents()
+    }
+
+    /// Parses identifiers up to and including the closing ')'.
+    fn parse_idents(&mut self) -> Result<Vec<String>, AstNode> {
+        let mut idents = vec![];
+        loop {
+            self.skip_whitespace();
+            if let Some(Token::Delimiter(RParen)) = self.peek_next_token() {
+                self.next_token();
+                return Ok(idents);
+            }
+            idents.push(self.parse_ident()?);
+        }
+    }
+
+    /// `recur` is compiled to a jump back to the enclosing `loop`, so it is
+    /// only allowed where nothing is left to evaluate after it.
+    fn check_tail(node: &AstNode, tail: bool) -> Result<(), AstNode> {
+        match node {
+            AstNode::Recur(_) if !tail => Err(Self::error("recur must be in tail position")),
+            AstNode::Recur(args) => Self::check_tail_body(args, false),
+            AstNode::If { cond, then, otherwise } => {
+                Self::check_tail(cond, false)?;
+                Self::check_tail(then, tail)?;
+                Self::check_tail(otherwise, tail)
+            }
+            AstNode::Do(body) => Self::check_tail_body(body, tail),
+            AstNode::Match { expr, arms } => {
+                Self::check_tail(expr, false)?;
+                for arm in arms {
+                    if let Some(guard) = &arm.guard {
+                        Self::check_tail(guard, false)?;
+                    }
+                    Self::check_tail_body(&arm.body, tail)?;
+                }
+                Ok(())
+            }
+            AstNode::Let { bindings, body } => {
+                for (_, expr) in bindings {
+                    Self::check_tail(expr, false)?;
+                }
+                Self::check_tail_body(body, tail)
+            }
+            // A nested loop is its own recur target, its body was checked
+            // when it was parsed.
+            AstNode::Loop { bindings, .. } => {
+                for (_, expr) in bindings {
+                    Self::check_tail(expr, false)?;
+                }
+                Ok(())
+            }
+            AstNode::While { cond, body } => {
+                Self::check_tail(cond, false)?;
+                Self::check_tail_body(body, false)
+            }
+            AstNode::For { iter, body, .. } => {
+                Self::check_tail(iter, false)?;
+                Self::check_tail_body(body, false)
+            }
+            AstNode::BinaryOp { lhs, rhs, .. } => {
+                Self::check_tail(lhs, false)?;
+                Self::check_tail(rhs, false)
+            }
+            AstNode::UnaryOp { expr, .. }
+            | AstNode::Def { expr, .. }
+            | AstNode::Set { expr, .. }
+            | AstNode::Break(expr)
+            | AstNode::Yield(expr) => Self::check_tail(expr, false),
+            AstNode::FnCall { args, .. } => Self::check_tail_body(args, false),
+            AstNode::Call { callee, args } => {
+                Self::check_tail(callee, false)?;
+                Self::check_tail_body(args, false)
+            }
+            // A function body cannot jump back into the loop it was created
+            // in, a `recur` in there fails when it runs.
+            _ => Ok(()),
+        }
+    }
+
+    fn check_tail_body(body: &[AstNode], tail: bool) -> Result<(), AstNode> {
+        for (i, node) in body.iter().enumerate() {
+            Self::check_tail(node, tail && i == body.len() - 1)?;
+        }
+        Ok(())
+    }
+
+    /// A generator suspends at `yield` and resumes after it, which is only
+    /// possible between the statements of its body: directly in the body,
+    /// or in the body of a `do`, `let`, `if` branch, loop or `match` arm
+    /// there. The functions nested inside were checked when parsed.
+    fn check_yield(node: &AstNode, statement: bool) -> Result<(), AstNode> {
+        match node {
+            AstNode::Yield(_) if !statement => Err(Self::error(
+                "yield must be a statement of a function body",
+            )),
+            AstNode::Fn { .. } | AstNode::Defn { .. } => Ok(()),
+            AstNode::Do(body) => Self::check_yield_body(body, statement),
+            AstNode::If { cond, then, otherwise } => {
+                Self::check_yield(cond, false)?;
+                Self::check_yield(then, statement)?;
+                Self::check_yield(otherwise, statement)
+            }
+            AstNode::Let { bindings, body } | AstNode::Loop { bindings, body } => {
+                for (_, expr) in bindings {
+                    Self::check_yield(expr, false)?;
+                }
+                Self::check_yield_body(body, statement)
+            }
+            AstNode::While { cond: expr, body } | AstNode::For { iter: expr, body, .. } => {
+                Self::check_yield(expr, false)?;
+                Self::check_yield_body(body, statement)
+            }
+            AstNode::Match { expr, arms } => {
+                Self::check_yield(expr, false)?;
+                for arm in arms {
+                    if let Some(guard) = &arm.guard {
+                        Self::check_yield(guard, false)?;
+                    }
+                    Self::check_yield_body(&arm.body, statement)?;
+                }
+                Ok(())
+            }
+            node => node
+                .children()
+                .into_iter()
+                .try_for_each(|child| Self::check_yield(child, false)),
+        }
+    }
+
+    fn check_yield_body(body: &[AstNode], statement: bool) -> Result<(), AstNode> {
+        body.iter().try_for_each(|node| Self::check_yield(node, statement))
+    }
+
     // -- end region : helpers --
 }
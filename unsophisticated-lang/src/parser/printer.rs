@@ -0,0 +1,246 @@
+//! Pretty printer turning an AST back into source code.
+//!
+//! Printing then parsing a well-formed tree yields the same tree.
+
+use std::fmt::{Display, Formatter, Result};
+
+use super::{AstNode, Datum, Pattern};
+use crate::tokenizer::{BinaryOp, Literal, UnaryOp};
+
+impl Display for AstNode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            AstNode::Literal(literal) => write!(f, "{}", literal),
+            AstNode::Ident(ident) => write!(f, "{}", ident),
+            AstNode::Nil => write!(f, "nil"),
+
+            AstNode::BinaryOp { op, lhs, rhs } => write!(f, "({} {} {})", op, lhs, rhs),
+            AstNode::UnaryOp { op, expr } => write!(f, "({} {})", op, expr),
+            AstNode::FnCall { ident, args } => {
+                write!(f, "({}", ident)?;
+                write_body(f, args)?;
+                write!(f, ")")
+            }
+            AstNode::Call { callee, args } => {
+                write!(f, "({}", callee)?;
+                write_body(f, args)?;
+                write!(f, ")")
+            }
+
+            AstNode::If {
+                cond,
+                then,
+                otherwise,
+            } => {
+                write!(f, "(if {} {} {})", cond, then, otherwise)
+            }
+            AstNode::Def { ident, expr } => write!(f, "(def {} {})", ident, expr),
+            AstNode::Set { ident, expr } => write!(f, "(set {} {})", ident, expr),
+            AstNode::Let { bindings, body } => {
+                write!(f, "(let ")?;
+                write_bindings(f, bindings)?;
+                write_body(f, body)?;
+                write!(f, ")")
+            }
+            AstNode::Do(body) => {
+                write!(f, "(do")?;
+                write_body(f, body)?;
+                write!(f, ")")
+            }
+
+            AstNode::While { cond, body } => {
+                write!(f, "(while {}", cond)?;
+                write_body(f, body)?;
+                write!(f, ")")
+            }
+            AstNode::For { ident, iter, body } => {
+                write!(f, "(for ({} {})", ident, iter)?;
+                write_body(f, body)?;
+                write!(f, ")")
+            }
+            AstNode::Loop { bindings, body } => {
+                write!(f, "(loop ")?;
+                write_bindings(f, bindings)?;
+                write_body(f, body)?;
+                write!(f, ")")
+            }
+            AstNode::Recur(args) => {
+                write!(f, "(recur")?;
+                write_body(f, args)?;
+                write!(f, ")")
+            }
+            AstNode::Break(expr) => write!(f, "(break {})", expr),
+            AstNode::Continue => write!(f, "(continue)"),
+
+            AstNode::Fn { params, body } => {
+                write!(f, "(fn ")?;
+                write_params(f, params)?;
+                write_body(f, body)?;
+                write!(f, ")")
+            }
+            AstNode::Defn { ident, params, body } => {
+                write!(f, "(defn {} ", ident)?;
+                write_params(f, params)?;
+                write_body(f, body)?;
+                write!(f, ")")
+            }
+
+            AstNode::Defstruct { ident, fields } => {
+                write!(f, "(defstruct {}", ident)?;
+                for field in fields {
+                    write!(f, " {}", field)?;
+                }
+                write!(f, ")")
+            }
+            AstNode::Match { expr, arms } => {
+                write!(f, "(match {}", expr)?;
+                for arm in arms {
+                    write!(f, " ({}", arm.pattern)?;
+                    if let Some(guard) = &arm.guard {
+                        write!(f, " if {}", guard)?;
+                    }
+                    write_body(f, &arm.body)?;
+                    write!(f, ")")?;
+                }
+                write!(f, ")")
+            }
+
+            AstNode::Quote(datum) => write!(f, "(quote {})", datum),
+
+            AstNode::LazySeq(body) => {
+                write!(f, "(lazy-seq")?;
+                write_body(f, body)?;
+                write!(f, ")")
+            }
+            AstNode::Yield(expr) => write!(f, "(yield {})", expr),
+
+            AstNode::TokenError(token) => write!(f, "<token error {:?}>", token),
+            AstNode::ParserError(msg, _) => write!(f, "<parser error {}>", msg),
+        }
+    }
+}
+
+impl Display for BinaryOp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let op = match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::Lt => "<",
+            BinaryOp::Gt => ">",
+            BinaryOp::Le => "<=",
+            BinaryOp::Ge => ">=",
+            BinaryOp::Eq => "=",
+        };
+        write!(f, "{}", op)
+    }
+}
+
+impl Display for Literal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Literal::NumberLit(n) => write!(f, "{}", n),
+            Literal::StringLit(s) => {
+                write!(f, "\"")?;
+                for c in s.chars() {
+                    if matches!(c, '"' | '\\') {
+                        write!(f, "\\")?;
+                    }
+                    write!(f, "{}", c)?;
+                }
+                write!(f, "\"")
+            }
+            Literal::BoolLit(b) => write!(f, "{}", b),
+            Literal::CharLit(c) => write!(f, "{}", c),
+        }
+    }
+}
+
+impl Display for Pattern {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Pattern::Wildcard => write!(f, "_"),
+            Pattern::Nil => write!(f, "nil"),
+            Pattern::Literal(literal) => write!(f, "{}", literal),
+            Pattern::Bind(ident) => write!(f, "{}", ident),
+            Pattern::List { items, rest } => {
+                write!(f, "(")?;
+                write_patterns(f, items)?;
+                if let Some(rest) = rest {
+                    let dot = if items.is_empty() { ". " } else { " . " };
+                    write!(f, "{}{}", dot, rest)?;
+                }
+                write!(f, ")")
+            }
+            Pattern::Record { ident, fields } => {
+                write!(f, "#({}", ident)?;
+                for field in fields {
+                    write!(f, " {}", field)?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+impl Display for Datum {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Datum::Nil => write!(f, "nil"),
+            Datum::Literal(literal) => write!(f, "{}", literal),
+            Datum::Symbol(symbol) => write!(f, "{}", symbol),
+            Datum::List(items) => {
+                write!(f, "(")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+impl Display for UnaryOp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            UnaryOp::Neg => write!(f, "~"),
+            UnaryOp::Not => write!(f, "!"),
+        }
+    }
+}
+
+fn write_body(f: &mut Formatter<'_>, body: &[AstNode]) -> Result {
+    for node in body {
+        write!(f, " {}", node)?;
+    }
+    Ok(())
+}
+
+fn write_patterns(f: &mut Formatter<'_>, patterns: &[Pattern]) -> Result {
+    for (i, pattern) in patterns.iter().enumerate() {
+        if i > 0 {
+            write!(f, " ")?;
+        }
+        write!(f, "{}", pattern)?;
+    }
+    Ok(())
+}
+
+fn write_params(f: &mut Formatter<'_>, params: &[String]) -> Result {
+    write!(f, "({})", params.join(" "))
+}
+
+fn write_bindings(f: &mut Formatter<'_>, bindings: &[(String, AstNode)]) -> Result {
+    write!(f, "(")?;
+    for (i, (ident, expr)) in bindings.iter().enumerate() {
+        if i > 0 {
+            write!(f, " ")?;
+        }
+        write!(f, "{} {}", ident, expr)?;
+    }
+    write!(f, ")")
+}
@@ -1,36 +1,66 @@
 use super::*;
-use crate::tokenizer::ReservedKeyword::*;
+use crate::tokenizer::{
+    BinaryOp::*, Delimiter::*, Literal, ReservedKw, ReservedKw::*, Tokenizer, UnaryOp::*,
+};
+
+use proptest::prelude::*;
+
+fn num(n: &str) -> Token {
+    Token::Literal(Literal::NumberLit(n.to_string()))
+}
+
+fn num_node(n: &str) -> AstNode {
+    AstNode::Literal(Literal::NumberLit(n.to_string()))
+}
+
+fn parse(input: &str) -> AstNode {
+    Parser::new(Tokenizer::new(input).tokenize()).parse_expr()
+}
 
 #[test]
 fn test_parse_number() {
-    let tokens = vec![Token::Number(1), Token::Delimiter(EOF)];
+    let tokens = vec![num("1"), Token::Delimiter(EOF)];
     let mut parser = Parser::new(tokens);
-    let ast = parser.parse_expr().unwrap();
-    assert_eq!(ast, AstNode::new_leaf(AstKind::Number(1)));
+    let ast = parser.parse_expr();
+    assert_eq!(ast, num_node("1"));
 }
 
 #[test]
 fn test_parse_string() {
-    let tokens = vec![Token::String("hello".to_string()), Token::Delimiter(EOF)];
+    let tokens = vec![
+        Token::Literal(Literal::StringLit("hello".to_string())),
+        Token::Delimiter(EOF),
+    ];
     let mut parser = Parser::new(tokens);
-    let ast = parser.parse_expr().unwrap();
-    assert_eq!(ast, AstNode::new_leaf(AstKind::String("hello".to_string())));
+    let ast = parser.parse_expr();
+    assert_eq!(
+        ast,
+        AstNode::Literal(Literal::StringLit("hello".to_string()))
+    );
 }
 
 #[test]
 fn test_parse_nil() {
-    let tokens = vec![Token::Nil, Token::Delimiter(EOF)];
+    let tokens = vec![Token::ReservedKw(Nil), Token::Delimiter(EOF)];
+    let mut parser = Parser::new(tokens);
+    let ast = parser.parse_expr();
+    assert_eq!(ast, AstNode::Nil);
+}
+
+#[test]
+fn test_parse_bool() {
+    let tokens = vec![Token::ReservedKw(True), Token::Delimiter(EOF)];
     let mut parser = Parser::new(tokens);
-    let ast = parser.parse_expr().unwrap();
-    assert_eq!(ast, AstNode::new_leaf(AstKind::Nil));
+    let ast = parser.parse_expr();
+    assert_eq!(ast, AstNode::Literal(Literal::BoolLit("true".to_string())));
 }
 
 #[test]
 fn test_parse_ident() {
     let tokens = vec![Token::Ident("hello".to_string()), Token::Delimiter(EOF)];
     let mut parser = Parser::new(tokens);
-    let ast = parser.parse_expr().unwrap();
-    assert_eq!(ast, AstNode::new_leaf(AstKind::Ident("hello".to_string())));
+    let ast = parser.parse_expr();
+    assert_eq!(ast, AstNode::Ident("hello".to_string()));
 }
 
 #[test]
@@ -38,17 +68,23 @@ fn test_parse_binary_op() {
     let tokens = vec![
         Token::Delimiter(LParen),
         Token::BinaryOp(Add),
-        Token::Number(1),
-        Token::Number(2),
+        Token::Delimiter(Space),
+        num("1"),
+        Token::Delimiter(Space),
+        num("2"),
         Token::Delimiter(RParen),
         Token::Delimiter(EOF),
     ];
     let mut parser = Parser::new(tokens);
-    let ast = parser.parse_expr().unwrap();
-    let mut node = AstNode::new_node(AstKind::Add);
-    node.add_child(AstNode::new_leaf(AstKind::Number(1)));
-    node.add_child(AstNode::new_leaf(AstKind::Number(2)));
-    assert_eq!(ast, node);
+    let ast = parser.parse_expr();
+    assert_eq!(
+        ast,
+        AstNode::BinaryOp {
+            op: Add,
+            lhs: Box::new(num_node("1")),
+            rhs: Box::new(num_node("2")),
+        }
+    );
 }
 
 #[test]
@@ -56,15 +92,19 @@ fn test_parse_unary_op() {
     let tokens = vec![
         Token::Delimiter(LParen),
         Token::UnaryOp(Neg),
-        Token::Number(1),
+        num("1"),
         Token::Delimiter(RParen),
         Token::Delimiter(EOF),
     ];
     let mut parser = Parser::new(tokens);
-    let ast = parser.parse_expr().unwrap();
-    let mut node = AstNode::new_node(AstKind::Neg);
-    node.add_child(AstNode::new_leaf(AstKind::Number(1)));
-    assert_eq!(ast, node);
+    let ast = parser.parse_expr();
+    assert_eq!(
+        ast,
+        AstNode::UnaryOp {
+            op: Neg,
+            expr: Box::new(num_node("1")),
+        }
+    );
 }
 
 #[test]
@@ -72,30 +112,536 @@ fn test_parse_func_call() {
     let tokens = vec![
         Token::Delimiter(LParen),
         Token::Ident("hello".to_string()),
-        Token::Number(1),
+        Token::Delimiter(Space),
+        num("1"),
+        Token::Delimiter(Space),
+        num("2"),
         Token::Delimiter(RParen),
         Token::Delimiter(EOF),
     ];
     let mut parser = Parser::new(tokens);
-    let ast = parser.parse_expr().unwrap();
-    let mut node = AstNode::new_node(AstKind::FnCall);
-    node.add_child(AstNode::new_leaf(AstKind::Ident("hello".to_string())));
-    node.add_child(AstNode::new_leaf(AstKind::Number(1)));
-    assert_eq!(ast, node);
+    let ast = parser.parse_expr();
+    assert_eq!(
+        ast,
+        AstNode::FnCall {
+            ident: "hello".to_string(),
+            args: vec![num_node("1"), num_node("2")],
+        }
+    );
 }
 
 #[test]
 fn test_parse_reserved_expr() {
     let tokens = vec![
         Token::Delimiter(LParen),
-        Token::Keyword(If),
-        Token::Number(1),
+        Token::ReservedKw(If),
+        Token::Delimiter(Space),
+        num("1"),
+        Token::Delimiter(Space),
+        num("2"),
         Token::Delimiter(RParen),
         Token::Delimiter(EOF),
     ];
     let mut parser = Parser::new(tokens);
-    let ast = parser.parse_expr().unwrap();
-    let mut node = AstNode::new_node(AstKind::Reserved(If));
-    node.add_child(AstNode::new_leaf(AstKind::Number(1)));
-    assert_eq!(ast, node);
+    let ast = parser.parse_expr();
+    assert_eq!(
+        ast,
+        AstNode::If {
+            cond: Box::new(num_node("1")),
+            then: Box::new(num_node("2")),
+            otherwise: Box::new(AstNode::Nil),
+        }
+    );
+}
+
+#[test]
+fn test_parse_let() {
+    assert_eq!(
+        parse("(let (x 1 y 2) x)"),
+        AstNode::Let {
+            bindings: vec![
+                ("x".to_string(), num_node("1")),
+                ("y".to_string(), num_node("2"))
+            ],
+            body: vec![AstNode::Ident("x".to_string())],
+        }
+    );
+}
+
+#[test]
+fn test_parse_for() {
+    assert_eq!(
+        parse("(for (x xs)\n  (print x))"),
+        AstNode::For {
+            ident: "x".to_string(),
+            iter: Box::new(AstNode::Ident("xs".to_string())),
+            body: vec![AstNode::FnCall {
+                ident: "print".to_string(),
+                args: vec![AstNode::Ident("x".to_string())],
+            }],
+        }
+    );
+}
+
+#[test]
+fn test_parse_defn() {
+    assert_eq!(
+        parse("(defn add (x y) (+ x y))"),
+        AstNode::Defn {
+            ident: "add".to_string(),
+            params: vec!["x".to_string(), "y".to_string()],
+            body: vec![AstNode::BinaryOp {
+                op: Add,
+                lhs: Box::new(AstNode::Ident("x".to_string())),
+                rhs: Box::new(AstNode::Ident("y".to_string())),
+            }],
+        }
+    );
+    assert_eq!(
+        parse("(fn () nil)"),
+        AstNode::Fn {
+            params: vec![],
+            body: vec![AstNode::Nil],
+        }
+    );
+}
+
+#[test]
+fn test_parse_defstruct() {
+    assert_eq!(
+        parse("(defstruct point x y)"),
+        AstNode::Defstruct {
+            ident: "point".to_string(),
+            fields: vec!["x".to_string(), "y".to_string()],
+        }
+    );
+}
+
+#[test]
+fn test_parse_match() {
+    let ident = |s: &str| AstNode::Ident(s.to_string());
+    assert_eq!(
+        parse("(match xs (() 0) ((x . _) if x x) (#(point 1 y) y) (_))"),
+        AstNode::Match {
+            expr: Box::new(ident("xs")),
+            arms: vec![
+                MatchArm::new(
+                    Pattern::List {
+                        items: vec![],
+                        rest: None
+                    },
+                    None,
+                    vec![num_node("0")],
+                ),
+                MatchArm::new(
+                    Pattern::List {
+                        items: vec![Pattern::Bind("x".to_string())],
+                        rest: Some(Box::new(Pattern::Wildcard)),
+                    },
+                    Some(ident("x")),
+                    vec![ident("x")],
+                ),
+                MatchArm::new(
+                    Pattern::Record {
+                        ident: "point".to_string(),
+                        fields: vec![
+                            Pattern::Literal(Literal::NumberLit("1".to_string())),
+                            Pattern::Bind("y".to_string()),
+                        ],
+                    },
+                    None,
+                    vec![ident("y")],
+                ),
+                MatchArm::new(Pattern::Wildcard, None, vec![]),
+            ],
+        }
+    );
+}
+
+#[test]
+fn test_compile_pattern() {
+    let arm = match parse("(match xs ((1 #(point x _) . rest) x))") {
+        AstNode::Match { mut arms, .. } => arms.remove(0),
+        node => panic!("Expected a Match node, found {:?}", node),
+    };
+    assert_eq!(
+        arm.decision,
+        Decision {
+            checks: vec![
+                (vec![], Check::List { len: 2, rest: true }),
+                (
+                    vec![Step::Item(0)],
+                    Check::Literal(Literal::NumberLit("1".to_string()))
+                ),
+                (
+                    vec![Step::Item(1)],
+                    Check::Record {
+                        ident: "point".to_string(),
+                        fields: 2
+                    }
+                ),
+            ],
+            bindings: vec![
+                ("x".to_string(), vec![Step::Item(1), Step::Item(0)]),
+                ("rest".to_string(), vec![Step::Rest(2)]),
+            ],
+        }
+    );
 }
+
+#[test]
+fn test_parse_quote() {
+    let symbol = |s: &str| Datum::Symbol(s.to_string());
+    assert_eq!(parse("'x"), AstNode::Quote(symbol("x")));
+    assert_eq!(parse("(quote nil)"), AstNode::Quote(Datum::Nil));
+    assert_eq!(
+        parse("'(if (<= n 1) 1 . #'x)"),
+        AstNode::Quote(Datum::List(vec![
+            symbol("if"),
+            Datum::List(vec![
+                symbol("<="),
+                symbol("n"),
+                Datum::Literal(Literal::NumberLit("1".to_string())),
+            ]),
+            Datum::Literal(Literal::NumberLit("1".to_string())),
+            symbol("."),
+            symbol("#"),
+            Datum::List(vec![symbol("quote"), symbol("x")]),
+        ]))
+    );
+    assert_eq!(
+        parse("'(true \"s\" ())"),
+        AstNode::Quote(Datum::List(vec![
+            Datum::Literal(Literal::BoolLit("true".to_string())),
+            Datum::Literal(Literal::StringLit("s".to_string())),
+            Datum::List(vec![]),
+        ]))
+    );
+}
+
+#[test]
+fn test_parse_lazy_seq_and_yield() {
+    let ident = |s: &str| AstNode::Ident(s.to_string());
+    assert_eq!(
+        parse("(lazy-seq xs)"),
+        AstNode::LazySeq(vec![ident("xs")])
+    );
+    assert_eq!(
+        parse("(fn (xs) (for (x xs) (if x (yield x))) (yield))"),
+        AstNode::Fn {
+            params: vec!["xs".to_string()],
+            body: vec![
+                AstNode::For {
+                    ident: "x".to_string(),
+                    iter: Box::new(ident("xs")),
+                    body: vec![AstNode::If {
+                        cond: Box::new(ident("x")),
+                        then: Box::new(AstNode::Yield(Box::new(ident("x")))),
+                        otherwise: Box::new(AstNode::Nil),
+                    }],
+                },
+                AstNode::Yield(Box::new(AstNode::Nil)),
+            ],
+        }
+    );
+}
+
+#[test]
+fn test_parse_call_expression() {
+    assert_eq!(
+        parse("((fn (x) x) 1)"),
+        AstNode::Call {
+            callee: Box::new(AstNode::Fn {
+                params: vec!["x".to_string()],
+                body: vec![AstNode::Ident("x".to_string())],
+            }),
+            args: vec![num_node("1")],
+        }
+    );
+    assert_eq!(
+        parse("((f) )"),
+        AstNode::Call {
+            callee: Box::new(AstNode::FnCall {
+                ident: "f".to_string(),
+                args: vec![],
+            }),
+            args: vec![],
+        }
+    );
+}
+
+#[test]
+fn test_parse_errors() {
+    for input in [
+        "(+ 1",
+        "(+ (+ 1 2",
+        "((",
+        "(let (1 2) x)",
+        "(if)",
+        "(for x)",
+        "(fn x)",
+        "(defn (x) x)",
+        "(fn (1) x)",
+        "(defstruct)",
+        "(defstruct point (x y))",
+        "(defstruct point x x)",
+        "(match)",
+        "(match x y)",
+        "(match x ((a a) a))",
+        "(match x ((a . ) a))",
+        "(match x ((a . b c) a))",
+        "(match x (#point a))",
+        "(match x ((+ 1 2) 3))",
+        "(loop (i 0) (match i (0 (recur 1)) (_ (+ 1 (recur 2)))))",
+        "(yield 1 2)",
+        "(fn () (+ 1 (yield 2)))",
+        "(fn () (let (x (yield 1)) x))",
+        "(defn f () (if (yield 1) 2 3))",
+        "(defn f () (lazy-seq (yield 1)))",
+        "(fn () (f (fn () (yield 1)) (yield 2)))",
+        "'",
+        "')",
+        "'(a b",
+        "(quote a b)",
+        ")",
+    ] {
+        match parse(input) {
+            AstNode::TokenError(_) | AstNode::ParserError(..) => {}
+            ast => panic!("{:?} parsed as {:?}", input, ast),
+        }
+    }
+}
+
+#[test]
+fn test_parse_nesting_limit() {
+    let nested = |depth: usize| format!("{}1{}", "(+ 1 ".repeat(depth), ")".repeat(depth));
+    assert_eq!(parse(&nested(512)).find_error(), None);
+
+    for input in [
+        nested(513),
+        nested(200_000),
+        "(".repeat(100_000),
+        format!("(match x ({}", "(".repeat(100_000)),
+        format!("{}x", "'".repeat(100_000)),
+        format!("'{}", "(".repeat(100_000)),
+    ] {
+        let error = parse(&input).find_error().unwrap();
+        assert!(error.contains("nesting too deep"), "{}", error);
+    }
+}
+
+// -- region : property tests --
+
+fn ident() -> BoxedStrategy<String> {
+    "[a-z][a-z0-9_?!=-]{0,6}"
+        .prop_filter("reserved keyword", |s| ReservedKw::from_str(s).is_none())
+        .boxed()
+}
+
+fn datum() -> impl Strategy<Value = Datum> {
+    let leaf = prop_oneof![
+        Just(Datum::Nil),
+        "[0-9]{1,5}".prop_map(|n| Datum::Literal(Literal::NumberLit(n))),
+        "[a-zA-Z0-9 \"\\\\]{0,8}".prop_map(|s| Datum::Literal(Literal::StringLit(s))),
+        any::<bool>().prop_map(|b| Datum::Literal(Literal::BoolLit(b.to_string()))),
+        ident().prop_map(Datum::Symbol),
+        prop_oneof![Just("if"), Just("quote"), Just("<="), Just("~"), Just("."), Just("#")]
+            .prop_map(|s| Datum::Symbol(s.to_string())),
+    ];
+    leaf.prop_recursive(3, 16, 4, |inner| {
+        prop::collection::vec(inner, 0..4).prop_map(Datum::List)
+    })
+}
+
+fn leaf() -> impl Strategy<Value = AstNode> {
+    prop_oneof![
+        "[0-9]{1,5}".prop_map(|n| AstNode::Literal(Literal::NumberLit(n))),
+        "[a-zA-Z0-9 \"\\\\]{0,8}".prop_map(|s| AstNode::Literal(Literal::StringLit(s))),
+        any::<bool>().prop_map(|b| AstNode::Literal(Literal::BoolLit(b.to_string()))),
+        ident().prop_map(AstNode::Ident),
+        Just(AstNode::Nil),
+        Just(AstNode::Continue),
+        datum().prop_map(AstNode::Quote),
+    ]
+}
+
+fn binary_op() -> impl Strategy<Value = crate::tokenizer::BinaryOp> {
+    prop_oneof![
+        Just(Add),
+        Just(Sub),
+        Just(Mul),
+        Just(Div),
+        Just(Lt),
+        Just(Gt),
+        Just(Le),
+        Just(Ge),
+        Just(Eq),
+    ]
+}
+
+/// Patterns, renamed so no identifier is bound twice.
+fn pattern() -> impl Strategy<Value = Pattern> {
+    let leaf = prop_oneof![
+        Just(Pattern::Wildcard),
+        Just(Pattern::Nil),
+        "[0-9]{1,3}".prop_map(|n| Pattern::Literal(Literal::NumberLit(n))),
+        any::<bool>().prop_map(|b| Pattern::Literal(Literal::BoolLit(b.to_string()))),
+        Just(Pattern::Bind("x".to_string())),
+    ];
+    leaf.prop_recursive(3, 12, 3, |inner| {
+        prop_oneof![
+            (
+                prop::collection::vec(inner.clone(), 0..3),
+                prop::option::of(inner.clone())
+            )
+                .prop_map(|(items, rest)| Pattern::List {
+                    items,
+                    rest: rest.map(Box::new),
+                }),
+            (ident(), prop::collection::vec(inner, 0..3))
+                .prop_map(|(ident, fields)| Pattern::Record { ident, fields }),
+        ]
+    })
+    .prop_map(|pattern| rename(pattern, &mut 0))
+}
+
+/// Gives every binding a distinct name.
+fn rename(pattern: Pattern, n: &mut usize) -> Pattern {
+    match pattern {
+        Pattern::Bind(_) => {
+            *n += 1;
+            Pattern::Bind(format!("x{}", n))
+        }
+        Pattern::List { items, rest } => Pattern::List {
+            items: items.into_iter().map(|item| rename(item, n)).collect(),
+            rest: rest.map(|rest| Box::new(rename(*rest, n))),
+        },
+        Pattern::Record { ident, fields } => Pattern::Record {
+            ident,
+            fields: fields.into_iter().map(|field| rename(field, n)).collect(),
+        },
+        pattern => pattern,
+    }
+}
+
+fn arm(expr: impl Strategy<Value = AstNode> + Clone) -> impl Strategy<Value = MatchArm> {
+    (
+        pattern(),
+        prop::option::of(expr.clone()),
+        prop::collection::vec(expr, 0..2),
+    )
+        .prop_map(|(pattern, guard, body)| MatchArm::new(pattern, guard, body))
+}
+
+/// Well-formed trees, `recur` only ever appears in tail position of a `loop`.
+fn ast() -> impl Strategy<Value = AstNode> {
+    leaf().prop_recursive(4, 48, 4, |inner| {
+        let body = prop::collection::vec(inner.clone(), 0..3);
+        let bindings = prop::collection::vec((ident(), inner.clone()), 0..3);
+        prop_oneof![
+            (binary_op(), inner.clone(), inner.clone()).prop_map(|(op, lhs, rhs)| {
+                AstNode::BinaryOp {
+                    op,
+                    lhs: Box::new(lhs),
+                    rhs: Box::new(rhs),
+                }
+            }),
+            (prop_oneof![Just(Neg), Just(Not)], inner.clone()).prop_map(|(op, expr)| {
+                AstNode::UnaryOp {
+                    op,
+                    expr: Box::new(expr),
+                }
+            }),
+            (ident(), body.clone()).prop_map(|(ident, args)| AstNode::FnCall { ident, args }),
+            // The printed head must be a parenthesised form to parse back as a call
+            (ident(), body.clone(), body.clone()).prop_map(|(ident, callee_args, args)| {
+                AstNode::Call {
+                    callee: Box::new(AstNode::FnCall {
+                        ident,
+                        args: callee_args,
+                    }),
+                    args,
+                }
+            }),
+            (inner.clone(), inner.clone(), inner.clone()).prop_map(|(cond, then, otherwise)| {
+                AstNode::If {
+                    cond: Box::new(cond),
+                    then: Box::new(then),
+                    otherwise: Box::new(otherwise),
+                }
+            }),
+            (ident(), inner.clone()).prop_map(|(ident, expr)| AstNode::Def {
+                ident,
+                expr: Box::new(expr),
+            }),
+            (ident(), inner.clone()).prop_map(|(ident, expr)| AstNode::Set {
+                ident,
+                expr: Box::new(expr),
+            }),
+            (bindings.clone(), body.clone())
+                .prop_map(|(bindings, body)| AstNode::Let { bindings, body }),
+            body.clone().prop_map(AstNode::Do),
+            (inner.clone(), body.clone()).prop_map(|(cond, body)| AstNode::While {
+                cond: Box::new(cond),
+                body,
+            }),
+            (ident(), inner.clone(), body.clone()).prop_map(|(ident, iter, body)| {
+                AstNode::For {
+                    ident,
+                    iter: Box::new(iter),
+                    body,
+                }
+            }),
+            (bindings, body.clone(), prop::option::of(body.clone())).prop_map(
+                |(bindings, mut body, recur)| {
+                    body.extend(recur.map(AstNode::Recur));
+                    AstNode::Loop { bindings, body }
+                }
+            ),
+            (prop::collection::vec(ident(), 0..3), body.clone())
+                .prop_map(|(params, body)| AstNode::Fn { params, body }),
+            (ident(), prop::collection::vec(ident(), 0..3), body.clone()).prop_map(
+                |(ident, params, body)| AstNode::Defn {
+                    ident,
+                    params,
+                    body
+                }
+            ),
+            (ident(), prop::collection::btree_set(ident(), 0..3)).prop_map(|(ident, fields)| {
+                AstNode::Defstruct {
+                    ident,
+                    fields: fields.into_iter().collect(),
+                }
+            }),
+            (inner.clone(), prop::collection::vec(arm(inner.clone()), 0..3))
+                .prop_map(|(expr, arms)| AstNode::Match {
+                    expr: Box::new(expr),
+                    arms,
+                }),
+            body.clone().prop_map(AstNode::LazySeq),
+            (body.clone(), leaf()).prop_map(|(mut body, value)| {
+                body.push(AstNode::Yield(Box::new(value)));
+                AstNode::Fn {
+                    params: vec![],
+                    body,
+                }
+            }),
+            inner.prop_map(|expr| AstNode::Break(Box::new(expr))),
+        ]
+    })
+}
+
+proptest! {
+    #[test]
+    fn prop_print_then_parse_round_trips(ast in ast()) {
+        let source = ast.to_string();
+        prop_assert_eq!(parse(&source), ast, "source: {}", source);
+    }
+
+    #[test]
+    fn prop_parse_never_panics(input in "[()a-z0-9 +*/<>=~!'.#\"\n-]{0,64}") {
+        parse(&input);
+    }
+}
+
+// -- end region : property tests --
@@ -1,9 +1,22 @@
-use crate::interpreter::{AstInterpreter, Value};
+use crate::interpreter::{AstInterpreter, Capability, Limits, Profile, Value};
 use crate::parser::Parser;
 use crate::tokenizer::Tokenizer;
 
+use std::fmt::{Display, Formatter};
 use std::io::{Write, Read};
 
+/// Deepest function call nesting allowed to scripts, each call takes a
+/// few kilobytes of Rust stack so the host thread needs a large one.
+pub const MAX_CALL_DEPTH: usize = 10_000;
+
+/// Deepest nesting of expressions being evaluated allowed to scripts, calls
+/// included. Fits `STACK_SIZE` with room to spare, and leaves each call
+/// within `MAX_CALL_DEPTH` a few levels of its own.
+pub const MAX_NESTING: usize = 50_000;
+
+/// Stack size of the threads scripts run on, large enough for `MAX_CALL_DEPTH`.
+pub const STACK_SIZE: usize = 256 << 20;
+
 pub struct Repl {
     interactive: bool,
     prompt: &'static str,
@@ -11,6 +24,39 @@ pub struct Repl {
     history: History,
     interpreter: AstInterpreter,
     input_filepath: String,
+    print_result: bool,
+}
+
+/// Why running a program failed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RunError {
+    /// The input could not be read.
+    Input(String),
+    /// The program could not be tokenized or parsed, nothing was evaluated.
+    Parse(String),
+    /// Evaluation failed.
+    Runtime(String),
+    /// The program called `exit` with the given code.
+    Exit(i32),
+}
+
+impl Display for RunError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RunError::Input(e) => write!(f, "{}", e),
+            RunError::Parse(e) => write!(f, "Parse error: {}", e),
+            RunError::Runtime(e) => write!(f, "Error: {}", e),
+            RunError::Exit(code) => write!(f, "exit {}", code),
+        }
+    }
+}
+
+/// Drops a leading `#!` line, keeping its newline so line numbers do not move.
+pub fn strip_shebang(source: &str) -> &str {
+    match source.strip_prefix("#!") {
+        Some(shebang) => shebang.find('\n').map_or("", |i| &shebang[i..]),
+        None => source,
+    }
 }
 
 #[derive(Debug)]
@@ -58,40 +104,101 @@ impl Repl {
         }
     }
 
+    /// Runs the program at `filepath`, `-` reads it from stdin.
     pub fn non_interactive(filepath: &str) -> Self {
+        let mut interpreter = AstInterpreter::with_capabilities(Capability::all());
+        interpreter.limits = Limits {
+            max_depth: Some(MAX_CALL_DEPTH),
+            max_nesting: Some(MAX_NESTING),
+            ..Limits::default()
+        };
+        interpreter.spawn_stack_size = Some(STACK_SIZE);
 
         Self {
             interactive: false,
             prompt: "",
             last_result: Value::Nil,
             history: History::new(),
-            interpreter: AstInterpreter::new(),
+            interpreter,
             input_filepath: filepath.to_string(),
+            print_result: false,
         }
     }
 
-    fn read_line(&mut self) -> String {
-        if self.interactive {
-            let mut input = String::new();
-            loop {
-                std::io::stdin().read_line(&mut input).unwrap();
-            }
+    /// Prints the value of the program once it ran, always on in interactive mode.
+    pub fn set_print_result(&mut self, print_result: bool) {
+        self.print_result = print_result;
+    }
+
+    /// Starts over with a fresh interpreter, with or without the prelude,
+    /// keeping its capabilities, limits and arguments.
+    pub fn set_prelude(&mut self, prelude: bool) {
+        let capabilities = self.interpreter.capabilities.clone();
+        let mut interpreter = if prelude {
+            AstInterpreter::with_capabilities(capabilities)
         } else {
-            self.read_file()
-        }
+            AstInterpreter::without_prelude(capabilities)
+        };
+        interpreter.limits = self.interpreter.limits.clone();
+        interpreter.args = std::mem::take(&mut self.interpreter.args);
+        self.interpreter = interpreter;
+    }
+
+    /// Arguments passed to the script, returned by the `args` builtin.
+    pub fn set_args(&mut self, args: Vec<String>) {
+        self.interpreter.args = args;
+    }
+
+    /// Profiles the function calls of the programs run from now on.
+    pub fn set_profile(&mut self, profile: bool) {
+        self.interpreter.profile = profile.then(Profile::default);
+    }
+
+    /// What was called since profiling was turned on.
+    pub fn profile(&self) -> Option<&Profile> {
+        self.interpreter.profile.as_ref()
+    }
+
+    /// Exit code requested by the script through the `exit` builtin.
+    pub fn exit_code(&self) -> Option<i32> {
+        self.interpreter.exit_code
+    }
 
-        // let mut input = String::new();
-        // std::io::stdin().read_line(&mut input).unwrap();
-        // input
+    fn read_line(&mut self) -> Option<String> {
+        let mut input = String::new();
+        match std::io::stdin().read_line(&mut input) {
+            Ok(0) | Err(_) => None,
+            Ok(_) => Some(input),
+        }
     }
 
-    fn read_file(&mut self) -> String {
+    fn read_file(&mut self) -> Result<String, RunError> {
         let mut input = String::new();
-        std::fs::File::open(&self.input_filepath)
-            .unwrap()
-            .read_to_string(&mut input)
-            .unwrap();
-        input
+        let read = if self.input_filepath == "-" {
+            std::io::stdin().read_to_string(&mut input)
+        } else {
+            std::fs::File::open(&self.input_filepath)
+                .and_then(|mut file| file.read_to_string(&mut input))
+        };
+        read.map(|_| input)
+            .map_err(|e| RunError::Input(format!("{}: {}", self.input_filepath, e)))
+    }
+
+    /// Evaluates a whole program and returns the value of its last expression.
+    ///
+    /// A leading `#!` line is ignored so scripts can be made executable.
+    pub fn run(&mut self, source: &str) -> Result<Value, RunError> {
+        let tokens = Tokenizer::new(strip_shebang(source)).tokenize();
+        let program = Parser::new(tokens).parse_program().map_err(RunError::Parse)?;
+
+        if let Err(e) = self.interpreter.eval_ast(program) {
+            return Err(match self.exit_code() {
+                Some(code) => RunError::Exit(code),
+                None => RunError::Runtime(e),
+            });
+        }
+        self.last_result = self.interpreter.stack.pop().unwrap();
+        Ok(self.last_result.clone())
     }
 
     /// REPL main loop
@@ -99,8 +206,8 @@ impl Repl {
     /// This is where the REPL reads, evaluates and prints the results.
     ///
     /// ### Usage
-    /// ```
-    /// use s_expressions_interpreter::repl::Repl;
+    /// ```no_run
+    /// use unsophisticated_lang::repl::Repl;
     ///
     /// let mut repl = Repl::interactive(">> ");
 
@@ -108,43 +215,35 @@ impl Repl {
     ///     println!("Error: {}", e);
     /// }
     /// ```
-    pub fn mainloop(&mut self) -> Result<(), String> {
+    pub fn mainloop(&mut self) -> Result<(), RunError> {
+        if !self.interactive {
+            let input = self.read_file()?;
+            let result = self.run(&input)?;
+            if self.print_result {
+                println!("{:?}", result);
+            }
+            return Ok(());
+        }
+
         // Loop
         loop {
             // Read
-            if self.interactive {
-                print!("{}", self.prompt);
-                std::io::stdout().flush().unwrap();
-            }
-            let input = self.read_line();
-            if self.interactive {
-                if input.trim() == "exit" {
-                    break;
-                }
-                self.history.add(input.clone());
+            print!("{}", self.prompt);
+            std::io::stdout().flush().unwrap();
+            let input = match self.read_line() {
+                Some(input) => input,
+                None => break,
+            };
+            if input.trim() == "exit" {
+                break;
             }
+            self.history.add(input.clone());
 
             // Evaluate
-            let tokens = Tokenizer::new(input).tokenize();
-            let node = Parser::new(tokens).parse_expr();
-            if let Err(e) = self.interpreter.eval_ast(node) {
-                if !self.interactive {
-                    Err(e.clone())?
-                }
-                println!("Error: {}", e);
-                continue;
-            }
-
-            self.last_result = self.interpreter.stack.pop().unwrap();
-
-            // Print
-            println!("{:?}", self.last_result);
-            if self.interactive {
-                println!("History: {:?}", self.history)
-            }
-
-            if !self.interactive {
-                break;
+            match self.run(&input) {
+                Ok(result) => println!("{:?}", result),
+                Err(e @ RunError::Exit(_)) => return Err(e),
+                Err(e) => println!("{}", e),
             }
         }
 
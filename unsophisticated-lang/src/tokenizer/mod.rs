@@ -17,6 +17,9 @@ pub enum Delimiter {
     NewLine, // '\n'
     LParen,  // '('
     RParen,  // ')'
+    Dot,     // '.'
+    Hash,    // '#'
+    Quote,   // '\''
     EOF,
 }
 
@@ -26,6 +29,11 @@ pub enum BinaryOp {
     Sub, // '-'
     Mul, // '*'
     Div, // '/'
+    Lt,  // '<'
+    Gt,  // '>'
+    Le,  // '<='
+    Ge,  // '>='
+    Eq,  // '='
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -44,16 +52,31 @@ pub enum Literal {
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum ReservedKw {
-    If,     // 'if'
-    Else,   // 'else'
-    ElseIf, // 'elseif'
-    Def,    // 'def'
-    Let,    // 'let'
-    True,   // 'true'
-    False,  // 'false'
-    And,    // 'and'
-    Or,     // 'or',
-    Nil,    // 'nil'
+    If,       // 'if'
+    Else,     // 'else'
+    ElseIf,   // 'elseif'
+    Def,      // 'def'
+    Let,      // 'let'
+    True,     // 'true'
+    False,    // 'false'
+    And,      // 'and'
+    Or,       // 'or',
+    Nil,      // 'nil'
+    Do,       // 'do'
+    Set,      // 'set'
+    While,    // 'while'
+    For,      // 'for'
+    Loop,     // 'loop'
+    Recur,    // 'recur'
+    Break,    // 'break'
+    Continue, // 'continue'
+    Fn,       // 'fn'
+    Defn,     // 'defn'
+    Defstruct, // 'defstruct'
+    Match,    // 'match'
+    Quote,    // 'quote'
+    LazySeq,  // 'lazy-seq'
+    Yield,    // 'yield'
 }
 
 impl ReservedKw {
@@ -71,17 +94,65 @@ impl ReservedKw {
             "and" => Some(And),
             "or" => Some(Or),
             "nil" => Some(Nil),
+            "do" => Some(Do),
+            "set" => Some(Set),
+            "while" => Some(While),
+            "for" => Some(For),
+            "loop" => Some(Loop),
+            "recur" => Some(Recur),
+            "break" => Some(Break),
+            "continue" => Some(Continue),
+            "fn" => Some(Fn),
+            "defn" => Some(Defn),
+            "defstruct" => Some(Defstruct),
+            "match" => Some(Match),
+            "quote" => Some(Quote),
+            "lazy-seq" => Some(LazySeq),
+            "yield" => Some(Yield),
             _ => None,
         }
     }
+
+    pub fn as_str(&self) -> &'static str {
+        use ReservedKw::*;
+
+        match self {
+            If => "if",
+            Else => "else",
+            ElseIf => "elseif",
+            Def => "def",
+            Let => "let",
+            True => "true",
+            False => "false",
+            And => "and",
+            Or => "or",
+            Nil => "nil",
+            Do => "do",
+            Set => "set",
+            While => "while",
+            For => "for",
+            Loop => "loop",
+            Recur => "recur",
+            Break => "break",
+            Continue => "continue",
+            Fn => "fn",
+            Defn => "defn",
+            Defstruct => "defstruct",
+            Match => "match",
+            Quote => "quote",
+            LazySeq => "lazy-seq",
+            Yield => "yield",
+        }
+    }
 }
 
 pub struct Tokenizer {
-    input: String,
+    /// Decoded up front, so looking a character up by position is O(1).
+    input: Vec<char>,
     pos: usize,
 }
 
 mod tokenizer;
 
-// #[cfg(test)]
-// mod tests;
+#[cfg(test)]
+mod tests;
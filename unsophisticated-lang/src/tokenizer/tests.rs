@@ -1,16 +1,27 @@
 use super::*;
 
+use BinaryOp::*;
+use Delimiter::*;
+use ReservedKw::*;
 use Token as T;
 
+fn num(n: &str) -> Token {
+    T::Literal(Literal::NumberLit(n.to_string()))
+}
+
+fn string(s: &str) -> Token {
+    T::Literal(Literal::StringLit(s.to_string()))
+}
+
 #[test]
 fn test_next_token() {
     let mut tokenizer = Tokenizer::new("(+ 1 2)".to_string());
     assert_eq!(tokenizer.next_token(), T::Delimiter(LParen));
     assert_eq!(tokenizer.next_token(), T::BinaryOp(Add));
     assert_eq!(tokenizer.next_token(), T::Delimiter(Space));
-    assert_eq!(tokenizer.next_token(), T::Number(1));
+    assert_eq!(tokenizer.next_token(), num("1"));
     assert_eq!(tokenizer.next_token(), T::Delimiter(Space));
-    assert_eq!(tokenizer.next_token(), T::Number(2));
+    assert_eq!(tokenizer.next_token(), num("2"));
     assert_eq!(tokenizer.next_token(), T::Delimiter(RParen));
     assert_eq!(tokenizer.next_token(), T::Delimiter(EOF));
 }
@@ -25,9 +36,9 @@ fn test_tokenize() {
             T::Delimiter(LParen),
             T::BinaryOp(Add),
             T::Delimiter(Space),
-            T::Number(1),
+            num("1"),
             T::Delimiter(Space),
-            T::Number(2),
+            num("2"),
             T::Delimiter(RParen),
             T::Delimiter(EOF),
         ]
@@ -44,17 +55,17 @@ fn test_tokenize_with_newline() {
             T::Delimiter(LParen),
             T::BinaryOp(Add),
             T::Delimiter(Space),
-            T::Number(1),
+            num("1"),
             T::Delimiter(Space),
-            T::Number(2),
+            num("2"),
             T::Delimiter(RParen),
             T::Delimiter(NewLine),
             T::Delimiter(LParen),
             T::BinaryOp(Add),
             T::Delimiter(Space),
-            T::Number(3),
+            num("3"),
             T::Delimiter(Space),
-            T::Number(4),
+            num("4"),
             T::Delimiter(RParen),
             T::Delimiter(EOF),
         ]
@@ -71,17 +82,17 @@ fn test_tokenize_with_space() {
             T::Delimiter(LParen),
             T::BinaryOp(Add),
             T::Delimiter(Space),
-            T::Number(1),
+            num("1"),
             T::Delimiter(Space),
-            T::Number(2),
+            num("2"),
             T::Delimiter(RParen),
             T::Delimiter(Space),
             T::Delimiter(LParen),
             T::BinaryOp(Add),
             T::Delimiter(Space),
-            T::Number(3),
+            num("3"),
             T::Delimiter(Space),
-            T::Number(4),
+            num("4"),
             T::Delimiter(RParen),
             T::Delimiter(EOF),
         ]
@@ -98,20 +109,45 @@ fn test_tokenize_with_string() {
             T::Delimiter(LParen),
             T::BinaryOp(Add),
             T::Delimiter(Space),
-            T::String("abc".to_string()),
+            string("abc"),
             T::Delimiter(Space),
-            T::String("def".to_string()),
+            string("def"),
             T::Delimiter(RParen),
             T::Delimiter(EOF),
         ]
     );
 }
 
+#[test]
+fn test_tokenize_string_escapes() {
+    let mut tokenizer = Tokenizer::new(r#""say \"hi\"" "a\\b" "\n""#);
+    let tokens = tokenizer.tokenize();
+    assert_eq!(
+        tokens,
+        vec![
+            string("say \"hi\""),
+            T::Delimiter(Space),
+            string("a\\b"),
+            T::Delimiter(Space),
+            string("\\n"),
+            T::Delimiter(EOF),
+        ]
+    );
+}
+
 #[test]
 fn test_tokenize_with_nil() {
-    let mut tokenizer = Tokenizer::new("()".to_string());
+    let mut tokenizer = Tokenizer::new("(nil)".to_string());
     let tokens = tokenizer.tokenize();
-    assert_eq!(tokens, vec![T::Nil, T::Delimiter(EOF),]);
+    assert_eq!(
+        tokens,
+        vec![
+            T::Delimiter(LParen),
+            T::ReservedKw(Nil),
+            T::Delimiter(RParen),
+            T::Delimiter(EOF),
+        ]
+    );
 }
 
 #[test]
@@ -141,13 +177,123 @@ fn test_tokenize_with_reserved() {
         tokens,
         vec![
             T::Delimiter(LParen),
-            T::Keyword(If),
+            T::ReservedKw(If),
             T::Delimiter(Space),
-            T::Keyword(True),
+            T::ReservedKw(True),
             T::Delimiter(Space),
-            T::Keyword(False),
+            T::ReservedKw(False),
             T::Delimiter(RParen),
             T::Delimiter(EOF),
         ]
     );
 }
+
+#[test]
+fn test_tokenize_comparisons() {
+    let mut tokenizer = Tokenizer::new("< <= > >= =".to_string());
+    let tokens = tokenizer.tokenize();
+    assert_eq!(
+        tokens,
+        vec![
+            T::BinaryOp(Lt),
+            T::Delimiter(Space),
+            T::BinaryOp(Le),
+            T::Delimiter(Space),
+            T::BinaryOp(Gt),
+            T::Delimiter(Space),
+            T::BinaryOp(Ge),
+            T::Delimiter(Space),
+            T::BinaryOp(Eq),
+            T::Delimiter(EOF),
+        ]
+    );
+}
+
+#[test]
+fn test_tokenize_lisp_style_ident() {
+    let mut tokenizer = Tokenizer::new("(read-file empty? not= x_1)".to_string());
+    let tokens = tokenizer.tokenize();
+    assert_eq!(
+        tokens,
+        vec![
+            T::Delimiter(LParen),
+            T::Ident("read-file".to_string()),
+            T::Delimiter(Space),
+            T::Ident("empty?".to_string()),
+            T::Delimiter(Space),
+            T::Ident("not=".to_string()),
+            T::Delimiter(Space),
+            T::Ident("x_1".to_string()),
+            T::Delimiter(RParen),
+            T::Delimiter(EOF),
+        ]
+    );
+}
+
+#[test]
+fn test_tokenize_multibyte_string() {
+    let mut tokenizer = Tokenizer::new("\"héllo wörld\" 12".to_string());
+    let tokens = tokenizer.tokenize();
+    assert_eq!(
+        tokens,
+        vec![
+            string("héllo wörld"),
+            T::Delimiter(Space),
+            num("12"),
+            T::Delimiter(EOF),
+        ]
+    );
+}
+
+#[test]
+fn test_tokenize_pattern_delimiters() {
+    let mut tokenizer = Tokenizer::new("(_ . #(p))".to_string());
+    let tokens = tokenizer.tokenize();
+    assert_eq!(
+        tokens,
+        vec![
+            T::Delimiter(LParen),
+            T::Ident("_".to_string()),
+            T::Delimiter(Space),
+            T::Delimiter(Dot),
+            T::Delimiter(Space),
+            T::Delimiter(Hash),
+            T::Delimiter(LParen),
+            T::Ident("p".to_string()),
+            T::Delimiter(RParen),
+            T::Delimiter(RParen),
+            T::Delimiter(EOF),
+        ]
+    );
+}
+
+#[test]
+fn test_tokenize_quote() {
+    let mut tokenizer = Tokenizer::new("'(quote symbol->string)".to_string());
+    let tokens = tokenizer.tokenize();
+    assert_eq!(
+        tokens,
+        vec![
+            T::Delimiter(Delimiter::Quote),
+            T::Delimiter(LParen),
+            T::ReservedKw(ReservedKw::Quote),
+            T::Delimiter(Space),
+            T::Ident("symbol->string".to_string()),
+            T::Delimiter(RParen),
+            T::Delimiter(EOF),
+        ]
+    );
+}
+
+#[test]
+fn test_tokenize_unexpected_char() {
+    let mut tokenizer = Tokenizer::new("$".to_string());
+    let tokens = tokenizer.tokenize();
+    assert_eq!(
+        tokens,
+        vec![
+            T::TokenError("unexpected char '$'".to_string()),
+            T::Delimiter(EOF),
+        ]
+    );
+}
@@ -6,7 +6,7 @@ impl Tokenizer {
         T: Into<String>,
     {
         Self {
-            input: input.into(),
+            input: input.into().chars().collect(),
             pos: 0,
         }
     }
@@ -25,7 +25,7 @@ impl Tokenizer {
     }
 
     fn peek_next_char(&self) -> Option<char> {
-        self.input.chars().nth(self.pos)
+        self.input.get(self.pos).copied()
     }
 
     pub fn next_token(&mut self) -> Token {
@@ -35,13 +35,31 @@ impl Tokenizer {
             '\0' => Token::Delimiter(EOF),
             '(' => Token::Delimiter(LParen),
             ')' => Token::Delimiter(RParen),
-            ' ' => Token::Delimiter(Space),
+            '.' => Token::Delimiter(Dot),
+            '#' => Token::Delimiter(Hash),
+            '\'' => Token::Delimiter(Quote),
+            ' ' | '\t' | '\r' => Token::Delimiter(Space),
             '\n' => Token::Delimiter(NewLine),
 
             '+' => Token::BinaryOp(Add),
             '-' => Token::BinaryOp(Sub),
             '*' => Token::BinaryOp(Mul),
             '/' => Token::BinaryOp(Div),
+            '=' => Token::BinaryOp(Eq),
+            '<' => match self.peek_next_char() {
+                Some('=') => {
+                    self.next_char();
+                    Token::BinaryOp(Le)
+                }
+                _ => Token::BinaryOp(Lt),
+            },
+            '>' => match self.peek_next_char() {
+                Some('=') => {
+                    self.next_char();
+                    Token::BinaryOp(Ge)
+                }
+                _ => Token::BinaryOp(Gt),
+            },
 
             '~' => Token::UnaryOp(Neg),
             '!' => Token::UnaryOp(Not),
@@ -56,7 +74,7 @@ impl Tokenizer {
                 self.tokenize_number()
             }
 
-            c if c.is_alphabetic() => {
+            c if c.is_alphabetic() || c == '_' => {
                 self.back_char();
                 let word = self.build_word();
                 match ReservedKw::from_str(&word) {
@@ -90,12 +108,19 @@ impl Tokenizer {
         match self.next_char().unwrap() {
             '"' => {
                 let mut s = String::new();
-                while self.pos < self.input.len() {
-                    let c = self.next_char().unwrap();
-                    if c == '"' {
-                        break;
+                while let Some(c) = self.next_char() {
+                    match c {
+                        '"' => break,
+                        // `\"` and `\\` escape, any other backslash is kept as is
+                        '\\' => match self.peek_next_char() {
+                            Some(escaped @ ('"' | '\\')) => {
+                                self.next_char();
+                                s.push(escaped);
+                            }
+                            _ => s.push(c),
+                        },
+                        c => s.push(c),
                     }
-                    s.push(c);
                 }
                 Token::Literal(StringLit(s))
             }
@@ -108,8 +133,7 @@ impl Tokenizer {
             c if c.is_digit(10) => {
                 let mut n = String::new();
                 n.push(c);
-                while self.pos < self.input.len() {
-                    let c = self.input.chars().nth(self.pos).unwrap();
+                while let Some(c) = self.peek_next_char() {
                     if !c.is_digit(10) {
                         break;
                     }
@@ -126,7 +150,10 @@ impl Tokenizer {
         let mut word: String = String::new();
         while let Some(c) = self.next_char() {
             match c {
-                c if c.is_alphanumeric() => word.push(c),
+                // Lisp-style names such as `read-file`, `empty?`, `not=` or `symbol->string`
+                c if c.is_alphanumeric() || matches!(c, '-' | '_' | '?' | '!' | '=' | '>') => {
+                    word.push(c)
+                }
                 _ => {
                     self.back_char();
                     break;
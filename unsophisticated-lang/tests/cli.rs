@@ -0,0 +1,146 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+fn unsoph(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_unsoph"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(stdin.as_bytes()).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn script(name: &str, source: &str) -> String {
+    let path = std::env::temp_dir().join(format!("unsoph-cli-{}-{}", std::process::id(), name));
+    std::fs::write(&path, source).unwrap();
+    path.to_str().unwrap().to_string()
+}
+
+#[test]
+fn eval_expression() {
+    let output = unsoph(&["-e", "(+ 1 2)"], "");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "");
+
+    let output = unsoph(&["--print-result", "-e", "(+ 1 2)"], "");
+    assert_eq!(stdout(&output), "Number(3.0)\n");
+}
+
+#[test]
+fn run_evaluates_every_expression() {
+    let path = script("every.unsoph", "(def x 20)\n(print x)\n\t(+ x 1)\n");
+    let output = unsoph(&["-p", "run", &path], "");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "20\nNumber(21.0)\n");
+}
+
+#[test]
+fn run_from_stdin() {
+    let output = unsoph(&["-p", "-"], "(* 6 7)");
+    assert_eq!(stdout(&output), "Number(42.0)\n");
+
+    let output = unsoph(&["-p", "run", "-"], "(* 6 7)");
+    assert_eq!(stdout(&output), "Number(42.0)\n");
+}
+
+#[test]
+fn script_arguments() {
+    let path = script("args.unsoph", "(print (args))");
+    let output = unsoph(&[&path, "a", "-e", "--print-result"], "");
+    assert_eq!(
+        stdout(&output),
+        "(\"a\" \"-e\" \"--print-result\")\n"
+    );
+}
+
+#[test]
+fn shebang_line_is_ignored() {
+    let path = script("shebang.unsoph", "#!/usr/bin/env unsoph\n(+ 1 1)\n");
+    let output = unsoph(&["-p", &path], "");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "Number(2.0)\n");
+}
+
+#[test]
+fn exit_codes() {
+    // usage
+    assert_eq!(unsoph(&["--nope"], "").status.code(), Some(64));
+    assert_eq!(unsoph(&["-e"], "").status.code(), Some(64));
+    assert_eq!(unsoph(&["compile"], "").status.code(), Some(64));
+    // parse error, nothing is evaluated
+    let output = unsoph(&["-e", "(print 1) (+ 1"], "");
+    assert_eq!(output.status.code(), Some(65));
+    assert_eq!(stdout(&output), "");
+    // missing input
+    assert_eq!(unsoph(&["run", "/does/not/exist"], "").status.code(), Some(66));
+    // runtime error
+    assert_eq!(unsoph(&["-e", "(+ 1 \"a\")"], "").status.code(), Some(70));
+    // explicit exit
+    assert_eq!(unsoph(&["-e", "(exit 3)"], "").status.code(), Some(3));
+}
+
+#[test]
+fn no_prelude() {
+    let output = unsoph(&["-p", "-e", "(inc 1)"], "");
+    assert_eq!(stdout(&output), "Number(2.0)\n");
+
+    let output = unsoph(&["--no-prelude", "-e", "(inc 1)"], "");
+    assert_eq!(output.status.code(), Some(70));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "Error: Unsupported function call: inc\n"
+    );
+}
+
+#[test]
+fn help() {
+    let output = unsoph(&["--help"], "");
+    assert_eq!(output.status.code(), Some(0));
+    assert!(stdout(&output).starts_with("Usage:"));
+}
+
+#[test]
+fn interactive_repl() {
+    let output = unsoph(&[], "(def x 2)\n(+ x 1)\n(+ 1\nexit\n");
+    assert_eq!(output.status.code(), Some(0));
+    let out = stdout(&output);
+    assert!(out.contains("Number(3.0)"), "{}", out);
+    assert!(out.contains("Parse error"), "{}", out);
+}
+
+#[test]
+fn interactive_exit_code() {
+    let output = unsoph(&["-i"], "(def x 4)\n(exit x)\n(print 1)\n");
+    assert_eq!(output.status.code(), Some(4));
+    assert!(!stdout(&output).contains("Number(1.0)"));
+}
+
+#[test]
+fn profile() {
+    let stacks = std::env::temp_dir().join(format!("unsoph-cli-{}-stacks", std::process::id()));
+    let stacks = stacks.to_str().unwrap();
+    let src = "(defn sq (x) (* x x)) (map sq (list 1 2 3))";
+    let output = unsoph(&["--profile", "--profile-stacks", stacks, "-e", src], "");
+    assert_eq!(output.status.code(), Some(0));
+
+    let report = String::from_utf8_lossy(&output.stderr);
+    let mut lines = report.lines();
+    assert!(lines.next().unwrap().contains("calls"), "{}", report);
+    let sq = lines.find(|line| line.ends_with(" sq")).unwrap();
+    assert_eq!(sq.split_whitespace().next(), Some("3"));
+
+    let stacks = std::fs::read_to_string(stacks).unwrap();
+    assert!(stacks.lines().any(|line| line.starts_with("map;seq->list;sq ")), "{}", stacks);
+
+    // Reported even when the script fails
+    let output = unsoph(&["--profile", "-e", "(defn f () (+ 1 \"a\")) (f)"], "");
+    assert_eq!(output.status.code(), Some(70));
+    assert!(String::from_utf8_lossy(&output.stderr).contains(" f\n"));
+}
@@ -0,0 +1,154 @@
+//! Runs every program in `examples/` through the interpreter and through the
+//! Rust backend, and checks both behave the same.
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+const ARGS: [&str; 2] = ["first", "second"];
+
+fn example(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("examples")
+        .join(format!("{}.unsoph", name))
+}
+
+fn interpret(path: &Path) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_unsoph"))
+        .arg("run")
+        .arg(path)
+        .args(ARGS)
+        .output()
+        .unwrap()
+}
+
+fn compile_and_run(name: &str, path: &Path) -> Output {
+    let dir = std::env::temp_dir().join(format!("unsoph-compile-{}-{}", std::process::id(), name));
+    std::fs::create_dir_all(&dir).unwrap();
+    let source = dir.join("main.rs");
+    let binary = dir.join("main");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_unsoph"))
+        .arg("compile")
+        .arg(path)
+        .arg("-o")
+        .arg(&source)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let output = Command::new(rustc)
+        .arg(&source)
+        .arg("-o")
+        .arg(&binary)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let output = Command::new(&binary).args(ARGS).output().unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    output
+}
+
+fn check(name: &str) {
+    let path = example(name);
+    let expected = interpret(&path);
+    let actual = compile_and_run(name, &path);
+
+    assert_eq!(
+        String::from_utf8_lossy(&actual.stdout),
+        String::from_utf8_lossy(&expected.stdout)
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&actual.stderr),
+        String::from_utf8_lossy(&expected.stderr)
+    );
+    assert_eq!(actual.status.code(), expected.status.code());
+}
+
+#[test]
+fn control() {
+    check("control");
+}
+
+#[test]
+fn exit() {
+    check("exit");
+}
+
+#[test]
+fn factorial() {
+    check("factorial");
+}
+
+#[test]
+fn functions() {
+    check("functions");
+}
+
+#[test]
+fn fizzbuzz() {
+    check("fizzbuzz");
+}
+
+#[test]
+fn pattern_matching() {
+    check("match");
+}
+
+#[test]
+fn lazy() {
+    check("lazy");
+}
+
+#[test]
+fn prelude() {
+    check("prelude");
+}
+
+#[test]
+fn quote() {
+    check("quote");
+}
+
+#[test]
+fn ranges() {
+    check("ranges");
+}
+
+#[test]
+fn records() {
+    check("records");
+}
+
+#[test]
+fn runtime_error() {
+    check("runtime_error");
+}
+
+#[test]
+fn strings() {
+    check("strings");
+}
+
+#[test]
+fn unsupported_builtins() {
+    let path =
+        std::env::temp_dir().join(format!("unsoph-unsupported-{}.unsoph", std::process::id()));
+    std::fs::write(&path, "(print 1)\n(hash-map \"a\" 1)\n(print 2)\n").unwrap();
+    let output = compile_and_run("unsupported", &path);
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "1\n");
+    assert!(
+        String::from_utf8_lossy(&output.stderr)
+            .contains("hash-map is not supported by compiled programs"),
+        "{:?}",
+        output
+    );
+    assert!(!output.status.success());
+}
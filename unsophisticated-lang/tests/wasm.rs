@@ -0,0 +1,192 @@
+//! Compiles programs with the wasm backend, validates and runs the modules
+//! with wasmi, and checks they agree with the interpreter.
+
+use unsophisticated_lang::compiler::compile_to_wat;
+use unsophisticated_lang::interpreter::{AstInterpreter, Value};
+
+use proptest::prelude::*;
+use wasmi::{Engine, Instance, Linker, Module, Store, Val};
+
+struct Wasm {
+    store: Store<()>,
+    instance: Instance,
+}
+
+impl Wasm {
+    fn new(source: &str) -> Self {
+        let wat = compile_to_wat(source).unwrap();
+        let bytes = wat::parse_str(&wat).unwrap_or_else(|e| panic!("{}\n{}", e, wat));
+
+        // Module::new validates the module.
+        let engine = Engine::default();
+        let module = Module::new(&engine, &bytes).unwrap_or_else(|e| panic!("{}\n{}", e, wat));
+        let mut store = Store::new(&engine, ());
+        let instance = Linker::new(&engine)
+            .instantiate(&mut store, &module)
+            .unwrap()
+            .start(&mut store)
+            .unwrap();
+        Self { store, instance }
+    }
+
+    fn call(&mut self, name: &str, args: &[f64]) -> Value {
+        let func = self.instance.get_func(&self.store, name).unwrap();
+        let params = args.iter().map(|n| Val::F64((*n).into())).collect::<Vec<_>>();
+        let mut results = [Val::I32(0)];
+        func.call(&mut self.store, &params, &mut results).unwrap();
+        match results[0] {
+            Val::F64(n) => Value::Number(n.into()),
+            Val::I32(b) => Value::Bool(b != 0),
+            ref val => panic!("unexpected result {:?}", val),
+        }
+    }
+}
+
+fn interpret(source: &str) -> Value {
+    let mut interpreter = AstInterpreter::new();
+    interpreter.eval(source).unwrap();
+    interpreter.stack.pop().unwrap()
+}
+
+/// Runs `main` both ways.
+fn check(source: &str) -> Value {
+    let expected = interpret(source);
+    assert_eq!(Wasm::new(source).call("main", &[]), expected, "{}", source);
+    expected
+}
+
+const FUNCTIONS: &str = "
+(defn fact (n) (if (<= n 1) 1 (* n (fact (- n 1)))))
+(defn fib (n) (if (< n 2) n (+ (fib (- n 1)) (fib (- n 2)))))
+(defn even? (n) (if (= n 0) true (odd? (- n 1))))
+(defn odd? (n) (if (= n 0) false (even? (- n 1))))
+(defn hypot2 (a b) (let (aa (* a a) bb (* b b)) (+ aa bb)))
+(defn clamp (x lo hi) (if (< x lo) lo (if (> x hi) hi x)))
+";
+
+#[test]
+fn exported_functions() {
+    let mut wasm = Wasm::new(FUNCTIONS);
+    let cases: &[(&str, &[f64])] = &[
+        ("fact", &[10.0]),
+        ("fib", &[15.0]),
+        ("even?", &[10.0]),
+        ("odd?", &[7.0]),
+        ("even?", &[7.0]),
+        ("hypot2", &[3.0, 4.0]),
+        ("clamp", &[-5.0, 0.0, 10.0]),
+        ("clamp", &[5.0, 0.0, 10.0]),
+        ("clamp", &[50.0, 0.0, 10.0]),
+    ];
+    for (name, args) in cases {
+        let args_src = args.iter().map(|n| format!("{}", n)).collect::<Vec<_>>();
+        let call = format!("({} {})", name, args_src.join(" ")).replace("-5", "(~ 5)");
+        let expected = interpret(&format!("{}{}", FUNCTIONS, call));
+        assert_eq!(wasm.call(name, args), expected, "{}", call);
+    }
+}
+
+#[test]
+fn main_is_the_last_top_level_expression() {
+    assert_eq!(check("(+ 1 2) (* 6 7)"), Value::Number(42.0));
+    assert_eq!(check("(defn sq (x) (* x x)) (sq 12)"), Value::Number(144.0));
+    assert_eq!(check("(< 1 2)"), Value::Bool(true));
+}
+
+#[test]
+fn let_set_and_do() {
+    check("(let (x 1 y (+ x 1)) (set x (* y 10)) (+ x y))");
+    check("(let (x 1) (let (x (+ x 1)) x))");
+    check("(let (t true) (do (set t (! t)) (= t false)))");
+}
+
+#[test]
+fn if_and_truthiness() {
+    check("(if (< 2 1) 1 (if true 2 3))");
+    check("(if 0 10 20)");
+    check("(if (- 3 1) 10 20)");
+    check("(= (= 1 1) (! false))");
+}
+
+#[test]
+fn float_semantics() {
+    check("(/ 1 3)");
+    check("(/ 1 0)");
+    check("(~ (/ 1 0))");
+    check("(= (/ 0 0) (/ 0 0))");
+}
+
+#[test]
+fn outside_of_the_subset() {
+    for source in [
+        "\"strings\"",
+        "(if true 1)",
+        "(if true 1 false)",
+        "(+ 1 true)",
+        "(print 1)",
+        "(def x 1)",
+        "(fn (x) x)",
+        "((fn (x) x) 1)",
+        "(while false 1)",
+        "(defn f (x) x) (f 1 2)",
+        "(defn f (x) x) (f true)",
+        "(defn f () 1) (defn f () 2)",
+        "(let (x 1) (set x true))",
+        "y",
+    ] {
+        assert!(compile_to_wat(source).is_err(), "{}", source);
+    }
+}
+
+// -- region : property tests --
+
+fn expr() -> impl Strategy<Value = String> {
+    let leaf = prop_oneof![
+        (0u32..100).prop_map(|n| n.to_string()),
+        Just("true".to_string()),
+        Just("false".to_string()),
+        Just("x".to_string()),
+    ];
+    leaf.prop_recursive(4, 32, 3, |inner| {
+        prop_oneof![
+            (
+                prop_oneof![
+                    Just("+"),
+                    Just("-"),
+                    Just("*"),
+                    Just("/"),
+                    Just("<"),
+                    Just("<="),
+                    Just("="),
+                ],
+                inner.clone(),
+                inner.clone()
+            )
+                .prop_map(|(op, lhs, rhs)| format!("({} {} {})", op, lhs, rhs)),
+            inner.clone().prop_map(|expr| format!("(~ {})", expr)),
+            inner.clone().prop_map(|expr| format!("(! {})", expr)),
+            (inner.clone(), inner.clone(), inner.clone())
+                .prop_map(|(c, t, e)| format!("(if {} {} {})", c, t, e)),
+            (inner.clone(), inner)
+                .prop_map(|(value, body)| format!("(let (x {}) {})", value, body)),
+        ]
+    })
+}
+
+proptest! {
+    /// Whatever type checks computes the same value as the interpreter.
+    #[test]
+    fn prop_agrees_with_interpreter(body in expr()) {
+        let source = format!("(defn f (x) {}) (f 7)", body);
+        prop_assume!(compile_to_wat(&source).is_ok());
+
+        let expected = interpret(&source);
+        let actual = Wasm::new(&source).call("main", &[]);
+        match (&actual, &expected) {
+            (Value::Number(a), Value::Number(b)) if a.is_nan() && b.is_nan() => {}
+            _ => prop_assert_eq!(actual, expected, "{}", source),
+        }
+    }
+}
+
+// -- end region : property tests --